@@ -0,0 +1,201 @@
+package zk
+
+/*
+#include "./lib/zk.h"
+*/
+import "C"
+import (
+	"errors"
+)
+
+// BLS key and signature sizes, public keys are compressed G1 points and
+// signatures compressed G2 points of BLS12-381.
+const (
+	BLSSecretKeySize = 32
+	BLSPublicKeySize = 48
+	BLSSignatureSize = 96
+)
+
+// BLSScheme selects the IETF BLS signature ciphersuite, it mirrors Scheme in
+// zk/src/bls.rs.
+type BLSScheme int
+
+const (
+	// BLSBasic requires aggregates to be over distinct messages.
+	BLSBasic BLSScheme = 0
+	// BLSAugmented prefixes messages with the signer's public key.
+	BLSAugmented BLSScheme = 1
+	// BLSProofOfPossession requires every key to come with a proof of
+	// possession, signatures of the same message can then be checked with
+	// BLSFastAggregateVerify.
+	BLSProofOfPossession BLSScheme = 2
+)
+
+// BLSKeyGen derives a secret key from at least 32 bytes of secret
+// randomness with the IETF draft's KeyGen.
+func BLSKeyGen(ikm []byte) ([]byte, error) {
+	sk := make([]byte, BLSSecretKeySize)
+	_, err := call(func() C.int {
+		return C.bls_keygen(bytesPtr(ikm), C.size_t(len(ikm)), bytesPtr(sk))
+	})
+	if err != nil {
+		return nil, err
+	}
+	return sk, nil
+}
+
+// BLSPublicKey returns the public key of a secret key.
+func BLSPublicKey(sk []byte) ([]byte, error) {
+	if len(sk) != BLSSecretKeySize {
+		return nil, errors.New("zk: BLS secret key must be 32 bytes")
+	}
+	pk := make([]byte, BLSPublicKeySize)
+	_, err := call(func() C.int {
+		return C.bls_public_key(bytesPtr(sk), bytesPtr(pk))
+	})
+	if err != nil {
+		return nil, err
+	}
+	return pk, nil
+}
+
+// BLSSign signs msg with a secret key.
+func BLSSign(scheme BLSScheme, sk, msg []byte) ([]byte, error) {
+	if len(sk) != BLSSecretKeySize {
+		return nil, errors.New("zk: BLS secret key must be 32 bytes")
+	}
+	sig := make([]byte, BLSSignatureSize)
+	_, err := call(func() C.int {
+		return C.bls_sign(C.int(scheme), bytesPtr(sk), bytesPtr(msg), C.size_t(len(msg)), bytesPtr(sig))
+	})
+	if err != nil {
+		return nil, err
+	}
+	return sig, nil
+}
+
+// BLSVerify checks a signature of msg by pk. The error is only set for
+// malformed arguments, an invalid signature yields false.
+func BLSVerify(scheme BLSScheme, pk, msg, sig []byte) (bool, error) {
+	if len(pk) != BLSPublicKeySize || len(sig) != BLSSignatureSize {
+		return false, errors.New("zk: wrong BLS public key or signature size")
+	}
+	return call(func() C.int {
+		return C.bls_verify(C.int(scheme), bytesPtr(pk), bytesPtr(msg), C.size_t(len(msg)), bytesPtr(sig))
+	})
+}
+
+// BLSAggregateSignatures sums signatures into one.
+func BLSAggregateSignatures(sigs ...[]byte) ([]byte, error) {
+	buf := concat(sigs)
+	agg := make([]byte, BLSSignatureSize)
+	_, err := call(func() C.int {
+		return C.bls_aggregate_signatures(bytesPtr(buf), C.size_t(len(buf)), bytesPtr(agg))
+	})
+	if err != nil {
+		return nil, err
+	}
+	return agg, nil
+}
+
+// BLSAggregatePublicKeys sums public keys into one, only meaningful for
+// signers of the same message.
+func BLSAggregatePublicKeys(pks ...[]byte) ([]byte, error) {
+	buf := concat(pks)
+	agg := make([]byte, BLSPublicKeySize)
+	_, err := call(func() C.int {
+		return C.bls_aggregate_public_keys(bytesPtr(buf), C.size_t(len(buf)), bytesPtr(agg))
+	})
+	if err != nil {
+		return nil, err
+	}
+	return agg, nil
+}
+
+// BLSAggregateVerify checks an aggregate signature where pks[i] signed
+// msgs[i] with a single pairing check. With BLSBasic messages must be
+// distinct.
+func BLSAggregateVerify(scheme BLSScheme, pks, msgs [][]byte, sig []byte) (bool, error) {
+	if len(pks) == 0 || len(pks) != len(msgs) {
+		return false, errors.New("zk: need one message per BLS public key")
+	}
+	if len(sig) != BLSSignatureSize {
+		return false, errors.New("zk: BLS signature must be 96 bytes")
+	}
+	buf := concat(pks)
+	lens := make([]C.size_t, len(msgs))
+	for i, m := range msgs {
+		lens[i] = C.size_t(len(m))
+	}
+	all := concat(msgs)
+	return call(func() C.int {
+		return C.bls_aggregate_verify(C.int(scheme), bytesPtr(buf), C.size_t(len(buf)), bytesPtr(all), &lens[0], bytesPtr(sig))
+	})
+}
+
+// BLSPopProve returns the proof of possession of a secret key, a signature
+// of its public key under the POP tag.
+func BLSPopProve(sk []byte) ([]byte, error) {
+	if len(sk) != BLSSecretKeySize {
+		return nil, errors.New("zk: BLS secret key must be 32 bytes")
+	}
+	proof := make([]byte, BLSSignatureSize)
+	_, err := call(func() C.int {
+		return C.bls_pop_prove(bytesPtr(sk), bytesPtr(proof))
+	})
+	if err != nil {
+		return nil, err
+	}
+	return proof, nil
+}
+
+// BLSPopVerify checks the proof of possession of a public key.
+func BLSPopVerify(pk, proof []byte) (bool, error) {
+	if len(pk) != BLSPublicKeySize || len(proof) != BLSSignatureSize {
+		return false, errors.New("zk: wrong BLS public key or proof size")
+	}
+	return call(func() C.int {
+		return C.bls_pop_verify(bytesPtr(pk), bytesPtr(proof))
+	})
+}
+
+// BLSPopVerifyBatch checks the proofs of possession of a key set at once,
+// proofs[i] being the proof of pks[i]. It returns whether they're all valid
+// along with a verdict for every key; keys or proofs that can't be decoded
+// are reported as invalid.
+func BLSPopVerifyBatch(pks, proofs [][]byte) (bool, []bool, error) {
+	if len(pks) != len(proofs) {
+		return false, nil, errors.New("zk: need one proof per BLS public key")
+	}
+	for i := range pks {
+		if len(pks[i]) != BLSPublicKeySize || len(proofs[i]) != BLSSignatureSize {
+			return false, nil, errors.New("zk: wrong BLS public key or proof size")
+		}
+	}
+	bpks, bproofs := concat(pks), concat(proofs)
+	results := make([]byte, len(pks))
+	ok, err := call(func() C.int {
+		return C.bls_pop_verify_batch(bytesPtr(bpks), C.size_t(len(bpks)), bytesPtr(bproofs), C.size_t(len(bproofs)), bytesPtr(results), C.size_t(len(results)))
+	})
+	if err != nil {
+		return false, nil, err
+	}
+	verdicts := make([]bool, len(results))
+	for i, r := range results {
+		verdicts[i] = r == 1
+	}
+	return ok, verdicts, nil
+}
+
+// BLSFastAggregateVerify checks an aggregate of signatures of msg by all of
+// pks, whose possession must have been proven. Only BLSProofOfPossession is
+// accepted.
+func BLSFastAggregateVerify(scheme BLSScheme, pks [][]byte, msg, sig []byte) (bool, error) {
+	if len(sig) != BLSSignatureSize {
+		return false, errors.New("zk: BLS signature must be 96 bytes")
+	}
+	buf := concat(pks)
+	return call(func() C.int {
+		return C.bls_fast_aggregate_verify(C.int(scheme), bytesPtr(buf), C.size_t(len(buf)), bytesPtr(msg), C.size_t(len(msg)), bytesPtr(sig))
+	})
+}
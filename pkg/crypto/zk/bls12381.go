@@ -0,0 +1,63 @@
+package zk
+
+/*
+#include "./lib/zk.h"
+*/
+import "C"
+
+// BLS12381Operation is one of the EIP-2537 precompiles, it's at address
+// 10 plus its value. Field elements are 64-byte big-endian with the 16 top
+// bytes zero, G1 points x | y, G2 points x.c0 | x.c1 | y.c0 | y.c1 and the
+// point at infinity all zeroes, scalars 32-byte big-endian.
+type BLS12381Operation int
+
+// EIP-2537 precompiles, in address order.
+const (
+	BLS12381G1Add BLS12381Operation = iota
+	BLS12381G1Mul
+	BLS12381G1MultiExp
+	BLS12381G2Add
+	BLS12381G2Mul
+	BLS12381G2MultiExp
+	BLS12381Pairing
+	BLS12381MapG1
+	BLS12381MapG2
+)
+
+// EIP-2537 output sizes.
+const (
+	BLS12381G1Size      = 128
+	BLS12381G2Size      = 256
+	BLS12381PairingSize = 32
+)
+
+func (op BLS12381Operation) outputSize() int {
+	switch op {
+	case BLS12381G1Add, BLS12381G1Mul, BLS12381G1MultiExp, BLS12381MapG1:
+		return BLS12381G1Size
+	case BLS12381Pairing:
+		return BLS12381PairingSize
+	default:
+		return BLS12381G2Size
+	}
+}
+
+// RequiredGas returns the gas required to run the contract, input needn't
+// be valid.
+func (op BLS12381Operation) RequiredGas(input []byte) uint64 {
+	return uint64(C.bls12381_precompile_gas(C.int(op), bytesPtr(input), C.size_t(len(input))))
+}
+
+// Run runs the contract, malformed input, points not on the curve or, for
+// the pairing, not in the subgroup are reported as an error.
+func (op BLS12381Operation) Run(input []byte) ([]byte, error) {
+	output := make([]byte, op.outputSize())
+	_, err := call(func() C.int {
+		return C.bls12381_precompile(C.int(op), bytesPtr(input), C.size_t(len(input)),
+			bytesPtr(output), C.size_t(len(output)))
+	})
+	if err != nil {
+		return nil, err
+	}
+	return output, nil
+}
@@ -0,0 +1,94 @@
+package zk
+
+import (
+	"encoding/hex"
+	"errors"
+	"strings"
+	"testing"
+
+	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
+)
+
+// fp pads 48-byte field elements to 64 bytes.
+func fp(t *testing.T, elements ...string) []byte {
+	var b []byte
+	for _, e := range elements {
+		v, err := hex.DecodeString(e)
+		require.NoError(t, err)
+		b = append(b, make([]byte, 16)...)
+		b = append(b, v...)
+	}
+	return b
+}
+
+func TestBLS12381Precompiles(t *testing.T) {
+	g1 := fp(t,
+		"17f1d3a73197d7942695638c4fa9ac0fc3688c4f9774b905a14e3a3f171bac586c55e83ff97a1aeffb3af00adb22c6bb",
+		"08b3f481e3aaa0f1a09e30ed741d8ae4fcf5e095d5d00af600db18cb2c04b3edd03cc744a2888ae40caa232946c5e7e1")
+	negG1 := fp(t,
+		"17f1d3a73197d7942695638c4fa9ac0fc3688c4f9774b905a14e3a3f171bac586c55e83ff97a1aeffb3af00adb22c6bb",
+		"114d1d6855d545a8aa7d76c8cf2e21f267816aef1db507c96655b9d5caac42364e6f38ba0ecb751bad54dcd6b939c2ca")
+	double := fp(t,
+		"0572cbea904d67468808c8eb50a9450c9721db309128012543902d0ac358a62ae28f75bb8f1c7c42c39a8c5529bf0f4e",
+		"166a9d8cabc673a322fda673779d8e3822ba3ecb8670e461f73bb9021d5fd76a4c56d9d4cd16bd1bba86881979749d28")
+	g2 := fp(t,
+		"024aa2b2f08f0a91260805272dc51051c6e47ad4fa403b02b4510b647ae3d1770bac0326a805bbefd48056c8c121bdb8",
+		"13e02b6052719f607dacd3a088274f65596bd0d09920b61ab5da61bbdc7f5049334cf11213945d57e5ac7d055d042b7e",
+		"0ce5d527727d6e118cc9cdc6da2e351aadfd9baa8cbdd3a76d429a695160d12c923ac9cc3baca289e193548608b82801",
+		"0606c4a02ea734cc32acd2b02bc28b99cb3e287e85a763af267492ab572e99ab3f370d275cec1da1aaa9075ff05f79be")
+	two := make([]byte, 32)
+	two[31] = 2
+
+	out, err := BLS12381G1Add.Run(append(append([]byte{}, g1...), g1...))
+	require.NoError(t, err)
+	assert.Equal(t, double, out)
+	assert.Equal(t, uint64(600), BLS12381G1Add.RequiredGas(nil))
+
+	out, err = BLS12381G1Mul.Run(append(append([]byte{}, g1...), two...))
+	require.NoError(t, err)
+	assert.Equal(t, double, out)
+
+	pair := append(append([]byte{}, g1...), two...)
+	out, err = BLS12381G1MultiExp.Run(pair)
+	require.NoError(t, err)
+	assert.Equal(t, double, out)
+	assert.Equal(t, uint64(14400), BLS12381G1MultiExp.RequiredGas(pair))
+
+	out, err = BLS12381G2Add.Run(append(append([]byte{}, g2...), make([]byte, BLS12381G2Size)...))
+	require.NoError(t, err)
+	assert.Equal(t, g2, out)
+
+	input := append(append(append(append([]byte{}, g1...), g2...), negG1...), g2...)
+	out, err = BLS12381Pairing.Run(input)
+	require.NoError(t, err)
+	assert.Equal(t, strings.Repeat("00", 31)+"01", hex.EncodeToString(out))
+	assert.Equal(t, uint64(115000+2*23000), BLS12381Pairing.RequiredGas(input))
+	out, err = BLS12381Pairing.Run(input[:BLS12381G1Size+BLS12381G2Size])
+	require.NoError(t, err)
+	assert.Equal(t, make([]byte, 32), out)
+
+	out, err = BLS12381MapG1.Run(make([]byte, 64))
+	require.NoError(t, err)
+	assert.Len(t, out, BLS12381G1Size)
+	out, err = BLS12381MapG2.Run(make([]byte, 128))
+	require.NoError(t, err)
+	assert.Len(t, out, BLS12381G2Size)
+
+	_, err = BLS12381G1Add.Run(g1)
+	var zkErr *Error
+	require.True(t, errors.As(err, &zkErr))
+	assert.Equal(t, CodeMalformedInputs, zkErr.Code)
+	bad := append([]byte{}, g1...)
+	bad[len(bad)-1] ^= 1
+	_, err = BLS12381G1Mul.Run(append(bad, two...))
+	require.Error(t, err)
+	bad = append([]byte{}, g1...)
+	bad[0] = 1
+	_, err = BLS12381G1Mul.Run(append(bad, two...))
+	require.Error(t, err)
+
+	assert.Equal(t, ^uint64(0), BLS12381Operation(9).RequiredGas(nil))
+	_, err = BLS12381Operation(9).Run(nil)
+	require.Error(t, err)
+}
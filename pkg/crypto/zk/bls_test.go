@@ -0,0 +1,95 @@
+package zk
+
+import (
+	"bytes"
+	"testing"
+
+	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
+)
+
+func TestBLSSignVerify(t *testing.T) {
+	sk, err := BLSKeyGen(bytes.Repeat([]byte{1}, 32))
+	require.NoError(t, err)
+	pk, err := BLSPublicKey(sk)
+	require.NoError(t, err)
+	sig, err := BLSSign(BLSBasic, sk, []byte("root"))
+	require.NoError(t, err)
+
+	ok, err := BLSVerify(BLSBasic, pk, []byte("root"), sig)
+	require.NoError(t, err)
+	assert.True(t, ok)
+	ok, err = BLSVerify(BLSBasic, pk, []byte("rook"), sig)
+	require.NoError(t, err)
+	assert.False(t, ok)
+	ok, err = BLSVerify(BLSAugmented, pk, []byte("root"), sig)
+	require.NoError(t, err)
+	assert.False(t, ok)
+
+	_, err = BLSKeyGen([]byte{1})
+	require.Error(t, err)
+}
+
+func TestBLSAggregate(t *testing.T) {
+	var pks, msgs, sigs [][]byte
+	for i := byte(1); i <= 3; i++ {
+		sk, err := BLSKeyGen(bytes.Repeat([]byte{i}, 32))
+		require.NoError(t, err)
+		pk, err := BLSPublicKey(sk)
+		require.NoError(t, err)
+		msg := []byte{i}
+		sig, err := BLSSign(BLSAugmented, sk, msg)
+		require.NoError(t, err)
+		pks, msgs, sigs = append(pks, pk), append(msgs, msg), append(sigs, sig)
+	}
+	agg, err := BLSAggregateSignatures(sigs...)
+	require.NoError(t, err)
+	ok, err := BLSAggregateVerify(BLSAugmented, pks, msgs, agg)
+	require.NoError(t, err)
+	assert.True(t, ok)
+	ok, err = BLSAggregateVerify(BLSAugmented, pks, [][]byte{msgs[1], msgs[0], msgs[2]}, agg)
+	require.NoError(t, err)
+	assert.False(t, ok)
+
+	_, err = BLSAggregatePublicKeys(pks...)
+	require.NoError(t, err)
+	_, err = BLSAggregateSignatures()
+	var zkErr *Error
+	require.ErrorAs(t, err, &zkErr)
+	assert.Equal(t, CodeMalformedSignature, zkErr.Code)
+}
+
+func TestBLSProofOfPossession(t *testing.T) {
+	var sks, pks, proofs, sigs [][]byte
+	for i := byte(1); i <= 3; i++ {
+		sk, err := BLSKeyGen(bytes.Repeat([]byte{i}, 32))
+		require.NoError(t, err)
+		pk, err := BLSPublicKey(sk)
+		require.NoError(t, err)
+		proof, err := BLSPopProve(sk)
+		require.NoError(t, err)
+		sig, err := BLSSign(BLSProofOfPossession, sk, []byte("root"))
+		require.NoError(t, err)
+		sks, pks, proofs, sigs = append(sks, sk), append(pks, pk), append(proofs, proof), append(sigs, sig)
+	}
+	ok, err := BLSPopVerify(pks[0], proofs[0])
+	require.NoError(t, err)
+	assert.True(t, ok)
+
+	ok, verdicts, err := BLSPopVerifyBatch(pks, proofs)
+	require.NoError(t, err)
+	assert.True(t, ok)
+	assert.Equal(t, []bool{true, true, true}, verdicts)
+	ok, verdicts, err = BLSPopVerifyBatch(pks, [][]byte{proofs[1], proofs[0], proofs[2]})
+	require.NoError(t, err)
+	assert.False(t, ok)
+	assert.Equal(t, []bool{false, false, true}, verdicts)
+
+	agg, err := BLSAggregateSignatures(sigs...)
+	require.NoError(t, err)
+	ok, err = BLSFastAggregateVerify(BLSProofOfPossession, pks, []byte("root"), agg)
+	require.NoError(t, err)
+	assert.True(t, ok)
+	_, err = BLSFastAggregateVerify(BLSBasic, pks, []byte("root"), agg)
+	require.Error(t, err)
+}
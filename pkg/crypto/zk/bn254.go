@@ -0,0 +1,49 @@
+package zk
+
+/*
+#include "./lib/zk.h"
+*/
+import "C"
+
+// BN254Operation is one of the EIP-196/197 precompiles, it's at address 6
+// plus its value. Field elements are 32-byte big-endian, G1 points x | y,
+// G2 points x.c1 | x.c0 | y.c1 | y.c0 and the point at infinity all zeroes.
+// Gas is that of Istanbul.
+type BN254Operation int
+
+// EIP-196/197 precompiles, in address order.
+const (
+	BN254Add BN254Operation = iota
+	BN254Mul
+	BN254Pairing
+)
+
+// EIP-196/197 output sizes.
+const (
+	BN254G1Size      = 64
+	BN254PairingSize = 32
+)
+
+// RequiredGas returns the gas required to run the contract, input needn't
+// be valid.
+func (op BN254Operation) RequiredGas(input []byte) uint64 {
+	return uint64(C.bn254_precompile_gas(C.int(op), bytesPtr(input), C.size_t(len(input))))
+}
+
+// Run runs the contract, malformed input and points not on the curve or,
+// for G2, not in the subgroup are reported as an error.
+func (op BN254Operation) Run(input []byte) ([]byte, error) {
+	size := BN254G1Size
+	if op == BN254Pairing {
+		size = BN254PairingSize
+	}
+	output := make([]byte, size)
+	_, err := call(func() C.int {
+		return C.bn254_precompile(C.int(op), bytesPtr(input), C.size_t(len(input)),
+			bytesPtr(output), C.size_t(len(output)))
+	})
+	if err != nil {
+		return nil, err
+	}
+	return output, nil
+}
@@ -0,0 +1,61 @@
+package zk
+
+import (
+	"errors"
+	"math/big"
+	"math/rand"
+	"testing"
+
+	"github.com/ethereum/go-ethereum/crypto/bn256"
+	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
+)
+
+// TestBN254Precompiles checks the results against go-ethereum's bn256.
+func TestBN254Precompiles(t *testing.T) {
+	r := rand.New(rand.NewSource(7))
+	scalar := func() *big.Int {
+		return new(big.Int).Rand(r, bn256.Order)
+	}
+	for i := 0; i < 8; i++ {
+		a := new(bn256.G1).ScalarBaseMult(scalar())
+		b := new(bn256.G1).ScalarBaseMult(scalar())
+		out, err := BN254Add.Run(append(a.Marshal(), b.Marshal()...))
+		require.NoError(t, err)
+		assert.Equal(t, new(bn256.G1).Add(a, b).Marshal(), out)
+
+		// Scalars aren't reduced.
+		k := new(big.Int).Lsh(scalar(), 2)
+		out, err = BN254Mul.Run(append(a.Marshal(), k.FillBytes(make([]byte, 32))...))
+		require.NoError(t, err)
+		assert.Equal(t, new(bn256.G1).ScalarMult(a, k).Marshal(), out)
+
+		// e(x*G1, y*G2) * e(-x*y*G1, G2) is one.
+		x, y := scalar(), scalar()
+		xy := new(big.Int).Mul(x, y)
+		input := append(new(bn256.G1).ScalarBaseMult(x).Marshal(), new(bn256.G2).ScalarBaseMult(y).Marshal()...)
+		input = append(input, new(bn256.G1).Neg(new(bn256.G1).ScalarBaseMult(xy)).Marshal()...)
+		input = append(input, new(bn256.G2).ScalarBaseMult(big.NewInt(1)).Marshal()...)
+		out, err = BN254Pairing.Run(input)
+		require.NoError(t, err)
+		assert.Equal(t, byte(1), out[31])
+		input[0] ^= 1
+		_, err = BN254Pairing.Run(input)
+		require.Error(t, err)
+		out, err = BN254Pairing.Run(input[192:])
+		require.NoError(t, err)
+		assert.Equal(t, make([]byte, 32), out)
+	}
+
+	assert.Equal(t, uint64(150), BN254Add.RequiredGas(nil))
+	assert.Equal(t, uint64(6000), BN254Mul.RequiredGas(nil))
+	assert.Equal(t, uint64(45000+2*34000), BN254Pairing.RequiredGas(make([]byte, 384)))
+
+	_, err := BN254Pairing.Run(make([]byte, 191))
+	var zkErr *Error
+	require.True(t, errors.As(err, &zkErr))
+	assert.Equal(t, CodeMalformedInputs, zkErr.Code)
+	out, err := BN254Add.Run(nil)
+	require.NoError(t, err)
+	assert.Equal(t, make([]byte, 64), out)
+}
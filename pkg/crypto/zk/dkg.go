@@ -0,0 +1,157 @@
+package zk
+
+/*
+#include "./lib/zk.h"
+*/
+import "C"
+import (
+	"encoding/binary"
+	"errors"
+	"sync"
+)
+
+// Sizes of the fixed-size DKG messages. Dealt shares and justifications are
+// the dealer's big-endian uint32 index followed by the share, complaints the
+// indices of the complainer and of the dealer.
+const (
+	DKGDealtShareSize = 4 + BLSShareSize
+	DKGComplaintSize  = 8
+)
+
+// DKG is a participant in a distributed generation of a threshold BLS key,
+// see zk/src/dkg.rs for the protocol. Deals, complaints and justifications
+// must be broadcast reliably to all participants, dealt shares encrypted to
+// their recipient. Its methods can be used by several goroutines at once
+// until it's freed.
+type DKG struct {
+	lock      sync.Mutex
+	threshold int
+	n         int
+	handle    *C.dkg
+}
+
+// DKGComplaint encodes a complaint of participant complainer against the
+// dealer, to be sent when no share came from it.
+func DKGComplaint(complainer, dealer uint32) []byte {
+	c := make([]byte, DKGComplaintSize)
+	binary.BigEndian.PutUint32(c, complainer)
+	binary.BigEndian.PutUint32(c[4:], dealer)
+	return c
+}
+
+// NewDKG starts a run as participant index (1 to n) of a group where
+// threshold participants can sign.
+func NewDKG(index uint32, threshold, n int) (*DKG, error) {
+	var handle *C.dkg
+	_, err := call(func() C.int {
+		return C.dkg_new(C.uint(index), C.size_t(threshold), C.size_t(n), &handle)
+	})
+	if err != nil {
+		return nil, err
+	}
+	return &DKG{threshold: threshold, n: n, handle: handle}, nil
+}
+
+func (d *DKG) run(f func() C.int) (bool, error) {
+	d.lock.Lock()
+	defer d.lock.Unlock()
+	if d.handle == nil {
+		return false, errors.New("zk: DKG is freed")
+	}
+	return call(f)
+}
+
+// Deal returns the participant's deal to broadcast and the shares to send
+// the other participants, in increasing order of their index.
+func (d *DKG) Deal() ([]byte, [][]byte, error) {
+	deal := make([]byte, 4+d.threshold*BLSPublicKeySize)
+	buf := make([]byte, (d.n-1)*DKGDealtShareSize)
+	_, err := d.run(func() C.int {
+		return C.dkg_deal(d.handle, bytesPtr(deal), C.size_t(len(deal)), bytesPtr(buf), C.size_t(len(buf)))
+	})
+	if err != nil {
+		return nil, nil, err
+	}
+	shares := make([][]byte, d.n-1)
+	for i := range shares {
+		shares[i] = buf[i*DKGDealtShareSize : (i+1)*DKGDealtShareSize]
+	}
+	return deal, shares, nil
+}
+
+// ReceiveDeal records the deal of another participant, it returns false if
+// the deal disqualifies its dealer. Deals must be received before the
+// shares they commit to.
+func (d *DKG) ReceiveDeal(deal []byte) (bool, error) {
+	return d.run(func() C.int {
+		return C.dkg_receive_deal(d.handle, bytesPtr(deal), C.size_t(len(deal)))
+	})
+}
+
+// ReceiveShare checks a share sent to the participant, it returns the
+// complaint to broadcast if the share is wrong and nil otherwise.
+func (d *DKG) ReceiveShare(share []byte) ([]byte, error) {
+	if len(share) != DKGDealtShareSize {
+		return nil, errors.New("zk: dealt share must be 40 bytes")
+	}
+	complaint := make([]byte, DKGComplaintSize)
+	ok, err := d.run(func() C.int {
+		return C.dkg_receive_share(d.handle, bytesPtr(share), bytesPtr(complaint))
+	})
+	if err != nil || ok {
+		return nil, err
+	}
+	return complaint, nil
+}
+
+// ReceiveComplaint records a complaint, it returns the justification to
+// broadcast if the complaint is against the participant and nil otherwise.
+func (d *DKG) ReceiveComplaint(complaint []byte) ([]byte, error) {
+	if len(complaint) != DKGComplaintSize {
+		return nil, errors.New("zk: complaint must be 8 bytes")
+	}
+	justification := make([]byte, DKGDealtShareSize)
+	ok, err := d.run(func() C.int {
+		return C.dkg_receive_complaint(d.handle, bytesPtr(complaint), bytesPtr(justification))
+	})
+	if err != nil || !ok {
+		return nil, err
+	}
+	return justification, nil
+}
+
+// ReceiveJustification settles a complaint with the share revealed by its
+// dealer, it returns false if the share is wrong, which disqualifies the
+// dealer.
+func (d *DKG) ReceiveJustification(justification []byte) (bool, error) {
+	if len(justification) != DKGDealtShareSize {
+		return false, errors.New("zk: justification must be 40 bytes")
+	}
+	return d.run(func() C.int {
+		return C.dkg_receive_justification(d.handle, bytesPtr(justification))
+	})
+}
+
+// Finalize ends the run once complaints had time to be answered, dealers
+// with a pending complaint being disqualified. It returns the participant's
+// share of the group key for BLSThresholdSign and the group commitments,
+// whose first 48 bytes are the group public key.
+func (d *DKG) Finalize() ([]byte, []byte, error) {
+	share := make([]byte, BLSShareSize)
+	commitments := make([]byte, d.threshold*BLSPublicKeySize)
+	_, err := d.run(func() C.int {
+		return C.dkg_finalize(d.handle, bytesPtr(share), bytesPtr(commitments), C.size_t(len(commitments)))
+	})
+	if err != nil {
+		return nil, nil, err
+	}
+	return share, commitments, nil
+}
+
+// Free releases the participant, it's a no-op if it's already freed.
+func (d *DKG) Free() {
+	d.lock.Lock()
+	defer d.lock.Unlock()
+	C.dkg_free(d.handle)
+	d.handle = nil
+}
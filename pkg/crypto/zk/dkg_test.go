@@ -0,0 +1,84 @@
+package zk
+
+import (
+	"testing"
+
+	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
+)
+
+func TestDKG(t *testing.T) {
+	const threshold, n = 2, 3
+	var ps []*DKG
+	for i := uint32(1); i <= n; i++ {
+		p, err := NewDKG(i, threshold, n)
+		require.NoError(t, err)
+		defer p.Free()
+		ps = append(ps, p)
+	}
+	deals := make([][]byte, n)
+	shares := make([][][]byte, n)
+	for i, p := range ps {
+		var err error
+		deals[i], shares[i], err = p.Deal()
+		require.NoError(t, err)
+	}
+	for i, p := range ps {
+		for j := range ps {
+			if i == j {
+				continue
+			}
+			ok, err := p.ReceiveDeal(deals[j])
+			require.NoError(t, err)
+			require.True(t, ok)
+			// Dealer j's shares skip j itself.
+			k := i
+			if i > j {
+				k--
+			}
+			complaint, err := p.ReceiveShare(shares[j][k])
+			require.NoError(t, err)
+			require.Nil(t, complaint)
+		}
+	}
+
+	// A groundless complaint is answered and dismissed.
+	justification, err := ps[1].ReceiveComplaint(DKGComplaint(1, 2))
+	require.NoError(t, err)
+	require.NotNil(t, justification)
+	j, err := ps[2].ReceiveComplaint(DKGComplaint(1, 2))
+	require.NoError(t, err)
+	require.Nil(t, j)
+	ok, err := ps[2].ReceiveJustification(justification)
+	require.NoError(t, err)
+	assert.True(t, ok)
+
+	var keys [][]byte
+	var commitments []byte
+	for _, p := range ps {
+		share, c, err := p.Finalize()
+		require.NoError(t, err)
+		ok, err := BLSThresholdVerifyShare(c, share)
+		require.NoError(t, err)
+		assert.True(t, ok)
+		if commitments != nil {
+			assert.Equal(t, commitments, c)
+		}
+		commitments = c
+		keys = append(keys, share)
+	}
+
+	groupPk := commitments[:BLSPublicKeySize]
+	msg := []byte("state root")
+	var partials [][]byte
+	for _, k := range keys[1:] {
+		p, err := BLSThresholdSign(BLSBasic, k, groupPk, msg)
+		require.NoError(t, err)
+		partials = append(partials, p)
+	}
+	sig, err := BLSThresholdCombine(partials, threshold)
+	require.NoError(t, err)
+	ok, err = BLSVerify(BLSBasic, groupPk, msg, sig)
+	require.NoError(t, err)
+	assert.True(t, ok)
+}
@@ -0,0 +1,61 @@
+package zk
+
+/*
+#include "./lib/zk.h"
+*/
+import "C"
+
+// HashToG1 hashes msg to BLS12-381 G1 with the RFC 9380 suite
+// BLS12381G1_XMD:SHA-256_SSWU_RO_ under a non-empty domain separation tag.
+// The point is uncompressed, x | y as 48-byte big-endian coordinates.
+func HashToG1(msg, dst []byte) ([]byte, error) {
+	point := make([]byte, 96)
+	_, err := call(func() C.int {
+		return C.hash_to_curve_g1(bytesPtr(msg), C.size_t(len(msg)), bytesPtr(dst), C.size_t(len(dst)), bytesPtr(point))
+	})
+	if err != nil {
+		return nil, err
+	}
+	return point, nil
+}
+
+// HashToG2 hashes msg to BLS12-381 G2 with the RFC 9380 suite
+// BLS12381G2_XMD:SHA-256_SSWU_RO_ under a non-empty domain separation tag.
+// The point is uncompressed, x | y with coordinates as c1 | c0.
+func HashToG2(msg, dst []byte) ([]byte, error) {
+	point := make([]byte, 192)
+	_, err := call(func() C.int {
+		return C.hash_to_curve_g2(bytesPtr(msg), C.size_t(len(msg)), bytesPtr(dst), C.size_t(len(dst)), bytesPtr(point))
+	})
+	if err != nil {
+		return nil, err
+	}
+	return point, nil
+}
+
+// HashToScalar hashes msg to a BLS12-381 scalar with RFC 9380's
+// hash_to_field and expand_message_xmd over SHA-256, returning it as 32
+// big-endian bytes.
+func HashToScalar(msg, dst []byte) ([]byte, error) {
+	scalar := make([]byte, 32)
+	_, err := call(func() C.int {
+		return C.hash_to_field_scalar(bytesPtr(msg), C.size_t(len(msg)), bytesPtr(dst), C.size_t(len(dst)), bytesPtr(scalar))
+	})
+	if err != nil {
+		return nil, err
+	}
+	return scalar, nil
+}
+
+// ExpandMessageXMD returns n uniform bytes (1 to 8160) derived from msg with
+// RFC 9380's expand_message_xmd over SHA-256.
+func ExpandMessageXMD(msg, dst []byte, n int) ([]byte, error) {
+	out := make([]byte, n)
+	_, err := call(func() C.int {
+		return C.expand_message(bytesPtr(msg), C.size_t(len(msg)), bytesPtr(dst), C.size_t(len(dst)), bytesPtr(out), C.size_t(len(out)))
+	})
+	if err != nil {
+		return nil, err
+	}
+	return out, nil
+}
@@ -0,0 +1,27 @@
+package zk
+
+import (
+	"encoding/hex"
+	"testing"
+
+	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
+)
+
+func TestHashToCurve(t *testing.T) {
+	// RFC 9380 appendix J.9.1 and K.1.
+	p, err := HashToG1([]byte("abc"), []byte("QUUX-V01-CS02-with-BLS12381G1_XMD:SHA-256_SSWU_RO_"))
+	require.NoError(t, err)
+	assert.Equal(t, "03567bc5ef9c690c2ab2ecdf6a96ef1c139cc0b2f284dca0a9a7943388a49a3aee664ba5379a7655d3c68900be2f69030b9c15f3fe6e5cf4211f346271d7b01c8f3b28be689c8429c85b67af215533311f0b8dfaaa154fa6b88176c229f2885d", hex.EncodeToString(p))
+	out, err := ExpandMessageXMD([]byte("abc"), []byte("QUUX-V01-CS02-with-expander-SHA256-128"), 32)
+	require.NoError(t, err)
+	assert.Equal(t, "d8ccab23b5985ccea865c6c97b6e5b8350e794e603b4b97902f53a8a0d605615", hex.EncodeToString(out))
+
+	q, err := HashToG2([]byte("abc"), []byte("QUUX-V01-CS02-with-BLS12381G2_XMD:SHA-256_SSWU_RO_"))
+	require.NoError(t, err)
+	assert.Equal(t, "139cddbccdc5e91b9623efd38c49f81a6f83f175e80b06fc374de9eb4b41dfe4", hex.EncodeToString(q[:32]))
+	_, err = HashToScalar([]byte("abc"), []byte("tag"))
+	require.NoError(t, err)
+	_, err = HashToG1([]byte("abc"), nil)
+	require.Error(t, err)
+}
@@ -0,0 +1,248 @@
+package zk
+
+/*
+#include "./lib/zk.h"
+*/
+import "C"
+import (
+	"errors"
+	"os"
+	"sync"
+)
+
+// KZG commitment and precompile sizes, commitments and proofs are
+// compressed G1 points of BLS12-381.
+const (
+	KZGCommitmentSize         = 48
+	KZGProofSize              = 48
+	PointEvaluationInputSize  = 192
+	PointEvaluationOutputSize = 64
+)
+
+// LoadTrustedSetup loads the KZG trusted setup used by PointEvaluation from
+// a file in the c-kzg text format, such as zk/data/trusted_setup.txt which
+// holds the Ethereum mainnet one. It replaces any setup loaded before.
+func LoadTrustedSetup(path string) error {
+	data, err := os.ReadFile(path)
+	if err != nil {
+		return err
+	}
+	_, err = call(func() C.int {
+		return C.kzg_load_trusted_setup(bytesPtr(data), C.size_t(len(data)))
+	})
+	return err
+}
+
+// KZGVersionedHash returns the versioned hash of a commitment, as found in
+// blob transactions.
+func KZGVersionedHash(commitment []byte) ([]byte, error) {
+	if len(commitment) != KZGCommitmentSize {
+		return nil, errors.New("zk: KZG commitment must be 48 bytes")
+	}
+	hash := make([]byte, 32)
+	_, err := call(func() C.int {
+		return C.kzg_versioned_hash(bytesPtr(commitment), bytesPtr(hash))
+	})
+	if err != nil {
+		return nil, err
+	}
+	return hash, nil
+}
+
+// PointEvaluation is the point evaluation precompiled contract of
+// EIP-4844. Its 192-byte input is
+//
+//	versioned_hash | z | y | commitment | proof
+//
+// and it succeeds if the commitment, whose versioned hash is given, opens
+// to y at z, returning FIELD_ELEMENTS_PER_BLOB and BLS_MODULUS as 32-byte
+// words. A trusted setup must be loaded with LoadTrustedSetup.
+type PointEvaluation struct{}
+
+// RequiredGas returns the gas required to run the contract, fixed.
+func (c *PointEvaluation) RequiredGas(input []byte) uint64 {
+	return uint64(C.kzg_point_evaluation_gas())
+}
+
+// Run checks the opening in input, an invalid proof or malformed input is
+// reported as an error.
+func (c *PointEvaluation) Run(input []byte) ([]byte, error) {
+	output := make([]byte, PointEvaluationOutputSize)
+	ok, err := call(func() C.int {
+		return C.kzg_point_evaluation(bytesPtr(input), C.size_t(len(input)), bytesPtr(output))
+	})
+	if err != nil {
+		return nil, err
+	}
+	if !ok {
+		return nil, errors.New("zk: invalid KZG proof")
+	}
+	return output, nil
+}
+
+// KZGSrs is a structured reference string of powers of a secret in G1 and
+// G2 kept by the library, for KZG commitments to arbitrary polynomials.
+// Field elements are 32-byte big-endian, polynomials concatenated
+// coefficients from the constant one up. Its methods can be used by several
+// goroutines at once until it's freed.
+type KZGSrs struct {
+	lock   sync.RWMutex
+	size   int
+	handle *C.kzg_srs
+}
+
+// NewKZGSrs decodes an SRS in the format of Srs::save in zk/src/kzg.rs: the
+// big-endian uint32 numbers of G1 and G2 powers followed by the compressed
+// powers.
+func NewKZGSrs(data []byte) (*KZGSrs, error) {
+	var handle *C.kzg_srs
+	_, err := call(func() C.int {
+		return C.kzg_srs_new(bytesPtr(data), C.size_t(len(data)), &handle)
+	})
+	if err != nil {
+		return nil, err
+	}
+	return &KZGSrs{size: len(data), handle: handle}, nil
+}
+
+// GenerateKZGSrs generates an SRS of g1Size G1 and g2Size G2 powers,
+// drawing the secret from seed if it's not empty and from the OS otherwise.
+// Whoever generates an SRS can open commitments to anything, a seeded one
+// is only fit for tests and devnets.
+func GenerateKZGSrs(g1Size, g2Size int, seed []byte) (*KZGSrs, error) {
+	var handle *C.kzg_srs
+	_, err := call(func() C.int {
+		return C.kzg_srs_generate(C.size_t(g1Size), C.size_t(g2Size), bytesPtr(seed), C.size_t(len(seed)), &handle)
+	})
+	if err != nil {
+		return nil, err
+	}
+	return &KZGSrs{size: 8 + g1Size*48 + g2Size*96, handle: handle}, nil
+}
+
+// LoadKZGSrs reads an SRS from a file, see NewKZGSrs.
+func LoadKZGSrs(path string) (*KZGSrs, error) {
+	data, err := os.ReadFile(path)
+	if err != nil {
+		return nil, err
+	}
+	return NewKZGSrs(data)
+}
+
+// Bytes returns the SRS in the format NewKZGSrs reads.
+func (s *KZGSrs) Bytes() ([]byte, error) {
+	data := make([]byte, s.size)
+	_, err := s.run(func() C.int {
+		return C.kzg_srs_write(s.handle, bytesPtr(data), C.size_t(len(data)))
+	})
+	if err != nil {
+		return nil, err
+	}
+	return data, nil
+}
+
+// Save writes the SRS to a file, see Bytes.
+func (s *KZGSrs) Save(path string) error {
+	data, err := s.Bytes()
+	if err != nil {
+		return err
+	}
+	return os.WriteFile(path, data, 0o644)
+}
+
+func (s *KZGSrs) run(f func() C.int) (bool, error) {
+	s.lock.RLock()
+	defer s.lock.RUnlock()
+	if s.handle == nil {
+		return false, errors.New("zk: KZG SRS is freed")
+	}
+	return call(f)
+}
+
+// Commit commits to the polynomial with the given coefficients.
+func (s *KZGSrs) Commit(coefficients []byte) ([]byte, error) {
+	c := make([]byte, KZGCommitmentSize)
+	_, err := s.run(func() C.int {
+		return C.kzg_commit(s.handle, bytesPtr(coefficients), C.size_t(len(coefficients)), bytesPtr(c))
+	})
+	if err != nil {
+		return nil, err
+	}
+	return c, nil
+}
+
+// CommitEvaluations commits to the polynomial taking the given values at
+// the roots of unity of their number, a power of two, in natural order.
+func (s *KZGSrs) CommitEvaluations(evaluations []byte) ([]byte, error) {
+	c := make([]byte, KZGCommitmentSize)
+	_, err := s.run(func() C.int {
+		return C.kzg_commit_evaluations(s.handle, bytesPtr(evaluations), C.size_t(len(evaluations)), bytesPtr(c))
+	})
+	if err != nil {
+		return nil, err
+	}
+	return c, nil
+}
+
+// Open opens the polynomial with the given coefficients at z and returns
+// its value there and the proof.
+func (s *KZGSrs) Open(coefficients, z []byte) ([]byte, []byte, error) {
+	if len(z) != 32 {
+		return nil, nil, errors.New("zk: field element must be 32 bytes")
+	}
+	y := make([]byte, 32)
+	proof := make([]byte, KZGProofSize)
+	_, err := s.run(func() C.int {
+		return C.kzg_open(s.handle, bytesPtr(coefficients), C.size_t(len(coefficients)), bytesPtr(z), bytesPtr(y), bytesPtr(proof))
+	})
+	if err != nil {
+		return nil, nil, err
+	}
+	return y, proof, nil
+}
+
+// Verify checks that a commitment opens to y at z. The error is only set
+// for malformed arguments, an invalid proof yields false.
+func (s *KZGSrs) Verify(commitment, z, y, proof []byte) (bool, error) {
+	if len(commitment) != KZGCommitmentSize || len(z) != 32 || len(y) != 32 || len(proof) != KZGProofSize {
+		return false, errors.New("zk: wrong KZG commitment, field element or proof size")
+	}
+	return s.run(func() C.int {
+		return C.kzg_verify(s.handle, bytesPtr(commitment), bytesPtr(z), bytesPtr(y), bytesPtr(proof))
+	})
+}
+
+// OpenMulti opens the polynomial with the given coefficients at the
+// concatenated distinct points with a single proof, and returns its
+// concatenated values there and the proof. An SRS of n G2 powers opens at
+// up to n-1 points.
+func (s *KZGSrs) OpenMulti(coefficients, points []byte) ([]byte, []byte, error) {
+	values := make([]byte, len(points))
+	proof := make([]byte, KZGProofSize)
+	_, err := s.run(func() C.int {
+		return C.kzg_open_multi(s.handle, bytesPtr(coefficients), C.size_t(len(coefficients)), bytesPtr(points), C.size_t(len(points)), bytesPtr(values), C.size_t(len(values)), bytesPtr(proof))
+	})
+	if err != nil {
+		return nil, nil, err
+	}
+	return values, proof, nil
+}
+
+// VerifyMulti checks that a commitment opens to the concatenated values at
+// the concatenated points.
+func (s *KZGSrs) VerifyMulti(commitment, points, values, proof []byte) (bool, error) {
+	if len(commitment) != KZGCommitmentSize || len(proof) != KZGProofSize {
+		return false, errors.New("zk: wrong KZG commitment or proof size")
+	}
+	return s.run(func() C.int {
+		return C.kzg_verify_multi(s.handle, bytesPtr(commitment), bytesPtr(points), C.size_t(len(points)), bytesPtr(values), C.size_t(len(values)), bytesPtr(proof))
+	})
+}
+
+// Free releases the SRS, it's a no-op if it's already freed.
+func (s *KZGSrs) Free() {
+	s.lock.Lock()
+	defer s.lock.Unlock()
+	C.kzg_srs_free(s.handle)
+	s.handle = nil
+}
@@ -0,0 +1,83 @@
+package zk
+
+import (
+	"encoding/hex"
+	"path/filepath"
+	"testing"
+
+	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
+)
+
+func TestPointEvaluation(t *testing.T) {
+	require.NoError(t, LoadTrustedSetup("../../../zk/data/trusted_setup.txt"))
+	input, err := hex.DecodeString("01e798154708fe7789429634053cbf9f99b619f9f084048927333fce637f549b564c0a11a0f704f4fc3e8acfe0f8245f0ad1347b378fbf96e206da11a5d3630624d25032e67a7e6a4910df5834b8fe70e6bcfeeac0352434196bdf4b2485d5a18f59a8d2a1a625a17f3fea0fe5eb8c896db3764f3185481bc22f91b4aaffcca25f26936857bc3a7c2539ea8ec3a952b7873033e038326e87ed3e1276fd140253fa08e9fc25fb2d9a98527fc22a2c9612fbeafdad446cbc7bcdbdcd780af2c16a")
+	require.NoError(t, err)
+
+	c := &PointEvaluation{}
+	assert.Equal(t, uint64(50000), c.RequiredGas(input))
+	out, err := c.Run(input)
+	require.NoError(t, err)
+	assert.Equal(t, "0000000000000000000000000000000000000000000000000000000000001000"+
+		"73eda753299d7d483339d80809a1d80553bda402fffe5bfeffffffff00000001", hex.EncodeToString(out))
+
+	hash, err := KZGVersionedHash(input[96:144])
+	require.NoError(t, err)
+	assert.Equal(t, input[:32], hash)
+
+	input[95] ^= 1
+	_, err = c.Run(input)
+	require.Error(t, err)
+	_, err = c.Run(input[1:])
+	require.Error(t, err)
+}
+
+func fieldElements(vals ...byte) []byte {
+	var b []byte
+	for _, v := range vals {
+		e := make([]byte, 32)
+		e[31] = v
+		b = append(b, e...)
+	}
+	return b
+}
+
+func TestKZGSrs(t *testing.T) {
+	srs, err := GenerateKZGSrs(16, 4, []byte("kzg tests"))
+	require.NoError(t, err)
+	defer srs.Free()
+	path := filepath.Join(t.TempDir(), "srs")
+	require.NoError(t, srs.Save(path))
+	loaded, err := LoadKZGSrs(path)
+	require.NoError(t, err)
+	defer loaded.Free()
+
+	// p = 5 + X^2 + 3X^3
+	coefficients := fieldElements(5, 0, 1, 3)
+	c, err := srs.Commit(coefficients)
+	require.NoError(t, err)
+	c2, err := loaded.Commit(coefficients)
+	require.NoError(t, err)
+	assert.Equal(t, c, c2)
+
+	z := fieldElements(2)
+	y, proof, err := srs.Open(coefficients, z)
+	require.NoError(t, err)
+	assert.Equal(t, fieldElements(5+4+24), y)
+	ok, err := srs.Verify(c, z, y, proof)
+	require.NoError(t, err)
+	assert.True(t, ok)
+	ok, err = srs.Verify(c, z, fieldElements(1), proof)
+	require.NoError(t, err)
+	assert.False(t, ok)
+
+	points := fieldElements(1, 2, 3)
+	values, proof, err := srs.OpenMulti(coefficients, points)
+	require.NoError(t, err)
+	assert.Equal(t, fieldElements(9, 33, 95), values)
+	ok, err = srs.VerifyMulti(c, points, values, proof)
+	require.NoError(t, err)
+	assert.True(t, ok)
+	_, _, err = srs.OpenMulti(coefficients, fieldElements(1, 2, 3, 4))
+	require.Error(t, err)
+}
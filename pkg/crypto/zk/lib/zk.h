@@ -1 +1,98 @@
-int verify(unsigned char *proof, unsigned int proof_len, unsigned char *key, unsigned int key_len);
+#include <stddef.h>
+
+int verify(unsigned char *proof, size_t proof_len, unsigned char *key, size_t key_len);
+int verify_with_inputs(unsigned char *proof, size_t proof_len, unsigned char *key, size_t key_len, unsigned char *inputs, size_t inputs_len);
+int verify_on_curve(int curve, unsigned char *proof, size_t proof_len, unsigned char *key, size_t key_len, unsigned char *inputs, size_t inputs_len);
+int verify_with_system(int system, int curve, unsigned char *proof, size_t proof_len, unsigned char *key, size_t key_len, unsigned char *inputs, size_t inputs_len);
+int verify_snarkjs(unsigned char *proof, size_t proof_len, unsigned char *key, size_t key_len, unsigned char *inputs, size_t inputs_len);
+int last_error(unsigned char *buf, size_t buf_len);
+
+typedef struct prepared_key prepared_key;
+int prepare_key(int curve, unsigned char *key, size_t key_len, prepared_key **handle);
+int verify_prepared(const prepared_key *handle, unsigned char *proof, size_t proof_len, unsigned char *inputs, size_t inputs_len);
+void free_prepared_key(prepared_key *handle);
+int verify_batch(const prepared_key *handle, unsigned char *proofs, size_t proofs_len, unsigned char *inputs, size_t inputs_len, unsigned char *results, size_t results_len);
+
+int register_key(int curve, unsigned char *key, size_t key_len, unsigned char *id);
+int unregister_key(unsigned char *id);
+int groth16_precompile(unsigned char *input, size_t input_len, unsigned char *output);
+unsigned long long groth16_precompile_gas(unsigned char *input, size_t input_len);
+unsigned long long required_gas(int curve, unsigned long long inputs, unsigned long long batch);
+int prove(int circuit, unsigned char *params, size_t params_len, unsigned char *witness, size_t witness_len, unsigned char *proof, size_t proof_len);
+int poseidon(int curve, unsigned char *inputs, size_t inputs_len, unsigned char *output);
+int mimc7_hash(int curve, unsigned char *inputs, size_t inputs_len, unsigned char *key, unsigned char *output);
+int mimc_sponge(int curve, unsigned char *inputs, size_t inputs_len, unsigned char *key, unsigned char *outputs, size_t outputs_len);
+
+typedef struct merkle_tree merkle_tree;
+int merkle_tree_new(int curve, size_t depth, merkle_tree **handle);
+int merkle_tree_append(merkle_tree *handle, unsigned char *leaf, unsigned long long *index);
+int merkle_tree_root(merkle_tree *handle, unsigned char *root);
+int merkle_tree_path(merkle_tree *handle, unsigned long long index, unsigned char *path, size_t path_len);
+void merkle_tree_free(merkle_tree *handle);
+
+int note_new(int curve, unsigned char *note);
+int note_commitment(int curve, unsigned char *note, unsigned char *commitment);
+int note_nullifier_hash(int curve, unsigned char *note, unsigned char *nullifier_hash);
+int external_data_hash(int curve, unsigned char *data, size_t data_len, unsigned char *hash);
+int spend_witness_new(unsigned char *note, unsigned char *path, size_t path_len, unsigned char *external, unsigned char *witness, size_t witness_len);
+
+int bls_keygen(unsigned char *ikm, size_t ikm_len, unsigned char *secret_key);
+int bls_public_key(unsigned char *secret_key, unsigned char *public_key);
+int bls_sign(int scheme, unsigned char *secret_key, unsigned char *msg, size_t msg_len, unsigned char *signature);
+int bls_verify(int scheme, unsigned char *public_key, unsigned char *msg, size_t msg_len, unsigned char *signature);
+int bls_aggregate_signatures(unsigned char *signatures, size_t signatures_len, unsigned char *aggregate);
+int bls_aggregate_public_keys(unsigned char *public_keys, size_t public_keys_len, unsigned char *aggregate);
+int bls_aggregate_verify(int scheme, unsigned char *public_keys, size_t public_keys_len, unsigned char *msgs, const size_t *msg_lens, unsigned char *signature);
+
+int expand_message(unsigned char *msg, size_t msg_len, unsigned char *dst, size_t dst_len, unsigned char *out, size_t out_len);
+int hash_to_curve_g1(unsigned char *msg, size_t msg_len, unsigned char *dst, size_t dst_len, unsigned char *point);
+int hash_to_curve_g2(unsigned char *msg, size_t msg_len, unsigned char *dst, size_t dst_len, unsigned char *point);
+int hash_to_field_scalar(unsigned char *msg, size_t msg_len, unsigned char *dst, size_t dst_len, unsigned char *scalar);
+int bls_pop_prove(unsigned char *secret_key, unsigned char *proof);
+int bls_pop_verify(unsigned char *public_key, unsigned char *proof);
+int bls_pop_verify_batch(unsigned char *public_keys, size_t public_keys_len, unsigned char *proofs, size_t proofs_len, unsigned char *results, size_t results_len);
+int bls_fast_aggregate_verify(int scheme, unsigned char *public_keys, size_t public_keys_len, unsigned char *msg, size_t msg_len, unsigned char *signature);
+
+int bls_threshold_split(unsigned char *secret_key, size_t threshold, size_t n, unsigned char *shares, size_t shares_len, unsigned char *commitments, size_t commitments_len);
+int bls_threshold_share_public_key(unsigned char *commitments, size_t commitments_len, unsigned int index, unsigned char *public_key);
+int bls_threshold_verify_share(unsigned char *commitments, size_t commitments_len, unsigned char *share);
+int bls_threshold_sign(int scheme, unsigned char *share, unsigned char *group_public_key, unsigned char *msg, size_t msg_len, unsigned char *partial);
+int bls_threshold_verify_partial(int scheme, unsigned char *commitments, size_t commitments_len, unsigned char *msg, size_t msg_len, unsigned char *partial);
+int bls_threshold_combine(unsigned char *partials, size_t partials_len, size_t threshold, unsigned char *signature);
+
+typedef struct dkg dkg;
+int dkg_new(unsigned int index, size_t threshold, size_t n, dkg **handle);
+int dkg_deal(dkg *handle, unsigned char *deal, size_t deal_len, unsigned char *shares, size_t shares_len);
+int dkg_receive_deal(dkg *handle, unsigned char *deal, size_t deal_len);
+int dkg_receive_share(dkg *handle, unsigned char *share, unsigned char *complaint);
+int dkg_receive_complaint(dkg *handle, unsigned char *complaint, unsigned char *justification);
+int dkg_receive_justification(dkg *handle, unsigned char *justification);
+int dkg_finalize(dkg *handle, unsigned char *share, unsigned char *commitments, size_t commitments_len);
+void dkg_free(dkg *handle);
+
+int vrf_prove(unsigned char *secret_key, unsigned char *alpha, size_t alpha_len, unsigned char *proof);
+int vrf_verify(unsigned char *public_key, unsigned char *alpha, size_t alpha_len, unsigned char *proof, unsigned char *output);
+int vrf_proof_to_hash(unsigned char *proof, unsigned char *output);
+
+int kzg_load_trusted_setup(unsigned char *data, size_t data_len);
+int kzg_versioned_hash(unsigned char *commitment, unsigned char *hash);
+int kzg_point_evaluation(unsigned char *input, size_t input_len, unsigned char *output);
+unsigned long long kzg_point_evaluation_gas();
+
+typedef struct kzg_srs kzg_srs;
+int kzg_srs_new(unsigned char *data, size_t data_len, kzg_srs **handle);
+int kzg_srs_generate(size_t g1_size, size_t g2_size, unsigned char *seed, size_t seed_len, kzg_srs **handle);
+int kzg_srs_write(kzg_srs *handle, unsigned char *out, size_t out_len);
+void kzg_srs_free(kzg_srs *handle);
+int kzg_commit(kzg_srs *handle, unsigned char *coefficients, size_t coefficients_len, unsigned char *commitment);
+int kzg_commit_evaluations(kzg_srs *handle, unsigned char *evaluations, size_t evaluations_len, unsigned char *commitment);
+int kzg_open(kzg_srs *handle, unsigned char *coefficients, size_t coefficients_len, unsigned char *z, unsigned char *y, unsigned char *proof);
+int kzg_verify(kzg_srs *handle, unsigned char *commitment, unsigned char *z, unsigned char *y, unsigned char *proof);
+int kzg_open_multi(kzg_srs *handle, unsigned char *coefficients, size_t coefficients_len, unsigned char *points, size_t points_len, unsigned char *values, size_t values_len, unsigned char *proof);
+int kzg_verify_multi(kzg_srs *handle, unsigned char *commitment, unsigned char *points, size_t points_len, unsigned char *values, size_t values_len, unsigned char *proof);
+
+int bls12381_precompile(int op, unsigned char *input, size_t input_len, unsigned char *output, size_t output_len);
+unsigned long long bls12381_precompile_gas(int op, unsigned char *input, size_t input_len);
+
+int bn254_precompile(int op, unsigned char *input, size_t input_len, unsigned char *output, size_t output_len);
+unsigned long long bn254_precompile_gas(int op, unsigned char *input, size_t input_len);
@@ -0,0 +1,109 @@
+package zk
+
+/*
+#include "./lib/zk.h"
+*/
+import "C"
+import (
+	"errors"
+)
+
+// Sizes of threshold BLS shares and partial signatures, both prefixed with
+// the 1-based big-endian uint32 index of the share.
+const (
+	BLSShareSize            = 4 + BLSSecretKeySize
+	BLSPartialSignatureSize = 4 + BLSSignatureSize
+)
+
+// BLSThresholdSplit splits a secret key into n shares, any threshold of
+// which can sign for it. It returns the shares, share i having index i+1,
+// and the threshold Feldman commitments to the sharing polynomial,
+// concatenated, the first 48 bytes being the group public key.
+func BLSThresholdSplit(sk []byte, threshold, n int) ([][]byte, []byte, error) {
+	if len(sk) != BLSSecretKeySize {
+		return nil, nil, errors.New("zk: BLS secret key must be 32 bytes")
+	}
+	if threshold <= 0 || threshold > n {
+		return nil, nil, errors.New("zk: threshold must be 1 to the number of shares")
+	}
+	buf := make([]byte, n*BLSShareSize)
+	commitments := make([]byte, threshold*BLSPublicKeySize)
+	_, err := call(func() C.int {
+		return C.bls_threshold_split(bytesPtr(sk), C.size_t(threshold), C.size_t(n), bytesPtr(buf), C.size_t(len(buf)), bytesPtr(commitments), C.size_t(len(commitments)))
+	})
+	if err != nil {
+		return nil, nil, err
+	}
+	shares := make([][]byte, n)
+	for i := range shares {
+		shares[i] = buf[i*BLSShareSize : (i+1)*BLSShareSize]
+	}
+	return shares, commitments, nil
+}
+
+// BLSThresholdSharePublicKey returns the public key of the share with the
+// given index.
+func BLSThresholdSharePublicKey(commitments []byte, index uint32) ([]byte, error) {
+	pk := make([]byte, BLSPublicKeySize)
+	_, err := call(func() C.int {
+		return C.bls_threshold_share_public_key(bytesPtr(commitments), C.size_t(len(commitments)), C.uint(index), bytesPtr(pk))
+	})
+	if err != nil {
+		return nil, err
+	}
+	return pk, nil
+}
+
+// BLSThresholdVerifyShare checks a share against the commitments of its
+// dealer.
+func BLSThresholdVerifyShare(commitments, share []byte) (bool, error) {
+	if len(share) != BLSShareSize {
+		return false, errors.New("zk: BLS share must be 36 bytes")
+	}
+	return call(func() C.int {
+		return C.bls_threshold_verify_share(bytesPtr(commitments), C.size_t(len(commitments)), bytesPtr(share))
+	})
+}
+
+// BLSThresholdSign signs msg with a share for the group with public key
+// groupPk and returns the partial signature.
+func BLSThresholdSign(scheme BLSScheme, share, groupPk, msg []byte) ([]byte, error) {
+	if len(share) != BLSShareSize || len(groupPk) != BLSPublicKeySize {
+		return nil, errors.New("zk: wrong BLS share or public key size")
+	}
+	partial := make([]byte, BLSPartialSignatureSize)
+	_, err := call(func() C.int {
+		return C.bls_threshold_sign(C.int(scheme), bytesPtr(share), bytesPtr(groupPk), bytesPtr(msg), C.size_t(len(msg)), bytesPtr(partial))
+	})
+	if err != nil {
+		return nil, err
+	}
+	return partial, nil
+}
+
+// BLSThresholdVerifyPartial checks a partial signature of msg against the
+// commitments of the group.
+func BLSThresholdVerifyPartial(scheme BLSScheme, commitments, msg, partial []byte) (bool, error) {
+	if len(partial) != BLSPartialSignatureSize {
+		return false, errors.New("zk: BLS partial signature must be 100 bytes")
+	}
+	return call(func() C.int {
+		return C.bls_threshold_verify_partial(C.int(scheme), bytesPtr(commitments), C.size_t(len(commitments)), bytesPtr(msg), C.size_t(len(msg)), bytesPtr(partial))
+	})
+}
+
+// BLSThresholdCombine recovers the group signature, checked with BLSVerify
+// against the group public key, from the first threshold partial
+// signatures, which must come from distinct shares. Partial signatures
+// aren't checked, a wrong one yields a group signature that doesn't verify.
+func BLSThresholdCombine(partials [][]byte, threshold int) ([]byte, error) {
+	buf := concat(partials)
+	sig := make([]byte, BLSSignatureSize)
+	_, err := call(func() C.int {
+		return C.bls_threshold_combine(bytesPtr(buf), C.size_t(len(buf)), C.size_t(threshold), bytesPtr(sig))
+	})
+	if err != nil {
+		return nil, err
+	}
+	return sig, nil
+}
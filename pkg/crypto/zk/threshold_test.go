@@ -0,0 +1,49 @@
+package zk
+
+import (
+	"bytes"
+	"testing"
+
+	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
+)
+
+func TestBLSThreshold(t *testing.T) {
+	sk, err := BLSKeyGen(bytes.Repeat([]byte{7}, 32))
+	require.NoError(t, err)
+	shares, commitments, err := BLSThresholdSplit(sk, 3, 5)
+	require.NoError(t, err)
+	require.Len(t, shares, 5)
+	groupPk := commitments[:BLSPublicKeySize]
+	pk, err := BLSPublicKey(sk)
+	require.NoError(t, err)
+	assert.Equal(t, pk, groupPk)
+
+	msg := []byte("state root")
+	var partials [][]byte
+	for _, s := range shares {
+		ok, err := BLSThresholdVerifyShare(commitments, s)
+		require.NoError(t, err)
+		assert.True(t, ok)
+		p, err := BLSThresholdSign(BLSBasic, s, groupPk, msg)
+		require.NoError(t, err)
+		ok, err = BLSThresholdVerifyPartial(BLSBasic, commitments, msg, p)
+		require.NoError(t, err)
+		assert.True(t, ok)
+		partials = append(partials, p)
+	}
+
+	sig, err := BLSThresholdCombine([][]byte{partials[4], partials[1], partials[2]}, 3)
+	require.NoError(t, err)
+	ok, err := BLSVerify(BLSBasic, groupPk, msg, sig)
+	require.NoError(t, err)
+	assert.True(t, ok)
+	expected, err := BLSSign(BLSBasic, sk, msg)
+	require.NoError(t, err)
+	assert.Equal(t, expected, sig)
+
+	_, err = BLSThresholdCombine(partials[:2], 3)
+	var zkErr *Error
+	require.ErrorAs(t, err, &zkErr)
+	assert.Equal(t, CodeInputCountMismatch, zkErr.Code)
+}
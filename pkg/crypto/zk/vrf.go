@@ -0,0 +1,64 @@
+package zk
+
+/*
+#include "./lib/zk.h"
+*/
+import "C"
+import (
+	"errors"
+)
+
+// VRF proof and output sizes. Keys are BLS keys, see BLSKeyGen.
+const (
+	VRFProofSize  = BLSSignatureSize
+	VRFOutputSize = 32
+)
+
+// VRFProve returns the proof of alpha by a secret key, from which anyone
+// with the public key gets the output with VRFVerify. A key has a single
+// proof of an input, so the output can't be chosen by the prover.
+func VRFProve(sk, alpha []byte) ([]byte, error) {
+	if len(sk) != BLSSecretKeySize {
+		return nil, errors.New("zk: BLS secret key must be 32 bytes")
+	}
+	proof := make([]byte, VRFProofSize)
+	_, err := call(func() C.int {
+		return C.vrf_prove(bytesPtr(sk), bytesPtr(alpha), C.size_t(len(alpha)), bytesPtr(proof))
+	})
+	if err != nil {
+		return nil, err
+	}
+	return proof, nil
+}
+
+// VRFVerify checks the proof of alpha by pk and returns its output, nil if
+// the proof is invalid. The error is only set for malformed arguments.
+func VRFVerify(pk, alpha, proof []byte) ([]byte, error) {
+	if len(pk) != BLSPublicKeySize || len(proof) != VRFProofSize {
+		return nil, errors.New("zk: wrong BLS public key or VRF proof size")
+	}
+	output := make([]byte, VRFOutputSize)
+	ok, err := call(func() C.int {
+		return C.vrf_verify(bytesPtr(pk), bytesPtr(alpha), C.size_t(len(alpha)), bytesPtr(proof), bytesPtr(output))
+	})
+	if err != nil || !ok {
+		return nil, err
+	}
+	return output, nil
+}
+
+// VRFProofToHash returns the output of a proof without checking it, for
+// proofs already verified.
+func VRFProofToHash(proof []byte) ([]byte, error) {
+	if len(proof) != VRFProofSize {
+		return nil, errors.New("zk: VRF proof must be 96 bytes")
+	}
+	output := make([]byte, VRFOutputSize)
+	_, err := call(func() C.int {
+		return C.vrf_proof_to_hash(bytesPtr(proof), bytesPtr(output))
+	})
+	if err != nil {
+		return nil, err
+	}
+	return output, nil
+}
@@ -0,0 +1,33 @@
+package zk
+
+import (
+	"bytes"
+	"testing"
+
+	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
+)
+
+func TestVRF(t *testing.T) {
+	sk, err := BLSKeyGen(bytes.Repeat([]byte{3}, 32))
+	require.NoError(t, err)
+	pk, err := BLSPublicKey(sk)
+	require.NoError(t, err)
+
+	proof, err := VRFProve(sk, []byte("height 7 view 0"))
+	require.NoError(t, err)
+	again, err := VRFProve(sk, []byte("height 7 view 0"))
+	require.NoError(t, err)
+	assert.Equal(t, proof, again)
+
+	out, err := VRFVerify(pk, []byte("height 7 view 0"), proof)
+	require.NoError(t, err)
+	require.Len(t, out, VRFOutputSize)
+	hash, err := VRFProofToHash(proof)
+	require.NoError(t, err)
+	assert.Equal(t, out, hash)
+
+	out, err = VRFVerify(pk, []byte("height 7 view 1"), proof)
+	require.NoError(t, err)
+	assert.Nil(t, out)
+}
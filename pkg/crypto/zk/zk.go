@@ -6,8 +6,534 @@ package zk
 #include "./lib/zk.h"
 */
 import "C"
-import "unsafe"
+import (
+	"errors"
+	"fmt"
+	"runtime"
+	"sync"
+	"unsafe"
+)
 
-func Verify(proof, key []byte) int {
-	return int(C.verify((*C.uchar)(unsafe.Pointer(&proof[0])), C.uint(len(proof)), (*C.uchar)(unsafe.Pointer(&key[0])), C.uint(len(key))))
+// Codes returned by the zk library, they mirror ErrorCode in zk/src/error.rs.
+const (
+	CodeOK                     = 1
+	CodeInvalid                = 0
+	CodeNullPointer            = -1
+	CodeMalformedProof         = -2
+	CodeMalformedKey           = -3
+	CodeMalformedInputs        = -4
+	CodeInputCountMismatch     = -5
+	CodeUnsupportedCurve       = -6
+	CodeUnknownKey             = -7
+	CodeMalformedParams        = -8
+	CodeMalformedWitness       = -9
+	CodeUnsupportedCircuit     = -10
+	CodeBufferTooSmall         = -11
+	CodeTreeFull               = -12
+	CodeMalformedSignature     = -13
+	CodeUnsupportedProofSystem = -14
+)
+
+// Curve selects the pairing-friendly curve of a proof and its verifying key,
+// it mirrors Curve in zk/src/groth16.rs.
+type Curve int
+
+const (
+	// BLS12381 proofs and keys use bellman's encoding, public inputs are
+	// 32-byte little-endian scalars.
+	BLS12381 Curve = 0
+	// BN254 (alt_bn128) proofs and keys use Ethereum's uncompressed
+	// big-endian encoding, public inputs are 32-byte big-endian scalars.
+	BN254 Curve = 1
+)
+
+// ProofSize returns the size of a serialized Groth16 proof on the curve.
+func (c Curve) ProofSize() int {
+	if c == BN254 {
+		return 256
+	}
+	return 192
+}
+
+// ProofSystem selects the proof system of a proof and its verifying key, it
+// mirrors ProofSystem in zk/src/groth16.rs.
+type ProofSystem int
+
+const (
+	// Groth16 proofs come with a verifying key from a per-circuit setup.
+	Groth16 ProofSystem = 0
+	// Plonk proofs are snarkjs PLONK proofs with KZG commitments, whose
+	// verifying keys of any circuit derive from a single universal setup.
+	// Proofs are A | B | C | Z | T1 | T2 | T3 | Wxi | Wxiw followed by the
+	// six evaluations, keys are documented in zk/src/plonk.rs.
+	Plonk ProofSystem = 1
+)
+
+// Circuit selects one of the circuits built into the zk library, it mirrors
+// CircuitId in zk/src/circuits.rs. Built-in circuits are on BLS12381.
+type Circuit int
+
+const (
+	// Multiply proves knowledge of a and b such that a*b = c for public c.
+	// Its witness is a | b as 32-byte little-endian scalars.
+	Multiply Circuit = 0
+)
+
+// MerkleMembership is the circuit proving that a private leaf is in a
+// Poseidon Merkle tree of the given depth (1 to 32) with public root. Its
+// witness is the leaf followed by the path returned by MerkleTree.Path.
+func MerkleMembership(depth int) Circuit {
+	return Circuit(0x100 | depth)
+}
+
+// Spend is the circuit spending a shielded pool note whose commitment is in
+// a Poseidon Merkle tree of the given depth (1 to 32). Its witness is built
+// by SpendWitness and its public inputs by SpendInputs.
+func Spend(depth int) Circuit {
+	return Circuit(0x200 | depth)
+}
+
+// Error is a failure reported by the zk library before a proof could be
+// checked, e.g. a malformed proof or verifying key.
+type Error struct {
+	Code    int
+	Message string
+}
+
+func (e *Error) Error() string {
+	return fmt.Sprintf("zk: %s (code %d)", e.Message, e.Code)
+}
+
+// Verify verifies a Groth16 proof for a circuit without public inputs. It
+// returns false and no error if the proof is well-formed but invalid.
+func Verify(proof, key []byte) (bool, error) {
+	return call(func() C.int {
+		return C.verify(bytesPtr(proof), C.size_t(len(proof)), bytesPtr(key), C.size_t(len(key)))
+	})
+}
+
+// VerifyWithInputs verifies a Groth16 proof for a circuit with public inputs,
+// inputs being a concatenation of 32-byte little-endian BLS12-381 scalars.
+func VerifyWithInputs(proof, key, inputs []byte) (bool, error) {
+	return call(func() C.int {
+		return C.verify_with_inputs(bytesPtr(proof), C.size_t(len(proof)), bytesPtr(key), C.size_t(len(key)), bytesPtr(inputs), C.size_t(len(inputs)))
+	})
+}
+
+// VerifyOnCurve verifies a Groth16 proof on the given curve, the proof, key
+// and inputs being in that curve's encoding.
+func VerifyOnCurve(curve Curve, proof, key, inputs []byte) (bool, error) {
+	return call(func() C.int {
+		return C.verify_on_curve(C.int(curve), bytesPtr(proof), C.size_t(len(proof)), bytesPtr(key), C.size_t(len(key)), bytesPtr(inputs), C.size_t(len(inputs)))
+	})
+}
+
+// VerifyWithSystem verifies a proof of the given proof system on the given
+// curve, the proof, key and inputs being in that curve's encoding.
+func VerifyWithSystem(system ProofSystem, curve Curve, proof, key, inputs []byte) (bool, error) {
+	return call(func() C.int {
+		return C.verify_with_system(C.int(system), C.int(curve), bytesPtr(proof), C.size_t(len(proof)), bytesPtr(key), C.size_t(len(key)), bytesPtr(inputs), C.size_t(len(inputs)))
+	})
+}
+
+// VerifySnarkjs verifies a Groth16 or PLONK proof exported by snarkjs, given
+// the contents of its proof.json, verification_key.json and public.json
+// files. Both bn128 and bls12381 files are supported.
+func VerifySnarkjs(proof, key, public []byte) (bool, error) {
+	return call(func() C.int {
+		return C.verify_snarkjs(bytesPtr(proof), C.size_t(len(proof)), bytesPtr(key), C.size_t(len(key)), bytesPtr(public), C.size_t(len(public)))
+	})
+}
+
+// RequiredGas returns the gas required to verify batch proofs with inputs
+// public inputs each on the curve. The formula is documented in
+// zk/src/gas.rs, it's the one Groth16Verifier charges for a single proof.
+func RequiredGas(curve Curve, inputs, batch uint64) uint64 {
+	return uint64(C.required_gas(C.int(curve), C.ulonglong(inputs), C.ulonglong(batch)))
+}
+
+// Prove creates a Groth16 proof for a built-in circuit from its serialized
+// proving parameters and witness. The proof is in the encoding Verify and
+// VerifyWithInputs consume; a witness that doesn't satisfy the circuit
+// yields a proof that doesn't verify.
+func Prove(circuit Circuit, params, witness []byte) ([]byte, error) {
+	proof := make([]byte, BLS12381.ProofSize())
+	_, err := call(func() C.int {
+		return C.prove(C.int(circuit), bytesPtr(params), C.size_t(len(params)), bytesPtr(witness), C.size_t(len(witness)), bytesPtr(proof), C.size_t(len(proof)))
+	})
+	if err != nil {
+		return nil, err
+	}
+	return proof, nil
+}
+
+// Poseidon hashes 1 to 15 scalars of the curve with the Poseidon
+// permutation of width len(inputs)+1. Inputs and the hash are 32-byte
+// scalars in the curve's encoding, on BN254 hashes match circomlib's.
+func Poseidon(curve Curve, inputs ...[]byte) ([]byte, error) {
+	buf := concat(inputs)
+	out := make([]byte, 32)
+	_, err := call(func() C.int {
+		return C.poseidon(C.int(curve), bytesPtr(buf), C.size_t(len(buf)), bytesPtr(out))
+	})
+	if err != nil {
+		return nil, err
+	}
+	return out, nil
+}
+
+// MiMC7 hashes scalars of the curve with circomlib's MultiMiMC7 under key.
+// Inputs, key and the hash are 32-byte scalars in the curve's encoding.
+func MiMC7(curve Curve, key []byte, inputs ...[]byte) ([]byte, error) {
+	if len(key) != 32 {
+		return nil, errors.New("zk: MiMC key must be 32 bytes")
+	}
+	buf := concat(inputs)
+	out := make([]byte, 32)
+	_, err := call(func() C.int {
+		return C.mimc7_hash(C.int(curve), bytesPtr(buf), C.size_t(len(buf)), bytesPtr(key), bytesPtr(out))
+	})
+	if err != nil {
+		return nil, err
+	}
+	return out, nil
+}
+
+// MiMCSponge hashes scalars of the curve with circomlib's MiMCSponge under
+// key and squeezes outputs 32-byte scalars, all in the curve's encoding.
+func MiMCSponge(curve Curve, key []byte, outputs int, inputs ...[]byte) ([][]byte, error) {
+	if len(key) != 32 {
+		return nil, errors.New("zk: MiMC key must be 32 bytes")
+	}
+	if outputs < 1 {
+		return nil, errors.New("zk: MiMCSponge needs at least one output")
+	}
+	buf := concat(inputs)
+	out := make([]byte, 32*outputs)
+	_, err := call(func() C.int {
+		return C.mimc_sponge(C.int(curve), bytesPtr(buf), C.size_t(len(buf)), bytesPtr(key), bytesPtr(out), C.size_t(len(out)))
+	})
+	if err != nil {
+		return nil, err
+	}
+	res := make([][]byte, outputs)
+	for i := range res {
+		res[i] = out[32*i : 32*(i+1)]
+	}
+	return res, nil
+}
+
+// Note is a shielded pool note, nullifier | secret as 32-byte scalars in
+// the curve's encoding. Whoever knows it can spend it. See zk/src/pool.rs.
+type Note [64]byte
+
+// NewNote draws a random note over the scalar field of the curve.
+func NewNote(curve Curve) (Note, error) {
+	var note Note
+	_, err := call(func() C.int {
+		return C.note_new(C.int(curve), bytesPtr(note[:]))
+	})
+	return note, err
+}
+
+// Commitment returns the leaf a deposit of the note appends to the pool's
+// tree.
+func (n Note) Commitment(curve Curve) ([]byte, error) {
+	out := make([]byte, 32)
+	_, err := call(func() C.int {
+		return C.note_commitment(C.int(curve), bytesPtr(n[:]), bytesPtr(out))
+	})
+	if err != nil {
+		return nil, err
+	}
+	return out, nil
+}
+
+// NullifierHash returns the value a withdrawal of the note reveals.
+func (n Note) NullifierHash(curve Curve) ([]byte, error) {
+	out := make([]byte, 32)
+	_, err := call(func() C.int {
+		return C.note_nullifier_hash(C.int(curve), bytesPtr(n[:]), bytesPtr(out))
+	})
+	if err != nil {
+		return nil, err
+	}
+	return out, nil
+}
+
+// ExternalHash hashes withdrawal data (recipient, relayer, fee...) to the
+// scalar a spend proof is bound to: Keccak-256 of data with the top three
+// bits cleared, in the curve's encoding.
+func ExternalHash(curve Curve, data []byte) ([]byte, error) {
+	out := make([]byte, 32)
+	_, err := call(func() C.int {
+		return C.external_data_hash(C.int(curve), bytesPtr(data), C.size_t(len(data)), bytesPtr(out))
+	})
+	if err != nil {
+		return nil, err
+	}
+	return out, nil
+}
+
+// SpendWitness builds the witness of the Spend circuit from a BLS12381
+// note, the path of its commitment returned by MerkleTree.Path and the
+// external hash of the withdrawal data.
+func SpendWitness(note Note, path, external []byte) ([]byte, error) {
+	if len(external) != 32 {
+		return nil, errors.New("zk: external hash must be 32 bytes")
+	}
+	witness := make([]byte, len(note)+len(path)+len(external))
+	_, err := call(func() C.int {
+		return C.spend_witness_new(bytesPtr(note[:]), bytesPtr(path), C.size_t(len(path)), bytesPtr(external), bytesPtr(witness), C.size_t(len(witness)))
+	})
+	if err != nil {
+		return nil, err
+	}
+	return witness, nil
+}
+
+// SpendInputs returns the public inputs of a Spend proof for
+// VerifyWithInputs: the tree root, the note's nullifier hash and the
+// external hash.
+func SpendInputs(root, nullifierHash, external []byte) []byte {
+	return concat([][]byte{root, nullifierHash, external})
+}
+
+// PreparedKey is a verifying key parsed and prepared once by the library, it
+// can be used by several goroutines at once until it's freed.
+type PreparedKey struct {
+	lock   sync.RWMutex
+	curve  Curve
+	handle *C.prepared_key
+}
+
+// Prepare parses and prepares a serialized Groth16 verifying key.
+func Prepare(curve Curve, key []byte) (*PreparedKey, error) {
+	var handle *C.prepared_key
+	_, err := call(func() C.int {
+		return C.prepare_key(C.int(curve), bytesPtr(key), C.size_t(len(key)), &handle)
+	})
+	if err != nil {
+		return nil, err
+	}
+	return &PreparedKey{curve: curve, handle: handle}, nil
+}
+
+// Verify verifies a Groth16 proof against the prepared key, inputs being
+// encoded as for VerifyWithInputs.
+func (k *PreparedKey) Verify(proof, inputs []byte) (bool, error) {
+	k.lock.RLock()
+	defer k.lock.RUnlock()
+	if k.handle == nil {
+		return false, errors.New("zk: prepared key is freed")
+	}
+	return call(func() C.int {
+		return C.verify_prepared(k.handle, bytesPtr(proof), C.size_t(len(proof)), bytesPtr(inputs), C.size_t(len(inputs)))
+	})
+}
+
+// VerifyBatch verifies a batch of Groth16 proofs against the prepared key in
+// a single multi-pairing. proofs is a concatenation of Curve.ProofSize proofs
+// and inputs a concatenation of their public inputs. It returns whether all
+// proofs are valid along with a verdict per proof; malformed proofs are
+// reported as invalid.
+func (k *PreparedKey) VerifyBatch(proofs, inputs []byte) (bool, []bool, error) {
+	k.lock.RLock()
+	defer k.lock.RUnlock()
+	if k.handle == nil {
+		return false, nil, errors.New("zk: prepared key is freed")
+	}
+	results := make([]byte, len(proofs)/k.curve.ProofSize())
+	ok, err := call(func() C.int {
+		return C.verify_batch(k.handle, bytesPtr(proofs), C.size_t(len(proofs)), bytesPtr(inputs), C.size_t(len(inputs)), bytesPtr(results), C.size_t(len(results)))
+	})
+	if err != nil {
+		return false, nil, err
+	}
+	verdicts := make([]bool, len(results))
+	for i := range results {
+		verdicts[i] = results[i] == 1
+	}
+	return ok, verdicts, nil
+}
+
+// Free releases the library resources held by the key, it's a no-op if the
+// key is already freed.
+func (k *PreparedKey) Free() {
+	k.lock.Lock()
+	defer k.lock.Unlock()
+	C.free_prepared_key(k.handle)
+	k.handle = nil
+}
+
+// KeyID identifies a verifying key registered with RegisterKey, it's the
+// BLAKE2s-256 hash of the serialized key.
+type KeyID [32]byte
+
+// RegisterKey prepares a verifying key and keeps it in the library for the
+// lifetime of the process, so that Groth16Verifier calls can refer to it by
+// its id instead of carrying the whole key.
+func RegisterKey(curve Curve, key []byte) (KeyID, error) {
+	var id KeyID
+	_, err := call(func() C.int {
+		return C.register_key(C.int(curve), bytesPtr(key), C.size_t(len(key)), bytesPtr(id[:]))
+	})
+	return id, err
+}
+
+// UnregisterKey removes a key registered with RegisterKey.
+func UnregisterKey(id KeyID) error {
+	_, err := call(func() C.int {
+		return C.unregister_key(bytesPtr(id[:]))
+	})
+	return err
+}
+
+// Groth16Verifier is a precompiled contract verifying Groth16 proofs. Its
+// input is the ABI encoding of
+//
+//	(uint256 curve, bytes key, bytes proof, uint256[] inputs)
+//
+// where key is either a KeyID or a serialized verifying key and inputs are
+// the public inputs as big-endian words. It returns a 32-byte word set to 1
+// if the proof is valid and 0 otherwise.
+type Groth16Verifier struct{}
+
+// RequiredGas returns the gas required to run the contract on input.
+func (c *Groth16Verifier) RequiredGas(input []byte) uint64 {
+	return uint64(C.groth16_precompile_gas(bytesPtr(input), C.size_t(len(input))))
+}
+
+// Run verifies the proof in input. Malformed input is reported as an error.
+func (c *Groth16Verifier) Run(input []byte) ([]byte, error) {
+	output := make([]byte, 32)
+	_, err := call(func() C.int {
+		return C.groth16_precompile(bytesPtr(input), C.size_t(len(input)), bytesPtr(output))
+	})
+	if err != nil {
+		return nil, err
+	}
+	return output, nil
+}
+
+// MerkleTree is an incremental Poseidon Merkle tree kept by the library,
+// its methods can be used by several goroutines at once until it's freed.
+type MerkleTree struct {
+	lock   sync.Mutex
+	depth  int
+	handle *C.merkle_tree
+}
+
+// NewMerkleTree creates an empty tree of the given depth (1 to 32) over the
+// scalar field of the curve.
+func NewMerkleTree(curve Curve, depth int) (*MerkleTree, error) {
+	var handle *C.merkle_tree
+	_, err := call(func() C.int {
+		return C.merkle_tree_new(C.int(curve), C.size_t(depth), &handle)
+	})
+	if err != nil {
+		return nil, err
+	}
+	return &MerkleTree{depth: depth, handle: handle}, nil
+}
+
+// Append adds a 32-byte leaf in the curve's encoding and returns its index.
+func (t *MerkleTree) Append(leaf []byte) (uint64, error) {
+	if len(leaf) != 32 {
+		return 0, errors.New("zk: leaf must be 32 bytes")
+	}
+	t.lock.Lock()
+	defer t.lock.Unlock()
+	if t.handle == nil {
+		return 0, errors.New("zk: Merkle tree is freed")
+	}
+	var index C.ulonglong
+	_, err := call(func() C.int {
+		return C.merkle_tree_append(t.handle, bytesPtr(leaf), &index)
+	})
+	return uint64(index), err
+}
+
+// Root returns the current 32-byte root of the tree.
+func (t *MerkleTree) Root() ([]byte, error) {
+	t.lock.Lock()
+	defer t.lock.Unlock()
+	if t.handle == nil {
+		return nil, errors.New("zk: Merkle tree is freed")
+	}
+	root := make([]byte, 32)
+	_, err := call(func() C.int {
+		return C.merkle_tree_root(t.handle, bytesPtr(root))
+	})
+	if err != nil {
+		return nil, err
+	}
+	return root, nil
+}
+
+// Path returns the authentication path of the leaf at index in the current
+// tree: the siblings from the leaf level up followed by the index, all
+// 32-byte scalars in the curve's encoding.
+func (t *MerkleTree) Path(index uint64) ([]byte, error) {
+	t.lock.Lock()
+	defer t.lock.Unlock()
+	if t.handle == nil {
+		return nil, errors.New("zk: Merkle tree is freed")
+	}
+	path := make([]byte, 32*(t.depth+1))
+	_, err := call(func() C.int {
+		return C.merkle_tree_path(t.handle, C.ulonglong(index), bytesPtr(path), C.size_t(len(path)))
+	})
+	if err != nil {
+		return nil, err
+	}
+	return path, nil
+}
+
+// Free releases the tree, it's a no-op if it's already freed.
+func (t *MerkleTree) Free() {
+	t.lock.Lock()
+	defer t.lock.Unlock()
+	C.merkle_tree_free(t.handle)
+	t.handle = nil
+}
+
+// call runs a library function and converts its code. The last error is kept
+// per OS thread, so the goroutine is pinned until it has been fetched.
+func call(f func() C.int) (bool, error) {
+	runtime.LockOSThread()
+	defer runtime.UnlockOSThread()
+	switch code := int(f()); code {
+	case CodeOK:
+		return true, nil
+	case CodeInvalid:
+		return false, nil
+	default:
+		return false, &Error{Code: code, Message: lastError()}
+	}
+}
+
+func lastError() string {
+	n := C.last_error(nil, 0)
+	if n <= 0 {
+		return ""
+	}
+	buf := make([]byte, n)
+	C.last_error(bytesPtr(buf), C.size_t(n))
+	return string(buf)
+}
+
+func concat(chunks [][]byte) []byte {
+	var buf []byte
+	for _, c := range chunks {
+		buf = append(buf, c...)
+	}
+	return buf
+}
+
+func bytesPtr(b []byte) *C.uchar {
+	if len(b) == 0 {
+		return nil
+	}
+	return (*C.uchar)(unsafe.Pointer(&b[0]))
 }
@@ -1,14 +1,189 @@
 package zk
 
 import (
+	"encoding/hex"
+	"errors"
+	"math"
 	"testing"
 
 	"github.com/stretchr/testify/assert"
+	"github.com/stretchr/testify/require"
 )
 
 func TestVerif(t *testing.T) {
 	proof := []byte{1}
 	key := []byte{1}
-	r := Verify(proof, key)
-	assert.Equal(t, 0, r)
+	ok, err := Verify(proof, key)
+	assert.False(t, ok)
+	var zkErr *Error
+	require.True(t, errors.As(err, &zkErr))
+	assert.Equal(t, CodeMalformedProof, zkErr.Code)
+	assert.NotEmpty(t, zkErr.Message)
+}
+
+func TestVerifyWithInputs(t *testing.T) {
+	proof := []byte{1}
+	key := []byte{1}
+	ok, err := VerifyWithInputs(proof, key, nil)
+	assert.False(t, ok)
+	assert.Error(t, err)
+}
+
+func TestPrepare(t *testing.T) {
+	_, err := Prepare(BLS12381, []byte{1})
+	var zkErr *Error
+	require.True(t, errors.As(err, &zkErr))
+	assert.Equal(t, CodeMalformedKey, zkErr.Code)
+}
+
+func TestVerifyOnCurve(t *testing.T) {
+	_, err := VerifyOnCurve(BN254, make([]byte, 256), []byte{1}, nil)
+	var zkErr *Error
+	require.True(t, errors.As(err, &zkErr))
+	assert.Equal(t, CodeMalformedKey, zkErr.Code)
+
+	_, err = VerifyOnCurve(Curve(7), []byte{1}, []byte{1}, nil)
+	require.True(t, errors.As(err, &zkErr))
+	assert.Equal(t, CodeUnsupportedCurve, zkErr.Code)
+}
+
+func TestVerifyWithSystem(t *testing.T) {
+	_, err := VerifyWithSystem(Plonk, BN254, make([]byte, 768), []byte{1}, nil)
+	var zkErr *Error
+	require.True(t, errors.As(err, &zkErr))
+	assert.Equal(t, CodeMalformedKey, zkErr.Code)
+
+	_, err = VerifyWithSystem(ProofSystem(7), BN254, []byte{1}, []byte{1}, nil)
+	require.True(t, errors.As(err, &zkErr))
+	assert.Equal(t, CodeUnsupportedProofSystem, zkErr.Code)
+}
+
+func TestVerifySnarkjs(t *testing.T) {
+	_, err := VerifySnarkjs([]byte("{}"), []byte(`{"protocol": "fflonk", "curve": "bn128"}`), []byte("[]"))
+	var zkErr *Error
+	require.True(t, errors.As(err, &zkErr))
+	assert.Equal(t, CodeMalformedKey, zkErr.Code)
+}
+
+func TestRegisterKey(t *testing.T) {
+	_, err := RegisterKey(BN254, []byte{1})
+	var zkErr *Error
+	require.True(t, errors.As(err, &zkErr))
+	assert.Equal(t, CodeMalformedKey, zkErr.Code)
+
+	err = UnregisterKey(KeyID{1})
+	require.True(t, errors.As(err, &zkErr))
+	assert.Equal(t, CodeUnknownKey, zkErr.Code)
+}
+
+func TestGroth16Verifier(t *testing.T) {
+	c := &Groth16Verifier{}
+	_, err := c.Run([]byte{1})
+	var zkErr *Error
+	require.True(t, errors.As(err, &zkErr))
+	assert.Equal(t, CodeMalformedInputs, zkErr.Code)
+	assert.NotZero(t, c.RequiredGas([]byte{1}))
+}
+
+func TestRequiredGas(t *testing.T) {
+	assert.Equal(t, uint64(116900), RequiredGas(BN254, 2, 1))
+	assert.Less(t, RequiredGas(BLS12381, 2, 8), 8*RequiredGas(BLS12381, 2, 1))
+	assert.Zero(t, RequiredGas(BN254, 2, 0))
+	assert.Equal(t, uint64(math.MaxUint64), RequiredGas(Curve(7), 1, 1))
+}
+
+func TestProve(t *testing.T) {
+	_, err := Prove(Multiply, []byte{1}, make([]byte, 64))
+	var zkErr *Error
+	require.True(t, errors.As(err, &zkErr))
+	assert.Equal(t, CodeMalformedParams, zkErr.Code)
+
+	_, err = Prove(Circuit(42), nil, nil)
+	require.True(t, errors.As(err, &zkErr))
+	assert.Equal(t, CodeUnsupportedCircuit, zkErr.Code)
+}
+
+func TestPoseidon(t *testing.T) {
+	one, two := make([]byte, 32), make([]byte, 32)
+	one[31], two[31] = 1, 2
+	h, err := Poseidon(BN254, one, two)
+	require.NoError(t, err)
+	assert.Equal(t, "115cc0f5e7d690413df64c6b9662e9cf2a3617f2743245519e19607a4417189a", hex.EncodeToString(h))
+
+	_, err = Poseidon(BN254)
+	var zkErr *Error
+	require.True(t, errors.As(err, &zkErr))
+	assert.Equal(t, CodeMalformedInputs, zkErr.Code)
+}
+
+func TestMiMC(t *testing.T) {
+	key := make([]byte, 32)
+	a, b := make([]byte, 32), make([]byte, 32)
+	a[31], b[31] = 78, 41
+	h, err := MiMC7(BN254, key, a, b)
+	require.NoError(t, err)
+	assert.Equal(t, "067f3202335ea256ae6e6aadcd2d5f7f4b06a00b2d1e0de903980d5ab552dc70", hex.EncodeToString(h))
+
+	one, zero := make([]byte, 32), make([]byte, 32)
+	one[31] = 1
+	out, err := MiMCSponge(BN254, key, 2, one, zero)
+	require.NoError(t, err)
+	require.Len(t, out, 2)
+	assert.Equal(t, "1da263d3a84800d345556c801d614888a7a0f0e112c97da2b9a66bfd97befd17", hex.EncodeToString(out[0]))
+}
+
+func TestMerkleTree(t *testing.T) {
+	tree, err := NewMerkleTree(BLS12381, 1)
+	require.NoError(t, err)
+	defer tree.Free()
+	empty, err := tree.Root()
+	require.NoError(t, err)
+
+	leaf := make([]byte, 32)
+	leaf[0] = 7
+	for i := uint64(0); i < 2; i++ {
+		index, err := tree.Append(leaf)
+		require.NoError(t, err)
+		assert.Equal(t, i, index)
+	}
+	_, err = tree.Append(leaf)
+	var zkErr *Error
+	require.True(t, errors.As(err, &zkErr))
+	assert.Equal(t, CodeTreeFull, zkErr.Code)
+
+	root, err := tree.Root()
+	require.NoError(t, err)
+	assert.NotEqual(t, empty, root)
+	path, err := tree.Path(1)
+	require.NoError(t, err)
+	assert.Equal(t, leaf, path[:32])
+	assert.Equal(t, byte(1), path[32])
+}
+
+func TestShieldedNote(t *testing.T) {
+	note, err := NewNote(BLS12381)
+	require.NoError(t, err)
+	commitment, err := note.Commitment(BLS12381)
+	require.NoError(t, err)
+	nullifierHash, err := note.NullifierHash(BLS12381)
+	require.NoError(t, err)
+	assert.NotEqual(t, commitment, nullifierHash)
+
+	tree, err := NewMerkleTree(BLS12381, 4)
+	require.NoError(t, err)
+	defer tree.Free()
+	index, err := tree.Append(commitment)
+	require.NoError(t, err)
+	path, err := tree.Path(index)
+	require.NoError(t, err)
+	external, err := ExternalHash(BLS12381, []byte("recipient"))
+	require.NoError(t, err)
+	witness, err := SpendWitness(note, path, external)
+	require.NoError(t, err)
+	assert.Equal(t, 32*8, len(witness))
+	assert.Equal(t, note[:], witness[:64])
+
+	root, err := tree.Root()
+	require.NoError(t, err)
+	assert.Equal(t, 96, len(SpendInputs(root, nullifierHash, external)))
 }
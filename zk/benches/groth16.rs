@@ -0,0 +1,153 @@
+//! Timings the gas schedule in `zk::gas` is derived from, see its module
+//! documentation for how.
+
+use bellman::groth16::{Proof, VerifyingKey};
+use bls12_381::Bls12;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use group::Curve;
+use halo2curves::bn256::Bn256;
+use zk::bn254;
+use zk::groth16::{read_proof, read_scalars, Groth16Engine, PreparedKey};
+
+const INPUTS: [usize; 4] = [0, 4, 16, 64];
+const BATCHES: [usize; 4] = [2, 4, 16, 64];
+const KEY_INPUTS: [usize; 3] = [0, 1024, 4096];
+/// Number of inputs the cost of inputs in a batch is measured with.
+const BATCH_INPUTS: usize = 64;
+
+/// Trapdoor values of the simulated setups, the verifier only checks the
+/// pairing equation so proofs can be built from them directly.
+const ALPHA: u64 = 2;
+const BETA: u64 = 3;
+const GAMMA: u64 = 5;
+const DELTA: u64 = 7;
+
+/// Serialized verifying key, proof and public inputs.
+type Simulated = (Vec<u8>, Vec<u8>, Vec<u8>);
+type Simulate = fn(usize) -> Simulated;
+
+/// Returns a verifying key for `n` public inputs, a proof for the inputs
+/// 1..=n and the serialized inputs.
+fn simulate_bls12(n: usize) -> Simulated {
+    use bls12_381::{G1Projective, G2Projective, Scalar};
+    let g1 = |s: Scalar| (G1Projective::generator() * s).to_affine();
+    let g2 = |s: Scalar| (G2Projective::generator() * s).to_affine();
+    let (alpha, beta, gamma, delta) = (
+        Scalar::from(ALPHA),
+        Scalar::from(BETA),
+        Scalar::from(GAMMA),
+        Scalar::from(DELTA),
+    );
+    let ic = (0..=n as u64)
+        .map(|i| Scalar::from(11 + i))
+        .collect::<Vec<_>>();
+    let vk = VerifyingKey::<Bls12> {
+        alpha_g1: g1(alpha),
+        beta_g1: g1(beta),
+        beta_g2: g2(beta),
+        gamma_g2: g2(gamma),
+        delta_g1: g1(delta),
+        delta_g2: g2(delta),
+        ic: ic.iter().map(|s| g1(*s)).collect(),
+    };
+    let ic_x = (1..=n as u64)
+        .zip(ic.iter().skip(1))
+        .fold(ic[0], |acc, (x, k)| acc + Scalar::from(x) * k);
+    let (a, b) = (Scalar::from(13), Scalar::from(17));
+    let c = (a * b - alpha * beta - gamma * ic_x) * delta.invert().unwrap();
+    let proof = Proof::<Bls12> {
+        a: g1(a),
+        b: g2(b),
+        c: g1(c),
+    };
+    let (mut bkey, mut bproof) = (vec![], vec![]);
+    vk.write(&mut bkey).unwrap();
+    proof.write(&mut bproof).unwrap();
+    let inputs = (1..=n as u64)
+        .flat_map(|x| Scalar::from(x).to_bytes())
+        .collect();
+    (bkey, bproof, inputs)
+}
+
+/// Same as `simulate_bls12` on BN254.
+fn simulate_bn254(n: usize) -> Simulated {
+    use halo2curves::bn256::{Fr, G1, G2};
+    let (alpha, beta, gamma, delta) = (
+        Fr::from(ALPHA),
+        Fr::from(BETA),
+        Fr::from(GAMMA),
+        Fr::from(DELTA),
+    );
+    let ic = (0..=n as u64).map(|i| Fr::from(11 + i)).collect::<Vec<_>>();
+    let mut bkey = vec![];
+    bkey.extend(bn254::g1_bytes(&(G1::generator() * alpha)));
+    for s in [beta, gamma, delta] {
+        bkey.extend(bn254::g2_bytes(&(G2::generator() * s)));
+    }
+    bkey.extend((ic.len() as u32).to_be_bytes());
+    for s in ic.iter() {
+        bkey.extend(bn254::g1_bytes(&(G1::generator() * s)));
+    }
+    let ic_x = (1..=n as u64)
+        .zip(ic.iter().skip(1))
+        .fold(ic[0], |acc, (x, k)| acc + Fr::from(x) * k);
+    let (a, b) = (Fr::from(13), Fr::from(17));
+    let c = (a * b - alpha * beta - gamma * ic_x) * delta.invert().unwrap();
+    let mut bproof = vec![];
+    bproof.extend(bn254::g1_bytes(&(G1::generator() * a)));
+    bproof.extend(bn254::g2_bytes(&(G2::generator() * b)));
+    bproof.extend(bn254::g1_bytes(&(G1::generator() * c)));
+    let inputs = (1..=n as u64)
+        .flat_map(|x| bn254::write_fr(&Fr::from(x)))
+        .collect();
+    (bkey, bproof, inputs)
+}
+
+fn bench_curve<E: Groth16Engine>(c: &mut Criterion, name: &str, simulate: Simulate) {
+    // Decoding the proof and inputs is part of what a call pays for, so it's
+    // timed along with the verification.
+    let mut group = c.benchmark_group(format!("verify_inputs/{}", name));
+    for n in INPUTS {
+        let (bkey, bproof, binputs) = simulate(n);
+        let key = PreparedKey::<E>::read(&bkey).unwrap();
+        let verify = || {
+            let proof = read_proof::<E>(&bproof).unwrap();
+            let inputs = read_scalars::<E>(&binputs).unwrap();
+            key.verify_inputs(&proof, &inputs).unwrap()
+        };
+        assert!(verify());
+        group.bench_function(BenchmarkId::from_parameter(n), |b| b.iter(verify));
+    }
+    group.finish();
+
+    // Batches of `k` proofs with `n` inputs each, labelled `k x n`.
+    let mut group = c.benchmark_group(format!("verify_batch/{}", name));
+    let sizes = BATCHES.iter().flat_map(|k| [(*k, 0), (*k, BATCH_INPUTS)]);
+    for (k, n) in sizes {
+        let (bkey, bproof, binputs) = simulate(n);
+        let key = PreparedKey::<E>::read(&bkey).unwrap();
+        let (bproofs, binputs) = (bproof.repeat(k), binputs.repeat(k));
+        let verify = || key.verify_batch(&bproofs, &binputs).unwrap();
+        assert!(verify().iter().all(|v| *v));
+        let id = BenchmarkId::from_parameter(format!("{}x{}", k, n));
+        group.bench_function(id, |b| b.iter(verify));
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group(format!("read_key/{}", name));
+    for n in KEY_INPUTS {
+        let (bkey, _, _) = simulate(n);
+        group.bench_function(BenchmarkId::from_parameter(n + 1), |b| {
+            b.iter(|| PreparedKey::<E>::read(&bkey).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn benches(c: &mut Criterion) {
+    bench_curve::<Bn256>(c, "bn254", simulate_bn254);
+    bench_curve::<Bls12>(c, "bls12381", simulate_bls12);
+}
+
+criterion_group!(groth16, benches);
+criterion_main!(groth16);
@@ -0,0 +1,34 @@
+//! Runs the trusted setup of a built-in circuit:
+//!
+//! ```text
+//! zk-setup <circuit> <params file> <verifying key file> [--seed <seed>]
+//! ```
+//!
+//! A seeded setup is reproducible and thus only fit for devnets and
+//! privnets.
+
+use std::path::Path;
+use std::process::exit;
+
+use zk::circuits::{CircuitId, CIRCUIT_NAMES};
+use zk::setup::write_setup;
+
+fn usage() -> ! {
+    eprintln!("usage: zk-setup <circuit> <params file> <verifying key file> [--seed <seed>]");
+    eprintln!("circuits: {}", CIRCUIT_NAMES);
+    exit(2)
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (circuit, params, key, seed) = match args.as_slice() {
+        [c, p, k] => (c, p, k, None),
+        [c, p, k, flag, s] if flag == "--seed" => (c, p, k, Some(s.as_bytes())),
+        _ => usage(),
+    };
+    let circuit = CircuitId::from_name(circuit).unwrap_or_else(|| usage());
+    if let Err(e) = write_setup(circuit, seed, Path::new(params), Path::new(key)) {
+        eprintln!("zk-setup: {}", e);
+        exit(1)
+    }
+}
@@ -0,0 +1,923 @@
+//! BLS signatures on BLS12-381 following the IETF draft
+//! (<https://datatracker.ietf.org/doc/draft-irtf-cfrg-bls-signature/>) in
+//! its minimal-pubkey-size variant: public keys are compressed G1 points of
+//! 48 bytes and signatures compressed G2 points of 96 bytes, as on Ethereum.
+//! Messages are hashed to G2 with `hash_to_curve` under the domain
+//! separation tag of the selected `Scheme`.
+//!
+//! Secret keys are 32-byte big-endian scalars derived with the draft's
+//! `KeyGen`. Signatures and public keys aggregate by point addition, and an
+//! aggregate of signatures over distinct messages is checked with a single
+//! multi-pairing.
+//!
+//! With the proof of possession scheme every key comes with a signature of
+//! itself, its `pop_prove`, that must be checked with `pop_verify` before
+//! the key is trusted. That rules out rogue keys, so signatures of the same
+//! message can be checked against the sum of the signers' keys with
+//! `fast_aggregate_verify`.
+
+use bls12_381::{
+    multi_miller_loop, G1Affine, G1Projective, G2Affine, G2Prepared, G2Projective, Scalar,
+};
+use group::{Curve as _, Group};
+use rand_chacha::rand_core::{RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use sha2::{Digest, Sha256};
+
+use crate::error::{self, Error, ErrorCode, Result};
+use crate::ffi::{bytes, write_out};
+use crate::hash_to_curve::hash_to_g2;
+
+/// Size of a serialized secret key.
+pub const SECRET_KEY_SIZE: usize = 32;
+/// Size of a compressed public key.
+pub const PUBLIC_KEY_SIZE: usize = 48;
+/// Size of a compressed signature.
+pub const SIGNATURE_SIZE: usize = 96;
+
+/// Shortest input keying material `key_gen` accepts.
+pub const MIN_IKM_SIZE: usize = 32;
+
+/// Domain separation tag of proofs of possession.
+const POP_DST: &[u8] = b"BLS_POP_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_";
+
+/// Personalization of the transcript hash seeding batch coefficients.
+const POP_BATCH_PERSONALIZATION: &[u8; 8] = b"zkBlsPop";
+
+/// The draft's schemes, which differ in how they prevent rogue key attacks
+/// on aggregates. Passed over the FFI as `scheme`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scheme {
+    /// Aggregates must be over distinct messages.
+    Basic = 0,
+    /// Messages are prefixed with the signer's public key, so they're
+    /// always distinct.
+    Augmented = 1,
+    /// Keys must come with a proof of possession, signatures of the same
+    /// message can then be checked with `fast_aggregate_verify`.
+    ProofOfPossession = 2,
+}
+
+impl Scheme {
+    pub fn from_raw(scheme: libc::c_int) -> Result<Self> {
+        match scheme {
+            0 => Ok(Scheme::Basic),
+            1 => Ok(Scheme::Augmented),
+            2 => Ok(Scheme::ProofOfPossession),
+            _ => Err(Error::new(
+                ErrorCode::MalformedInputs,
+                format!("unsupported BLS scheme {}", scheme),
+            )),
+        }
+    }
+
+    /// Domain separation tag of the scheme's ciphersuite.
+    pub fn dst(self) -> &'static [u8] {
+        match self {
+            Scheme::Basic => b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_NUL_",
+            Scheme::Augmented => b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_AUG_",
+            Scheme::ProofOfPossession => b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_",
+        }
+    }
+
+    /// Hashes the message `pk` signs to G2.
+    pub(crate) fn hash(self, pk: &PublicKey, msg: &[u8]) -> G2Projective {
+        let h = match self {
+            Scheme::Basic | Scheme::ProofOfPossession => hash_to_g2(msg, self.dst()),
+            Scheme::Augmented => hash_to_g2(&[&pk.to_bytes()[..], msg].concat(), self.dst()),
+        };
+        h.expect("ciphersuite tags aren't empty")
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SecretKey(pub(crate) Scalar);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PublicKey(pub(crate) G1Affine);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Signature(pub(crate) G2Affine);
+
+/// HMAC-SHA-256 of the concatenated chunks, keys are digests so they fit
+/// in a block.
+fn hmac_sha256(key: &[u8], chunks: &[&[u8]]) -> [u8; 32] {
+    let mut block = [0u8; 64];
+    block[..key.len()].copy_from_slice(key);
+    let mut inner = Sha256::new();
+    inner.update(block.map(|b| b ^ 0x36));
+    for c in chunks {
+        inner.update(c);
+    }
+    let mut outer = Sha256::new();
+    outer.update(block.map(|b| b ^ 0x5c));
+    outer.update(inner.finalize());
+    outer.finalize().into()
+}
+
+/// Derives a secret key from at least 32 bytes of input keying material,
+/// the draft's `KeyGen(IKM, key_info)`: HKDF-SHA-256 to 48 bytes reduced
+/// modulo the group order, with a rehashed salt until the key isn't zero.
+pub fn key_gen(ikm: &[u8], key_info: &[u8]) -> Result<SecretKey> {
+    if ikm.len() < MIN_IKM_SIZE {
+        return Err(Error::new(
+            ErrorCode::MalformedInputs,
+            format!(
+                "input keying material must be at least {} bytes, got {}",
+                MIN_IKM_SIZE,
+                ikm.len()
+            ),
+        ));
+    }
+    let mut salt: [u8; 32] = Sha256::digest(b"BLS-SIG-KEYGEN-SALT-").into();
+    loop {
+        let prk = hmac_sha256(&salt, &[ikm, &[0]]);
+        // L = 48 takes two HKDF-Expand blocks.
+        let t1 = hmac_sha256(&prk, &[key_info, &[0, 48], &[1]]);
+        let t2 = hmac_sha256(&prk, &[&t1, key_info, &[0, 48], &[2]]);
+        let mut wide = [0u8; 64];
+        for (w, b) in wide.iter_mut().zip(t1.iter().chain(&t2[..16]).rev()) {
+            *w = *b;
+        }
+        let sk = Scalar::from_bytes_wide(&wide);
+        if sk != Scalar::zero() {
+            return Ok(SecretKey(sk));
+        }
+        salt = Sha256::digest(&salt).into();
+    }
+}
+
+impl SecretKey {
+    /// Decodes a big-endian scalar, which must be canonical and not zero.
+    pub fn from_bytes(b: &[u8]) -> Result<Self> {
+        let malformed = || Error::new(ErrorCode::MalformedKey, "malformed BLS secret key");
+        let mut repr: [u8; SECRET_KEY_SIZE] = b.try_into().map_err(|_| malformed())?;
+        repr.reverse();
+        Option::<Scalar>::from(Scalar::from_bytes(&repr))
+            .filter(|sk| *sk != Scalar::zero())
+            .map(SecretKey)
+            .ok_or_else(malformed)
+    }
+
+    pub fn to_bytes(&self) -> [u8; SECRET_KEY_SIZE] {
+        let mut b = self.0.to_bytes();
+        b.reverse();
+        b
+    }
+
+    pub fn public_key(&self) -> PublicKey {
+        PublicKey((G1Affine::generator() * self.0).to_affine())
+    }
+
+    pub fn sign(&self, scheme: Scheme, msg: &[u8]) -> Signature {
+        Signature((scheme.hash(&self.public_key(), msg) * self.0).to_affine())
+    }
+
+    /// Signs the key's own public key under the proof of possession tag.
+    pub fn pop_prove(&self) -> Signature {
+        Signature((pop_hash(&self.public_key()) * self.0).to_affine())
+    }
+}
+
+impl PublicKey {
+    /// Decodes a compressed point, which must be in G1 and not the identity
+    /// (the draft's `KeyValidate`).
+    pub fn from_bytes(b: &[u8]) -> Result<Self> {
+        let malformed = || Error::new(ErrorCode::MalformedKey, "malformed BLS public key");
+        let repr: [u8; PUBLIC_KEY_SIZE] = b.try_into().map_err(|_| malformed())?;
+        Option::<G1Affine>::from(G1Affine::from_compressed(&repr))
+            .filter(|p| !bool::from(p.is_identity()))
+            .map(PublicKey)
+            .ok_or_else(malformed)
+    }
+
+    pub fn to_bytes(&self) -> [u8; PUBLIC_KEY_SIZE] {
+        self.0.to_compressed()
+    }
+
+    /// Sums public keys, which only makes sense for signers of the same
+    /// message.
+    pub fn aggregate(pks: &[PublicKey]) -> Result<Self> {
+        if pks.is_empty() {
+            return Err(Error::new(
+                ErrorCode::MalformedKey,
+                "no public keys to aggregate",
+            ));
+        }
+        let sum: G1Projective = pks.iter().map(|pk| G1Projective::from(pk.0)).sum();
+        Ok(PublicKey(sum.to_affine()))
+    }
+}
+
+impl Signature {
+    /// Decodes a compressed point, which must be in G2.
+    pub fn from_bytes(b: &[u8]) -> Result<Self> {
+        let malformed = || Error::new(ErrorCode::MalformedSignature, "malformed BLS signature");
+        let repr: [u8; SIGNATURE_SIZE] = b.try_into().map_err(|_| malformed())?;
+        Option::<G2Affine>::from(G2Affine::from_compressed(&repr))
+            .map(Signature)
+            .ok_or_else(malformed)
+    }
+
+    pub fn to_bytes(&self) -> [u8; SIGNATURE_SIZE] {
+        self.0.to_compressed()
+    }
+
+    pub fn aggregate(sigs: &[Signature]) -> Result<Self> {
+        if sigs.is_empty() {
+            return Err(Error::new(
+                ErrorCode::MalformedSignature,
+                "no signatures to aggregate",
+            ));
+        }
+        let sum: G2Projective = sigs.iter().map(|s| G2Projective::from(s.0)).sum();
+        Ok(Signature(sum.to_affine()))
+    }
+}
+
+/// Checks `e(g1, sig) == prod e(pk_i, h_i)` with a single final
+/// exponentiation.
+pub(crate) fn pairing_check(sig: &Signature, terms: &[(G1Affine, G2Projective)]) -> bool {
+    let neg_g1 = -G1Affine::generator();
+    let prepared: Vec<(G1Affine, G2Prepared)> = std::iter::once((neg_g1, G2Prepared::from(sig.0)))
+        .chain(
+            terms
+                .iter()
+                .map(|(pk, h)| (*pk, G2Prepared::from(h.to_affine()))),
+        )
+        .collect();
+    let refs: Vec<(&G1Affine, &G2Prepared)> = prepared.iter().map(|(p, q)| (p, q)).collect();
+    bool::from(
+        multi_miller_loop(&refs)
+            .final_exponentiation()
+            .is_identity(),
+    )
+}
+
+fn pop_hash(pk: &PublicKey) -> G2Projective {
+    hash_to_g2(&pk.to_bytes(), POP_DST).expect("the tag isn't empty")
+}
+
+pub fn verify(scheme: Scheme, pk: &PublicKey, msg: &[u8], sig: &Signature) -> bool {
+    pairing_check(sig, &[(pk.0, scheme.hash(pk, msg))])
+}
+
+/// Verifies an aggregate of signatures by `pks[i]` over `msgs[i]`. With the
+/// basic scheme messages must be distinct, the aggregate is rejected
+/// otherwise.
+pub fn aggregate_verify(
+    scheme: Scheme,
+    pks: &[PublicKey],
+    msgs: &[&[u8]],
+    sig: &Signature,
+) -> Result<bool> {
+    if pks.is_empty() || pks.len() != msgs.len() {
+        return Err(Error::new(
+            ErrorCode::InputCountMismatch,
+            format!("{} public keys for {} messages", pks.len(), msgs.len()),
+        ));
+    }
+    if scheme == Scheme::Basic && (1..msgs.len()).any(|i| msgs[..i].contains(&msgs[i])) {
+        return Ok(false);
+    }
+    let terms: Vec<_> = pks
+        .iter()
+        .zip(msgs)
+        .map(|(pk, msg)| (pk.0, scheme.hash(pk, msg)))
+        .collect();
+    Ok(pairing_check(sig, &terms))
+}
+
+/// Verifies signatures of the same message by keys whose possession has been
+/// proven, against the sum of the keys. That's only sound with the proof of
+/// possession scheme, other schemes are rejected.
+pub fn fast_aggregate_verify(
+    scheme: Scheme,
+    pks: &[PublicKey],
+    msg: &[u8],
+    sig: &Signature,
+) -> Result<bool> {
+    if scheme != Scheme::ProofOfPossession {
+        return Err(Error::new(
+            ErrorCode::MalformedInputs,
+            "fast aggregate verification needs the proof of possession scheme",
+        ));
+    }
+    let pk = PublicKey::aggregate(pks)?;
+    Ok(verify(scheme, &pk, msg, sig))
+}
+
+pub fn pop_verify(pk: &PublicKey, proof: &Signature) -> bool {
+    pairing_check(proof, &[(pk.0, pop_hash(pk))])
+}
+
+/// Verifies proofs of possession of serialized keys, returning a verdict
+/// for every key. Keys or proofs that can't be decoded are reported as
+/// invalid rather than failing the whole batch. All proofs are checked with
+/// a single multi-pairing over a random linear combination, if that fails
+/// they're rechecked one by one to find the culprits.
+pub fn pop_verify_batch(bpks: &[u8], bproofs: &[u8]) -> Result<Vec<bool>> {
+    if !bpks.len().is_multiple_of(PUBLIC_KEY_SIZE) {
+        return Err(Error::new(
+            ErrorCode::MalformedKey,
+            format!(
+                "public keys length {} is not a multiple of {}",
+                bpks.len(),
+                PUBLIC_KEY_SIZE
+            ),
+        ));
+    }
+    let n = bpks.len() / PUBLIC_KEY_SIZE;
+    if bproofs.len() != n * SIGNATURE_SIZE {
+        return Err(Error::new(
+            ErrorCode::InputCountMismatch,
+            format!(
+                "expected {} bytes of proofs for {} keys, got {}",
+                n * SIGNATURE_SIZE,
+                n,
+                bproofs.len()
+            ),
+        ));
+    }
+
+    let mut verdicts = vec![false; n];
+    let mut items = Vec::with_capacity(n);
+    for i in 0..n {
+        let pk = PublicKey::from_bytes(&bpks[i * PUBLIC_KEY_SIZE..(i + 1) * PUBLIC_KEY_SIZE]);
+        let proof = Signature::from_bytes(&bproofs[i * SIGNATURE_SIZE..(i + 1) * SIGNATURE_SIZE]);
+        if let (Ok(pk), Ok(proof)) = (pk, proof) {
+            items.push((i, pk, proof));
+        }
+    }
+    if items.is_empty() {
+        return Ok(verdicts);
+    }
+
+    // With 128-bit coefficients unknown when the proofs are fixed, invalid
+    // proofs cancel out with negligible probability.
+    let mut rng = ChaCha20Rng::from_seed(pop_batch_seed(bpks, bproofs));
+    let mut sum = G2Projective::identity();
+    let mut terms = Vec::with_capacity(items.len());
+    for (_, pk, proof) in items.iter() {
+        let r = Scalar::from_raw([rng.next_u64(), rng.next_u64(), 0, 0]);
+        sum += proof.0 * r;
+        terms.push(((pk.0 * r).to_affine(), pop_hash(pk)));
+    }
+    if pairing_check(&Signature(sum.to_affine()), &terms) {
+        for (i, _, _) in items.iter() {
+            verdicts[*i] = true;
+        }
+    } else {
+        for (i, pk, proof) in items.iter() {
+            verdicts[*i] = pop_verify(pk, proof);
+        }
+    }
+    Ok(verdicts)
+}
+
+fn pop_batch_seed(bpks: &[u8], bproofs: &[u8]) -> [u8; 32] {
+    let mut state = blake2s_simd::Params::new()
+        .personal(POP_BATCH_PERSONALIZATION)
+        .to_state();
+    state.update(&(bpks.len() as u64).to_le_bytes());
+    state.update(bpks);
+    state.update(bproofs);
+    *state.finalize().as_array()
+}
+
+/// Splits a buffer of fixed-size elements.
+pub(crate) fn read_all<T>(
+    b: &[u8],
+    size: usize,
+    read: impl Fn(&[u8]) -> Result<T>,
+) -> Result<Vec<T>> {
+    if !b.len().is_multiple_of(size) {
+        return Err(Error::new(
+            ErrorCode::MalformedInputs,
+            format!("buffer length {} is not a multiple of {}", b.len(), size),
+        ));
+    }
+    b.chunks(size).map(read).collect()
+}
+
+/// Derives a secret key from `ikm`, at least 32 bytes of secret randomness,
+/// and writes it to `secret_key` (32 bytes).
+#[no_mangle]
+pub extern "C" fn bls_keygen(
+    ikm: *mut libc::c_uchar,
+    ikm_len: libc::size_t,
+    secret_key: *mut libc::c_uchar,
+) -> libc::c_int {
+    error::status((|| {
+        let sk = key_gen(bytes(ikm, ikm_len)?, &[])?;
+        write_out(secret_key, SECRET_KEY_SIZE, &sk.to_bytes())?;
+        Ok(true)
+    })())
+}
+
+/// Writes the 48-byte public key of a secret key to `public_key`.
+#[no_mangle]
+pub extern "C" fn bls_public_key(
+    secret_key: *mut libc::c_uchar,
+    public_key: *mut libc::c_uchar,
+) -> libc::c_int {
+    error::status((|| {
+        let sk = SecretKey::from_bytes(bytes(secret_key, SECRET_KEY_SIZE)?)?;
+        write_out(public_key, PUBLIC_KEY_SIZE, &sk.public_key().to_bytes())?;
+        Ok(true)
+    })())
+}
+
+/// Signs a message and writes the 96-byte signature to `signature`.
+#[no_mangle]
+pub extern "C" fn bls_sign(
+    scheme: libc::c_int,
+    secret_key: *mut libc::c_uchar,
+    msg: *mut libc::c_uchar,
+    msg_len: libc::size_t,
+    signature: *mut libc::c_uchar,
+) -> libc::c_int {
+    error::status((|| {
+        let scheme = Scheme::from_raw(scheme)?;
+        let sk = SecretKey::from_bytes(bytes(secret_key, SECRET_KEY_SIZE)?)?;
+        let sig = sk.sign(scheme, bytes(msg, msg_len)?);
+        write_out(signature, SIGNATURE_SIZE, &sig.to_bytes())?;
+        Ok(true)
+    })())
+}
+
+/// Verifies a signature, returns `Ok` if it's valid and `Invalid` if not.
+#[no_mangle]
+pub extern "C" fn bls_verify(
+    scheme: libc::c_int,
+    public_key: *mut libc::c_uchar,
+    msg: *mut libc::c_uchar,
+    msg_len: libc::size_t,
+    signature: *mut libc::c_uchar,
+) -> libc::c_int {
+    error::status((|| {
+        let scheme = Scheme::from_raw(scheme)?;
+        let pk = PublicKey::from_bytes(bytes(public_key, PUBLIC_KEY_SIZE)?)?;
+        let sig = Signature::from_bytes(bytes(signature, SIGNATURE_SIZE)?)?;
+        Ok(verify(scheme, &pk, bytes(msg, msg_len)?, &sig))
+    })())
+}
+
+/// Sums the concatenated 96-byte signatures in `signatures` and writes the
+/// aggregate to `aggregate`.
+#[no_mangle]
+pub extern "C" fn bls_aggregate_signatures(
+    signatures: *mut libc::c_uchar,
+    signatures_len: libc::size_t,
+    aggregate: *mut libc::c_uchar,
+) -> libc::c_int {
+    error::status((|| {
+        let sigs = read_all(
+            bytes(signatures, signatures_len)?,
+            SIGNATURE_SIZE,
+            Signature::from_bytes,
+        )?;
+        write_out(
+            aggregate,
+            SIGNATURE_SIZE,
+            &Signature::aggregate(&sigs)?.to_bytes(),
+        )?;
+        Ok(true)
+    })())
+}
+
+/// Sums the concatenated 48-byte public keys in `public_keys` and writes
+/// the aggregate to `aggregate`.
+#[no_mangle]
+pub extern "C" fn bls_aggregate_public_keys(
+    public_keys: *mut libc::c_uchar,
+    public_keys_len: libc::size_t,
+    aggregate: *mut libc::c_uchar,
+) -> libc::c_int {
+    error::status((|| {
+        let pks = read_all(
+            bytes(public_keys, public_keys_len)?,
+            PUBLIC_KEY_SIZE,
+            PublicKey::from_bytes,
+        )?;
+        write_out(
+            aggregate,
+            PUBLIC_KEY_SIZE,
+            &PublicKey::aggregate(&pks)?.to_bytes(),
+        )?;
+        Ok(true)
+    })())
+}
+
+/// Verifies an aggregate signature with a single pairing check. The
+/// concatenated 48-byte `public_keys` each signed the message of the same
+/// rank in `msgs`, which holds the messages back to back with their sizes in
+/// `msg_lens`, one per public key.
+#[no_mangle]
+pub extern "C" fn bls_aggregate_verify(
+    scheme: libc::c_int,
+    public_keys: *mut libc::c_uchar,
+    public_keys_len: libc::size_t,
+    msgs: *mut libc::c_uchar,
+    msg_lens: *const libc::size_t,
+    signature: *mut libc::c_uchar,
+) -> libc::c_int {
+    error::status((|| {
+        let scheme = Scheme::from_raw(scheme)?;
+        let pks = read_all(
+            bytes(public_keys, public_keys_len)?,
+            PUBLIC_KEY_SIZE,
+            PublicKey::from_bytes,
+        )?;
+        if pks.is_empty() {
+            return Err(Error::new(ErrorCode::MalformedKey, "no public keys"));
+        }
+        if msg_lens.is_null() {
+            return Err(Error::new(ErrorCode::NullPointer, "null message sizes"));
+        }
+        let lens = unsafe { std::slice::from_raw_parts(msg_lens, pks.len()) };
+        let total = lens
+            .iter()
+            .try_fold(0usize, |acc, &l| acc.checked_add(l))
+            .ok_or_else(|| Error::new(ErrorCode::MalformedInputs, "message sizes overflow"))?;
+        let all = bytes(msgs, total)?;
+        let mut rest = all;
+        let msgs: Vec<&[u8]> = lens
+            .iter()
+            .map(|&l| {
+                let (m, r) = rest.split_at(l);
+                rest = r;
+                m
+            })
+            .collect();
+        let sig = Signature::from_bytes(bytes(signature, SIGNATURE_SIZE)?)?;
+        aggregate_verify(scheme, &pks, &msgs, &sig)
+    })())
+}
+
+/// Writes the 96-byte proof of possession of a secret key to `proof`.
+#[no_mangle]
+pub extern "C" fn bls_pop_prove(
+    secret_key: *mut libc::c_uchar,
+    proof: *mut libc::c_uchar,
+) -> libc::c_int {
+    error::status((|| {
+        let sk = SecretKey::from_bytes(bytes(secret_key, SECRET_KEY_SIZE)?)?;
+        write_out(proof, SIGNATURE_SIZE, &sk.pop_prove().to_bytes())?;
+        Ok(true)
+    })())
+}
+
+/// Verifies the proof of possession of a public key, returns `Ok` if it's
+/// valid and `Invalid` if not.
+#[no_mangle]
+pub extern "C" fn bls_pop_verify(
+    public_key: *mut libc::c_uchar,
+    proof: *mut libc::c_uchar,
+) -> libc::c_int {
+    error::status((|| {
+        let pk = PublicKey::from_bytes(bytes(public_key, PUBLIC_KEY_SIZE)?)?;
+        let proof = Signature::from_bytes(bytes(proof, SIGNATURE_SIZE)?)?;
+        Ok(pop_verify(&pk, &proof))
+    })())
+}
+
+/// Verifies the proofs of possession of the concatenated 48-byte
+/// `public_keys`, `proofs` holding the 96-byte proof of each key in order.
+/// `results` gets a byte per key, 1 if its proof is valid and 0 if not, and
+/// the call returns `Ok` only if they all are.
+#[no_mangle]
+pub extern "C" fn bls_pop_verify_batch(
+    public_keys: *mut libc::c_uchar,
+    public_keys_len: libc::size_t,
+    proofs: *mut libc::c_uchar,
+    proofs_len: libc::size_t,
+    results: *mut libc::c_uchar,
+    results_len: libc::size_t,
+) -> libc::c_int {
+    error::status((|| {
+        let verdicts = pop_verify_batch(
+            bytes(public_keys, public_keys_len)?,
+            bytes(proofs, proofs_len)?,
+        )?;
+        let b: Vec<u8> = verdicts.iter().map(|&valid| valid as u8).collect();
+        if !b.is_empty() {
+            write_out(results, results_len, &b)?;
+        }
+        Ok(verdicts.iter().all(|&valid| valid))
+    })())
+}
+
+/// Verifies a signature of `msg` by all the concatenated 48-byte
+/// `public_keys`, whose possession must have been proven. Only the proof of
+/// possession scheme is accepted.
+#[no_mangle]
+pub extern "C" fn bls_fast_aggregate_verify(
+    scheme: libc::c_int,
+    public_keys: *mut libc::c_uchar,
+    public_keys_len: libc::size_t,
+    msg: *mut libc::c_uchar,
+    msg_len: libc::size_t,
+    signature: *mut libc::c_uchar,
+) -> libc::c_int {
+    error::status((|| {
+        let scheme = Scheme::from_raw(scheme)?;
+        let pks = read_all(
+            bytes(public_keys, public_keys_len)?,
+            PUBLIC_KEY_SIZE,
+            PublicKey::from_bytes,
+        )?;
+        let sig = Signature::from_bytes(bytes(signature, SIGNATURE_SIZE)?)?;
+        fast_aggregate_verify(scheme, &pks, bytes(msg, msg_len)?, &sig)
+    })())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_key_gen() {
+        // EIP-2333 test case 0, its master key is KeyGen of the seed.
+        let seed = hex("c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04");
+        let sk = key_gen(&seed, &[]).unwrap();
+        assert_eq!(
+            hex("0d7359d57963ab8fbbde1852dcf553fedbc31f464d80ee7d40ae683122b45070"),
+            sk.to_bytes()
+        );
+        assert_eq!(sk, SecretKey::from_bytes(&sk.to_bytes()).unwrap());
+        assert_ne!(sk, key_gen(&seed, b"info").unwrap());
+        assert!(key_gen(&seed[..31], &[]).is_err());
+        assert!(SecretKey::from_bytes(&[0; SECRET_KEY_SIZE]).is_err());
+    }
+
+    #[test]
+    fn test_eth2_vector() {
+        // Ethereum consensus specs use the proof of possession ciphersuite.
+        let sk = SecretKey::from_bytes(&hex(
+            "263dbd792f5b1be47ed85f8938c0f29586af0d3ac7b977f21c278fe1462040e3",
+        ))
+        .unwrap();
+        assert_eq!(
+            hex("a491d1b0ecd9bb917989f0e74f0dea0422eac4a873e5e2644f368dffb9a6e20fd6e10c1b77654d067c0618f6e5a7f79a"),
+            sk.public_key().to_bytes()
+        );
+        assert_eq!(
+            hex("b6ed936746e01f8ecf281f020953fbf1f01debd5657c4a383940b020b26507f6076334f91e2366c96e9ab279fb5158090352ea1c5b0c9274504f4f0e7053af24802e51e4568d164fe986834f41e55c8e850ce1f98458c0cfc9ab380b55285a55"),
+            sk.sign(Scheme::ProofOfPossession, &[0; 32]).to_bytes()
+        );
+    }
+
+    fn keys(n: u8) -> Vec<SecretKey> {
+        (1..=n).map(|i| key_gen(&[i; 32], &[]).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_sign_verify() {
+        for scheme in [Scheme::Basic, Scheme::Augmented, Scheme::ProofOfPossession] {
+            let sk = keys(1)[0];
+            let sig = sk.sign(scheme, b"root");
+            assert!(verify(scheme, &sk.public_key(), b"root", &sig));
+            assert!(!verify(scheme, &sk.public_key(), b"rook", &sig));
+            let other = keys(2)[1].public_key();
+            assert!(!verify(scheme, &other, b"root", &sig));
+            assert_eq!(sig, Signature::from_bytes(&sig.to_bytes()).unwrap());
+        }
+        let sk = keys(1)[0];
+        let sig = sk.sign(Scheme::Basic, b"root");
+        assert!(!verify(Scheme::Augmented, &sk.public_key(), b"root", &sig));
+        assert!(Signature::from_bytes(&[0; SIGNATURE_SIZE]).is_err());
+        assert!(PublicKey::from_bytes(&G1Affine::identity().to_compressed()).is_err());
+    }
+
+    #[test]
+    fn test_aggregate_verify() {
+        let sks = keys(3);
+        let pks: Vec<_> = sks.iter().map(SecretKey::public_key).collect();
+        let msgs: [&[u8]; 3] = [b"a", b"b", b"c"];
+        for scheme in [Scheme::Basic, Scheme::Augmented] {
+            let sigs: Vec<_> = sks
+                .iter()
+                .zip(msgs)
+                .map(|(sk, m)| sk.sign(scheme, m))
+                .collect();
+            let agg = Signature::aggregate(&sigs).unwrap();
+            assert!(aggregate_verify(scheme, &pks, &msgs, &agg).unwrap());
+            let swapped = [msgs[1], msgs[0], msgs[2]];
+            assert!(!aggregate_verify(scheme, &pks, &swapped, &agg).unwrap());
+            assert!(aggregate_verify(scheme, &pks[1..], &msgs, &agg).is_err());
+        }
+
+        // The basic scheme rejects repeated messages, the augmented one
+        // doesn't need to.
+        let same: [&[u8]; 3] = [b"a"; 3];
+        for (scheme, valid) in [(Scheme::Basic, false), (Scheme::Augmented, true)] {
+            let sigs: Vec<_> = sks.iter().map(|sk| sk.sign(scheme, b"a")).collect();
+            let agg = Signature::aggregate(&sigs).unwrap();
+            assert_eq!(valid, aggregate_verify(scheme, &pks, &same, &agg).unwrap());
+        }
+        assert!(Signature::aggregate(&[]).is_err());
+    }
+
+    #[test]
+    fn test_pop() {
+        let sks = keys(3);
+        let pks: Vec<_> = sks.iter().map(SecretKey::public_key).collect();
+        let proof = sks[0].pop_prove();
+        assert!(pop_verify(&pks[0], &proof));
+        assert!(!pop_verify(&pks[1], &proof));
+        // A proof of possession isn't a signature of the key's bytes.
+        let sig = sks[0].sign(Scheme::ProofOfPossession, &pks[0].to_bytes());
+        assert!(!pop_verify(&pks[0], &sig));
+
+        let sigs: Vec<_> = sks
+            .iter()
+            .map(|sk| sk.sign(Scheme::ProofOfPossession, b"root"))
+            .collect();
+        let agg = Signature::aggregate(&sigs).unwrap();
+        let pop = Scheme::ProofOfPossession;
+        assert!(fast_aggregate_verify(pop, &pks, b"root", &agg).unwrap());
+        assert!(!fast_aggregate_verify(pop, &pks[1..], b"root", &agg).unwrap());
+        assert!(!fast_aggregate_verify(pop, &pks, b"rook", &agg).unwrap());
+        assert!(fast_aggregate_verify(Scheme::Basic, &pks, b"root", &agg).is_err());
+        assert!(fast_aggregate_verify(pop, &[], b"root", &agg).is_err());
+    }
+
+    #[test]
+    fn test_pop_verify_batch() {
+        let sks = keys(5);
+        let mut bpks: Vec<u8> = sks
+            .iter()
+            .flat_map(|sk| sk.public_key().to_bytes())
+            .collect();
+        let mut bproofs: Vec<u8> = sks
+            .iter()
+            .flat_map(|sk| sk.pop_prove().to_bytes())
+            .collect();
+        assert_eq!(vec![true; 5], pop_verify_batch(&bpks, &bproofs).unwrap());
+        assert!(pop_verify_batch(&[], &[]).unwrap().is_empty());
+        assert_eq!(
+            ErrorCode::InputCountMismatch,
+            pop_verify_batch(&bpks, &bproofs[SIGNATURE_SIZE..])
+                .err()
+                .unwrap()
+                .code()
+        );
+
+        // Swap two proofs and corrupt a key.
+        let (p1, p2) = bproofs.split_at_mut(2 * SIGNATURE_SIZE);
+        p1[SIGNATURE_SIZE..].swap_with_slice(&mut p2[..SIGNATURE_SIZE]);
+        bpks[4 * PUBLIC_KEY_SIZE] ^= 0x01;
+        assert_eq!(
+            vec![true, false, false, true, false],
+            pop_verify_batch(&bpks, &bproofs).unwrap()
+        );
+
+        let mut results = [0u8; 5];
+        let code = bls_pop_verify_batch(
+            bpks.as_mut_ptr(),
+            bpks.len(),
+            bproofs.as_mut_ptr(),
+            bproofs.len(),
+            results.as_mut_ptr(),
+            results.len(),
+        );
+        assert_eq!(0, code);
+        assert_eq!([1, 0, 0, 1, 0], results);
+    }
+
+    #[test]
+    fn test_ffi() {
+        let mut ikm = [7u8; 32];
+        let mut sk = [0u8; SECRET_KEY_SIZE];
+        assert_eq!(1, bls_keygen(ikm.as_mut_ptr(), ikm.len(), sk.as_mut_ptr()));
+        let mut pk = [0u8; PUBLIC_KEY_SIZE];
+        assert_eq!(1, bls_public_key(sk.as_mut_ptr(), pk.as_mut_ptr()));
+
+        let mut msg = *b"state root";
+        let mut sig = [0u8; SIGNATURE_SIZE];
+        let code = bls_sign(
+            0,
+            sk.as_mut_ptr(),
+            msg.as_mut_ptr(),
+            msg.len(),
+            sig.as_mut_ptr(),
+        );
+        assert_eq!(1, code);
+        let code = bls_verify(
+            0,
+            pk.as_mut_ptr(),
+            msg.as_mut_ptr(),
+            msg.len(),
+            sig.as_mut_ptr(),
+        );
+        assert_eq!(1, code);
+        let code = bls_verify(
+            1,
+            pk.as_mut_ptr(),
+            msg.as_mut_ptr(),
+            msg.len(),
+            sig.as_mut_ptr(),
+        );
+        assert_eq!(0, code);
+        let code = bls_verify(
+            3,
+            pk.as_mut_ptr(),
+            msg.as_mut_ptr(),
+            msg.len(),
+            sig.as_mut_ptr(),
+        );
+        assert_eq!(ErrorCode::MalformedInputs as libc::c_int, code);
+        let mut bad = [0u8; SIGNATURE_SIZE];
+        let code = bls_verify(
+            0,
+            pk.as_mut_ptr(),
+            msg.as_mut_ptr(),
+            msg.len(),
+            bad.as_mut_ptr(),
+        );
+        assert_eq!(ErrorCode::MalformedSignature as libc::c_int, code);
+
+        let sks = keys(2);
+        let mut pks = [
+            sks[0].public_key().to_bytes(),
+            sks[1].public_key().to_bytes(),
+        ]
+        .concat();
+        let mut msgs = *b"firstsecond";
+        let lens = [5, 6];
+        let mut sigs = [
+            sks[0].sign(Scheme::Basic, b"first").to_bytes(),
+            sks[1].sign(Scheme::Basic, b"second").to_bytes(),
+        ]
+        .concat();
+        let mut agg = [0u8; SIGNATURE_SIZE];
+        let code = bls_aggregate_signatures(sigs.as_mut_ptr(), sigs.len(), agg.as_mut_ptr());
+        assert_eq!(1, code);
+        let code = bls_aggregate_verify(
+            0,
+            pks.as_mut_ptr(),
+            pks.len(),
+            msgs.as_mut_ptr(),
+            lens.as_ptr(),
+            agg.as_mut_ptr(),
+        );
+        assert_eq!(1, code);
+        let code = bls_aggregate_verify(
+            0,
+            pks.as_mut_ptr(),
+            pks.len(),
+            msgs.as_mut_ptr(),
+            [6, 5].as_ptr(),
+            agg.as_mut_ptr(),
+        );
+        assert_eq!(0, code);
+
+        let mut proof = [0u8; SIGNATURE_SIZE];
+        assert_eq!(1, bls_pop_prove(sk.as_mut_ptr(), proof.as_mut_ptr()));
+        assert_eq!(1, bls_pop_verify(pk.as_mut_ptr(), proof.as_mut_ptr()));
+        assert_eq!(0, bls_pop_verify(pk.as_mut_ptr(), sig.as_mut_ptr()));
+
+        let mut msg = *b"root";
+        let mut sigs = [
+            sks[0].sign(Scheme::ProofOfPossession, &msg).to_bytes(),
+            sks[1].sign(Scheme::ProofOfPossession, &msg).to_bytes(),
+        ]
+        .concat();
+        let code = bls_aggregate_signatures(sigs.as_mut_ptr(), sigs.len(), agg.as_mut_ptr());
+        assert_eq!(1, code);
+        let code = bls_fast_aggregate_verify(
+            2,
+            pks.as_mut_ptr(),
+            pks.len(),
+            msg.as_mut_ptr(),
+            msg.len(),
+            agg.as_mut_ptr(),
+        );
+        assert_eq!(1, code);
+        let code = bls_fast_aggregate_verify(
+            0,
+            pks.as_mut_ptr(),
+            pks.len(),
+            msg.as_mut_ptr(),
+            msg.len(),
+            agg.as_mut_ptr(),
+        );
+        assert_eq!(ErrorCode::MalformedInputs as libc::c_int, code);
+
+        let mut agg_pk = [0u8; PUBLIC_KEY_SIZE];
+        let code = bls_aggregate_public_keys(pks.as_mut_ptr(), pks.len(), agg_pk.as_mut_ptr());
+        assert_eq!(1, code);
+        let sum = PublicKey::aggregate(&[sks[0].public_key(), sks[1].public_key()]).unwrap();
+        assert_eq!(sum.to_bytes(), agg_pk);
+        let code = bls_aggregate_public_keys(pks.as_mut_ptr(), pks.len() - 1, agg_pk.as_mut_ptr());
+        assert_eq!(ErrorCode::MalformedInputs as libc::c_int, code);
+    }
+}
@@ -0,0 +1,701 @@
+//! The BLS12-381 precompiles of EIP-2537 with their exact encodings, gas and
+//! error cases, as run at addresses 10 to 18 (`Operation` in address
+//! order).
+//!
+//! Field elements are 64 bytes, big-endian with the 16 top bytes zero and
+//! less than the modulus. G1 points are `x | y` and G2 points
+//! `x.c0 | x.c1 | y.c0 | y.c1`, the point at infinity being all zeroes.
+//! Scalars are any 32-byte big-endian number, not reduced by the group
+//! order. Points given to the additions and multiplications need only be on
+//! the curve, those given to the pairing must be in the subgroup. The maps
+//! to the curves are the simplified SWU maps and isogenies of RFC 9380
+//! followed by the cofactor clearing, without hashing.
+
+use bls12_381::hash_to_curve::{HashToField, MapToCurve};
+use bls12_381::{
+    multi_miller_loop, G1Affine, G1Projective, G2Affine, G2Prepared, G2Projective, Gt,
+};
+use group::{Curve as _, Group};
+use sha2::digest::generic_array::GenericArray;
+
+use crate::error::{self, Error, ErrorCode, Result};
+use crate::ffi::{bytes, write_out};
+
+/// Size of an encoded field element.
+pub const FIELD_SIZE: usize = 64;
+/// Size of an encoded scalar.
+pub const SCALAR_SIZE: usize = 32;
+/// Size of an encoded G1 point.
+pub const G1_SIZE: usize = 2 * FIELD_SIZE;
+/// Size of an encoded G2 point.
+pub const G2_SIZE: usize = 4 * FIELD_SIZE;
+/// Size of the output of the pairing.
+pub const PAIRING_OUTPUT_SIZE: usize = 32;
+
+pub const G1_ADD_GAS: u64 = 600;
+pub const G1_MUL_GAS: u64 = 12000;
+pub const G2_ADD_GAS: u64 = 4500;
+pub const G2_MUL_GAS: u64 = 55000;
+pub const PAIRING_BASE_GAS: u64 = 115000;
+pub const PAIRING_PER_PAIR_GAS: u64 = 23000;
+pub const MAP_G1_GAS: u64 = 5500;
+pub const MAP_G2_GAS: u64 = 110000;
+
+/// Per-mille discounts of a multi-exponentiation of `k` pairs, indexed by
+/// `k - 1`.
+pub const MULTI_EXP_DISCOUNT: [u64; 128] = [
+    1200, 888, 764, 641, 594, 547, 500, 453, 438, 423, 408, 394, 379, 364, 349, 334, 330, 326, 322,
+    318, 314, 310, 306, 302, 298, 294, 289, 285, 281, 277, 273, 269, 268, 266, 265, 263, 262, 260,
+    259, 257, 256, 254, 253, 251, 250, 248, 247, 245, 244, 242, 241, 239, 238, 236, 235, 233, 232,
+    231, 229, 228, 226, 225, 223, 222, 221, 220, 219, 219, 218, 217, 216, 216, 215, 214, 213, 213,
+    212, 211, 211, 210, 209, 208, 208, 207, 206, 205, 205, 204, 203, 202, 202, 201, 200, 199, 199,
+    198, 197, 196, 196, 195, 194, 193, 193, 192, 191, 191, 190, 189, 188, 188, 187, 186, 185, 185,
+    184, 183, 182, 182, 181, 180, 179, 179, 178, 177, 176, 176, 175, 174,
+];
+
+/// The base field modulus, big-endian.
+const MODULUS: [u8; 48] = [
+    0x1a, 0x01, 0x11, 0xea, 0x39, 0x7f, 0xe6, 0x9a, 0x4b, 0x1b, 0xa7, 0xb6, 0x43, 0x4b, 0xac, 0xd7,
+    0x64, 0x77, 0x4b, 0x84, 0xf3, 0x85, 0x12, 0xbf, 0x67, 0x30, 0xd2, 0xa0, 0xf6, 0xb0, 0xf6, 0x24,
+    0x1e, 0xab, 0xff, 0xfe, 0xb1, 0x53, 0xff, 0xff, 0xb9, 0xfe, 0xff, 0xff, 0xff, 0xff, 0xaa, 0xab,
+];
+
+/// The EIP-2537 precompiles, passed as the `op` argument of the exported
+/// functions. The precompile of `op` is at address `10 + op`.
+#[repr(i32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operation {
+    G1Add = 0,
+    G1Mul = 1,
+    G1MultiExp = 2,
+    G2Add = 3,
+    G2Mul = 4,
+    G2MultiExp = 5,
+    Pairing = 6,
+    MapG1 = 7,
+    MapG2 = 8,
+}
+
+impl Operation {
+    pub fn from_raw(op: libc::c_int) -> Result<Self> {
+        use Operation::*;
+        [
+            G1Add, G1Mul, G1MultiExp, G2Add, G2Mul, G2MultiExp, Pairing, MapG1, MapG2,
+        ]
+        .get(usize::try_from(op).unwrap_or(usize::MAX))
+        .copied()
+        .ok_or_else(|| {
+            Error::new(
+                ErrorCode::MalformedInputs,
+                format!("unsupported EIP-2537 operation {}", op),
+            )
+        })
+    }
+
+    /// Size of the output.
+    pub fn output_size(self) -> usize {
+        use Operation::*;
+        match self {
+            G1Add | G1Mul | G1MultiExp | MapG1 => G1_SIZE,
+            G2Add | G2Mul | G2MultiExp | MapG2 => G2_SIZE,
+            Pairing => PAIRING_OUTPUT_SIZE,
+        }
+    }
+
+    /// Gas charged for `input`, which needn't be valid.
+    pub fn gas(self, input: &[u8]) -> u64 {
+        use Operation::*;
+        match self {
+            G1Add => G1_ADD_GAS,
+            G1Mul => G1_MUL_GAS,
+            G1MultiExp => multi_exp_gas(input.len() / (G1_SIZE + SCALAR_SIZE), G1_MUL_GAS),
+            G2Add => G2_ADD_GAS,
+            G2Mul => G2_MUL_GAS,
+            G2MultiExp => multi_exp_gas(input.len() / (G2_SIZE + SCALAR_SIZE), G2_MUL_GAS),
+            Pairing => {
+                PAIRING_BASE_GAS + (input.len() / (G1_SIZE + G2_SIZE)) as u64 * PAIRING_PER_PAIR_GAS
+            }
+            MapG1 => MAP_G1_GAS,
+            MapG2 => MAP_G2_GAS,
+        }
+    }
+
+    pub fn run(self, input: &[u8]) -> Result<Vec<u8>> {
+        use Operation::*;
+        match self {
+            G1Add => {
+                check_length(input, 2 * G1_SIZE)?;
+                let p = G1Projective::from(read_g1(&input[..G1_SIZE])?);
+                Ok(write_g1(&(p + read_g1(&input[G1_SIZE..])?)))
+            }
+            G1Mul => {
+                check_length(input, G1_SIZE + SCALAR_SIZE)?;
+                let p = G1Projective::from(read_g1(&input[..G1_SIZE])?);
+                Ok(write_g1(&mul(p, &input[G1_SIZE..])))
+            }
+            G1MultiExp => {
+                let pairs = read_pairs(input, G1_SIZE, |b| read_g1(b).map(G1Projective::from))?;
+                Ok(write_g1(&multi_exp(&pairs)))
+            }
+            G2Add => {
+                check_length(input, 2 * G2_SIZE)?;
+                let p = G2Projective::from(read_g2(&input[..G2_SIZE])?);
+                Ok(write_g2(&(p + read_g2(&input[G2_SIZE..])?)))
+            }
+            G2Mul => {
+                check_length(input, G2_SIZE + SCALAR_SIZE)?;
+                let p = G2Projective::from(read_g2(&input[..G2_SIZE])?);
+                Ok(write_g2(&mul(p, &input[G2_SIZE..])))
+            }
+            G2MultiExp => {
+                let pairs = read_pairs(input, G2_SIZE, |b| read_g2(b).map(G2Projective::from))?;
+                Ok(write_g2(&multi_exp(&pairs)))
+            }
+            Pairing => pairing(input),
+            MapG1 => {
+                check_length(input, FIELD_SIZE)?;
+                read_fp(input)?;
+                let u =
+                    <G1Projective as MapToCurve>::Field::from_okm(GenericArray::from_slice(input));
+                Ok(write_g1(&G1Projective::map_to_curve(&u).clear_h()))
+            }
+            MapG2 => {
+                check_length(input, 2 * FIELD_SIZE)?;
+                read_fp(&input[..FIELD_SIZE])?;
+                read_fp(&input[FIELD_SIZE..])?;
+                // c0 | c1, the order from_okm reads them in.
+                let u =
+                    <G2Projective as MapToCurve>::Field::from_okm(GenericArray::from_slice(input));
+                Ok(write_g2(&G2Projective::map_to_curve(&u).clear_h()))
+            }
+        }
+    }
+}
+
+fn malformed(msg: impl Into<String>) -> Error {
+    Error::new(ErrorCode::MalformedInputs, msg)
+}
+
+fn check_length(input: &[u8], size: usize) -> Result<()> {
+    if input.len() != size {
+        return Err(malformed("invalid input length"));
+    }
+    Ok(())
+}
+
+fn multi_exp_gas(k: usize, mul_gas: u64) -> u64 {
+    if k == 0 {
+        return 0;
+    }
+    let discount = MULTI_EXP_DISCOUNT[(k - 1).min(MULTI_EXP_DISCOUNT.len() - 1)];
+    (k as u64).saturating_mul(mul_gas).saturating_mul(discount) / 1000
+}
+
+/// Decodes a field element into its 48 significant bytes.
+fn read_fp(b: &[u8]) -> Result<[u8; 48]> {
+    let (top, fp) = b[..FIELD_SIZE].split_at(FIELD_SIZE - 48);
+    if top.iter().any(|&b| b != 0) {
+        return Err(malformed("invalid field element top bytes"));
+    }
+    if fp >= &MODULUS[..] {
+        return Err(malformed("field element is not less than the modulus"));
+    }
+    let mut out = [0u8; 48];
+    out.copy_from_slice(fp);
+    Ok(out)
+}
+
+fn write_fp(fp: &[u8], out: &mut Vec<u8>) {
+    out.extend([0u8; FIELD_SIZE - 48]);
+    out.extend_from_slice(fp);
+}
+
+/// Decodes a G1 point, checking it's on the curve but not that it's in the
+/// subgroup.
+fn read_g1(b: &[u8]) -> Result<G1Affine> {
+    if b.iter().all(|&b| b == 0) {
+        return Ok(G1Affine::identity());
+    }
+    let mut repr = [0u8; 96];
+    repr[..48].copy_from_slice(&read_fp(&b[..FIELD_SIZE])?);
+    repr[48..].copy_from_slice(&read_fp(&b[FIELD_SIZE..G1_SIZE])?);
+    // Canonical coordinates leave the flag bits clear.
+    Option::from(G1Affine::from_uncompressed_unchecked(&repr))
+        .filter(|p: &G1Affine| p.is_on_curve().into())
+        .ok_or_else(|| malformed("point is not on curve"))
+}
+
+fn write_g1(p: &G1Projective) -> Vec<u8> {
+    let p = p.to_affine();
+    if bool::from(p.is_identity()) {
+        return vec![0u8; G1_SIZE];
+    }
+    let repr = p.to_uncompressed();
+    let mut out = Vec::with_capacity(G1_SIZE);
+    write_fp(&repr[..48], &mut out);
+    write_fp(&repr[48..], &mut out);
+    out
+}
+
+/// Decodes a G2 point, checking it's on the curve but not that it's in the
+/// subgroup.
+fn read_g2(b: &[u8]) -> Result<G2Affine> {
+    if b.iter().all(|&b| b == 0) {
+        return Ok(G2Affine::identity());
+    }
+    // bls12_381 puts c1 before c0.
+    let mut repr = [0u8; 192];
+    for (i, at) in [48, 0, 144, 96].into_iter().enumerate() {
+        let fp = read_fp(&b[i * FIELD_SIZE..(i + 1) * FIELD_SIZE])?;
+        repr[at..at + 48].copy_from_slice(&fp);
+    }
+    Option::from(G2Affine::from_uncompressed_unchecked(&repr))
+        .filter(|p: &G2Affine| p.is_on_curve().into())
+        .ok_or_else(|| malformed("point is not on curve"))
+}
+
+fn write_g2(p: &G2Projective) -> Vec<u8> {
+    let p = p.to_affine();
+    if bool::from(p.is_identity()) {
+        return vec![0u8; G2_SIZE];
+    }
+    let repr = p.to_uncompressed();
+    let mut out = Vec::with_capacity(G2_SIZE);
+    for at in [48, 0, 144, 96] {
+        write_fp(&repr[at..at + 48], &mut out);
+    }
+    out
+}
+
+/// Bits `at..at + width` of a big-endian scalar.
+fn window(scalar: &[u8], at: usize, width: usize) -> usize {
+    (at..(at + width).min(8 * SCALAR_SIZE))
+        .rev()
+        .fold(0, |acc, bit| {
+            (acc << 1) | ((scalar[SCALAR_SIZE - 1 - bit / 8] >> (bit % 8)) & 1) as usize
+        })
+}
+
+/// Multiplies by a whole 256-bit scalar: the points needn't be in the
+/// subgroup, so the scalar can't be reduced.
+fn mul<G: Group>(p: G, scalar: &[u8]) -> G {
+    (0..8 * SCALAR_SIZE).rev().fold(G::identity(), |acc, bit| {
+        let acc = acc.double();
+        match window(scalar, bit, 1) {
+            1 => acc + p,
+            _ => acc,
+        }
+    })
+}
+
+/// Reads the `point | scalar` pairs of a multi-exponentiation.
+fn read_pairs<G>(
+    input: &[u8],
+    point_size: usize,
+    read: impl Fn(&[u8]) -> Result<G>,
+) -> Result<Vec<(G, &[u8])>> {
+    let size = point_size + SCALAR_SIZE;
+    if input.is_empty() || !input.len().is_multiple_of(size) {
+        return Err(malformed("invalid input length"));
+    }
+    input
+        .chunks_exact(size)
+        .map(|pair| Ok((read(&pair[..point_size])?, &pair[point_size..])))
+        .collect()
+}
+
+/// Pippenger's bucket method over whole 256-bit scalars.
+fn multi_exp<G: Group>(pairs: &[(G, &[u8])]) -> G {
+    let width = (usize::BITS - pairs.len().leading_zeros()).clamp(2, 16) as usize;
+    let mut acc = G::identity();
+    for w in (0..(8 * SCALAR_SIZE).div_ceil(width)).rev() {
+        for _ in 0..width {
+            acc = acc.double();
+        }
+        let mut buckets = vec![G::identity(); (1 << width) - 1];
+        for (p, s) in pairs {
+            match window(s, w * width, width) {
+                0 => {}
+                d => buckets[d - 1] += p,
+            }
+        }
+        let mut running = G::identity();
+        for b in buckets.iter().rev() {
+            running += b;
+            acc += running;
+        }
+    }
+    acc
+}
+
+fn pairing(input: &[u8]) -> Result<Vec<u8>> {
+    let size = G1_SIZE + G2_SIZE;
+    if input.is_empty() || !input.len().is_multiple_of(size) {
+        return Err(malformed("invalid input length"));
+    }
+    let pairs = input
+        .chunks_exact(size)
+        .map(|pair| {
+            let p = read_g1(&pair[..G1_SIZE])?;
+            let q = read_g2(&pair[G1_SIZE..])?;
+            if !bool::from(p.is_torsion_free()) {
+                return Err(malformed("g1 point is not on correct subgroup"));
+            }
+            if !bool::from(q.is_torsion_free()) {
+                return Err(malformed("g2 point is not on correct subgroup"));
+            }
+            Ok((p, G2Prepared::from(q)))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let terms = pairs.iter().map(|(p, q)| (p, q)).collect::<Vec<_>>();
+    let mut out = vec![0u8; PAIRING_OUTPUT_SIZE];
+    out[PAIRING_OUTPUT_SIZE - 1] =
+        (multi_miller_loop(&terms).final_exponentiation() == Gt::identity()) as u8;
+    Ok(out)
+}
+
+/// Runs the precompile `op` on `input`, writing its output, of
+/// `Operation::output_size` bytes, to `output`. A negative code means the
+/// call fails and `output` is left untouched; the pairing returns `Ok`
+/// whatever its result, which is the output word.
+#[no_mangle]
+pub extern "C" fn bls12381_precompile(
+    op: libc::c_int,
+    input: *mut libc::c_uchar,
+    input_len: libc::size_t,
+    output: *mut libc::c_uchar,
+    output_len: libc::size_t,
+) -> libc::c_int {
+    error::status((|| {
+        let out = Operation::from_raw(op)?.run(bytes(input, input_len)?)?;
+        write_out(output, output_len, &out)?;
+        Ok(true)
+    })())
+}
+
+/// Returns the gas the precompile `op` requires for `input`. An unsupported
+/// operation costs `u64::MAX`, so that a call relying on it runs out of gas.
+#[no_mangle]
+pub extern "C" fn bls12381_precompile_gas(
+    op: libc::c_int,
+    input: *mut libc::c_uchar,
+    input_len: libc::size_t,
+) -> u64 {
+    match (Operation::from_raw(op), bytes(input, input_len)) {
+        (Ok(op), Ok(input)) => op.gas(input),
+        _ => u64::MAX,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bls12_381::hash_to_curve::{ExpandMsgXmd, HashToCurve};
+    use bls12_381::Scalar;
+    use sha2::Sha256;
+
+    /// The group order, big-endian.
+    const ORDER: [u8; 32] = [
+        0x73, 0xed, 0xa7, 0x53, 0x29, 0x9d, 0x7d, 0x48, 0x33, 0x39, 0xd8, 0x08, 0x09, 0xa1, 0xd8,
+        0x05, 0x53, 0xbd, 0xa4, 0x02, 0xff, 0xfe, 0x5b, 0xfe, 0xff, 0xff, 0xff, 0xff, 0x00, 0x00,
+        0x00, 0x01,
+    ];
+
+    fn hex(b: &[u8]) -> String {
+        b.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn scalar(n: u64) -> [u8; SCALAR_SIZE] {
+        let mut s = [0u8; SCALAR_SIZE];
+        s[SCALAR_SIZE - 8..].copy_from_slice(&n.to_be_bytes());
+        s
+    }
+
+    fn run(op: Operation, parts: &[&[u8]]) -> Result<Vec<u8>> {
+        op.run(&parts.concat())
+    }
+
+    /// A point on the curve outside of the subgroup: the map before the
+    /// cofactor clearing.
+    fn off_subgroup_g1() -> G1Projective {
+        let mut u = [<G1Projective as MapToCurve>::Field::default()];
+        HashToField::hash_to_field::<ExpandMsgXmd<Sha256>>(b"off", b"TEST", &mut u);
+        let p = G1Projective::map_to_curve(&u[0]);
+        assert!(!bool::from(p.to_affine().is_torsion_free()));
+        p
+    }
+
+    #[test]
+    fn test_encoding() {
+        let g = write_g1(&G1Projective::generator());
+        assert_eq!(
+            "00000000000000000000000000000000\
+             17f1d3a73197d7942695638c4fa9ac0fc3688c4f9774b905a14e3a3f171bac586c55e83ff97a1aeffb3af00adb22c6bb\
+             00000000000000000000000000000000\
+             08b3f481e3aaa0f1a09e30ed741d8ae4fcf5e095d5d00af600db18cb2c04b3edd03cc744a2888ae40caa232946c5e7e1",
+            hex(&g)
+        );
+        assert_eq!(G1Affine::generator(), read_g1(&g).unwrap());
+        let g2 = write_g2(&G2Projective::generator());
+        // x.c0 comes first.
+        assert_eq!(
+            "024aa2b2f08f0a91260805272dc51051c6e47ad4fa403b02b4510b647ae3d1770bac0326a805bbefd48056c8c121bdb8",
+            hex(&g2[16..64])
+        );
+        assert_eq!(G2Affine::generator(), read_g2(&g2).unwrap());
+        assert_eq!(vec![0u8; G1_SIZE], write_g1(&G1Projective::identity()));
+        assert_eq!(G2Affine::identity(), read_g2(&[0u8; G2_SIZE]).unwrap());
+
+        let mut bad = g.clone();
+        bad[0] = 1;
+        let err = read_g1(&bad).unwrap_err();
+        assert_eq!("invalid field element top bytes", err.to_string());
+        let mut bad = g.clone();
+        bad[16..64].copy_from_slice(&MODULUS);
+        let err = read_g1(&bad).unwrap_err();
+        assert_eq!(
+            "field element is not less than the modulus",
+            err.to_string()
+        );
+        let mut bad = g.clone();
+        bad[G1_SIZE - 1] ^= 1;
+        assert_eq!(
+            "point is not on curve",
+            read_g1(&bad).unwrap_err().to_string()
+        );
+        let mut bad = g2.clone();
+        bad[G2_SIZE - 1] ^= 1;
+        assert_eq!(
+            "point is not on curve",
+            read_g2(&bad).unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn test_g1() {
+        let g = write_g1(&G1Projective::generator());
+        let double = write_g1(&G1Projective::generator().double());
+        assert_eq!(double, run(Operation::G1Add, &[&g, &g]).unwrap());
+        assert_eq!(double, run(Operation::G1Mul, &[&g, &scalar(2)]).unwrap());
+        assert_eq!(g, run(Operation::G1Add, &[&g, &[0u8; G1_SIZE]]).unwrap());
+        assert_eq!(
+            vec![0u8; G1_SIZE],
+            run(Operation::G1Mul, &[&g, &ORDER]).unwrap()
+        );
+        assert_eq!(
+            vec![0u8; G1_SIZE],
+            run(Operation::G1Mul, &[&g, &scalar(0)]).unwrap()
+        );
+        // Scalars aren't reduced, which shows off the subgroup.
+        let off = write_g1(&off_subgroup_g1());
+        assert_ne!(
+            vec![0u8; G1_SIZE],
+            run(Operation::G1Mul, &[&off, &ORDER]).unwrap()
+        );
+        assert_eq!(
+            write_g1(&(off_subgroup_g1() + G1Projective::generator())),
+            run(Operation::G1Add, &[&off, &g]).unwrap()
+        );
+        // The generator is in the subgroup, so the scalar works mod the order.
+        let mut wide = [0u8; 64];
+        wide[..SCALAR_SIZE].copy_from_slice(&[0xff; SCALAR_SIZE]);
+        assert_eq!(
+            write_g1(&(G1Projective::generator() * Scalar::from_bytes_wide(&wide))),
+            run(Operation::G1Mul, &[&g, &[0xff; SCALAR_SIZE]]).unwrap()
+        );
+
+        let err = run(Operation::G1Add, &[&g]).unwrap_err();
+        assert_eq!("invalid input length", err.to_string());
+        let err = run(Operation::G1Mul, &[&g, &scalar(2)[1..]]).unwrap_err();
+        assert_eq!("invalid input length", err.to_string());
+    }
+
+    #[test]
+    fn test_multi_exp() {
+        // Enough pairs to use several window widths.
+        for k in [1, 2, 5, 40] {
+            let points = (0..k)
+                .map(|i| match i % 3 {
+                    0 => off_subgroup_g1() * Scalar::from(i as u64 + 1),
+                    _ => G1Projective::generator() * Scalar::from(i as u64 * 7919 + 3),
+                })
+                .collect::<Vec<_>>();
+            let scalars = (0..k)
+                .map(|i| {
+                    let mut s = [0u8; SCALAR_SIZE];
+                    for (j, b) in s.iter_mut().enumerate() {
+                        *b = (i * 31 + j * 17) as u8;
+                    }
+                    s
+                })
+                .collect::<Vec<_>>();
+            let mut input = vec![];
+            let mut expected = G1Projective::identity();
+            for (p, s) in points.iter().zip(scalars.iter()) {
+                input.extend(write_g1(p));
+                input.extend(s);
+                expected += mul(*p, s);
+            }
+            assert_eq!(
+                write_g1(&expected),
+                Operation::G1MultiExp.run(&input).unwrap()
+            );
+        }
+
+        let g2 = write_g2(&G2Projective::generator());
+        let mut input = [&g2[..], &scalar(3), &g2, &scalar(4)].concat();
+        assert_eq!(
+            write_g2(&(G2Projective::generator() * Scalar::from(7))),
+            Operation::G2MultiExp.run(&input).unwrap()
+        );
+        input.pop();
+        let err = Operation::G2MultiExp.run(&input).unwrap_err();
+        assert_eq!("invalid input length", err.to_string());
+        assert!(Operation::G1MultiExp.run(&[]).is_err());
+    }
+
+    #[test]
+    fn test_g2() {
+        let g = write_g2(&G2Projective::generator());
+        let double = write_g2(&G2Projective::generator().double());
+        assert_eq!(double, run(Operation::G2Add, &[&g, &g]).unwrap());
+        assert_eq!(double, run(Operation::G2Mul, &[&g, &scalar(2)]).unwrap());
+        assert_eq!(
+            vec![0u8; G2_SIZE],
+            run(Operation::G2Mul, &[&g, &ORDER]).unwrap()
+        );
+        let err = run(Operation::G2Add, &[&g, &g[1..]]).unwrap_err();
+        assert_eq!("invalid input length", err.to_string());
+    }
+
+    #[test]
+    fn test_pairing() {
+        let g1 = write_g1(&G1Projective::generator());
+        let neg_g1 = write_g1(&-G1Projective::generator());
+        let g2 = write_g2(&G2Projective::generator());
+        let two_g2 = write_g2(&G2Projective::generator().double());
+        let two_g1 = write_g1(&G1Projective::generator().double());
+        let one = scalar(1).to_vec();
+        let zero = scalar(0).to_vec();
+
+        assert_eq!(
+            one,
+            run(Operation::Pairing, &[&g1, &g2, &neg_g1, &g2]).unwrap()
+        );
+        assert_eq!(
+            one,
+            run(Operation::Pairing, &[&two_g1, &g2, &neg_g1, &two_g2]).unwrap()
+        );
+        assert_eq!(zero, run(Operation::Pairing, &[&g1, &g2]).unwrap());
+        assert_eq!(
+            one,
+            run(Operation::Pairing, &[&[0u8; G1_SIZE], &g2]).unwrap()
+        );
+
+        let off = write_g1(&off_subgroup_g1());
+        let err = run(Operation::Pairing, &[&off, &g2]).unwrap_err();
+        assert_eq!("g1 point is not on correct subgroup", err.to_string());
+        let err = run(Operation::Pairing, &[]).unwrap_err();
+        assert_eq!("invalid input length", err.to_string());
+    }
+
+    #[test]
+    fn test_map() {
+        let dst = b"QUUX-V01-CS02-with-BLS12381G1_XMD:SHA-256_SSWU_NU_";
+        let mut u = [<G1Projective as MapToCurve>::Field::default()];
+        HashToField::hash_to_field::<ExpandMsgXmd<Sha256>>(b"abc", dst, &mut u);
+        let mut input = vec![0u8; 16];
+        input.extend(u[0].to_bytes());
+        let expected =
+            <G1Projective as HashToCurve<ExpandMsgXmd<Sha256>>>::encode_to_curve(b"abc", dst);
+        assert_eq!(write_g1(&expected), Operation::MapG1.run(&input).unwrap());
+
+        let dst = b"QUUX-V01-CS02-with-BLS12381G2_XMD:SHA-256_SSWU_NU_";
+        let mut u = [<G2Projective as MapToCurve>::Field::default()];
+        HashToField::hash_to_field::<ExpandMsgXmd<Sha256>>(b"abc", dst, &mut u);
+        let mut input = vec![0u8; 16];
+        input.extend(u[0].c0.to_bytes());
+        input.extend([0u8; 16]);
+        input.extend(u[0].c1.to_bytes());
+        let expected =
+            <G2Projective as HashToCurve<ExpandMsgXmd<Sha256>>>::encode_to_curve(b"abc", dst);
+        assert_eq!(write_g2(&expected), Operation::MapG2.run(&input).unwrap());
+
+        let mut bad = vec![0u8; 16];
+        bad.extend(MODULUS);
+        let err = Operation::MapG1.run(&bad).unwrap_err();
+        assert_eq!(
+            "field element is not less than the modulus",
+            err.to_string()
+        );
+        let err = Operation::MapG2.run(&bad).unwrap_err();
+        assert_eq!("invalid input length", err.to_string());
+    }
+
+    #[test]
+    fn test_gas() {
+        let pair = vec![0u8; G1_SIZE + SCALAR_SIZE];
+        assert_eq!(0, Operation::G1MultiExp.gas(&pair[1..]));
+        assert_eq!(14400, Operation::G1MultiExp.gas(&pair));
+        assert_eq!(21312, Operation::G1MultiExp.gas(&pair.repeat(2)));
+        assert_eq!(
+            200 * 55000 * 174 / 1000,
+            Operation::G2MultiExp.gas(&vec![0u8; 200 * (G2_SIZE + SCALAR_SIZE)])
+        );
+        assert_eq!(
+            115000 + 2 * 23000,
+            Operation::Pairing.gas(&[0u8; 2 * (G1_SIZE + G2_SIZE) + 1])
+        );
+        assert_eq!(5500, Operation::MapG1.gas(&[]));
+        assert_eq!(
+            u64::MAX,
+            bls12381_precompile_gas(9, std::ptr::null_mut(), 0)
+        );
+        assert_eq!(
+            110000,
+            bls12381_precompile_gas(Operation::MapG2 as libc::c_int, std::ptr::null_mut(), 0)
+        );
+    }
+
+    #[test]
+    fn test_ffi() {
+        let mut input = [write_g1(&G1Projective::generator()), scalar(3).to_vec()].concat();
+        let mut out = [0u8; G1_SIZE];
+        let code = bls12381_precompile(
+            Operation::G1Mul as libc::c_int,
+            input.as_mut_ptr(),
+            input.len(),
+            out.as_mut_ptr(),
+            out.len(),
+        );
+        assert_eq!(1, code);
+        assert_eq!(
+            write_g1(&(G1Projective::generator() * Scalar::from(3))),
+            out.to_vec()
+        );
+        let code = bls12381_precompile(
+            Operation::G2Mul as libc::c_int,
+            input.as_mut_ptr(),
+            input.len(),
+            out.as_mut_ptr(),
+            out.len(),
+        );
+        assert_eq!(ErrorCode::MalformedInputs as libc::c_int, code);
+        let code = bls12381_precompile(
+            Operation::G1Mul as libc::c_int,
+            input.as_mut_ptr(),
+            input.len(),
+            out.as_mut_ptr(),
+            out.len() - 1,
+        );
+        assert_eq!(ErrorCode::BufferTooSmall as libc::c_int, code);
+        let code = bls12381_precompile(
+            -1,
+            input.as_mut_ptr(),
+            input.len(),
+            out.as_mut_ptr(),
+            out.len(),
+        );
+        assert_eq!(ErrorCode::MalformedInputs as libc::c_int, code);
+    }
+}
@@ -0,0 +1,493 @@
+//! Circuits built into the library, selected over the FFI by their
+//! `CircuitId`. They are all over the BLS12-381 scalar field, bellman can't
+//! prove on BN254.
+//!
+//! A witness is the concatenation of the circuit's private values as 32-byte
+//! little-endian scalars, in the order the circuit documents. Public inputs
+//! are derived from it, they are what `verify` expects for the proof.
+
+use std::fmt;
+
+use bellman::gadgets::boolean::{AllocatedBit, Boolean};
+use bellman::gadgets::num::AllocatedNum;
+use bellman::{Circuit, ConstraintSystem, SynthesisError};
+use bls12_381::{Bls12, Scalar};
+use ff::PrimeFieldBits;
+
+use crate::error::{Error, ErrorCode, Result};
+use crate::groth16::{Groth16Engine, SCALAR_SIZE};
+use crate::merkle::{empty_leaf, MAX_DEPTH};
+use crate::poseidon;
+
+/// Raw id of `CircuitId::MerkleMembership`, or'ed with the depth.
+const MERKLE_MEMBERSHIP: libc::c_int = 0x100;
+/// Raw id of `CircuitId::Spend`, or'ed with the depth.
+const SPEND: libc::c_int = 0x200;
+
+/// Names of the circuits for usage messages.
+pub const CIRCUIT_NAMES: &str = "multiply, merkle-<depth>, spend-<depth> (depth 1 to 32)";
+
+/// Identifies a built-in circuit, passed as the `circuit` argument of the
+/// exported functions. `Multiply` is 0, `MerkleMembership(depth)` is
+/// `0x100 | depth` and `Spend(depth)` is `0x200 | depth`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CircuitId {
+    /// `Multiply`, the witness is `a | b`.
+    Multiply,
+    /// `MerkleMembership` of the given depth, the witness is
+    /// `leaf | siblings | index`, see `merkle_tree_path`.
+    MerkleMembership(usize),
+    /// `Spend` from a shielded pool tree of the given depth, the witness is
+    /// `nullifier | secret | siblings | index | external`, see
+    /// `pool::spend_witness`.
+    Spend(usize),
+}
+
+impl CircuitId {
+    pub fn from_raw(circuit: libc::c_int) -> Result<Self> {
+        let depth = (circuit & 0xff) as usize;
+        let family = circuit & !0xff;
+        match circuit {
+            0 => Ok(CircuitId::Multiply),
+            _ if family == MERKLE_MEMBERSHIP && (1..=MAX_DEPTH).contains(&depth) => {
+                Ok(CircuitId::MerkleMembership(depth))
+            }
+            _ if family == SPEND && (1..=MAX_DEPTH).contains(&depth) => Ok(CircuitId::Spend(depth)),
+            _ => Err(Error::new(
+                ErrorCode::UnsupportedCircuit,
+                format!("unsupported circuit {}", circuit),
+            )),
+        }
+    }
+
+    pub fn to_raw(self) -> libc::c_int {
+        match self {
+            CircuitId::Multiply => 0,
+            CircuitId::MerkleMembership(depth) => MERKLE_MEMBERSHIP | depth as libc::c_int,
+            CircuitId::Spend(depth) => SPEND | depth as libc::c_int,
+        }
+    }
+
+    /// Parses the name of the circuit on the command line of the setup
+    /// tool, as printed by `Display`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let depth = |prefix| {
+            name.strip_prefix(prefix)
+                .and_then(|d| d.parse().ok())
+                .filter(|d| (1..=MAX_DEPTH).contains(d))
+        };
+        match name {
+            "multiply" => Some(CircuitId::Multiply),
+            _ => depth("merkle-")
+                .map(CircuitId::MerkleMembership)
+                .or_else(|| depth("spend-").map(CircuitId::Spend)),
+        }
+    }
+
+    /// The circuit without a witness, for parameter generation.
+    pub fn blank(self) -> Builtin {
+        match self {
+            CircuitId::Multiply => Builtin::Multiply(Multiply { a: None, b: None }),
+            CircuitId::MerkleMembership(depth) => Builtin::MerkleMembership(MerkleMembership {
+                leaf: None,
+                siblings: vec![None; depth],
+                index: None,
+            }),
+            CircuitId::Spend(depth) => Builtin::Spend(Spend {
+                nullifier: None,
+                secret: None,
+                siblings: vec![None; depth],
+                index: None,
+                external: None,
+            }),
+        }
+    }
+
+    /// The circuit assigned with a serialized witness.
+    pub fn assign(self, witness: &[u8]) -> Result<Builtin> {
+        match self {
+            CircuitId::Multiply => {
+                let [a, b] = scalars(witness)?;
+                Ok(Builtin::Multiply(Multiply {
+                    a: Some(a),
+                    b: Some(b),
+                }))
+            }
+            CircuitId::MerkleMembership(depth) => {
+                let w = read_witness(witness, depth + 2)?;
+                Ok(Builtin::MerkleMembership(MerkleMembership {
+                    leaf: Some(w[0]),
+                    siblings: w[1..=depth].iter().copied().map(Some).collect(),
+                    index: Some(read_index(&w[depth + 1], depth)?),
+                }))
+            }
+            CircuitId::Spend(depth) => {
+                let w = read_witness(witness, depth + 4)?;
+                Ok(Builtin::Spend(Spend {
+                    nullifier: Some(w[0]),
+                    secret: Some(w[1]),
+                    siblings: w[2..depth + 2].iter().copied().map(Some).collect(),
+                    index: Some(read_index(&w[depth + 2], depth)?),
+                    external: Some(w[depth + 3]),
+                }))
+            }
+        }
+    }
+}
+
+impl fmt::Display for CircuitId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CircuitId::Multiply => f.write_str("multiply"),
+            CircuitId::MerkleMembership(depth) => write!(f, "merkle-{}", depth),
+            CircuitId::Spend(depth) => write!(f, "spend-{}", depth),
+        }
+    }
+}
+
+/// Decodes a witness made of exactly `n` scalars.
+fn read_witness(witness: &[u8], n: usize) -> Result<Vec<Scalar>> {
+    if witness.len() != n * SCALAR_SIZE {
+        return Err(Error::new(
+            ErrorCode::MalformedWitness,
+            format!(
+                "expected a witness of {} bytes, got {}",
+                n * SCALAR_SIZE,
+                witness.len()
+            ),
+        ));
+    }
+    witness
+        .chunks(SCALAR_SIZE)
+        .map(|b| {
+            Bls12::read_scalar(b).ok_or_else(|| {
+                Error::new(
+                    ErrorCode::MalformedWitness,
+                    "witness value is not a canonical scalar",
+                )
+            })
+        })
+        .collect()
+}
+
+/// Decodes the leaf index of a Merkle path, which must fit in `depth` bits.
+fn read_index(index: &Scalar, depth: usize) -> Result<u64> {
+    let bits = index.to_le_bits();
+    if bits.iter().by_vals().skip(depth).any(|b| b) {
+        return Err(Error::new(
+            ErrorCode::MalformedWitness,
+            format!("leaf index doesn't fit in a tree of depth {}", depth),
+        ));
+    }
+    Ok(bits
+        .iter()
+        .by_vals()
+        .take(depth)
+        .enumerate()
+        .fold(0u64, |acc, (i, b)| acc | ((b as u64) << i)))
+}
+
+/// Decodes a witness made of exactly `N` scalars.
+fn scalars<const N: usize>(witness: &[u8]) -> Result<[Scalar; N]> {
+    let w = read_witness(witness, N)?;
+    Ok(std::array::from_fn(|i| w[i]))
+}
+
+/// Any of the built-in circuits.
+pub enum Builtin {
+    Multiply(Multiply),
+    MerkleMembership(MerkleMembership),
+    Spend(Spend),
+}
+
+impl Circuit<Scalar> for Builtin {
+    fn synthesize<CS: ConstraintSystem<Scalar>>(
+        self,
+        cs: &mut CS,
+    ) -> std::result::Result<(), SynthesisError> {
+        match self {
+            Builtin::Multiply(c) => c.synthesize(cs),
+            Builtin::MerkleMembership(c) => c.synthesize(cs),
+            Builtin::Spend(c) => c.synthesize(cs),
+        }
+    }
+}
+
+/// Proves knowledge of `a` and `b` such that `a * b = c` for public `c`.
+pub struct Multiply {
+    pub a: Option<Scalar>,
+    pub b: Option<Scalar>,
+}
+
+impl Circuit<Scalar> for Multiply {
+    fn synthesize<CS: ConstraintSystem<Scalar>>(
+        self,
+        cs: &mut CS,
+    ) -> std::result::Result<(), SynthesisError> {
+        let a = cs.alloc(|| "a", || self.a.ok_or(SynthesisError::AssignmentMissing))?;
+        let b = cs.alloc(|| "b", || self.b.ok_or(SynthesisError::AssignmentMissing))?;
+        let c = cs.alloc_input(
+            || "c",
+            || {
+                Ok(self.a.ok_or(SynthesisError::AssignmentMissing)?
+                    * self.b.ok_or(SynthesisError::AssignmentMissing)?)
+            },
+        )?;
+        cs.enforce(|| "a * b = c", |lc| lc + a, |lc| lc + b, |lc| lc + c);
+        Ok(())
+    }
+}
+
+/// Proves that a private leaf is in a Poseidon Merkle tree with public
+/// root, see the `merkle` module. The path is given by the siblings from the
+/// leaf level up and the leaf index, whose bits say on which side the path
+/// goes at each level. The leaf can't be the empty leaf. Costs about 250
+/// constraints per level.
+pub struct MerkleMembership {
+    pub leaf: Option<Scalar>,
+    pub siblings: Vec<Option<Scalar>>,
+    pub index: Option<u64>,
+}
+
+impl Circuit<Scalar> for MerkleMembership {
+    fn synthesize<CS: ConstraintSystem<Scalar>>(
+        self,
+        cs: &mut CS,
+    ) -> std::result::Result<(), SynthesisError> {
+        let leaf = AllocatedNum::alloc(cs.namespace(|| "leaf"), || {
+            self.leaf.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        // Unfilled positions hold the empty leaf, which must not pass for a
+        // member: leaf - empty has an inverse.
+        let empty = empty_leaf::<Scalar>();
+        let inverse = cs.alloc(
+            || "leaf inverse",
+            || {
+                let leaf = self.leaf.ok_or(SynthesisError::AssignmentMissing)?;
+                Option::from((leaf - empty).invert()).ok_or(SynthesisError::Unsatisfiable)
+            },
+        )?;
+        cs.enforce(
+            || "leaf is not empty",
+            |lc| lc + leaf.get_variable() - (empty, CS::one()),
+            |lc| lc + inverse,
+            |lc| lc + CS::one(),
+        );
+        merkle_root(cs, leaf, self.siblings, self.index)?.inputize(cs.namespace(|| "root"))
+    }
+}
+
+/// Hashes `leaf` up its Merkle path to the root.
+fn merkle_root<CS: ConstraintSystem<Scalar>>(
+    cs: &mut CS,
+    leaf: AllocatedNum<Scalar>,
+    siblings: Vec<Option<Scalar>>,
+    index: Option<u64>,
+) -> std::result::Result<AllocatedNum<Scalar>, SynthesisError> {
+    let mut cur = leaf;
+    for (level, sibling) in siblings.into_iter().enumerate() {
+        let mut cs = cs.namespace(|| format!("level {}", level));
+        let right = Boolean::from(AllocatedBit::alloc(
+            cs.namespace(|| "index bit"),
+            index.map(|i| (i >> level) & 1 == 1),
+        )?);
+        let sibling = AllocatedNum::alloc(cs.namespace(|| "sibling"), || {
+            sibling.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        let (l, r) =
+            AllocatedNum::conditionally_reverse(cs.namespace(|| "order"), &cur, &sibling, &right)?;
+        cur = poseidon::gadget(cs.namespace(|| "hash"), &[l, r])?;
+    }
+    Ok(cur)
+}
+
+/// Proves the spending of a shielded pool note, see the `pool` module: the
+/// note's commitment is in the tree with public root, and the public
+/// nullifier hash is the note's. The public inputs are
+/// `root | nullifier hash | external`, `external` binds the proof to the
+/// withdrawal data the pool hashes with `pool::external_hash`.
+pub struct Spend {
+    pub nullifier: Option<Scalar>,
+    pub secret: Option<Scalar>,
+    pub siblings: Vec<Option<Scalar>>,
+    pub index: Option<u64>,
+    pub external: Option<Scalar>,
+}
+
+impl Circuit<Scalar> for Spend {
+    fn synthesize<CS: ConstraintSystem<Scalar>>(
+        self,
+        cs: &mut CS,
+    ) -> std::result::Result<(), SynthesisError> {
+        let nullifier = AllocatedNum::alloc(cs.namespace(|| "nullifier"), || {
+            self.nullifier.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        let secret = AllocatedNum::alloc(cs.namespace(|| "secret"), || {
+            self.secret.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        let commitment =
+            poseidon::gadget(cs.namespace(|| "commitment"), &[nullifier.clone(), secret])?;
+        merkle_root(cs, commitment, self.siblings, self.index)?
+            .inputize(cs.namespace(|| "root"))?;
+        poseidon::gadget(cs.namespace(|| "nullifier hash"), &[nullifier])?
+            .inputize(cs.namespace(|| "nullifier hash input"))?;
+
+        // The external data isn't otherwise used by the circuit, squaring it
+        // keeps it in a constraint so that a proof doesn't hold for another
+        // value.
+        let external = AllocatedNum::alloc(cs.namespace(|| "external"), || {
+            self.external.ok_or(SynthesisError::AssignmentMissing)
+        })?;
+        external.inputize(cs.namespace(|| "external input"))?;
+        external.square(cs.namespace(|| "external square"))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::groth16::verify_groth16;
+    use crate::merkle::MerkleTree;
+    use crate::prover::create_proof;
+    use crate::setup::generate_parameters;
+    use bellman::gadgets::test::TestConstraintSystem;
+    use rand_core::OsRng;
+
+    #[test]
+    fn test_assign() {
+        let mut witness = Scalar::from(3).to_bytes().to_vec();
+        assert_eq!(
+            ErrorCode::MalformedWitness,
+            CircuitId::Multiply.assign(&witness).err().unwrap().code()
+        );
+        witness.extend_from_slice(&[0xff; SCALAR_SIZE]);
+        assert_eq!(
+            ErrorCode::MalformedWitness,
+            CircuitId::Multiply.assign(&witness).err().unwrap().code()
+        );
+        assert_eq!(Some(CircuitId::Multiply), CircuitId::from_name("multiply"));
+        assert_eq!(None, CircuitId::from_name("divide"));
+        assert_eq!(
+            ErrorCode::UnsupportedCircuit,
+            CircuitId::from_raw(42).err().unwrap().code()
+        );
+    }
+
+    #[test]
+    fn test_circuit_ids() {
+        let merkle = CircuitId::MerkleMembership(20);
+        assert_eq!(0x114, merkle.to_raw());
+        assert_eq!(merkle, CircuitId::from_raw(0x114).unwrap());
+        assert_eq!(Some(merkle), CircuitId::from_name(&merkle.to_string()));
+        assert_eq!(None, CircuitId::from_name("merkle-33"));
+        assert!(CircuitId::from_raw(0x100).is_err());
+        assert!(CircuitId::from_raw(0x121).is_err());
+        assert_eq!(CircuitId::Spend(4), CircuitId::from_raw(0x200 | 4).unwrap());
+        assert_eq!(Some(CircuitId::Spend(4)), CircuitId::from_name("spend-4"));
+        assert!(CircuitId::from_raw(0x300 | 4).is_err());
+    }
+
+    fn merkle_witness(tree: &MerkleTree<Scalar>, leaf: Scalar, index: u64) -> Vec<u8> {
+        let path = tree.path(index).unwrap();
+        let mut w = leaf.to_bytes().to_vec();
+        for s in path.siblings.iter() {
+            w.extend_from_slice(&s.to_bytes());
+        }
+        w.extend_from_slice(&Scalar::from(index).to_bytes());
+        w
+    }
+
+    #[test]
+    fn test_merkle_membership() {
+        let mut tree = MerkleTree::new(4).unwrap();
+        for i in 0..5 {
+            tree.append(Scalar::from(100 + i)).unwrap();
+        }
+        let circuit = CircuitId::MerkleMembership(4);
+        let witness = merkle_witness(&tree, Scalar::from(103), 3);
+
+        let mut cs = TestConstraintSystem::new();
+        circuit
+            .assign(&witness)
+            .unwrap()
+            .synthesize(&mut cs)
+            .unwrap();
+        assert!(cs.is_satisfied());
+        assert_eq!(1, cs.num_inputs() - 1);
+        assert_eq!(tree.root(), cs.get_input(1, "root/input variable"));
+
+        let wrong = merkle_witness(&tree, Scalar::from(104), 3);
+        let mut cs = TestConstraintSystem::new();
+        circuit.assign(&wrong).unwrap().synthesize(&mut cs).unwrap();
+        assert_ne!(tree.root(), cs.get_input(1, "root/input variable"));
+
+        let mut far = witness.clone();
+        far[witness.len() - SCALAR_SIZE..].copy_from_slice(&Scalar::from(16).to_bytes());
+        assert_eq!(
+            ErrorCode::MalformedWitness,
+            circuit.assign(&far).err().unwrap().code()
+        );
+    }
+
+    #[test]
+    fn test_merkle_membership_empty_leaf() {
+        let mut tree = MerkleTree::new(4).unwrap();
+        for i in 0..3 {
+            tree.append(Scalar::from(100 + i)).unwrap();
+        }
+        // The path of the unfilled index 3 is public: its sibling is leaf 2
+        // and the rest is shared with leaf 2.
+        let mut path = tree.path(2).unwrap();
+        path.siblings[0] = Scalar::from(102);
+        path.index = 3;
+        assert_eq!(tree.root(), path.root(empty_leaf()));
+        let witness = |leaf: Scalar| {
+            let mut w = leaf.to_bytes().to_vec();
+            for s in path.siblings.iter() {
+                w.extend_from_slice(&s.to_bytes());
+            }
+            w.extend_from_slice(&Scalar::from(3).to_bytes());
+            w
+        };
+        let circuit = CircuitId::MerkleMembership(4);
+
+        // The empty leaf can't be proven, and a zero leaf isn't in the tree.
+        let mut cs = TestConstraintSystem::new();
+        let err = circuit
+            .assign(&witness(empty_leaf()))
+            .unwrap()
+            .synthesize(&mut cs)
+            .unwrap_err();
+        assert!(matches!(err, SynthesisError::Unsatisfiable));
+        let params = generate_parameters(circuit, Some(b"test")).unwrap();
+        assert!(create_proof(circuit, &params, &witness(empty_leaf()), &mut OsRng).is_err());
+        let mut cs = TestConstraintSystem::new();
+        circuit
+            .assign(&witness(Scalar::zero()))
+            .unwrap()
+            .synthesize(&mut cs)
+            .unwrap();
+        assert!(cs.is_satisfied());
+        assert_ne!(tree.root(), cs.get_input(1, "root/input variable"));
+    }
+
+    #[test]
+    fn test_merkle_membership_proof() {
+        let circuit = CircuitId::MerkleMembership(4);
+        let params = generate_parameters(circuit, Some(b"test")).unwrap();
+        let mut tree = MerkleTree::new(4).unwrap();
+        for i in 0..3 {
+            tree.append(Scalar::from(7 * i)).unwrap();
+        }
+        let witness = merkle_witness(&tree, Scalar::from(14), 2);
+        let bproof = create_proof(circuit, &params, &witness, &mut OsRng).unwrap();
+        let mut bkey = vec![];
+        params.vk.write(&mut bkey).unwrap();
+        let root = tree.root().to_bytes();
+        assert!(verify_groth16::<Bls12>(&bproof, &bkey, &root).unwrap());
+        tree.append(Scalar::from(21)).unwrap();
+        let root = tree.root().to_bytes();
+        assert!(!verify_groth16::<Bls12>(&bproof, &bkey, &root).unwrap());
+    }
+}
@@ -0,0 +1,748 @@
+//! Distributed generation of threshold BLS keys, the Joint-Feldman protocol
+//! of Pedersen's DKG. Each of the `n` participants deals a random secret
+//! with Feldman VSS: it broadcasts a `Deal` committing to its polynomial of
+//! degree `threshold - 1` and privately sends participant `j` its value at
+//! `j`, a `DealtShare`. A participant receiving a share that doesn't match
+//! the commitments broadcasts a `Complaint`, to which the accused dealer
+//! answers with a `Justification` revealing the share; a dealer failing to
+//! do so, or revealing a wrong one, is disqualified.
+//!
+//! The group secret is the sum of the secrets of the qualified dealers,
+//! known to none of them. A participant's share of it is the sum of the
+//! shares it got from them and the group commitments the sum of theirs, so
+//! the result is used with `threshold` as if a trusted dealer had split the
+//! key. As with any Joint-Feldman run, a rushing dealer can bias the group
+//! key by getting itself disqualified, which doesn't help forging
+//! signatures.
+//!
+//! Messages are plain byte strings for the node to carry, it must broadcast
+//! deals, complaints and justifications reliably so that all participants
+//! agree on the qualified dealers, and encrypt dealt shares to their
+//! recipient.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use bls12_381::{G1Affine, G1Projective, Scalar};
+use ff::Field;
+use rand_core::{OsRng, RngCore};
+
+use crate::bls::SecretKey;
+use crate::error::{self, Error, ErrorCode, Result};
+use crate::ffi::{bytes, write_out};
+use crate::threshold::{
+    check_threshold, commit, evaluate, read_index, Commitments, Share, SHARE_SIZE,
+};
+
+/// Size of a serialized dealt share or justification, the dealer's
+/// big-endian u32 index and the share.
+pub const DEALT_SHARE_SIZE: usize = 4 + SHARE_SIZE;
+/// Size of a serialized complaint, the big-endian u32 indices of the
+/// complainer and of the dealer.
+pub const COMPLAINT_SIZE: usize = 8;
+
+/// Commitments of a dealer to its polynomial, broadcast.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Deal {
+    pub dealer: u32,
+    pub commitments: Commitments,
+}
+
+/// A share sent by a dealer to the participant with the share's index.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DealtShare {
+    pub dealer: u32,
+    pub share: Share,
+}
+
+/// A claim that `dealer` sent `complainer` a wrong share or none, broadcast.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Complaint {
+    pub complainer: u32,
+    pub dealer: u32,
+}
+
+/// The share a dealer sent a complainer, revealed to all.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Justification {
+    pub dealer: u32,
+    pub share: Share,
+}
+
+impl Deal {
+    pub fn from_bytes(b: &[u8]) -> Result<Self> {
+        if b.len() < 4 {
+            return Err(Error::new(ErrorCode::MalformedInputs, "deal is too short"));
+        }
+        Ok(Deal {
+            dealer: read_index(b)?,
+            commitments: Commitments::from_bytes(&b[4..])?,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut b = self.dealer.to_be_bytes().to_vec();
+        b.extend_from_slice(&self.commitments.to_bytes());
+        b
+    }
+}
+
+fn read_dealt_share(b: &[u8], what: &str) -> Result<(u32, Share)> {
+    if b.len() != DEALT_SHARE_SIZE {
+        return Err(Error::new(
+            ErrorCode::MalformedInputs,
+            format!(
+                "{} must be {} bytes, got {}",
+                what,
+                DEALT_SHARE_SIZE,
+                b.len()
+            ),
+        ));
+    }
+    Ok((read_index(b)?, Share::from_bytes(&b[4..])?))
+}
+
+fn write_dealt_share(dealer: u32, share: &Share) -> [u8; DEALT_SHARE_SIZE] {
+    let mut b = [0u8; DEALT_SHARE_SIZE];
+    b[..4].copy_from_slice(&dealer.to_be_bytes());
+    b[4..].copy_from_slice(&share.to_bytes());
+    b
+}
+
+impl DealtShare {
+    pub fn from_bytes(b: &[u8]) -> Result<Self> {
+        let (dealer, share) = read_dealt_share(b, "dealt share")?;
+        Ok(DealtShare { dealer, share })
+    }
+
+    pub fn to_bytes(&self) -> [u8; DEALT_SHARE_SIZE] {
+        write_dealt_share(self.dealer, &self.share)
+    }
+}
+
+impl Justification {
+    pub fn from_bytes(b: &[u8]) -> Result<Self> {
+        let (dealer, share) = read_dealt_share(b, "justification")?;
+        Ok(Justification { dealer, share })
+    }
+
+    pub fn to_bytes(&self) -> [u8; DEALT_SHARE_SIZE] {
+        write_dealt_share(self.dealer, &self.share)
+    }
+}
+
+impl Complaint {
+    pub fn from_bytes(b: &[u8]) -> Result<Self> {
+        if b.len() != COMPLAINT_SIZE {
+            return Err(Error::new(
+                ErrorCode::MalformedInputs,
+                format!(
+                    "complaint must be {} bytes, got {}",
+                    COMPLAINT_SIZE,
+                    b.len()
+                ),
+            ));
+        }
+        Ok(Complaint {
+            complainer: read_index(b)?,
+            dealer: read_index(&b[4..])?,
+        })
+    }
+
+    pub fn to_bytes(&self) -> [u8; COMPLAINT_SIZE] {
+        let mut b = [0u8; COMPLAINT_SIZE];
+        b[..4].copy_from_slice(&self.complainer.to_be_bytes());
+        b[4..].copy_from_slice(&self.dealer.to_be_bytes());
+        b
+    }
+}
+
+/// Outcome of a DKG run for a participant.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GroupKey {
+    /// The participant's share of the group secret key.
+    pub share: Share,
+    /// Commitments to the group polynomial, the first being the group
+    /// public key.
+    pub commitments: Commitments,
+    /// Dealers whose secrets make up the group key, in increasing order.
+    pub qualified: Vec<u32>,
+}
+
+/// State of a participant in a DKG run, the object behind the handles
+/// returned by `dkg_new`.
+pub struct Participant {
+    index: u32,
+    threshold: usize,
+    n: usize,
+    coefficients: Vec<Scalar>,
+    deals: BTreeMap<u32, Commitments>,
+    /// Valid shares received, by dealer.
+    shares: BTreeMap<u32, Scalar>,
+    /// Complaints not answered yet.
+    complaints: BTreeSet<Complaint>,
+    disqualified: BTreeSet<u32>,
+}
+
+impl Participant {
+    /// Starts a run as participant `index` (1 to `n`) of a group where
+    /// `threshold` participants can sign.
+    pub fn new(index: u32, threshold: usize, n: usize, mut rng: impl RngCore) -> Result<Self> {
+        check_threshold(threshold, n)?;
+        let coefficients: Vec<Scalar> = (0..threshold).map(|_| Scalar::random(&mut rng)).collect();
+        let mut p = Participant {
+            index,
+            threshold,
+            n,
+            coefficients,
+            deals: BTreeMap::new(),
+            shares: BTreeMap::new(),
+            complaints: BTreeSet::new(),
+            disqualified: BTreeSet::new(),
+        };
+        p.check_index(index)?;
+        p.deals.insert(index, commit(&p.coefficients));
+        p.shares.insert(index, evaluate(&p.coefficients, index));
+        Ok(p)
+    }
+
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    fn check_index(&self, index: u32) -> Result<()> {
+        if index == 0 || index as usize > self.n {
+            return Err(Error::new(
+                ErrorCode::MalformedInputs,
+                format!("participant index must be 1 to {}, got {}", self.n, index),
+            ));
+        }
+        Ok(())
+    }
+
+    fn share_for(&self, index: u32) -> Share {
+        Share {
+            index,
+            key: SecretKey(evaluate(&self.coefficients, index)),
+        }
+    }
+
+    /// The participant's deal and the shares to send the others, by index.
+    pub fn deal(&self) -> (Deal, Vec<DealtShare>) {
+        let deal = Deal {
+            dealer: self.index,
+            commitments: self.deals[&self.index].clone(),
+        };
+        let shares = (1..=self.n as u32)
+            .filter(|&j| j != self.index)
+            .map(|j| DealtShare {
+                dealer: self.index,
+                share: self.share_for(j),
+            })
+            .collect();
+        (deal, shares)
+    }
+
+    /// Records the deal of another participant. A deal of the wrong degree
+    /// disqualifies its dealer and yields false. Deals must be received
+    /// before the shares they commit to.
+    pub fn receive_deal(&mut self, deal: Deal) -> Result<bool> {
+        self.check_index(deal.dealer)?;
+        if let Some(c) = self.deals.get(&deal.dealer) {
+            if *c == deal.commitments {
+                return Ok(true);
+            }
+            return Err(Error::new(
+                ErrorCode::MalformedInputs,
+                format!("second deal from dealer {}", deal.dealer),
+            ));
+        }
+        if deal.commitments.threshold() != self.threshold {
+            self.disqualified.insert(deal.dealer);
+            return Ok(false);
+        }
+        self.deals.insert(deal.dealer, deal.commitments);
+        Ok(true)
+    }
+
+    fn commitments(&self, dealer: u32) -> Result<&Commitments> {
+        self.deals.get(&dealer).ok_or_else(|| {
+            Error::new(
+                ErrorCode::MalformedInputs,
+                format!("no deal from dealer {}", dealer),
+            )
+        })
+    }
+
+    /// Checks a share sent to this participant. A wrong share is recorded as
+    /// a complaint, returned for broadcasting.
+    pub fn receive_share(&mut self, msg: DealtShare) -> Result<Option<Complaint>> {
+        if msg.share.index != self.index {
+            return Err(Error::new(
+                ErrorCode::MalformedInputs,
+                format!(
+                    "share for participant {} received by {}",
+                    msg.share.index, self.index
+                ),
+            ));
+        }
+        if self.commitments(msg.dealer)?.verify_share(&msg.share) {
+            self.shares.insert(msg.dealer, msg.share.key.0);
+            return Ok(None);
+        }
+        let c = Complaint {
+            complainer: self.index,
+            dealer: msg.dealer,
+        };
+        self.complaints.insert(c);
+        Ok(Some(c))
+    }
+
+    /// Records a complaint. If it's against this participant, the
+    /// justification to broadcast is returned.
+    pub fn receive_complaint(&mut self, c: Complaint) -> Result<Option<Justification>> {
+        self.check_index(c.complainer)?;
+        self.check_index(c.dealer)?;
+        if c.complainer == c.dealer {
+            return Err(Error::new(
+                ErrorCode::MalformedInputs,
+                "dealer complains about itself",
+            ));
+        }
+        if c.dealer == self.index {
+            return Ok(Some(Justification {
+                dealer: self.index,
+                share: self.share_for(c.complainer),
+            }));
+        }
+        if !self.disqualified.contains(&c.dealer) {
+            self.complaints.insert(c);
+        }
+        Ok(None)
+    }
+
+    /// Settles a complaint with the share revealed by its dealer. Returns
+    /// true if the share matches the dealer's commitments, which lets the
+    /// complainer use it, and disqualifies the dealer otherwise.
+    pub fn receive_justification(&mut self, j: Justification) -> Result<bool> {
+        let c = Complaint {
+            complainer: j.share.index,
+            dealer: j.dealer,
+        };
+        if !self.complaints.contains(&c) {
+            return Err(Error::new(
+                ErrorCode::MalformedInputs,
+                format!(
+                    "no complaint of participant {} against dealer {}",
+                    c.complainer, c.dealer
+                ),
+            ));
+        }
+        self.complaints.remove(&c);
+        if !self.commitments(j.dealer)?.verify_share(&j.share) {
+            self.disqualify(j.dealer);
+            return Ok(false);
+        }
+        if c.complainer == self.index {
+            self.shares.insert(j.dealer, j.share.key.0);
+        }
+        Ok(true)
+    }
+
+    fn disqualify(&mut self, dealer: u32) {
+        self.disqualified.insert(dealer);
+        self.complaints.retain(|c| c.dealer != dealer);
+    }
+
+    /// Ends the run once complaints had time to be answered: dealers with a
+    /// pending complaint are disqualified and the others that dealt make up
+    /// the group key. At least `threshold` dealers must qualify.
+    pub fn finalize(&mut self) -> Result<GroupKey> {
+        let pending: Vec<u32> = self.complaints.iter().map(|c| c.dealer).collect();
+        for dealer in pending {
+            self.disqualify(dealer);
+        }
+        let qualified: Vec<u32> = self
+            .deals
+            .keys()
+            .copied()
+            .filter(|d| !self.disqualified.contains(d))
+            .collect();
+        if qualified.len() < self.threshold {
+            return Err(Error::new(
+                ErrorCode::InputCountMismatch,
+                format!(
+                    "{} qualified dealers for a threshold of {}",
+                    qualified.len(),
+                    self.threshold
+                ),
+            ));
+        }
+        let mut key = Scalar::ZERO;
+        let mut points = vec![G1Projective::identity(); self.threshold];
+        for d in qualified.iter() {
+            key += self.shares.get(d).ok_or_else(|| {
+                Error::new(
+                    ErrorCode::MalformedInputs,
+                    format!("no share from dealer {}, complain first", d),
+                )
+            })?;
+            for (p, c) in points.iter_mut().zip(self.deals[d].0.iter()) {
+                *p += c;
+            }
+        }
+        let mut commitments = vec![G1Affine::identity(); self.threshold];
+        G1Projective::batch_normalize(&points, &mut commitments);
+        Ok(GroupKey {
+            share: Share {
+                index: self.index,
+                key: SecretKey(key),
+            },
+            commitments: Commitments(commitments),
+            qualified,
+        })
+    }
+}
+
+fn participant_mut<'a>(handle: *mut Participant) -> Result<&'a mut Participant> {
+    unsafe { handle.as_mut() }.ok_or_else(|| Error::new(ErrorCode::NullPointer, "null DKG handle"))
+}
+
+/// Starts a DKG run as participant `index` (1 to `n`) of a group where
+/// `threshold` participants can sign and stores its handle in `handle`. A
+/// handle must not be used by several threads at once, and must be released
+/// with `dkg_free`.
+#[no_mangle]
+pub extern "C" fn dkg_new(
+    index: u32,
+    threshold: libc::size_t,
+    n: libc::size_t,
+    handle: *mut *mut Participant,
+) -> libc::c_int {
+    error::status((|| {
+        if handle.is_null() {
+            return Err(Error::new(ErrorCode::NullPointer, "null handle pointer"));
+        }
+        let p = Participant::new(index, threshold, n, OsRng)?;
+        unsafe { *handle = Box::into_raw(Box::new(p)) };
+        Ok(true)
+    })())
+}
+
+/// Writes the participant's deal, `4 + threshold * 48` bytes, to `deal` and
+/// the `DEALT_SHARE_SIZE` (40) bytes shares for the other participants, by
+/// index, to `shares`.
+#[no_mangle]
+pub extern "C" fn dkg_deal(
+    handle: *mut Participant,
+    deal: *mut libc::c_uchar,
+    deal_len: libc::size_t,
+    shares: *mut libc::c_uchar,
+    shares_len: libc::size_t,
+) -> libc::c_int {
+    error::status((|| {
+        let (d, s) = participant_mut(handle)?.deal();
+        let bshares: Vec<u8> = s.iter().flat_map(DealtShare::to_bytes).collect();
+        write_out(deal, deal_len, &d.to_bytes())?;
+        write_out(shares, shares_len, &bshares)?;
+        Ok(true)
+    })())
+}
+
+/// Records the deal of another participant, returns `Invalid` if it
+/// disqualifies its dealer.
+#[no_mangle]
+pub extern "C" fn dkg_receive_deal(
+    handle: *mut Participant,
+    deal: *mut libc::c_uchar,
+    deal_len: libc::size_t,
+) -> libc::c_int {
+    error::status((|| {
+        let p = participant_mut(handle)?;
+        p.receive_deal(Deal::from_bytes(bytes(deal, deal_len)?)?)
+    })())
+}
+
+/// Checks a 40-byte share sent to the participant. Returns `Ok` if it's
+/// valid, and `Invalid` after writing the 8-byte complaint to broadcast to
+/// `complaint` if not.
+#[no_mangle]
+pub extern "C" fn dkg_receive_share(
+    handle: *mut Participant,
+    share: *mut libc::c_uchar,
+    complaint: *mut libc::c_uchar,
+) -> libc::c_int {
+    error::status((|| {
+        let p = participant_mut(handle)?;
+        match p.receive_share(DealtShare::from_bytes(bytes(share, DEALT_SHARE_SIZE)?)?)? {
+            None => Ok(true),
+            Some(c) => {
+                write_out(complaint, COMPLAINT_SIZE, &c.to_bytes())?;
+                Ok(false)
+            }
+        }
+    })())
+}
+
+/// Records an 8-byte complaint. Returns `Ok` after writing the 40-byte
+/// justification to broadcast to `justification` if the complaint is
+/// against the participant, and `Invalid` otherwise.
+#[no_mangle]
+pub extern "C" fn dkg_receive_complaint(
+    handle: *mut Participant,
+    complaint: *mut libc::c_uchar,
+    justification: *mut libc::c_uchar,
+) -> libc::c_int {
+    error::status((|| {
+        let p = participant_mut(handle)?;
+        match p.receive_complaint(Complaint::from_bytes(bytes(complaint, COMPLAINT_SIZE)?)?)? {
+            None => Ok(false),
+            Some(j) => {
+                write_out(justification, DEALT_SHARE_SIZE, &j.to_bytes())?;
+                Ok(true)
+            }
+        }
+    })())
+}
+
+/// Settles a complaint with a 40-byte justification, returns `Invalid` if
+/// it disqualifies the dealer.
+#[no_mangle]
+pub extern "C" fn dkg_receive_justification(
+    handle: *mut Participant,
+    justification: *mut libc::c_uchar,
+) -> libc::c_int {
+    error::status((|| {
+        let p = participant_mut(handle)?;
+        p.receive_justification(Justification::from_bytes(bytes(
+            justification,
+            DEALT_SHARE_SIZE,
+        )?)?)
+    })())
+}
+
+/// Ends the run, writing the participant's 36-byte share of the group key
+/// to `share` and the `threshold * 48` bytes group commitments, whose first
+/// is the group public key, to `commitments`.
+#[no_mangle]
+pub extern "C" fn dkg_finalize(
+    handle: *mut Participant,
+    share: *mut libc::c_uchar,
+    commitments: *mut libc::c_uchar,
+    commitments_len: libc::size_t,
+) -> libc::c_int {
+    error::status((|| {
+        let key = participant_mut(handle)?.finalize()?;
+        write_out(share, SHARE_SIZE, &key.share.to_bytes())?;
+        write_out(commitments, commitments_len, &key.commitments.to_bytes())?;
+        Ok(true)
+    })())
+}
+
+/// Releases a handle returned by `dkg_new`, null is ignored.
+#[no_mangle]
+pub extern "C" fn dkg_free(handle: *mut Participant) {
+    if !handle.is_null() {
+        drop(unsafe { Box::from_raw(handle) });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bls::{self, Scheme, PUBLIC_KEY_SIZE};
+    use crate::threshold::combine;
+
+    fn run(t: usize, n: usize, cheater: Option<u32>) -> Vec<GroupKey> {
+        let mut ps: Vec<_> = (1..=n as u32)
+            .map(|i| Participant::new(i, t, n, OsRng).unwrap())
+            .collect();
+        let deals: Vec<_> = ps.iter().map(Participant::deal).collect();
+        for p in ps.iter_mut() {
+            for (d, _) in deals.iter() {
+                assert!(p.receive_deal(d.clone()).unwrap());
+            }
+        }
+        let mut complaints = vec![];
+        for (d, shares) in deals.iter() {
+            for s in shares.iter() {
+                let mut s = *s;
+                // The cheater sends participant 1 a wrong share.
+                if Some(d.dealer) == cheater && s.share.index == 1 {
+                    s.share.key = SecretKey(s.share.key.0 + Scalar::ONE);
+                }
+                let p = &mut ps[s.share.index as usize - 1];
+                complaints.extend(
+                    p.receive_share(DealtShare::from_bytes(&s.to_bytes()).unwrap())
+                        .unwrap(),
+                );
+            }
+        }
+        let mut justifications = vec![];
+        for c in complaints.iter() {
+            let c = Complaint::from_bytes(&c.to_bytes()).unwrap();
+            for p in ps.iter_mut() {
+                justifications.extend(p.receive_complaint(c).unwrap());
+            }
+        }
+        for j in justifications.iter() {
+            for p in ps.iter_mut().filter(|p| p.index() != j.dealer) {
+                assert!(p.receive_justification(*j).unwrap());
+            }
+        }
+        ps.iter_mut().map(|p| p.finalize().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_dkg() {
+        let keys = run(3, 5, None);
+        let c = &keys[0].commitments;
+        for k in keys.iter() {
+            assert_eq!(*c, k.commitments);
+            assert_eq!(vec![1, 2, 3, 4, 5], k.qualified);
+            assert!(c.verify_share(&k.share));
+        }
+        let group = c.public_key().unwrap();
+        let partials: Vec<_> = [4, 0, 2]
+            .iter()
+            .map(|&i| {
+                keys[i]
+                    .share
+                    .sign(Scheme::ProofOfPossession, &group, b"root")
+            })
+            .collect();
+        let sig = combine(&partials, 3).unwrap();
+        assert!(bls::verify(
+            Scheme::ProofOfPossession,
+            &group,
+            b"root",
+            &sig
+        ));
+    }
+
+    #[test]
+    fn test_complaints() {
+        // A wrong share is complained about, and the revealed share is used.
+        let keys = run(2, 3, Some(2));
+        for k in keys.iter() {
+            assert_eq!(vec![1, 2, 3], k.qualified);
+            assert!(keys[0].commitments.verify_share(&k.share));
+        }
+
+        let mut ps: Vec<_> = (1..=3u32)
+            .map(|i| Participant::new(i, 2, 3, OsRng).unwrap())
+            .collect();
+        let (d2, s2) = ps[1].deal();
+        let (d3, _) = ps[2].deal();
+        let p = &mut ps[0];
+        assert!(p.receive_share(s2[0]).is_err());
+        p.receive_deal(d2).unwrap();
+        p.receive_deal(d3).unwrap();
+        assert!(p.receive_share(s2[1]).is_err());
+        let mut bad = s2[0];
+        bad.share.key = SecretKey(Scalar::ONE);
+        let c = p.receive_share(bad).unwrap().unwrap();
+        assert_eq!(
+            Complaint {
+                complainer: 1,
+                dealer: 2
+            },
+            c
+        );
+
+        // A wrong justification disqualifies the dealer, as does no share.
+        let j = Justification {
+            dealer: 2,
+            share: bad.share,
+        };
+        assert!(!p.receive_justification(j).unwrap());
+        assert!(p.receive_justification(j).is_err());
+        assert_eq!(
+            ErrorCode::MalformedInputs,
+            p.finalize().err().unwrap().code()
+        );
+        p.receive_complaint(Complaint {
+            complainer: 1,
+            dealer: 3,
+        })
+        .unwrap();
+        assert_eq!(
+            ErrorCode::InputCountMismatch,
+            p.finalize().err().unwrap().code()
+        );
+    }
+
+    #[test]
+    fn test_ffi() {
+        let mut handles = [std::ptr::null_mut(); 3];
+        for (i, h) in handles.iter_mut().enumerate() {
+            assert_eq!(1, dkg_new(i as u32 + 1, 2, 3, h));
+        }
+        let mut h = std::ptr::null_mut();
+        assert_eq!(
+            ErrorCode::MalformedInputs as libc::c_int,
+            dkg_new(4, 2, 3, &mut h)
+        );
+
+        let mut deals = [[0u8; 4 + 2 * PUBLIC_KEY_SIZE]; 3];
+        let mut shares = [[0u8; 2 * DEALT_SHARE_SIZE]; 3];
+        for i in 0..3 {
+            let code = dkg_deal(
+                handles[i],
+                deals[i].as_mut_ptr(),
+                deals[i].len(),
+                shares[i].as_mut_ptr(),
+                shares[i].len(),
+            );
+            assert_eq!(1, code);
+        }
+        for (i, &h) in handles.iter().enumerate() {
+            for (j, d) in deals.iter_mut().enumerate().filter(|(j, _)| *j != i) {
+                assert_eq!(1, dkg_receive_deal(h, d.as_mut_ptr(), d.len()));
+                // Dealer j's shares skip j itself.
+                let k = if i < j { i } else { i - 1 };
+                let mut complaint = [0u8; COMPLAINT_SIZE];
+                let s = &mut shares[j][k * DEALT_SHARE_SIZE..];
+                assert_eq!(
+                    1,
+                    dkg_receive_share(h, s.as_mut_ptr(), complaint.as_mut_ptr())
+                );
+            }
+        }
+        let mut complaint = Complaint {
+            complainer: 1,
+            dealer: 2,
+        }
+        .to_bytes();
+        let mut justification = [0u8; DEALT_SHARE_SIZE];
+        let code = dkg_receive_complaint(
+            handles[1],
+            complaint.as_mut_ptr(),
+            justification.as_mut_ptr(),
+        );
+        assert_eq!(1, code);
+        let code = dkg_receive_complaint(handles[2], complaint.as_mut_ptr(), std::ptr::null_mut());
+        assert_eq!(0, code);
+        assert_eq!(
+            1,
+            dkg_receive_justification(handles[2], justification.as_mut_ptr())
+        );
+
+        let mut group = vec![];
+        for &h in handles.iter() {
+            let mut share = [0u8; SHARE_SIZE];
+            let mut commitments = [0u8; 2 * PUBLIC_KEY_SIZE];
+            let code = dkg_finalize(
+                h,
+                share.as_mut_ptr(),
+                commitments.as_mut_ptr(),
+                commitments.len(),
+            );
+            assert_eq!(1, code);
+            let c = Commitments::from_bytes(&commitments).unwrap();
+            assert!(c.verify_share(&Share::from_bytes(&share).unwrap()));
+            group.push(commitments);
+            dkg_free(h);
+        }
+        assert!(group.iter().all(|c| *c == group[0]));
+    }
+}
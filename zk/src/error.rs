@@ -0,0 +1,137 @@
+use std::cell::RefCell;
+use std::fmt;
+
+/// Codes returned by the exported functions. A verification call returns
+/// `Ok` or `Invalid` when it got as far as checking the proof and one of
+/// the negative codes when the arguments were rejected before that. The Go
+/// wrapper in `pkg/crypto/zk` mirrors these values, keep them in sync.
+#[repr(i32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorCode {
+    /// The call succeeded, for a verification this means the proof is valid.
+    Ok = 1,
+    /// The arguments are well-formed but the proof does not verify.
+    Invalid = 0,
+    /// A pointer argument is null while its length is not zero.
+    NullPointer = -1,
+    /// The proof can't be deserialized or contains points off the curve.
+    MalformedProof = -2,
+    /// The verifying key can't be deserialized or is inconsistent.
+    MalformedKey = -3,
+    /// The public inputs buffer has trailing bytes or non-canonical scalars.
+    MalformedInputs = -4,
+    /// The number of public inputs doesn't match the verifying key.
+    InputCountMismatch = -5,
+    /// The curve selector doesn't name a supported curve.
+    UnsupportedCurve = -6,
+    /// No key is registered under the given id.
+    UnknownKey = -7,
+    /// The proving parameters can't be deserialized.
+    MalformedParams = -8,
+    /// The witness has the wrong size or non-canonical scalars.
+    MalformedWitness = -9,
+    /// The circuit selector doesn't name a built-in circuit.
+    UnsupportedCircuit = -10,
+    /// An output buffer is too small for the result.
+    BufferTooSmall = -11,
+    /// A Merkle tree has no room for another leaf.
+    TreeFull = -12,
+    /// A signature can't be deserialized or isn't in the right subgroup.
+    MalformedSignature = -13,
+    /// The proof system selector doesn't name a supported proof system.
+    UnsupportedProofSystem = -14,
+}
+
+/// Error carrying the code returned over the FFI and a description that the
+/// caller can fetch with `last_error`.
+#[derive(Debug)]
+pub struct Error {
+    code: ErrorCode,
+    message: String,
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Error {
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        self.code
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for Error {}
+
+thread_local! {
+    static LAST_ERROR: RefCell<String> = const { RefCell::new(String::new()) };
+}
+
+/// Converts the outcome of a verification into its FFI code, recording the
+/// reason of a failure as the calling thread's last error.
+pub fn status(res: Result<bool>) -> libc::c_int {
+    let (code, message) = match res {
+        Ok(true) => (ErrorCode::Ok, String::new()),
+        Ok(false) => (ErrorCode::Invalid, "proof verification failed".to_string()),
+        Err(e) => (e.code, e.message),
+    };
+    LAST_ERROR.with(|last| *last.borrow_mut() = message);
+    code as libc::c_int
+}
+
+/// Copies the last error message of the calling thread into `buf`, truncating
+/// it to `buf_len` bytes. Returns the full length of the message, so a caller
+/// may pass a null buffer first to learn the size it needs. The message is
+/// empty after a successful call.
+#[no_mangle]
+pub extern "C" fn last_error(buf: *mut libc::c_uchar, buf_len: libc::size_t) -> libc::c_int {
+    LAST_ERROR.with(|last| {
+        let last = last.borrow();
+        let n = last.len().min(buf_len);
+        if !buf.is_null() && n > 0 {
+            unsafe { std::ptr::copy_nonoverlapping(last.as_ptr(), buf, n) };
+        }
+        last.len() as libc::c_int
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fetch() -> String {
+        let n = last_error(std::ptr::null_mut(), 0) as usize;
+        let mut buf = vec![0u8; n];
+        last_error(buf.as_mut_ptr(), n);
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn test_status() {
+        let err = Error::new(ErrorCode::MalformedKey, "bad key");
+        assert_eq!(-3, status(Err(err)));
+        assert_eq!("bad key", fetch());
+        assert_eq!(0, status(Ok(false)));
+        assert_eq!("proof verification failed", fetch());
+        assert_eq!(1, status(Ok(true)));
+        assert_eq!("", fetch());
+    }
+
+    #[test]
+    fn test_last_error_truncates() {
+        status(Err(Error::new(ErrorCode::MalformedProof, "bad proof")));
+        let mut buf = [0u8; 3];
+        assert_eq!(9, last_error(buf.as_mut_ptr(), buf.len()));
+        assert_eq!(b"bad", &buf);
+    }
+}
@@ -0,0 +1,31 @@
+use crate::error::{Error, ErrorCode, Result};
+
+/// Borrows a buffer passed over the FFI, an empty one may be null.
+pub fn bytes<'a>(ptr: *const libc::c_uchar, len: libc::size_t) -> Result<&'a [u8]> {
+    if len == 0 {
+        return Ok(&[]);
+    }
+    if ptr.is_null() {
+        return Err(Error::new(
+            ErrorCode::NullPointer,
+            "null buffer with non-zero length",
+        ));
+    }
+    Ok(unsafe { std::slice::from_raw_parts(ptr, len) })
+}
+
+/// Copies `b` to an output buffer passed over the FFI, which must hold
+/// exactly `b.len()` bytes.
+pub fn write_out(out: *mut libc::c_uchar, out_len: libc::size_t, b: &[u8]) -> Result<()> {
+    if out_len != b.len() {
+        return Err(Error::new(
+            ErrorCode::BufferTooSmall,
+            format!("output buffer must hold {} bytes, got {}", b.len(), out_len),
+        ));
+    }
+    if out.is_null() {
+        return Err(Error::new(ErrorCode::NullPointer, "null output buffer"));
+    }
+    unsafe { std::ptr::copy_nonoverlapping(b.as_ptr(), out, b.len()) };
+    Ok(())
+}
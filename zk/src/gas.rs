@@ -0,0 +1,146 @@
+//! Gas cost model of Groth16 verification, shared by the precompile and by
+//! any native contract wrapping the verifier so that callers never have to
+//! price it themselves.
+//!
+//! Verifying `k` proofs with `n` public inputs each costs
+//!
+//! ```text
+//! k = 1: VERIFY + n * INPUT
+//! k > 1: BATCH + k * BATCH_PROOF + n * INPUT
+//! ```
+//!
+//! A single proof is decoded and checked with the three pairings left once
+//! `e(alpha, beta)` is precomputed, after a multi-scalar multiplication of
+//! the inputs with the IC points. A batch decodes every proof and takes one
+//! Miller loop and two scalar multiplications by its random coefficient per
+//! proof, but the inputs of all proofs are combined into a single
+//! multi-scalar multiplication, so they cost what they do for one proof.
+//!
+//! A verifying key passed inline rather than registered also costs
+//! `KEY + n_ic * KEY_POINT`, for decoding it, checking its points are in the
+//! subgroup and preparing it.
+//!
+//! The constants come from `benches/groth16.rs`, measured with `cargo bench
+//! --bench groth16` on one core of an Intel Xeon at 2.0 GHz with AVX-512 (a
+//! cloud VM, 5 GB of RAM), rustc 1.95. These are the median times of the
+//! benches, in microseconds:
+//!
+//! ```text
+//!                                 BN254   BLS12-381
+//! verify_inputs, n = 0             1961        2562
+//! verify_inputs, n = 64           13918       28271
+//! verify_batch, k = 2, n = 0       4890        7224
+//! verify_batch, k = 2, n = 64     16457       32949
+//! verify_batch, k = 64, n = 0     99533      115850
+//! read_key, 1 IC point             3348        2674
+//! read_key, 4097 IC points         4730      358790
+//! ```
+//!
+//! Each constant is read off the line through two of these rows, at 50 gas
+//! per microsecond and rounded up to a hundred gas. `VERIFY` is
+//! `verify_inputs` at `n = 0` and `INPUT` its slope up to `n = 64`.
+//! `BATCH_PROOF` is the slope of `verify_batch` from `k = 2` to `k = 64` and
+//! `BATCH` the intercept of that line at `k = 0`. `KEY_POINT` is the slope of
+//! `read_key` from 1 to 4097 IC points and `KEY` its intercept at none.
+//! The 64 inputs of a batch of two take 11567 and 25725 microseconds, against
+//! 11957 and 25709 for a single proof, so batches share `INPUT`. BN254 key
+//! points have no subgroup check and cost a third of a microsecond, which
+//! rounds up to a hundred gas.
+
+use crate::groth16::Curve;
+
+/// Prices of the steps of a verification on a curve.
+struct Schedule {
+    verify: u64,
+    input: u64,
+    batch: u64,
+    batch_proof: u64,
+    key: u64,
+    key_point: u64,
+}
+
+const BN254: Schedule = Schedule {
+    verify: 98100,
+    input: 9400,
+    batch: 91900,
+    batch_proof: 76400,
+    key: 167400,
+    key_point: 100,
+};
+
+const BLS12381: Schedule = Schedule {
+    verify: 128100,
+    input: 20100,
+    batch: 186000,
+    batch_proof: 87700,
+    key: 129400,
+    key_point: 4400,
+};
+
+fn schedule(curve: Curve) -> &'static Schedule {
+    match curve {
+        Curve::Bn254 => &BN254,
+        Curve::Bls12381 => &BLS12381,
+    }
+}
+
+/// Gas required to verify `batch` proofs with `inputs` public inputs each on
+/// `curve`. An empty batch is free.
+pub fn groth16_gas(curve: Curve, inputs: u64, batch: u64) -> u64 {
+    let s = schedule(curve);
+    let inputs = inputs.saturating_mul(s.input);
+    match batch {
+        0 => 0,
+        1 => s.verify.saturating_add(inputs),
+        k => s
+            .batch
+            .saturating_add(k.saturating_mul(s.batch_proof))
+            .saturating_add(inputs),
+    }
+}
+
+/// Gas required to decode a verifying key with `ic_points` IC points on
+/// `curve`, on top of `groth16_gas` when the key isn't registered.
+pub fn key_gas(curve: Curve, ic_points: u64) -> u64 {
+    let s = schedule(curve);
+    s.key.saturating_add(ic_points.saturating_mul(s.key_point))
+}
+
+/// Returns the gas required to verify `batch` proofs with `inputs` public
+/// inputs each on `curve`, see the module documentation for the formula. An
+/// unsupported curve costs `u64::MAX`, so that a call relying on it runs out
+/// of gas.
+#[no_mangle]
+pub extern "C" fn required_gas(curve: libc::c_int, inputs: u64, batch: u64) -> u64 {
+    Curve::from_raw(curve)
+        .map(|curve| groth16_gas(curve, inputs, batch))
+        .unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_required_gas() {
+        assert_eq!(98100 + 2 * 9400, groth16_gas(Curve::Bn254, 2, 1));
+        assert_eq!(128100 + 3 * 20100, groth16_gas(Curve::Bls12381, 3, 1));
+        assert_eq!(
+            91900 + 10 * 76400 + 2 * 9400,
+            groth16_gas(Curve::Bn254, 2, 10)
+        );
+        assert_eq!(0, groth16_gas(Curve::Bn254, 2, 0));
+        assert_eq!(u64::MAX, groth16_gas(Curve::Bn254, u64::MAX, 2));
+        assert_eq!(u64::MAX, required_gas(7, 1, 1));
+        assert_eq!(129400 + 3 * 4400, key_gas(Curve::Bls12381, 3));
+        assert_eq!(u64::MAX, key_gas(Curve::Bn254, u64::MAX));
+    }
+
+    #[test]
+    fn test_batch_is_cheaper() {
+        for curve in [Curve::Bn254, Curve::Bls12381] {
+            let single = groth16_gas(curve, 5, 1);
+            assert!(groth16_gas(curve, 5, 8) < 8 * single);
+        }
+    }
+}
@@ -0,0 +1,1087 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::sync::{Arc, RwLock};
+
+use bellman::groth16::batch::Verifier;
+use bellman::groth16::{
+    prepare_verifying_key, verify_proof, PreparedVerifyingKey, Proof, VerifyingKey,
+};
+use bellman::VerificationError;
+use bls12_381::Bls12;
+use group::prime::PrimeCurveAffine;
+use halo2curves::bn256::Bn256;
+use pairing::MultiMillerLoop;
+use rand_chacha::rand_core::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+
+use crate::bn254;
+use crate::error::{self, Error, ErrorCode, Result};
+use crate::ffi::bytes;
+use crate::plonk;
+
+/// Size of a serialized public input, a 32-byte scalar.
+pub const SCALAR_SIZE: usize = 32;
+
+/// Size of the id of a registered key.
+pub const KEY_ID_SIZE: usize = 32;
+
+/// Personalization of the transcript hash seeding batch coefficients.
+const BATCH_PERSONALIZATION: &[u8; 8] = b"zkGrBtch";
+
+/// Pairing-friendly curves supported by the verifier, passed as the `curve`
+/// argument of the exported functions.
+#[repr(i32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Curve {
+    /// BLS12-381 with bellman's encoding: compressed points and
+    /// little-endian scalars.
+    Bls12381 = 0,
+    /// BN254 (alt_bn128) with Ethereum's encoding, see the `bn254` module:
+    /// uncompressed big-endian points and big-endian scalars.
+    Bn254 = 1,
+}
+
+impl Curve {
+    pub fn from_raw(curve: libc::c_int) -> Result<Self> {
+        match curve {
+            0 => Ok(Curve::Bls12381),
+            1 => Ok(Curve::Bn254),
+            _ => Err(Error::new(
+                ErrorCode::UnsupportedCurve,
+                format!("unsupported curve {}", curve),
+            )),
+        }
+    }
+}
+
+/// Proof systems supported by `verify_with_system`, passed as its `system`
+/// argument.
+#[repr(i32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProofSystem {
+    /// Groth16, with a verifying key from a per-circuit setup.
+    Groth16 = 0,
+    /// PLONK with KZG commitments as in snarkjs, with a verifying key
+    /// derived from a universal setup, see the `plonk` module.
+    Plonk = 1,
+}
+
+impl ProofSystem {
+    pub fn from_raw(system: libc::c_int) -> Result<Self> {
+        match system {
+            0 => Ok(ProofSystem::Groth16),
+            1 => Ok(ProofSystem::Plonk),
+            _ => Err(Error::new(
+                ErrorCode::UnsupportedProofSystem,
+                format!("unsupported proof system {}", system),
+            )),
+        }
+    }
+}
+
+/// Wire format of Groth16 proofs, verifying keys and public inputs on a
+/// curve.
+pub trait Groth16Engine: MultiMillerLoop {
+    /// Size of a serialized proof.
+    const PROOF_SIZE: usize;
+
+    /// Size of a serialized verifying key before the big-endian u32 number
+    /// of IC points.
+    const KEY_HEADER_SIZE: usize;
+
+    fn read_proof(b: &[u8]) -> io::Result<Proof<Self>>;
+
+    fn read_key(b: &[u8]) -> io::Result<VerifyingKey<Self>>;
+
+    /// Decodes a canonical 32-byte scalar.
+    fn read_scalar(b: &[u8]) -> Option<Self::Fr>;
+
+    /// Decodes a canonical big-endian 32-byte scalar, the way EVM words and
+    /// snarkjs numbers carry them whatever the curve.
+    fn read_word(b: &[u8]) -> Option<Self::Fr>;
+
+    fn write_proof(proof: &Proof<Self>) -> Vec<u8>;
+
+    fn write_key(vk: &VerifyingKey<Self>) -> Vec<u8>;
+
+    fn write_scalar(s: &Self::Fr) -> [u8; SCALAR_SIZE];
+}
+
+impl Groth16Engine for Bls12 {
+    const PROOF_SIZE: usize = 192;
+    const KEY_HEADER_SIZE: usize = 3 * 96 + 3 * 192;
+
+    fn read_proof(b: &[u8]) -> io::Result<Proof<Self>> {
+        Proof::read(b)
+    }
+
+    fn read_key(b: &[u8]) -> io::Result<VerifyingKey<Self>> {
+        VerifyingKey::read(b)
+    }
+
+    fn read_scalar(b: &[u8]) -> Option<Self::Fr> {
+        let mut repr = [0u8; SCALAR_SIZE];
+        repr.copy_from_slice(b);
+        Option::from(bls12_381::Scalar::from_bytes(&repr))
+    }
+
+    fn read_word(b: &[u8]) -> Option<Self::Fr> {
+        let mut repr = [0u8; SCALAR_SIZE];
+        repr.copy_from_slice(b);
+        repr.reverse();
+        Option::from(bls12_381::Scalar::from_bytes(&repr))
+    }
+
+    fn write_proof(proof: &Proof<Self>) -> Vec<u8> {
+        let mut b = Vec::with_capacity(Self::PROOF_SIZE);
+        proof.write(&mut b).expect("writing to a vector can't fail");
+        b
+    }
+
+    fn write_key(vk: &VerifyingKey<Self>) -> Vec<u8> {
+        let mut b = vec![];
+        vk.write(&mut b).expect("writing to a vector can't fail");
+        b
+    }
+
+    fn write_scalar(s: &Self::Fr) -> [u8; SCALAR_SIZE] {
+        s.to_bytes()
+    }
+}
+
+/// BN254 proofs are `A | B | C` and verifying keys are
+/// `alpha_g1 | beta_g2 | gamma_g2 | delta_g2 | n | ic[0] .. ic[n-1]` with `n`
+/// a big-endian u32, which is what Ethereum verifier contracts embed.
+impl Groth16Engine for Bn256 {
+    const PROOF_SIZE: usize = 2 * bn254::G1_SIZE + bn254::G2_SIZE;
+    const KEY_HEADER_SIZE: usize = bn254::G1_SIZE + 3 * bn254::G2_SIZE;
+
+    fn read_proof(b: &[u8]) -> io::Result<Proof<Self>> {
+        if b.len() != Self::PROOF_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("proof must be {} bytes", Self::PROOF_SIZE),
+            ));
+        }
+        let b_offset = bn254::G1_SIZE;
+        let c_offset = b_offset + bn254::G2_SIZE;
+        Ok(Proof {
+            a: bn254::read_g1(&b[..b_offset])?,
+            b: bn254::read_g2(&b[b_offset..c_offset])?,
+            c: bn254::read_g1(&b[c_offset..])?,
+        })
+    }
+
+    fn read_key(b: &[u8]) -> io::Result<VerifyingKey<Self>> {
+        let header = Self::KEY_HEADER_SIZE;
+        if b.len() < header + 4 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "verifying key is too short",
+            ));
+        }
+        let mut n = [0u8; 4];
+        n.copy_from_slice(&b[header..header + 4]);
+        let n = u32::from_be_bytes(n) as usize;
+        let ic = &b[header + 4..];
+        if ic.len() != n * bn254::G1_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("expected {} IC points", n),
+            ));
+        }
+        let g2 = |i: usize| bn254::read_g2(&b[bn254::G1_SIZE + i * bn254::G2_SIZE..]);
+        Ok(VerifyingKey {
+            alpha_g1: bn254::read_g1(b)?,
+            // beta_g1 and delta_g1 are only needed by the prover.
+            beta_g1: PrimeCurveAffine::identity(),
+            beta_g2: g2(0)?,
+            gamma_g2: g2(1)?,
+            delta_g1: PrimeCurveAffine::identity(),
+            delta_g2: g2(2)?,
+            ic: ic
+                .chunks_exact(bn254::G1_SIZE)
+                .map(bn254::read_g1)
+                .collect::<io::Result<_>>()?,
+        })
+    }
+
+    fn read_scalar(b: &[u8]) -> Option<Self::Fr> {
+        bn254::read_fr(b)
+    }
+
+    fn read_word(b: &[u8]) -> Option<Self::Fr> {
+        bn254::read_fr(b)
+    }
+
+    fn write_proof(proof: &Proof<Self>) -> Vec<u8> {
+        let mut b = vec![0u8; Self::PROOF_SIZE];
+        let c_offset = bn254::G1_SIZE + bn254::G2_SIZE;
+        bn254::write_g1(&proof.a, &mut b[..bn254::G1_SIZE]);
+        bn254::write_g2(&proof.b, &mut b[bn254::G1_SIZE..c_offset]);
+        bn254::write_g1(&proof.c, &mut b[c_offset..]);
+        b
+    }
+
+    fn write_key(vk: &VerifyingKey<Self>) -> Vec<u8> {
+        let header = bn254::G1_SIZE + 3 * bn254::G2_SIZE;
+        let mut b = vec![0u8; header];
+        bn254::write_g1(&vk.alpha_g1, &mut b);
+        for (i, p) in [vk.beta_g2, vk.gamma_g2, vk.delta_g2].iter().enumerate() {
+            bn254::write_g2(p, &mut b[bn254::G1_SIZE + i * bn254::G2_SIZE..]);
+        }
+        b.extend((vk.ic.len() as u32).to_be_bytes());
+        for p in vk.ic.iter() {
+            let mut point = [0u8; bn254::G1_SIZE];
+            bn254::write_g1(p, &mut point);
+            b.extend(point);
+        }
+        b
+    }
+
+    fn write_scalar(s: &Self::Fr) -> [u8; SCALAR_SIZE] {
+        bn254::write_fr(s)
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn verify(
+    proof: *mut libc::c_uchar,
+    proof_len: libc::size_t,
+    key: *mut libc::c_uchar,
+    key_len: libc::size_t,
+) -> libc::c_int {
+    error::status((|| {
+        let bproof = bytes(proof, proof_len)?;
+        let bkey = bytes(key, key_len)?;
+        verify_groth16::<Bls12>(bproof, bkey, &[])
+    })())
+}
+
+/// Verifies a BLS12-381 Groth16 proof against a circuit with public inputs.
+/// `inputs` is a concatenation of canonical 32-byte little-endian scalars,
+/// one per public input, in the order the circuit allocated them.
+#[no_mangle]
+pub extern "C" fn verify_with_inputs(
+    proof: *mut libc::c_uchar,
+    proof_len: libc::size_t,
+    key: *mut libc::c_uchar,
+    key_len: libc::size_t,
+    inputs: *mut libc::c_uchar,
+    inputs_len: libc::size_t,
+) -> libc::c_int {
+    error::status((|| {
+        let bproof = bytes(proof, proof_len)?;
+        let bkey = bytes(key, key_len)?;
+        let binputs = bytes(inputs, inputs_len)?;
+        verify_groth16::<Bls12>(bproof, bkey, binputs)
+    })())
+}
+
+/// Verifies a Groth16 proof on the given `Curve`, the proof, key and public
+/// inputs being in that curve's encoding.
+#[no_mangle]
+pub extern "C" fn verify_on_curve(
+    curve: libc::c_int,
+    proof: *mut libc::c_uchar,
+    proof_len: libc::size_t,
+    key: *mut libc::c_uchar,
+    key_len: libc::size_t,
+    inputs: *mut libc::c_uchar,
+    inputs_len: libc::size_t,
+) -> libc::c_int {
+    verify_with_system(
+        ProofSystem::Groth16 as libc::c_int,
+        curve,
+        proof,
+        proof_len,
+        key,
+        key_len,
+        inputs,
+        inputs_len,
+    )
+}
+
+/// Verifies a proof of the given `ProofSystem` on the given `Curve`, the
+/// proof, key and public inputs being in that curve's encoding.
+#[no_mangle]
+pub extern "C" fn verify_with_system(
+    system: libc::c_int,
+    curve: libc::c_int,
+    proof: *mut libc::c_uchar,
+    proof_len: libc::size_t,
+    key: *mut libc::c_uchar,
+    key_len: libc::size_t,
+    inputs: *mut libc::c_uchar,
+    inputs_len: libc::size_t,
+) -> libc::c_int {
+    error::status((|| {
+        let system = ProofSystem::from_raw(system)?;
+        let curve = Curve::from_raw(curve)?;
+        verify_system(
+            system,
+            curve,
+            bytes(proof, proof_len)?,
+            bytes(key, key_len)?,
+            bytes(inputs, inputs_len)?,
+        )
+    })())
+}
+
+/// Verifies a proof of any supported system and curve.
+pub fn verify_system(
+    system: ProofSystem,
+    curve: Curve,
+    bproof: &[u8],
+    bkey: &[u8],
+    binputs: &[u8],
+) -> Result<bool> {
+    match (system, curve) {
+        (ProofSystem::Groth16, Curve::Bls12381) => verify_groth16::<Bls12>(bproof, bkey, binputs),
+        (ProofSystem::Groth16, Curve::Bn254) => verify_groth16::<Bn256>(bproof, bkey, binputs),
+        (ProofSystem::Plonk, Curve::Bls12381) => {
+            plonk::verify_plonk::<Bls12>(bproof, bkey, binputs)
+        }
+        (ProofSystem::Plonk, Curve::Bn254) => plonk::verify_plonk::<Bn256>(bproof, bkey, binputs),
+    }
+}
+
+/// Parses and prepares a verifying key for the given `Curve` once, so that
+/// proofs for the same circuit can be checked with `verify_prepared` without
+/// paying for the key deserialization and the `e(alpha, beta)` pairing every
+/// time. On success the handle is stored to `handle` and must be released
+/// with `free_prepared_key`. The handle is immutable and may be used from
+/// several threads at once.
+#[no_mangle]
+pub extern "C" fn prepare_key(
+    curve: libc::c_int,
+    key: *mut libc::c_uchar,
+    key_len: libc::size_t,
+    handle: *mut *mut KeyHandle,
+) -> libc::c_int {
+    error::status((|| {
+        let curve = Curve::from_raw(curve)?;
+        if handle.is_null() {
+            return Err(Error::new(ErrorCode::NullPointer, "null handle pointer"));
+        }
+        let kh = KeyHandle::read(curve, bytes(key, key_len)?)?;
+        unsafe { *handle = Box::into_raw(Box::new(kh)) };
+        Ok(true)
+    })())
+}
+
+/// Verifies a Groth16 proof against a key prepared with `prepare_key`, the
+/// proof and `inputs` being encoded as for `verify_on_curve`.
+#[no_mangle]
+pub extern "C" fn verify_prepared(
+    handle: *const KeyHandle,
+    proof: *mut libc::c_uchar,
+    proof_len: libc::size_t,
+    inputs: *mut libc::c_uchar,
+    inputs_len: libc::size_t,
+) -> libc::c_int {
+    error::status((|| {
+        let kh = handle_ref(handle)?;
+        let bproof = bytes(proof, proof_len)?;
+        let binputs = bytes(inputs, inputs_len)?;
+        match kh {
+            KeyHandle::Bls12381(pk) => pk.verify(&read_proof(bproof)?, binputs),
+            KeyHandle::Bn254(pk) => pk.verify(&read_proof(bproof)?, binputs),
+        }
+    })())
+}
+
+/// Verifies a batch of proofs against a key prepared with `prepare_key` in a
+/// single multi-pairing. `proofs` is a concatenation of fixed-size proofs
+/// (192 bytes on BLS12-381, 256 on BN254) and `inputs` the concatenation of
+/// their public inputs, encoded as for `verify_on_curve`. `results` must have
+/// room for one byte per proof and receives 1 for every valid proof and 0 for
+/// every invalid or malformed one. Returns `Ok` when all proofs are valid and
+/// `Invalid` otherwise. The random coefficients of the batch are derived from
+/// the key, proofs and inputs, so every node gets the same verdict for the
+/// same batch.
+#[no_mangle]
+pub extern "C" fn verify_batch(
+    handle: *const KeyHandle,
+    proofs: *mut libc::c_uchar,
+    proofs_len: libc::size_t,
+    inputs: *mut libc::c_uchar,
+    inputs_len: libc::size_t,
+    results: *mut libc::c_uchar,
+    results_len: libc::size_t,
+) -> libc::c_int {
+    error::status((|| {
+        let kh = handle_ref(handle)?;
+        let bproofs = bytes(proofs, proofs_len)?;
+        let binputs = bytes(inputs, inputs_len)?;
+        let verdicts = match kh {
+            KeyHandle::Bls12381(pk) => pk.verify_batch(bproofs, binputs)?,
+            KeyHandle::Bn254(pk) => pk.verify_batch(bproofs, binputs)?,
+        };
+        if results_len != verdicts.len() {
+            return Err(Error::new(
+                ErrorCode::MalformedInputs,
+                format!(
+                    "expected results buffer of {} bytes, got {}",
+                    verdicts.len(),
+                    results_len
+                ),
+            ));
+        }
+        if results.is_null() && results_len != 0 {
+            return Err(Error::new(ErrorCode::NullPointer, "null results buffer"));
+        }
+        for (i, &valid) in verdicts.iter().enumerate() {
+            unsafe { *results.add(i) = valid as libc::c_uchar };
+        }
+        Ok(verdicts.iter().all(|&valid| valid))
+    })())
+}
+
+/// Releases a handle returned by `prepare_key`, null is ignored. The handle
+/// must not be used by any thread afterwards.
+#[no_mangle]
+pub extern "C" fn free_prepared_key(handle: *mut KeyHandle) {
+    if !handle.is_null() {
+        drop(unsafe { Box::from_raw(handle) });
+    }
+}
+
+/// Prepares a verifying key for the given `Curve` and keeps it in a registry
+/// for the lifetime of the process, so that precompile calls can refer to it
+/// by id instead of carrying the whole key. The 32-byte id, the BLAKE2s-256
+/// hash of the serialized key, is written to `id`. Registering the same key
+/// twice is a no-op.
+#[no_mangle]
+pub extern "C" fn register_key(
+    curve: libc::c_int,
+    key: *mut libc::c_uchar,
+    key_len: libc::size_t,
+    id: *mut libc::c_uchar,
+) -> libc::c_int {
+    error::status((|| {
+        let curve = Curve::from_raw(curve)?;
+        if id.is_null() {
+            return Err(Error::new(ErrorCode::NullPointer, "null id buffer"));
+        }
+        let kh = KeyHandle::read(curve, bytes(key, key_len)?)?;
+        let kid = kh.id();
+        REGISTRY
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(kid)
+            .or_insert_with(|| Arc::new(kh));
+        unsafe { std::ptr::copy_nonoverlapping(kid.as_ptr(), id, KEY_ID_SIZE) };
+        Ok(true)
+    })())
+}
+
+/// Removes a key from the registry, returns `UnknownKey` if it's not there.
+#[no_mangle]
+pub extern "C" fn unregister_key(id: *mut libc::c_uchar) -> libc::c_int {
+    error::status((|| {
+        let id = bytes(id, KEY_ID_SIZE)?;
+        match REGISTRY
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(id)
+        {
+            Some(_) => Ok(true),
+            None => Err(Error::new(ErrorCode::UnknownKey, "key is not registered")),
+        }
+    })())
+}
+
+fn handle_ref<'a>(handle: *const KeyHandle) -> Result<&'a KeyHandle> {
+    unsafe { handle.as_ref() }
+        .ok_or_else(|| Error::new(ErrorCode::NullPointer, "null prepared key handle"))
+}
+
+/// A prepared key on any of the supported curves, the object behind the
+/// handles returned by `prepare_key`. It's always boxed, so the size gap
+/// between variants doesn't matter.
+#[allow(clippy::large_enum_variant)]
+pub enum KeyHandle {
+    Bls12381(PreparedKey<Bls12>),
+    Bn254(PreparedKey<Bn256>),
+}
+
+impl KeyHandle {
+    pub fn read(curve: Curve, bkey: &[u8]) -> Result<Self> {
+        Ok(match curve {
+            Curve::Bls12381 => KeyHandle::Bls12381(PreparedKey::read(bkey)?),
+            Curve::Bn254 => KeyHandle::Bn254(PreparedKey::read(bkey)?),
+        })
+    }
+
+    pub fn curve(&self) -> Curve {
+        match self {
+            KeyHandle::Bls12381(_) => Curve::Bls12381,
+            KeyHandle::Bn254(_) => Curve::Bn254,
+        }
+    }
+
+    /// Identifier of the key in the registry, the BLAKE2s-256 hash of its
+    /// serialized form.
+    pub fn id(&self) -> [u8; KEY_ID_SIZE] {
+        match self {
+            KeyHandle::Bls12381(pk) => pk.digest,
+            KeyHandle::Bn254(pk) => pk.digest,
+        }
+    }
+}
+
+/// Number of IC points a serialized verifying key declares, read from its
+/// header without decoding any point.
+pub fn key_ic_count<E: Groth16Engine>(bkey: &[u8]) -> Result<usize> {
+    let n = bkey
+        .get(E::KEY_HEADER_SIZE..E::KEY_HEADER_SIZE + 4)
+        .ok_or_else(|| Error::new(ErrorCode::MalformedKey, "verifying key is too short"))?;
+    Ok(u32::from_be_bytes([n[0], n[1], n[2], n[3]]) as usize)
+}
+
+/// Keys registered with `register_key`, shared by all threads.
+static REGISTRY: RwLock<BTreeMap<[u8; KEY_ID_SIZE], Arc<KeyHandle>>> = RwLock::new(BTreeMap::new());
+
+/// Looks up a key registered with `register_key`.
+pub fn registered_key(id: &[u8]) -> Option<Arc<KeyHandle>> {
+    REGISTRY
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(id)
+        .cloned()
+}
+
+/// A verifying key ready for pairing checks, along with the number of public
+/// inputs it expects.
+pub struct PreparedKey<E: Groth16Engine> {
+    vk: VerifyingKey<E>,
+    pvk: PreparedVerifyingKey<E>,
+    inputs: usize,
+    /// Hash of the serialized key, binds batch coefficients to the key.
+    digest: [u8; 32],
+}
+
+impl<E: Groth16Engine> PreparedKey<E> {
+    pub fn read(bkey: &[u8]) -> Result<Self> {
+        let vk = E::read_key(bkey).map_err(|e| {
+            Error::new(
+                ErrorCode::MalformedKey,
+                format!("malformed verifying key: {}", e),
+            )
+        })?;
+        if vk.ic.is_empty() {
+            return Err(Error::new(
+                ErrorCode::MalformedKey,
+                "verifying key has no IC points",
+            ));
+        }
+        Ok(PreparedKey {
+            pvk: prepare_verifying_key(&vk),
+            inputs: vk.ic.len() - 1,
+            digest: *blake2s_simd::blake2s(bkey).as_array(),
+            vk,
+        })
+    }
+
+    /// Number of public inputs of the circuit.
+    pub fn inputs(&self) -> usize {
+        self.inputs
+    }
+
+    pub fn verify(&self, proof: &Proof<E>, binputs: &[u8]) -> Result<bool> {
+        self.verify_inputs(proof, &read_scalars::<E>(binputs)?)
+    }
+
+    /// Verifies a proof against already decoded public inputs.
+    pub fn verify_inputs(&self, proof: &Proof<E>, inputs: &[E::Fr]) -> Result<bool> {
+        if inputs.len() != self.inputs {
+            return Err(Error::new(
+                ErrorCode::InputCountMismatch,
+                format!(
+                    "expected {} public inputs, got {}",
+                    self.inputs,
+                    inputs.len()
+                ),
+            ));
+        }
+        self.verify_scalars(proof, inputs)
+    }
+
+    /// Verifies a batch of serialized proofs with their serialized public
+    /// inputs, returning a verdict for every proof. Proofs or inputs that
+    /// can't be decoded are reported as invalid rather than failing the whole
+    /// batch. If the combined check fails, proofs are rechecked one by one to
+    /// find the culprits.
+    pub fn verify_batch(&self, bproofs: &[u8], binputs: &[u8]) -> Result<Vec<bool>> {
+        if !bproofs.len().is_multiple_of(E::PROOF_SIZE) {
+            return Err(Error::new(
+                ErrorCode::MalformedProof,
+                format!(
+                    "proofs length {} is not a multiple of {}",
+                    bproofs.len(),
+                    E::PROOF_SIZE
+                ),
+            ));
+        }
+        let n = bproofs.len() / E::PROOF_SIZE;
+        let stride = self.inputs * SCALAR_SIZE;
+        if binputs.len() != n * stride {
+            return Err(Error::new(
+                ErrorCode::InputCountMismatch,
+                format!(
+                    "expected {} bytes of public inputs for {} proofs, got {}",
+                    n * stride,
+                    n,
+                    binputs.len()
+                ),
+            ));
+        }
+
+        let mut verdicts = vec![false; n];
+        let mut items = Vec::with_capacity(n);
+        for i in 0..n {
+            let proof = E::read_proof(&bproofs[i * E::PROOF_SIZE..(i + 1) * E::PROOF_SIZE]);
+            let inputs = read_scalars::<E>(&binputs[i * stride..(i + 1) * stride]);
+            if let (Ok(proof), Ok(inputs)) = (proof, inputs) {
+                items.push((i, proof, inputs));
+            }
+        }
+        if items.is_empty() {
+            return Ok(verdicts);
+        }
+
+        let mut batch = Verifier::new();
+        for (_, proof, inputs) in items.iter() {
+            batch.queue((proof, inputs.as_slice()));
+        }
+        let mut rng = ChaCha20Rng::from_seed(self.batch_seed(bproofs, binputs));
+        match batch.verify(&mut rng, &self.vk) {
+            Ok(_) => {
+                for (i, _, _) in items.iter() {
+                    verdicts[*i] = true;
+                }
+            }
+            Err(_) => {
+                for (i, proof, inputs) in items.iter() {
+                    verdicts[*i] = self.verify_scalars(proof, inputs)?;
+                }
+            }
+        }
+        Ok(verdicts)
+    }
+
+    /// Transcript hash of everything a batch is made of. Coefficients drawn
+    /// from it can't be predicted before the proofs are fixed.
+    fn batch_seed(&self, bproofs: &[u8], binputs: &[u8]) -> [u8; 32] {
+        let mut state = blake2s_simd::Params::new()
+            .personal(BATCH_PERSONALIZATION)
+            .to_state();
+        state.update(&self.digest);
+        state.update(&(bproofs.len() as u64).to_le_bytes());
+        state.update(bproofs);
+        state.update(&(binputs.len() as u64).to_le_bytes());
+        state.update(binputs);
+        *state.finalize().as_array()
+    }
+
+    fn verify_scalars(&self, proof: &Proof<E>, inputs: &[E::Fr]) -> Result<bool> {
+        match verify_proof(&self.pvk, proof, inputs) {
+            Ok(_) => Ok(true),
+            Err(VerificationError::InvalidProof) => Ok(false),
+            Err(VerificationError::InvalidVerifyingKey) => Err(Error::new(
+                ErrorCode::MalformedKey,
+                "verifying key doesn't match the public inputs",
+            )),
+        }
+    }
+}
+
+pub fn verify_groth16<E: Groth16Engine>(
+    bproof: &[u8],
+    bkey: &[u8],
+    binputs: &[u8],
+) -> Result<bool> {
+    let tproof = read_proof::<E>(bproof)?;
+    PreparedKey::<E>::read(bkey)?.verify(&tproof, binputs)
+}
+
+pub fn read_proof<E: Groth16Engine>(bproof: &[u8]) -> Result<Proof<E>> {
+    E::read_proof(bproof)
+        .map_err(|e| Error::new(ErrorCode::MalformedProof, format!("malformed proof: {}", e)))
+}
+
+/// Parses a buffer of 32-byte scalars, rejecting trailing bytes and values
+/// that are not reduced modulo the group order.
+pub fn read_scalars<E: Groth16Engine>(buf: &[u8]) -> Result<Vec<E::Fr>> {
+    if !buf.len().is_multiple_of(SCALAR_SIZE) {
+        return Err(Error::new(
+            ErrorCode::MalformedInputs,
+            format!(
+                "public inputs length {} is not a multiple of {}",
+                buf.len(),
+                SCALAR_SIZE
+            ),
+        ));
+    }
+    buf.chunks_exact(SCALAR_SIZE)
+        .enumerate()
+        .map(|(i, chunk)| {
+            E::read_scalar(chunk).ok_or_else(|| {
+                Error::new(
+                    ErrorCode::MalformedInputs,
+                    format!("public input {} is not a canonical scalar", i),
+                )
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::circuits::Multiply;
+    use bellman::groth16::{create_random_proof, generate_random_parameters, Parameters};
+    use bls12_381::Scalar;
+    use rand_core::OsRng;
+
+    pub(crate) fn setup() -> (Vec<u8>, Vec<u8>) {
+        let params = parameters();
+        let mut bkey = vec![];
+        params.vk.write(&mut bkey).unwrap();
+        (prove(&params, 3, 7), bkey)
+    }
+
+    pub(crate) fn parameters() -> Parameters<Bls12> {
+        generate_random_parameters::<Bls12, _, _>(Multiply { a: None, b: None }, &mut OsRng)
+            .unwrap()
+    }
+
+    fn prepared(vk: &VerifyingKey<Bls12>) -> PreparedKey<Bls12> {
+        let mut bkey = vec![];
+        vk.write(&mut bkey).unwrap();
+        PreparedKey::read(&bkey).unwrap()
+    }
+
+    pub(crate) fn prove(params: &Parameters<Bls12>, a: u64, b: u64) -> Vec<u8> {
+        let circuit = Multiply {
+            a: Some(Scalar::from(a)),
+            b: Some(Scalar::from(b)),
+        };
+        let proof = create_random_proof(circuit, params, &mut OsRng).unwrap();
+        let mut bproof = vec![];
+        proof.write(&mut bproof).unwrap();
+        bproof
+    }
+
+    fn code_of(res: Result<bool>) -> ErrorCode {
+        match res {
+            Ok(true) => ErrorCode::Ok,
+            Ok(false) => ErrorCode::Invalid,
+            Err(e) => e.code(),
+        }
+    }
+
+    #[test]
+    fn test_verify_with_inputs() {
+        let (proof, key) = setup();
+        let c = Scalar::from(21).to_bytes();
+        assert_eq!(
+            ErrorCode::Ok,
+            code_of(verify_groth16::<Bls12>(&proof, &key, &c))
+        );
+        let wrong = Scalar::from(22).to_bytes();
+        assert_eq!(
+            ErrorCode::Invalid,
+            code_of(verify_groth16::<Bls12>(&proof, &key, &wrong))
+        );
+        // The circuit has one public input, so both none and two are rejected.
+        assert_eq!(
+            ErrorCode::InputCountMismatch,
+            code_of(verify_groth16::<Bls12>(&proof, &key, &[]))
+        );
+        let two = [c, Scalar::one().to_bytes()].concat();
+        assert_eq!(
+            ErrorCode::InputCountMismatch,
+            code_of(verify_groth16::<Bls12>(&proof, &key, &two))
+        );
+        assert_eq!(
+            ErrorCode::MalformedInputs,
+            code_of(verify_groth16::<Bls12>(&proof, &key, &c[..31]))
+        );
+        assert_eq!(
+            ErrorCode::MalformedProof,
+            code_of(verify_groth16::<Bls12>(&proof[1..], &key, &c))
+        );
+        assert_eq!(
+            ErrorCode::MalformedKey,
+            code_of(verify_groth16::<Bls12>(&proof, &key[1..], &c))
+        );
+    }
+
+    #[test]
+    fn test_read_scalars_non_canonical() {
+        // -1 is the largest canonical scalar, one more is the group order itself.
+        let max = (-Scalar::one()).to_bytes();
+        let mut modulus = max;
+        modulus[0] += 1;
+        assert!(read_scalars::<Bls12>(&max).is_ok());
+        assert!(read_scalars::<Bls12>(&modulus).is_err());
+        assert!(read_scalars::<Bls12>(&[0xff; SCALAR_SIZE]).is_err());
+    }
+
+    #[test]
+    fn test_verify_null_pointer() {
+        let code = verify(std::ptr::null_mut(), 192, std::ptr::null_mut(), 0);
+        assert_eq!(ErrorCode::NullPointer as libc::c_int, code);
+    }
+
+    #[test]
+    fn test_verify_prepared() {
+        let (proof, key) = setup();
+        let mut handle = std::ptr::null_mut();
+        let code = prepare_key(
+            Curve::Bls12381 as libc::c_int,
+            key.as_ptr() as *mut _,
+            key.len(),
+            &mut handle,
+        );
+        assert_eq!(ErrorCode::Ok as libc::c_int, code);
+        let pk = match unsafe { &*handle } {
+            KeyHandle::Bls12381(pk) => pk,
+            _ => panic!("wrong curve"),
+        };
+        assert_eq!(1, pk.inputs());
+
+        let tproof = read_proof::<Bls12>(&proof).unwrap();
+        let c = Scalar::from(21).to_bytes();
+        let wrong = Scalar::from(22).to_bytes();
+        std::thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(|| {
+                    assert_eq!(ErrorCode::Ok, code_of(pk.verify(&tproof, &c)));
+                    assert_eq!(ErrorCode::Invalid, code_of(pk.verify(&tproof, &wrong)));
+                });
+            }
+        });
+        let code = verify_prepared(
+            handle,
+            proof.as_ptr() as *mut _,
+            proof.len(),
+            c.as_ptr() as *mut _,
+            c.len(),
+        );
+        assert_eq!(ErrorCode::Ok as libc::c_int, code);
+        free_prepared_key(handle);
+
+        let code = prepare_key(
+            Curve::Bls12381 as libc::c_int,
+            key.as_ptr() as *mut _,
+            key.len() - 1,
+            &mut handle,
+        );
+        assert_eq!(ErrorCode::MalformedKey as libc::c_int, code);
+        let code = verify_prepared(
+            std::ptr::null(),
+            proof.as_ptr() as *mut _,
+            proof.len(),
+            c.as_ptr() as *mut _,
+            c.len(),
+        );
+        assert_eq!(ErrorCode::NullPointer as libc::c_int, code);
+    }
+
+    #[test]
+    fn test_verify_batch() {
+        let params = parameters();
+        let pk = prepared(&params.vk);
+        let proofs = [
+            prove(&params, 3, 7),
+            prove(&params, 2, 5),
+            prove(&params, 4, 4),
+        ]
+        .concat();
+        let inputs = [Scalar::from(21), Scalar::from(10), Scalar::from(16)]
+            .iter()
+            .flat_map(|s| s.to_bytes())
+            .collect::<Vec<_>>();
+        assert_eq!(vec![true; 3], pk.verify_batch(&proofs, &inputs).unwrap());
+        assert_eq!(Vec::<bool>::new(), pk.verify_batch(&[], &[]).unwrap());
+
+        // A wrong input and a proof that doesn't decode only fail their own item.
+        let mut bad_inputs = inputs.clone();
+        bad_inputs[SCALAR_SIZE] ^= 1;
+        assert_eq!(
+            vec![true, false, true],
+            pk.verify_batch(&proofs, &bad_inputs).unwrap()
+        );
+        let mut bad_proofs = proofs.clone();
+        bad_proofs[2 * Bls12::PROOF_SIZE] ^= 0xff;
+        assert_eq!(
+            vec![true, true, false],
+            pk.verify_batch(&bad_proofs, &inputs).unwrap()
+        );
+
+        let mut results = [0xffu8; 3];
+        let kh = KeyHandle::Bls12381(pk);
+        let code = verify_batch(
+            &kh,
+            bad_proofs.as_ptr() as *mut _,
+            bad_proofs.len(),
+            inputs.as_ptr() as *mut _,
+            inputs.len(),
+            results.as_mut_ptr(),
+            results.len(),
+        );
+        assert_eq!(ErrorCode::Invalid as libc::c_int, code);
+        assert_eq!([1, 1, 0], results);
+
+        let KeyHandle::Bls12381(pk) = kh else {
+            unreachable!()
+        };
+        assert!(pk.verify_batch(&proofs[1..], &inputs).is_err());
+        assert!(pk.verify_batch(&proofs, &inputs[1..]).is_err());
+    }
+
+    #[test]
+    fn test_batch_seed() {
+        let params = parameters();
+        let pk = prepared(&params.vk);
+        let proof = prove(&params, 3, 7);
+        let input = Scalar::from(21).to_bytes();
+        assert_eq!(pk.batch_seed(&proof, &input), pk.batch_seed(&proof, &input));
+        assert_ne!(
+            pk.batch_seed(&proof, &input),
+            pk.batch_seed(&proof, &[0; 32])
+        );
+        let other = prepared(&parameters().vk);
+        assert_ne!(
+            pk.batch_seed(&proof, &input),
+            other.batch_seed(&proof, &input)
+        );
+    }
+
+    /// Builds a BN254 verifying key and a proof for `inputs` from known
+    /// trapdoor values: bellman's generator can't run on halo2curves, but the
+    /// verifier only checks the pairing equation, which the trapdoor lets us
+    /// satisfy directly.
+    pub(crate) fn simulate_bn254(inputs: &[u64]) -> (Vec<u8>, Vec<u8>) {
+        use halo2curves::bn256::{Fr, G1, G2};
+        let (alpha, beta, gamma, delta) = (Fr::from(2), Fr::from(3), Fr::from(5), Fr::from(7));
+        let ic = (0..=inputs.len() as u64)
+            .map(|i| Fr::from(11 + i))
+            .collect::<Vec<_>>();
+        let mut key = vec![];
+        key.extend(bn254::g1_bytes(&(G1::generator() * alpha)));
+        for s in [beta, gamma, delta] {
+            key.extend(bn254::g2_bytes(&(G2::generator() * s)));
+        }
+        key.extend((ic.len() as u32).to_be_bytes());
+        for s in ic.iter() {
+            key.extend(bn254::g1_bytes(&(G1::generator() * s)));
+        }
+
+        // a * b = alpha * beta + gamma * ic(x) + delta * c
+        let (a, b) = (Fr::from(13), Fr::from(17));
+        let ic_x = inputs
+            .iter()
+            .zip(ic.iter().skip(1))
+            .fold(ic[0], |acc, (x, k)| acc + Fr::from(*x) * k);
+        let c = (a * b - alpha * beta - gamma * ic_x) * delta.invert().unwrap();
+        let mut proof = vec![];
+        proof.extend(bn254::g1_bytes(&(G1::generator() * a)));
+        proof.extend(bn254::g2_bytes(&(G2::generator() * b)));
+        proof.extend(bn254::g1_bytes(&(G1::generator() * c)));
+        (key, proof)
+    }
+
+    pub(crate) fn bn254_inputs(inputs: &[u64]) -> Vec<u8> {
+        inputs
+            .iter()
+            .flat_map(|x| bn254::write_fr(&halo2curves::bn256::Fr::from(*x)))
+            .collect()
+    }
+
+    fn on_curve(curve: Curve, proof: &[u8], key: &[u8], inputs: &[u8]) -> libc::c_int {
+        verify_on_curve(
+            curve as libc::c_int,
+            proof.as_ptr() as *mut _,
+            proof.len(),
+            key.as_ptr() as *mut _,
+            key.len(),
+            inputs.as_ptr() as *mut _,
+            inputs.len(),
+        )
+    }
+
+    #[test]
+    fn test_verify_bn254() {
+        let (key, proof) = simulate_bn254(&[4, 9]);
+        let inputs = bn254_inputs(&[4, 9]);
+        assert_eq!(1, on_curve(Curve::Bn254, &proof, &key, &inputs));
+        assert_eq!(
+            0,
+            on_curve(Curve::Bn254, &proof, &key, &bn254_inputs(&[4, 8]))
+        );
+        assert_eq!(
+            ErrorCode::InputCountMismatch as libc::c_int,
+            on_curve(Curve::Bn254, &proof, &key, &bn254_inputs(&[4]))
+        );
+        assert_eq!(
+            ErrorCode::MalformedKey as libc::c_int,
+            on_curve(Curve::Bn254, &proof, &key[..key.len() - 1], &inputs)
+        );
+        assert_eq!(
+            ErrorCode::MalformedProof as libc::c_int,
+            on_curve(Curve::Bn254, &proof[1..], &key, &inputs)
+        );
+        assert_eq!(
+            ErrorCode::MalformedProof as libc::c_int,
+            on_curve(Curve::Bls12381, &proof, &key, &inputs)
+        );
+        assert_eq!(
+            ErrorCode::UnsupportedCurve as libc::c_int,
+            verify_on_curve(
+                7,
+                std::ptr::null_mut(),
+                0,
+                std::ptr::null_mut(),
+                0,
+                std::ptr::null_mut(),
+                0
+            )
+        );
+
+        let (bls_proof, bls_key) = setup();
+        let c = Scalar::from(21).to_bytes();
+        assert_eq!(1, on_curve(Curve::Bls12381, &bls_proof, &bls_key, &c));
+    }
+
+    #[test]
+    fn test_verify_batch_bn254() {
+        let (key, proof) = simulate_bn254(&[4, 9]);
+        let mut handle = std::ptr::null_mut();
+        let code = prepare_key(
+            Curve::Bn254 as libc::c_int,
+            key.as_ptr() as *mut _,
+            key.len(),
+            &mut handle,
+        );
+        assert_eq!(ErrorCode::Ok as libc::c_int, code);
+        let proofs = [proof.clone(), proof].concat();
+        let inputs = bn254_inputs(&[4, 9, 4, 10]);
+        let mut results = [0u8; 2];
+        let code = verify_batch(
+            handle,
+            proofs.as_ptr() as *mut _,
+            proofs.len(),
+            inputs.as_ptr() as *mut _,
+            inputs.len(),
+            results.as_mut_ptr(),
+            results.len(),
+        );
+        assert_eq!(ErrorCode::Invalid as libc::c_int, code);
+        assert_eq!([1, 0], results);
+        free_prepared_key(handle);
+    }
+}
@@ -0,0 +1,309 @@
+//! Hashing to BLS12-381 (RFC 9380) with the `BLS12381G1_XMD:SHA-256_SSWU_RO_`
+//! and `BLS12381G2_XMD:SHA-256_SSWU_RO_` suites: `expand_message_xmd` with
+//! SHA-256 feeds `hash_to_field`, whose two field elements are mapped with
+//! the simplified SWU map and its isogeny, added and cleared of the
+//! cofactor. The maps are those of the `bls12_381` crate, this module checks
+//! the arguments it would panic on and exposes the suites over the FFI.
+//!
+//! Points come out uncompressed, `x | y` as big-endian coordinates (`x` and
+//! `y` being `c1 | c0` on G2), the representation of the RFC's test vectors.
+
+use bls12_381::hash_to_curve::{
+    ExpandMessageState, ExpandMsgXmd, HashToCurve, HashToField, InitExpandMessage,
+};
+use bls12_381::{G1Affine, G1Projective, G2Affine, G2Projective, Scalar};
+use sha2::Sha256;
+
+use crate::error::{self, Error, ErrorCode, Result};
+use crate::ffi::{bytes, write_out};
+
+/// Longest output of `expand_message_xmd` with SHA-256, 255 blocks.
+pub const MAX_EXPAND_SIZE: usize = 255 * 32;
+/// Size of an uncompressed G1 point.
+pub const G1_SIZE: usize = 96;
+/// Size of an uncompressed G2 point.
+pub const G2_SIZE: usize = 192;
+
+fn check_dst(dst: &[u8]) -> Result<()> {
+    // Longer tags are hashed as the RFC prescribes.
+    if dst.is_empty() {
+        return Err(Error::new(
+            ErrorCode::MalformedInputs,
+            "domain separation tag must not be empty",
+        ));
+    }
+    Ok(())
+}
+
+/// Expands `msg` to `len` uniform bytes, 1 to `MAX_EXPAND_SIZE`.
+pub fn expand_message_xmd(msg: &[u8], dst: &[u8], len: usize) -> Result<Vec<u8>> {
+    check_dst(dst)?;
+    if !(1..=MAX_EXPAND_SIZE).contains(&len) {
+        return Err(Error::new(
+            ErrorCode::MalformedInputs,
+            format!(
+                "expand_message_xmd output must be 1 to {} bytes, got {}",
+                MAX_EXPAND_SIZE, len
+            ),
+        ));
+    }
+    Ok(ExpandMsgXmd::<Sha256>::init_expand(msg, dst, len).into_vec())
+}
+
+/// Hashes `msg` to G1 with `BLS12381G1_XMD:SHA-256_SSWU_RO_`.
+pub fn hash_to_g1(msg: &[u8], dst: &[u8]) -> Result<G1Projective> {
+    check_dst(dst)?;
+    Ok(<G1Projective as HashToCurve<ExpandMsgXmd<Sha256>>>::hash_to_curve(msg, dst))
+}
+
+/// Hashes `msg` to G2 with `BLS12381G2_XMD:SHA-256_SSWU_RO_`.
+pub fn hash_to_g2(msg: &[u8], dst: &[u8]) -> Result<G2Projective> {
+    check_dst(dst)?;
+    Ok(<G2Projective as HashToCurve<ExpandMsgXmd<Sha256>>>::hash_to_curve(msg, dst))
+}
+
+/// Hashes `msg` to a scalar with `hash_to_field` over the scalar field,
+/// expanding to `L = 48` bytes with SHA-256.
+pub fn hash_to_scalar(msg: &[u8], dst: &[u8]) -> Result<Scalar> {
+    check_dst(dst)?;
+    let mut s = [Scalar::zero()];
+    Scalar::hash_to_field::<ExpandMsgXmd<Sha256>>(msg, dst, &mut s);
+    Ok(s[0])
+}
+
+/// Writes `out_len` bytes of `expand_message_xmd(msg, dst)` with SHA-256
+/// to `out`.
+#[no_mangle]
+pub extern "C" fn expand_message(
+    msg: *mut libc::c_uchar,
+    msg_len: libc::size_t,
+    dst: *mut libc::c_uchar,
+    dst_len: libc::size_t,
+    out: *mut libc::c_uchar,
+    out_len: libc::size_t,
+) -> libc::c_int {
+    error::status((|| {
+        let b = expand_message_xmd(bytes(msg, msg_len)?, bytes(dst, dst_len)?, out_len)?;
+        write_out(out, out_len, &b)?;
+        Ok(true)
+    })())
+}
+
+/// Hashes a message to G1 and writes the uncompressed point to `point`,
+/// 96 bytes.
+#[no_mangle]
+pub extern "C" fn hash_to_curve_g1(
+    msg: *mut libc::c_uchar,
+    msg_len: libc::size_t,
+    dst: *mut libc::c_uchar,
+    dst_len: libc::size_t,
+    point: *mut libc::c_uchar,
+) -> libc::c_int {
+    error::status((|| {
+        let p = hash_to_g1(bytes(msg, msg_len)?, bytes(dst, dst_len)?)?;
+        write_out(point, G1_SIZE, &G1Affine::from(p).to_uncompressed())?;
+        Ok(true)
+    })())
+}
+
+/// Hashes a message to G2 and writes the uncompressed point to `point`,
+/// 192 bytes.
+#[no_mangle]
+pub extern "C" fn hash_to_curve_g2(
+    msg: *mut libc::c_uchar,
+    msg_len: libc::size_t,
+    dst: *mut libc::c_uchar,
+    dst_len: libc::size_t,
+    point: *mut libc::c_uchar,
+) -> libc::c_int {
+    error::status((|| {
+        let p = hash_to_g2(bytes(msg, msg_len)?, bytes(dst, dst_len)?)?;
+        write_out(point, G2_SIZE, &G2Affine::from(p).to_uncompressed())?;
+        Ok(true)
+    })())
+}
+
+/// Hashes a message to a scalar and writes it to `scalar` as 32 big-endian
+/// bytes.
+#[no_mangle]
+pub extern "C" fn hash_to_field_scalar(
+    msg: *mut libc::c_uchar,
+    msg_len: libc::size_t,
+    dst: *mut libc::c_uchar,
+    dst_len: libc::size_t,
+    scalar: *mut libc::c_uchar,
+) -> libc::c_int {
+    error::status((|| {
+        let mut s = hash_to_scalar(bytes(msg, msg_len)?, bytes(dst, dst_len)?)?.to_bytes();
+        s.reverse();
+        write_out(scalar, s.len(), &s)?;
+        Ok(true)
+    })())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    /// Messages of the RFC 9380 test vectors.
+    fn messages() -> [Vec<u8>; 5] {
+        [
+            b"".to_vec(),
+            b"abc".to_vec(),
+            b"abcdef0123456789".to_vec(),
+            [&b"q128_"[..], &[b'q'; 128]].concat(),
+            [&b"a512_"[..], &[b'a'; 512]].concat(),
+        ]
+    }
+
+    #[test]
+    fn test_expand_message_xmd() {
+        // RFC 9380 appendix K.1.
+        let dst = b"QUUX-V01-CS02-with-expander-SHA256-128";
+        let short = [
+            "68a985b87eb6b46952128911f2a4412bbc302a9d759667f87f7a21d803f07235",
+            "d8ccab23b5985ccea865c6c97b6e5b8350e794e603b4b97902f53a8a0d605615",
+            "eff31487c770a893cfb36f912fbfcbff40d5661771ca4b2cb4eafe524333f5c1",
+            "b23a1d2b4d97b2ef7785562a7e8bac7eed54ed6e97e29aa51bfe3f12ddad1ff9",
+            "4623227bcc01293b8c130bf771da8c298dede7383243dc0993d2d94823958c4c",
+        ];
+        for (msg, expected) in messages().iter().zip(short) {
+            assert_eq!(hex(expected), expand_message_xmd(msg, dst, 0x20).unwrap());
+        }
+        let long = "546aff5444b5b79aa6148bd81728704c32decb73a3ba76e9e75885cad9def1d06d6792f8a7d12794e90efed817d96920d728896a4510864370c207f99bd4a608ea121700ef01ed879745ee3e4ceef777eda6d9e5e38b90c86ea6fb0b36504ba4a45d22e86f6db5dd43d98a294bebb9125d5b794e9d2a81181066eb954966a487";
+        assert_eq!(
+            hex(long),
+            expand_message_xmd(&messages()[4], dst, 0x80).unwrap()
+        );
+
+        // A tag over 255 bytes is hashed first.
+        let dst = [
+            &b"QUUX-V01-CS02-with-expander-SHA256-128-long-DST-"[..],
+            &[b'1'; 208],
+        ]
+        .concat();
+        assert_eq!(
+            hex("e8dc0c8b686b7ef2074086fbdd2f30e3f8bfbd3bdf177f73f04b97ce618a3ed3"),
+            expand_message_xmd(b"", &dst, 0x20).unwrap()
+        );
+
+        assert!(expand_message_xmd(b"", b"", 0x20).is_err());
+        assert!(expand_message_xmd(b"", &dst, 0).is_err());
+        assert!(expand_message_xmd(b"", &dst, MAX_EXPAND_SIZE + 1).is_err());
+        assert_eq!(
+            MAX_EXPAND_SIZE,
+            expand_message_xmd(b"", &dst, MAX_EXPAND_SIZE)
+                .unwrap()
+                .len()
+        );
+    }
+
+    #[test]
+    fn test_hash_to_g1() {
+        // RFC 9380 appendix J.9.1.
+        let dst = b"QUUX-V01-CS02-with-BLS12381G1_XMD:SHA-256_SSWU_RO_";
+        let expected = [
+            "052926add2207b76ca4fa57a8734416c8dc95e24501772c814278700eed6d1e4e8cf62d9c09db0fac349612b759e79a108ba738453bfed09cb546dbb0783dbb3a5f1f566ed67bb6be0e8c67e2e81a4cc68ee29813bb7994998f3eae0c9c6a265",
+            "03567bc5ef9c690c2ab2ecdf6a96ef1c139cc0b2f284dca0a9a7943388a49a3aee664ba5379a7655d3c68900be2f69030b9c15f3fe6e5cf4211f346271d7b01c8f3b28be689c8429c85b67af215533311f0b8dfaaa154fa6b88176c229f2885d",
+            "11e0b079dea29a68f0383ee94fed1b940995272407e3bb916bbf268c263ddd57a6a27200a784cbc248e84f357ce82d9803a87ae2caf14e8ee52e51fa2ed8eefe80f02457004ba4d486d6aa1f517c0889501dc7413753f9599b099ebcbbd2d709",
+            "15f68eaa693b95ccb85215dc65fa81038d69629f70aeee0d0f677cf22285e7bf58d7cb86eefe8f2e9bc3f8cb84fac4881807a1d50c29f430b8cafc4f8638dfeeadf51211e1602a5f184443076715f91bb90a48ba1e370edce6ae1062f5e6dd38",
+            "082aabae8b7dedb0e78aeb619ad3bfd9277a2f77ba7fad20ef6aabdc6c31d19ba5a6d12283553294c1825c4b3ca2dcfe05b84ae5a942248eea39e1d91030458c40153f3b654ab7872d779ad1e942856a20c438e8d99bc8abfbf74729ce1f7ac8",
+        ];
+        for (msg, expected) in messages().iter().zip(expected) {
+            let p = G1Affine::from(hash_to_g1(msg, dst).unwrap());
+            assert_eq!(hex(expected), p.to_uncompressed());
+        }
+        assert!(hash_to_g1(b"abc", b"").is_err());
+    }
+
+    #[test]
+    fn test_hash_to_g2() {
+        // RFC 9380 appendix J.10.1.
+        let dst = b"QUUX-V01-CS02-with-BLS12381G2_XMD:SHA-256_SSWU_RO_";
+        let expected = [
+            "05cb8437535e20ecffaef7752baddf98034139c38452458baeefab379ba13dff5bf5dd71b72418717047f5b0f37da03d0141ebfbdca40eb85b87142e130ab689c673cf60f1a3e98d69335266f30d9b8d4ac44c1038e9dcdd5393faf5c41fb78a12424ac32561493f3fe3c260708a12b7c620e7be00099a974e259ddc7d1f6395c3c811cdd19f1e8dbf3e9ecfdcbab8d60503921d7f6a12805e72940b963c0cf3471c7b2a524950ca195d11062ee75ec076daf2d4bc358c4b190c0c98064fdd92",
+            "139cddbccdc5e91b9623efd38c49f81a6f83f175e80b06fc374de9eb4b41dfe4ca3a230ed250fbe3a2acf73a41177fd802c2d18e033b960562aae3cab37a27ce00d80ccd5ba4b7fe0e7a210245129dbec7780ccc7954725f4168aff2787776e600aa65dae3c8d732d10ecd2c50f8a1baf3001578f71c694e03866e9f3d49ac1e1ce70dd94a733534f106d4cec0eddd161787327b68159716a37440985269cf584bcb1e621d3a7202be6ea05c4cfe244aeb197642555a0645fb87bf7466b2ba48",
+            "190d119345b94fbd15497bcba94ecf7db2cbfd1e1fe7da034d26cbba169fb3968288b3fafb265f9ebd380512a71c3f2c121982811d2491fde9ba7ed31ef9ca474f0e1501297f68c298e9f4c0028add35aea8bb83d53c08cfc007c1e005723cd00bb5e7572275c567462d91807de765611490205a941a5a6af3b1691bfe596c31225d3aabdf15faff860cb4ef17c7c3be05571a0f8d3c08d094576981f4a3b8eda0a8e771fcdcc8ecceaf1356a6acf17574518acb506e435b639353c2e14827c8",
+            "0934aba516a52d8ae479939a91998299c76d39cc0c035cd18813bec433f587e2d7a4fef038260eef0cef4d02aae3eb9119a84dd7248a1066f737cc34502ee5555bd3c19f2ecdb3c7d9e24dc65d4e25e50d83f0f77105e955d78f4762d33c17da09bcccfa036b4847c9950780733633f13619994394c23ff0b32fa6b795844f4a0673e20282d07bc69641cee04f5e566214f81cd421617428bc3b9fe25afbb751d934a00493524bc4e065635b0555084dd54679df1536101b2c979c0152d09192",
+            "11fca2ff525572795a801eed17eb12785887c7b63fb77a42be46ce4a34131d71f7a73e95fee3f812aea3de78b4d0156901a6ba2f9a11fa5598b2d8ace0fbe0a0eacb65deceb476fbbcb64fd24557c2f4b18ecfc5663e54ae16a84f5ab7f6253403a47f8e6d1763ba0cad63d6114c0accbef65707825a511b251a660a9b3994249ae4e63fac38b23da0c398689ee2ab520b6798718c8aed24bc19cb27f866f1c9effcdbf92397ad6448b5c9db90d2b9da6cbabf48adc1adf59a1a28344e79d57e",
+        ];
+        for (msg, expected) in messages().iter().zip(expected) {
+            let p = G2Affine::from(hash_to_g2(msg, dst).unwrap());
+            assert_eq!(hex(expected), p.to_uncompressed());
+        }
+    }
+
+    #[test]
+    fn test_ffi() {
+        let mut msg = *b"abc";
+        let mut dst = *b"QUUX-V01-CS02-with-BLS12381G1_XMD:SHA-256_SSWU_RO_";
+        let mut p = [0u8; G1_SIZE];
+        let code = hash_to_curve_g1(
+            msg.as_mut_ptr(),
+            msg.len(),
+            dst.as_mut_ptr(),
+            dst.len(),
+            p.as_mut_ptr(),
+        );
+        assert_eq!(1, code);
+        assert_eq!(
+            G1Affine::from(hash_to_g1(&msg, &dst).unwrap()).to_uncompressed(),
+            p
+        );
+        let code = hash_to_curve_g1(
+            msg.as_mut_ptr(),
+            msg.len(),
+            dst.as_mut_ptr(),
+            0,
+            p.as_mut_ptr(),
+        );
+        assert_eq!(ErrorCode::MalformedInputs as libc::c_int, code);
+
+        let mut q = [0u8; G2_SIZE];
+        let code = hash_to_curve_g2(
+            msg.as_mut_ptr(),
+            msg.len(),
+            dst.as_mut_ptr(),
+            dst.len(),
+            q.as_mut_ptr(),
+        );
+        assert_eq!(1, code);
+        assert!(bool::from(G2Affine::from_uncompressed(&q).is_some()));
+
+        let mut out = [0u8; 48];
+        let code = expand_message(
+            msg.as_mut_ptr(),
+            msg.len(),
+            dst.as_mut_ptr(),
+            dst.len(),
+            out.as_mut_ptr(),
+            out.len(),
+        );
+        assert_eq!(1, code);
+        assert_eq!(expand_message_xmd(&msg, &dst, 48).unwrap(), out);
+
+        // hash_to_field reduces the 48 expanded bytes modulo the order.
+        let mut s = [0u8; 32];
+        let code = hash_to_field_scalar(
+            msg.as_mut_ptr(),
+            msg.len(),
+            dst.as_mut_ptr(),
+            dst.len(),
+            s.as_mut_ptr(),
+        );
+        assert_eq!(1, code);
+        let mut wide = [0u8; 64];
+        for (w, b) in wide.iter_mut().zip(out.iter().rev()) {
+            *w = *b;
+        }
+        s.reverse();
+        assert_eq!(Scalar::from_bytes_wide(&wide).to_bytes(), s);
+    }
+}
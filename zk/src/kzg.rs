@@ -0,0 +1,1372 @@
+//! KZG commitments on BLS12-381 as used by EIP-4844 blobs, and the point
+//! evaluation precompile checking openings of them. A commitment `C` is a
+//! polynomial evaluated at the secret point `s` of a trusted setup in G1,
+//! and the proof that it opens to `y` at `z` is the quotient
+//! `(p(X) - y) / (X - z)` evaluated at `s`, checked with
+//!
+//! ```text
+//! e(C - [y]1, [1]2) == e(proof, [s]2 - [z]2)
+//! ```
+//!
+//! The trusted setup is the output of the Ethereum KZG ceremony, loaded once
+//! per process with `kzg_load_trusted_setup` in the text format of c-kzg:
+//! the number of G1 and of G2 points on a line each, then the compressed G1
+//! points of the Lagrange basis, the compressed G2 points `[s^i]2` and the
+//! compressed G1 points `[s^i]1`, one hex string per line. Files written
+//! before c-kzg 2 lack the G1 monomial points and are accepted as well.
+//! `data/trusted_setup.txt` holds the mainnet one, as shipped with c-kzg.
+//!
+//! Beyond blobs, an `Srs` of monomial powers commits to any `Polynomial`,
+//! given by coefficients or by values over the roots of unity, and opens it
+//! at one point, at several points with a single proof checked against the
+//! vanishing polynomial of the points in G2, or checks many single openings
+//! at once with a random linear combination.
+
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+use bls12_381::{
+    multi_miller_loop, G1Affine, G1Projective, G2Affine, G2Prepared, G2Projective, Scalar,
+};
+use ff::{Field, PrimeField};
+use group::{Curve as _, Group};
+use rand_chacha::rand_core::{RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use rand_core::OsRng;
+use sha2::{Digest, Sha256};
+
+use crate::bls::read_all;
+use crate::error::{self, Error, ErrorCode, Result};
+use crate::ffi::{bytes, write_out};
+
+/// Number of field elements in a blob, the size of the Lagrange basis.
+pub const FIELD_ELEMENTS_PER_BLOB: usize = 4096;
+/// Size of a compressed commitment or proof.
+pub const COMMITMENT_SIZE: usize = 48;
+/// Size of a big-endian field element.
+pub const FIELD_ELEMENT_SIZE: usize = 32;
+/// Size of a versioned hash.
+pub const VERSIONED_HASH_SIZE: usize = 32;
+/// Version byte of the versioned hash of a KZG commitment.
+pub const VERSIONED_HASH_VERSION_KZG: u8 = 0x01;
+/// Size of the precompile input: versioned hash, z, y, commitment, proof.
+pub const POINT_EVALUATION_INPUT_SIZE: usize =
+    VERSIONED_HASH_SIZE + 2 * FIELD_ELEMENT_SIZE + 2 * COMMITMENT_SIZE;
+/// Size of the precompile output.
+pub const POINT_EVALUATION_OUTPUT_SIZE: usize = 64;
+/// Gas of a point evaluation, whatever the input.
+pub const POINT_EVALUATION_GAS: u64 = 50000;
+/// The order of the scalar field, big-endian.
+pub const BLS_MODULUS: [u8; 32] = [
+    0x73, 0xed, 0xa7, 0x53, 0x29, 0x9d, 0x7d, 0x48, 0x33, 0x39, 0xd8, 0x08, 0x09, 0xa1, 0xd8, 0x05,
+    0x53, 0xbd, 0xa4, 0x02, 0xff, 0xfe, 0x5b, 0xfe, 0xff, 0xff, 0xff, 0xff, 0x00, 0x00, 0x00, 0x01,
+];
+
+/// Largest number of powers of each group in an SRS.
+pub const MAX_SRS_SIZE: usize = 1 << 20;
+
+/// Size of a compressed G2 point.
+const G2_SIZE: usize = 96;
+
+/// Personalization of the hash turning a seed into the RNG key.
+const SRS_PERSONALIZATION: &[u8; 8] = b"zkKzgSrs";
+
+/// Personalization of the transcript hash seeding batch coefficients.
+const BATCH_PERSONALIZATION: &[u8; 8] = b"zkKzgBat";
+
+/// Points of a trusted setup. Point evaluation only needs `[s]2`, so the
+/// G1 points are kept compressed and decoded on demand.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TrustedSetup {
+    /// Lagrange basis over the roots of unity, in bit-reversed order.
+    g1_lagrange: Vec<[u8; COMMITMENT_SIZE]>,
+    /// `[s^i]1`, from `i = 0`, empty in a legacy setup.
+    g1_monomial: Vec<[u8; COMMITMENT_SIZE]>,
+    /// `[s^i]2`, from `i = 0`.
+    pub g2_monomial: Vec<G2Affine>,
+}
+
+fn malformed_setup(msg: impl Into<String>) -> Error {
+    Error::new(ErrorCode::MalformedParams, msg)
+}
+
+fn read_hex<const N: usize>(line: &str) -> Result<[u8; N]> {
+    if line.len() != 2 * N || !line.is_ascii() {
+        return Err(malformed_setup(format!(
+            "expected {} hex bytes, got {:?}",
+            N, line
+        )));
+    }
+    let mut b = [0u8; N];
+    for (i, byte) in b.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&line[2 * i..2 * i + 2], 16)
+            .map_err(|_| malformed_setup(format!("not hex: {:?}", line)))?;
+    }
+    Ok(b)
+}
+
+impl TrustedSetup {
+    /// Parses a setup in the c-kzg text format, with or without the G1
+    /// monomial points. The G2 points are checked to be in the subgroup and
+    /// to start with the generator, as is the first G1 monomial point; the
+    /// other G1 points are only checked to be hex of the right size.
+    pub fn read(text: &str) -> Result<Self> {
+        let mut lines = text.lines().map(str::trim).filter(|l| !l.is_empty());
+        let mut count = || -> Result<usize> {
+            lines
+                .next()
+                .and_then(|l| l.parse().ok())
+                .ok_or_else(|| malformed_setup("missing point count"))
+        };
+        let (n1, n2) = (count()?, count()?);
+        if n1 != FIELD_ELEMENTS_PER_BLOB || n2 < 2 {
+            return Err(malformed_setup(format!(
+                "expected {} G1 points and at least 2 G2 points, got {} and {}",
+                FIELD_ELEMENTS_PER_BLOB, n1, n2
+            )));
+        }
+        let mut next = || {
+            lines
+                .next()
+                .ok_or_else(|| malformed_setup("setup is truncated"))
+        };
+        let g1_lagrange = (0..n1)
+            .map(|_| read_hex::<COMMITMENT_SIZE>(next()?))
+            .collect::<Result<_>>()?;
+        let g2_monomial = (0..n2)
+            .map(|_| {
+                let b = read_hex::<G2_SIZE>(next()?)?;
+                Option::from(G2Affine::from_compressed(&b))
+                    .ok_or_else(|| malformed_setup("malformed G2 point"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if g2_monomial[0] != G2Affine::generator() {
+            return Err(malformed_setup("G2 points don't start with the generator"));
+        }
+        let rest = lines.collect::<Vec<_>>();
+        if !rest.is_empty() && rest.len() != n1 {
+            return Err(malformed_setup(format!(
+                "expected no or {} G1 monomial points, got {}",
+                n1,
+                rest.len()
+            )));
+        }
+        let g1_monomial = rest
+            .into_iter()
+            .map(read_hex::<COMMITMENT_SIZE>)
+            .collect::<Result<Vec<_>>>()?;
+        if g1_monomial
+            .first()
+            .is_some_and(|p| *p != G1Affine::generator().to_compressed())
+        {
+            return Err(malformed_setup("G1 points don't start with the generator"));
+        }
+        Ok(TrustedSetup {
+            g1_lagrange,
+            g1_monomial,
+            g2_monomial,
+        })
+    }
+
+    /// Decodes the Lagrange basis, checking its points are in the subgroup.
+    pub fn g1_lagrange(&self) -> Result<Vec<G1Affine>> {
+        read_g1_points(&self.g1_lagrange)
+    }
+
+    /// Decodes the G1 monomial points, checking they're in the subgroup.
+    /// Empty for a setup in the legacy format.
+    pub fn g1_monomial(&self) -> Result<Vec<G1Affine>> {
+        read_g1_points(&self.g1_monomial)
+    }
+
+    /// Checks that `commitment` opens to `y` at `z`.
+    pub fn verify_proof(
+        &self,
+        commitment: &G1Affine,
+        z: &Scalar,
+        y: &Scalar,
+        proof: &G1Affine,
+    ) -> bool {
+        let lhs = G1Projective::from(commitment) - G1Affine::generator() * y;
+        let divisor = G2Projective::from(self.g2_monomial[1]) - G2Affine::generator() * z;
+        check_opening(lhs, &G2Affine::generator(), proof, divisor)
+    }
+}
+
+fn read_g1_points(points: &[[u8; COMMITMENT_SIZE]]) -> Result<Vec<G1Affine>> {
+    points
+        .iter()
+        .map(|b| {
+            Option::from(G1Affine::from_compressed(b))
+                .ok_or_else(|| malformed_setup("malformed G1 point"))
+        })
+        .collect()
+}
+
+static SETUP: RwLock<Option<Arc<TrustedSetup>>> = RwLock::new(None);
+
+/// The setup loaded with `kzg_load_trusted_setup`.
+pub fn trusted_setup() -> Result<Arc<TrustedSetup>> {
+    SETUP
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+        .ok_or_else(|| malformed_setup("no trusted setup is loaded"))
+}
+
+/// Reads a big-endian field element, rejecting non-canonical ones.
+pub fn read_field_element(b: &[u8]) -> Result<Scalar> {
+    let mut repr: [u8; FIELD_ELEMENT_SIZE] = b.try_into().map_err(|_| {
+        Error::new(
+            ErrorCode::MalformedInputs,
+            format!("field element must be {} bytes", FIELD_ELEMENT_SIZE),
+        )
+    })?;
+    repr.reverse();
+    Option::from(Scalar::from_bytes(&repr))
+        .ok_or_else(|| Error::new(ErrorCode::MalformedInputs, "field element is not canonical"))
+}
+
+/// Reads a compressed G1 point in the subgroup, the identity included.
+fn read_g1(b: &[u8], code: ErrorCode, what: &str) -> Result<G1Affine> {
+    let repr: [u8; COMMITMENT_SIZE] = b
+        .try_into()
+        .map_err(|_| Error::new(code, format!("{} must be {} bytes", what, COMMITMENT_SIZE)))?;
+    Option::from(G1Affine::from_compressed(&repr))
+        .ok_or_else(|| Error::new(code, format!("malformed {}", what)))
+}
+
+/// The versioned hash of a commitment as found in blob transactions.
+pub fn versioned_hash(commitment: &[u8]) -> [u8; VERSIONED_HASH_SIZE] {
+    let mut h: [u8; VERSIONED_HASH_SIZE] = Sha256::digest(commitment).into();
+    h[0] = VERSIONED_HASH_VERSION_KZG;
+    h
+}
+
+/// Runs the point evaluation precompile of EIP-4844 on its 192-byte input.
+/// Returns `Ok(None)` if the proof is invalid and an error if the input is
+/// malformed, both of which fail the call.
+pub fn point_evaluation(
+    setup: &TrustedSetup,
+    input: &[u8],
+) -> Result<Option<[u8; POINT_EVALUATION_OUTPUT_SIZE]>> {
+    if input.len() != POINT_EVALUATION_INPUT_SIZE {
+        return Err(Error::new(
+            ErrorCode::MalformedInputs,
+            format!(
+                "point evaluation input must be {} bytes, got {}",
+                POINT_EVALUATION_INPUT_SIZE,
+                input.len()
+            ),
+        ));
+    }
+    let (hash, rest) = input.split_at(VERSIONED_HASH_SIZE);
+    let (z, rest) = rest.split_at(FIELD_ELEMENT_SIZE);
+    let (y, rest) = rest.split_at(FIELD_ELEMENT_SIZE);
+    let (commitment, proof) = rest.split_at(COMMITMENT_SIZE);
+    if versioned_hash(commitment) != hash {
+        return Err(Error::new(
+            ErrorCode::MalformedInputs,
+            "versioned hash doesn't match the commitment",
+        ));
+    }
+    let z = read_field_element(z)?;
+    let y = read_field_element(y)?;
+    let commitment = read_g1(commitment, ErrorCode::MalformedInputs, "commitment")?;
+    let proof = read_g1(proof, ErrorCode::MalformedProof, "proof")?;
+    if !setup.verify_proof(&commitment, &z, &y, &proof) {
+        return Ok(None);
+    }
+    let mut out = [0u8; POINT_EVALUATION_OUTPUT_SIZE];
+    out[24..32].copy_from_slice(&(FIELD_ELEMENTS_PER_BLOB as u64).to_be_bytes());
+    out[32..].copy_from_slice(&BLS_MODULUS);
+    Ok(Some(out))
+}
+
+/// Checks `e(lhs, [1]2) == e(proof, divisor)`, `g2` being the G2 generator
+/// of the setup.
+fn check_opening(
+    lhs: G1Projective,
+    g2: &G2Affine,
+    proof: &G1Affine,
+    divisor: G2Projective,
+) -> bool {
+    let lhs = lhs.to_affine();
+    let neg_g2 = G2Prepared::from(-g2);
+    let divisor = G2Prepared::from(divisor.to_affine());
+    bool::from(
+        multi_miller_loop(&[(&lhs, &neg_g2), (proof, &divisor)])
+            .final_exponentiation()
+            .is_identity(),
+    )
+}
+
+/// Returns a generator of the subgroup of order `size` of the roots of
+/// unity, `size` being a power of two up to 2^32.
+fn domain_generator(size: usize) -> Result<Scalar> {
+    if !size.is_power_of_two() || size.trailing_zeros() > Scalar::S {
+        return Err(Error::new(
+            ErrorCode::MalformedInputs,
+            format!(
+                "evaluation domain size must be a power of two up to 2^{}, got {}",
+                Scalar::S,
+                size
+            ),
+        ));
+    }
+    let mut omega = Scalar::ROOT_OF_UNITY;
+    for _ in size.trailing_zeros()..Scalar::S {
+        omega = omega.square();
+    }
+    Ok(omega)
+}
+
+/// In-place radix-2 FFT: `a[i]` becomes the polynomial with coefficients
+/// `a` evaluated at `omega^i`.
+fn fft(a: &mut [Scalar], omega: Scalar) {
+    let n = a.len();
+    let log_n = n.trailing_zeros();
+    for i in 0..n {
+        let j = i.reverse_bits() >> (usize::BITS - log_n);
+        if i < j {
+            a.swap(i, j);
+        }
+    }
+    let mut m = 1;
+    while m < n {
+        let w_m = omega.pow_vartime(&[(n / (2 * m)) as u64, 0, 0, 0]);
+        for k in (0..n).step_by(2 * m) {
+            let mut w = Scalar::ONE;
+            for j in 0..m {
+                let t = w * a[k + j + m];
+                a[k + j + m] = a[k + j] - t;
+                a[k + j] += t;
+                w *= w_m;
+            }
+        }
+        m *= 2;
+    }
+}
+
+/// A polynomial over the scalar field, by coefficients from the constant
+/// one up.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Polynomial(pub Vec<Scalar>);
+
+impl Polynomial {
+    /// The polynomial taking the values `evaluations[i]` at `omega^i`,
+    /// `omega` generating the roots of unity of order `evaluations.len()`, a
+    /// power of two.
+    pub fn from_evaluations(evaluations: &[Scalar]) -> Result<Self> {
+        let n = evaluations.len();
+        let omega = domain_generator(n)?;
+        let mut a = evaluations.to_vec();
+        fft(
+            &mut a,
+            omega.invert().expect("roots of unity are invertible"),
+        );
+        let n_inv = Scalar::from(n as u64)
+            .invert()
+            .expect("n is below the modulus");
+        a.iter_mut().for_each(|c| *c *= n_inv);
+        Ok(Polynomial(a))
+    }
+
+    /// The values of the polynomial at the `size` roots of unity of order
+    /// `size`, the inverse of `from_evaluations`.
+    pub fn evaluations(&self, size: usize) -> Result<Vec<Scalar>> {
+        let omega = domain_generator(size)?;
+        if self.degree() >= size {
+            return Err(Error::new(
+                ErrorCode::MalformedInputs,
+                format!("degree {} doesn't fit a domain of {}", self.degree(), size),
+            ));
+        }
+        let mut a = self.0.clone();
+        a.resize(size, Scalar::ZERO);
+        fft(&mut a, omega);
+        Ok(a)
+    }
+
+    /// The polynomial of lowest degree through the points `(xs[i], ys[i])`,
+    /// whose abscissas must be distinct.
+    pub fn interpolate(xs: &[Scalar], ys: &[Scalar]) -> Result<Self> {
+        if xs.len() != ys.len() {
+            return Err(Error::new(
+                ErrorCode::InputCountMismatch,
+                format!("{} points for {} values", xs.len(), ys.len()),
+            ));
+        }
+        let mut result = vec![Scalar::ZERO; xs.len()];
+        for (i, (xi, yi)) in xs.iter().zip(ys).enumerate() {
+            // yi * prod_{j != i} (X - xj) / (xi - xj)
+            let mut basis = Polynomial(vec![Scalar::ONE]);
+            let mut den = Scalar::ONE;
+            for xj in xs[..i].iter().chain(&xs[i + 1..]) {
+                basis = basis.mul_linear(xj);
+                den *= xi - xj;
+            }
+            let scale = yi
+                * Option::<Scalar>::from(den.invert()).ok_or_else(|| {
+                    Error::new(ErrorCode::MalformedInputs, "points must be distinct")
+                })?;
+            for (r, b) in result.iter_mut().zip(basis.0) {
+                *r += b * scale;
+            }
+        }
+        Ok(Polynomial(result))
+    }
+
+    /// `prod (X - xs[i])`.
+    pub fn vanishing(xs: &[Scalar]) -> Self {
+        xs.iter()
+            .fold(Polynomial(vec![Scalar::ONE]), |p, x| p.mul_linear(x))
+    }
+
+    /// Degree of the polynomial, zero for the zero polynomial.
+    pub fn degree(&self) -> usize {
+        self.0
+            .iter()
+            .rposition(|c| !bool::from(c.is_zero()))
+            .unwrap_or(0)
+    }
+
+    pub fn evaluate(&self, x: &Scalar) -> Scalar {
+        self.0.iter().rev().fold(Scalar::ZERO, |acc, c| acc * x + c)
+    }
+
+    /// Multiplies by `X - x`.
+    fn mul_linear(&self, x: &Scalar) -> Self {
+        let mut r = vec![Scalar::ZERO; self.0.len() + 1];
+        for (i, c) in self.0.iter().enumerate() {
+            r[i + 1] += c;
+            r[i] -= c * x;
+        }
+        Polynomial(r)
+    }
+
+    /// Quotient of the division by the monic polynomial `d`, the remainder
+    /// being dropped.
+    fn div_monic(&self, d: &Polynomial) -> Self {
+        let dd = d.degree();
+        let mut rem = self.0.clone();
+        if rem.len() <= dd {
+            return Polynomial(vec![]);
+        }
+        let mut q = vec![Scalar::ZERO; rem.len() - dd];
+        for i in (0..q.len()).rev() {
+            let c = rem[i + dd];
+            q[i] = c;
+            for (j, dj) in d.0[..=dd].iter().enumerate() {
+                rem[i + j] -= c * dj;
+            }
+        }
+        Polynomial(q)
+    }
+
+    fn sub(&self, other: &Polynomial) -> Self {
+        let mut r = self.0.clone();
+        r.resize(r.len().max(other.0.len()), Scalar::ZERO);
+        for (a, b) in r.iter_mut().zip(other.0.iter()) {
+            *a -= b;
+        }
+        Polynomial(r)
+    }
+}
+
+/// An opening to check in a batch with `Srs::verify_batch`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Opening {
+    pub commitment: G1Affine,
+    pub z: Scalar,
+    pub y: Scalar,
+    pub proof: G1Affine,
+}
+
+/// A structured reference string: the powers `[s^i]1` and `[s^i]2` of a
+/// secret `s` from `i = 0`. It commits to polynomials of degree below the
+/// number of G1 powers and opens them at as many points at once as there
+/// are G2 powers but one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Srs {
+    pub g1: Vec<G1Affine>,
+    pub g2: Vec<G2Affine>,
+}
+
+impl Srs {
+    /// Draws `s` from `seed` if it's given and from the OS otherwise and
+    /// computes its powers. Whoever runs it learns `s` and can open
+    /// commitments to anything, a seeded one is only fit for tests and
+    /// devnets.
+    pub fn generate(g1_size: usize, g2_size: usize, seed: Option<&[u8]>) -> Result<Self> {
+        if g1_size < 1 || g2_size < 2 || g1_size.max(g2_size) > MAX_SRS_SIZE {
+            return Err(malformed_setup(format!(
+                "need 1 to {} G1 powers and 2 to {} G2 powers, got {} and {}",
+                MAX_SRS_SIZE, MAX_SRS_SIZE, g1_size, g2_size
+            )));
+        }
+        let s = match seed {
+            Some(seed) => {
+                let key = blake2s_simd::Params::new()
+                    .personal(SRS_PERSONALIZATION)
+                    .hash(seed);
+                Scalar::random(ChaCha20Rng::from_seed(*key.as_array()))
+            }
+            None => Scalar::random(OsRng),
+        };
+        let mut powers = vec![Scalar::ONE; g1_size.max(g2_size)];
+        for i in 1..powers.len() {
+            powers[i] = powers[i - 1] * s;
+        }
+        let g1: Vec<G1Projective> = powers[..g1_size]
+            .iter()
+            .map(|p| G1Affine::generator() * p)
+            .collect();
+        let g2: Vec<G2Projective> = powers[..g2_size]
+            .iter()
+            .map(|p| G2Affine::generator() * p)
+            .collect();
+        let mut srs = Srs {
+            g1: vec![G1Affine::identity(); g1_size],
+            g2: vec![G2Affine::identity(); g2_size],
+        };
+        G1Projective::batch_normalize(&g1, &mut srs.g1);
+        G2Projective::batch_normalize(&g2, &mut srs.g2);
+        Ok(srs)
+    }
+
+    /// Decodes the big-endian u32 numbers of G1 and of G2 powers followed
+    /// by the compressed powers, checking they're all in the subgroup.
+    pub fn read(b: &[u8]) -> Result<Self> {
+        if b.len() < 8 {
+            return Err(malformed_setup("SRS is too short"));
+        }
+        let n1 = u32::from_be_bytes(b[..4].try_into().expect("4 bytes")) as usize;
+        let n2 = u32::from_be_bytes(b[4..8].try_into().expect("4 bytes")) as usize;
+        if n1 < 1 || n2 < 2 || n1.max(n2) > MAX_SRS_SIZE {
+            return Err(malformed_setup(format!(
+                "SRS has {} G1 and {} G2 powers",
+                n1, n2
+            )));
+        }
+        let g2_start = 8 + n1 * COMMITMENT_SIZE;
+        if b.len() != g2_start + n2 * G2_SIZE {
+            return Err(malformed_setup(format!(
+                "SRS with {} G1 and {} G2 powers must be {} bytes, got {}",
+                n1,
+                n2,
+                g2_start + n2 * G2_SIZE,
+                b.len()
+            )));
+        }
+        let g1 = b[8..g2_start]
+            .chunks(COMMITMENT_SIZE)
+            .map(|p| read_g1(p, ErrorCode::MalformedParams, "SRS G1 power"))
+            .collect::<Result<_>>()?;
+        let g2 = b[g2_start..]
+            .chunks(G2_SIZE)
+            .map(|p| {
+                let repr: [u8; G2_SIZE] = p.try_into().expect("chunk size");
+                Option::from(G2Affine::from_compressed(&repr))
+                    .ok_or_else(|| malformed_setup("malformed SRS G2 power"))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Srs { g1, g2 })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut b =
+            Vec::with_capacity(8 + self.g1.len() * COMMITMENT_SIZE + self.g2.len() * G2_SIZE);
+        b.extend_from_slice(&(self.g1.len() as u32).to_be_bytes());
+        b.extend_from_slice(&(self.g2.len() as u32).to_be_bytes());
+        for p in self.g1.iter() {
+            b.extend_from_slice(&p.to_compressed());
+        }
+        for p in self.g2.iter() {
+            b.extend_from_slice(&p.to_compressed());
+        }
+        b
+    }
+
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        Srs::read(&fs::read(path)?).map_err(std::io::Error::other)
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        fs::write(path, self.to_bytes())
+    }
+
+    /// Commits to a polynomial of degree below the number of G1 powers.
+    pub fn commit(&self, p: &Polynomial) -> Result<G1Affine> {
+        let n = p.degree() + 1;
+        if n > self.g1.len() {
+            return Err(Error::new(
+                ErrorCode::MalformedInputs,
+                format!(
+                    "degree {} is too high for an SRS of {} G1 powers",
+                    n - 1,
+                    self.g1.len()
+                ),
+            ));
+        }
+        // The zero polynomial may have no coefficients at all.
+        let c: G1Projective =
+            p.0.iter()
+                .take(n)
+                .zip(self.g1.iter())
+                .filter(|(c, _)| !bool::from(c.is_zero()))
+                .map(|(c, g)| g * c)
+                .sum();
+        Ok(c.to_affine())
+    }
+
+    /// Commits to the polynomial taking the given values at the roots of
+    /// unity, see `Polynomial::from_evaluations`.
+    pub fn commit_evaluations(&self, evaluations: &[Scalar]) -> Result<G1Affine> {
+        self.commit(&Polynomial::from_evaluations(evaluations)?)
+    }
+
+    /// Opens a polynomial at `z`, returning its value there and the proof.
+    pub fn open(&self, p: &Polynomial, z: &Scalar) -> Result<(Scalar, G1Affine)> {
+        let y = p.evaluate(z);
+        let q = p.div_monic(&Polynomial(vec![-z, Scalar::ONE]));
+        Ok((y, self.commit(&q)?))
+    }
+
+    /// Checks that `commitment` opens to `y` at `z`.
+    pub fn verify(&self, commitment: &G1Affine, z: &Scalar, y: &Scalar, proof: &G1Affine) -> bool {
+        let lhs = G1Projective::from(commitment) - self.g1[0] * y;
+        let divisor = G2Projective::from(self.g2[1]) - self.g2[0] * z;
+        check_opening(lhs, &self.g2[0], proof, divisor)
+    }
+
+    /// Opens a polynomial at several distinct points with a single proof,
+    /// returning its values there and the proof: the commitment to
+    /// `(p - I) / Z`, `I` interpolating the values and `Z` vanishing on the
+    /// points.
+    pub fn open_multi(&self, p: &Polynomial, points: &[Scalar]) -> Result<(Vec<Scalar>, G1Affine)> {
+        self.check_points(points)?;
+        let values: Vec<Scalar> = points.iter().map(|z| p.evaluate(z)).collect();
+        let i = Polynomial::interpolate(points, &values)?;
+        let q = p.sub(&i).div_monic(&Polynomial::vanishing(points));
+        Ok((values, self.commit(&q)?))
+    }
+
+    /// Checks that `commitment` opens to `values[i]` at `points[i]`, with
+    /// `e(C - [I(s)]1, [1]2) == e(proof, [Z(s)]2)`.
+    pub fn verify_multi(
+        &self,
+        commitment: &G1Affine,
+        points: &[Scalar],
+        values: &[Scalar],
+        proof: &G1Affine,
+    ) -> Result<bool> {
+        self.check_points(points)?;
+        let i = Polynomial::interpolate(points, values)?;
+        let lhs = G1Projective::from(commitment) - self.commit(&i)?;
+        let divisor: G2Projective = Polynomial::vanishing(points)
+            .0
+            .iter()
+            .zip(self.g2.iter())
+            .map(|(c, g)| g * c)
+            .sum();
+        Ok(check_opening(lhs, &self.g2[0], proof, divisor))
+    }
+
+    fn check_points(&self, points: &[Scalar]) -> Result<()> {
+        if points.is_empty() || points.len() >= self.g2.len() {
+            return Err(Error::new(
+                ErrorCode::InputCountMismatch,
+                format!(
+                    "an SRS of {} G2 powers opens at 1 to {} points, got {}",
+                    self.g2.len(),
+                    self.g2.len() - 1,
+                    points.len()
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Checks single-point openings of any commitments at once, with a
+    /// random linear combination of their equations:
+    ///
+    /// ```text
+    /// e(sum r_i (C_i - [y_i]1 + z_i proof_i), [1]2) == e(sum r_i proof_i, [s]2)
+    /// ```
+    ///
+    /// An empty batch is valid.
+    pub fn verify_batch(&self, openings: &[Opening]) -> bool {
+        if openings.is_empty() {
+            return true;
+        }
+        let mut rng = ChaCha20Rng::from_seed(batch_seed(openings));
+        let mut lhs = G1Projective::identity();
+        let mut proofs = G1Projective::identity();
+        for o in openings.iter() {
+            let r = Scalar::from_raw([rng.next_u64(), rng.next_u64(), 0, 0]);
+            let proof = G1Projective::from(o.proof) * r;
+            lhs += (G1Projective::from(o.commitment) - self.g1[0] * o.y) * r + proof * o.z;
+            proofs += proof;
+        }
+        check_opening(lhs, &self.g2[0], &proofs.to_affine(), self.g2[1].into())
+    }
+}
+
+fn batch_seed(openings: &[Opening]) -> [u8; 32] {
+    let mut state = blake2s_simd::Params::new()
+        .personal(BATCH_PERSONALIZATION)
+        .to_state();
+    for o in openings.iter() {
+        state.update(&o.commitment.to_compressed());
+        state.update(&o.z.to_bytes());
+        state.update(&o.y.to_bytes());
+        state.update(&o.proof.to_compressed());
+    }
+    *state.finalize().as_array()
+}
+
+/// Loads the trusted setup used by the point evaluation precompile from the
+/// contents of a setup file, replacing any setup loaded before.
+#[no_mangle]
+pub extern "C" fn kzg_load_trusted_setup(
+    data: *mut libc::c_uchar,
+    data_len: libc::size_t,
+) -> libc::c_int {
+    error::status((|| {
+        let text = std::str::from_utf8(bytes(data, data_len)?)
+            .map_err(|_| malformed_setup("setup is not text"))?;
+        let setup = TrustedSetup::read(text)?;
+        *SETUP.write().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(setup));
+        Ok(true)
+    })())
+}
+
+/// Writes the 32-byte versioned hash of a 48-byte commitment to `hash`.
+#[no_mangle]
+pub extern "C" fn kzg_versioned_hash(
+    commitment: *mut libc::c_uchar,
+    hash: *mut libc::c_uchar,
+) -> libc::c_int {
+    error::status((|| {
+        let c = bytes(commitment, COMMITMENT_SIZE)?;
+        write_out(hash, VERSIONED_HASH_SIZE, &versioned_hash(c))?;
+        Ok(true)
+    })())
+}
+
+/// Runs the point evaluation precompile on `input`, writing the 64-byte
+/// result, `FIELD_ELEMENTS_PER_BLOB` and `BLS_MODULUS` as big-endian words,
+/// to `output`. Returns `Ok` if the proof is valid and `Invalid` if not,
+/// leaving `output` untouched; the call fails in both cases but the latter.
+#[no_mangle]
+pub extern "C" fn kzg_point_evaluation(
+    input: *mut libc::c_uchar,
+    input_len: libc::size_t,
+    output: *mut libc::c_uchar,
+) -> libc::c_int {
+    error::status((|| {
+        let setup = trusted_setup()?;
+        match point_evaluation(&setup, bytes(input, input_len)?)? {
+            Some(out) => {
+                write_out(output, POINT_EVALUATION_OUTPUT_SIZE, &out)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    })())
+}
+
+/// Returns the gas of the point evaluation precompile, fixed.
+#[no_mangle]
+pub extern "C" fn kzg_point_evaluation_gas() -> u64 {
+    POINT_EVALUATION_GAS
+}
+
+fn srs_ref<'a>(handle: *const Srs) -> Result<&'a Srs> {
+    unsafe { handle.as_ref() }.ok_or_else(|| Error::new(ErrorCode::NullPointer, "null SRS handle"))
+}
+
+fn read_field_elements(b: &[u8]) -> Result<Vec<Scalar>> {
+    read_all(b, FIELD_ELEMENT_SIZE, read_field_element)
+}
+
+fn write_field_elements(s: &[Scalar]) -> Vec<u8> {
+    s.iter()
+        .flat_map(|s| {
+            let mut b = s.to_bytes();
+            b.reverse();
+            b
+        })
+        .collect()
+}
+
+/// Decodes an SRS saved with `Srs::save` and stores its handle in `handle`.
+/// A handle can be used by several threads at once, and must be released
+/// with `kzg_srs_free`.
+#[no_mangle]
+pub extern "C" fn kzg_srs_new(
+    data: *mut libc::c_uchar,
+    data_len: libc::size_t,
+    handle: *mut *mut Srs,
+) -> libc::c_int {
+    error::status((|| {
+        if handle.is_null() {
+            return Err(Error::new(ErrorCode::NullPointer, "null handle pointer"));
+        }
+        let srs = Srs::read(bytes(data, data_len)?)?;
+        unsafe { *handle = Box::into_raw(Box::new(srs)) };
+        Ok(true)
+    })())
+}
+
+/// Generates an SRS of `g1_size` G1 and `g2_size` G2 powers, drawing the
+/// secret from `seed` if it's not empty and from the OS otherwise, and
+/// stores its handle in `handle`. A seeded SRS is only fit for tests and
+/// devnets.
+#[no_mangle]
+pub extern "C" fn kzg_srs_generate(
+    g1_size: libc::size_t,
+    g2_size: libc::size_t,
+    seed: *mut libc::c_uchar,
+    seed_len: libc::size_t,
+    handle: *mut *mut Srs,
+) -> libc::c_int {
+    error::status((|| {
+        if handle.is_null() {
+            return Err(Error::new(ErrorCode::NullPointer, "null handle pointer"));
+        }
+        let seed = bytes(seed, seed_len)?;
+        let srs = Srs::generate(g1_size, g2_size, (!seed.is_empty()).then_some(seed))?;
+        unsafe { *handle = Box::into_raw(Box::new(srs)) };
+        Ok(true)
+    })())
+}
+
+/// Writes the SRS in the format `kzg_srs_new` reads to `out`, `8 + 48 *
+/// g1_size + 96 * g2_size` bytes.
+#[no_mangle]
+pub extern "C" fn kzg_srs_write(
+    handle: *const Srs,
+    out: *mut libc::c_uchar,
+    out_len: libc::size_t,
+) -> libc::c_int {
+    error::status((|| {
+        write_out(out, out_len, &srs_ref(handle)?.to_bytes())?;
+        Ok(true)
+    })())
+}
+
+/// Releases a handle returned by `kzg_srs_new`, null is ignored.
+#[no_mangle]
+pub extern "C" fn kzg_srs_free(handle: *mut Srs) {
+    if !handle.is_null() {
+        drop(unsafe { Box::from_raw(handle) });
+    }
+}
+
+/// Commits to the polynomial with the given concatenated 32-byte big-endian
+/// coefficients, from the constant one up, and writes the 48-byte
+/// commitment to `commitment`.
+#[no_mangle]
+pub extern "C" fn kzg_commit(
+    handle: *const Srs,
+    coefficients: *mut libc::c_uchar,
+    coefficients_len: libc::size_t,
+    commitment: *mut libc::c_uchar,
+) -> libc::c_int {
+    error::status((|| {
+        let p = Polynomial(read_field_elements(bytes(coefficients, coefficients_len)?)?);
+        let c = srs_ref(handle)?.commit(&p)?;
+        write_out(commitment, COMMITMENT_SIZE, &c.to_compressed())?;
+        Ok(true)
+    })())
+}
+
+/// Commits to the polynomial taking the given 32-byte big-endian values at
+/// the roots of unity of their number, a power of two, in natural order.
+#[no_mangle]
+pub extern "C" fn kzg_commit_evaluations(
+    handle: *const Srs,
+    evaluations: *mut libc::c_uchar,
+    evaluations_len: libc::size_t,
+    commitment: *mut libc::c_uchar,
+) -> libc::c_int {
+    error::status((|| {
+        let evals = read_field_elements(bytes(evaluations, evaluations_len)?)?;
+        let c = srs_ref(handle)?.commit_evaluations(&evals)?;
+        write_out(commitment, COMMITMENT_SIZE, &c.to_compressed())?;
+        Ok(true)
+    })())
+}
+
+/// Opens the polynomial with the given coefficients at the 32-byte `z`,
+/// writing its 32-byte value there to `y` and the 48-byte proof to `proof`.
+#[no_mangle]
+pub extern "C" fn kzg_open(
+    handle: *const Srs,
+    coefficients: *mut libc::c_uchar,
+    coefficients_len: libc::size_t,
+    z: *mut libc::c_uchar,
+    y: *mut libc::c_uchar,
+    proof: *mut libc::c_uchar,
+) -> libc::c_int {
+    error::status((|| {
+        let p = Polynomial(read_field_elements(bytes(coefficients, coefficients_len)?)?);
+        let z = read_field_element(bytes(z, FIELD_ELEMENT_SIZE)?)?;
+        let (v, pi) = srs_ref(handle)?.open(&p, &z)?;
+        write_out(y, FIELD_ELEMENT_SIZE, &write_field_elements(&[v]))?;
+        write_out(proof, COMMITMENT_SIZE, &pi.to_compressed())?;
+        Ok(true)
+    })())
+}
+
+/// Checks that a commitment opens to `y` at `z`, returns `Ok` if the proof
+/// is valid and `Invalid` if not.
+#[no_mangle]
+pub extern "C" fn kzg_verify(
+    handle: *const Srs,
+    commitment: *mut libc::c_uchar,
+    z: *mut libc::c_uchar,
+    y: *mut libc::c_uchar,
+    proof: *mut libc::c_uchar,
+) -> libc::c_int {
+    error::status((|| {
+        let c = read_g1(
+            bytes(commitment, COMMITMENT_SIZE)?,
+            ErrorCode::MalformedInputs,
+            "commitment",
+        )?;
+        let z = read_field_element(bytes(z, FIELD_ELEMENT_SIZE)?)?;
+        let y = read_field_element(bytes(y, FIELD_ELEMENT_SIZE)?)?;
+        let proof = read_g1(
+            bytes(proof, COMMITMENT_SIZE)?,
+            ErrorCode::MalformedProof,
+            "proof",
+        )?;
+        Ok(srs_ref(handle)?.verify(&c, &z, &y, &proof))
+    })())
+}
+
+/// Opens the polynomial with the given coefficients at the concatenated
+/// 32-byte `points` with a single proof, writing the values there to
+/// `values`, 32 bytes per point, and the 48-byte proof to `proof`.
+#[no_mangle]
+pub extern "C" fn kzg_open_multi(
+    handle: *const Srs,
+    coefficients: *mut libc::c_uchar,
+    coefficients_len: libc::size_t,
+    points: *mut libc::c_uchar,
+    points_len: libc::size_t,
+    values: *mut libc::c_uchar,
+    values_len: libc::size_t,
+    proof: *mut libc::c_uchar,
+) -> libc::c_int {
+    error::status((|| {
+        let p = Polynomial(read_field_elements(bytes(coefficients, coefficients_len)?)?);
+        let points = read_field_elements(bytes(points, points_len)?)?;
+        let (v, pi) = srs_ref(handle)?.open_multi(&p, &points)?;
+        write_out(values, values_len, &write_field_elements(&v))?;
+        write_out(proof, COMMITMENT_SIZE, &pi.to_compressed())?;
+        Ok(true)
+    })())
+}
+
+/// Checks that a commitment opens to the concatenated 32-byte `values` at
+/// the as many `points`, returns `Ok` if the proof is valid and `Invalid`
+/// if not.
+#[no_mangle]
+pub extern "C" fn kzg_verify_multi(
+    handle: *const Srs,
+    commitment: *mut libc::c_uchar,
+    points: *mut libc::c_uchar,
+    points_len: libc::size_t,
+    values: *mut libc::c_uchar,
+    values_len: libc::size_t,
+    proof: *mut libc::c_uchar,
+) -> libc::c_int {
+    error::status((|| {
+        let c = read_g1(
+            bytes(commitment, COMMITMENT_SIZE)?,
+            ErrorCode::MalformedInputs,
+            "commitment",
+        )?;
+        let points = read_field_elements(bytes(points, points_len)?)?;
+        let values = read_field_elements(bytes(values, values_len)?)?;
+        let proof = read_g1(
+            bytes(proof, COMMITMENT_SIZE)?,
+            ErrorCode::MalformedProof,
+            "proof",
+        )?;
+        srs_ref(handle)?.verify_multi(&c, &points, &values, &proof)
+    })())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use bls12_381::pairing;
+    use std::sync::OnceLock;
+
+    pub(crate) fn mainnet_setup() -> &'static TrustedSetup {
+        static SETUP: OnceLock<TrustedSetup> = OnceLock::new();
+        SETUP.get_or_init(|| {
+            let text = include_str!("../data/trusted_setup.txt");
+            TrustedSetup::read(text).unwrap()
+        })
+    }
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    // The point evaluation vector of go-ethereum's precompile tests.
+    const VECTOR: &str = "01e798154708fe7789429634053cbf9f99b619f9f084048927333fce637f549b564c0a11a0f704f4fc3e8acfe0f8245f0ad1347b378fbf96e206da11a5d3630624d25032e67a7e6a4910df5834b8fe70e6bcfeeac0352434196bdf4b2485d5a18f59a8d2a1a625a17f3fea0fe5eb8c896db3764f3185481bc22f91b4aaffcca25f26936857bc3a7c2539ea8ec3a952b7873033e038326e87ed3e1276fd140253fa08e9fc25fb2d9a98527fc22a2c9612fbeafdad446cbc7bcdbdcd780af2c16a";
+
+    #[test]
+    fn test_point_evaluation() {
+        let setup = mainnet_setup();
+        assert_eq!(FIELD_ELEMENTS_PER_BLOB, setup.g1_lagrange.len());
+        assert_eq!(FIELD_ELEMENTS_PER_BLOB, setup.g1_monomial.len());
+        assert_eq!(65, setup.g2_monomial.len());
+        assert_eq!(G2Affine::generator(), setup.g2_monomial[0]);
+
+        let input = hex(VECTOR);
+        let out = point_evaluation(setup, &input).unwrap().unwrap();
+        let mut expected = vec![0u8; 30];
+        expected.extend_from_slice(&[0x10, 0x00]);
+        expected.extend_from_slice(&BLS_MODULUS);
+        assert_eq!(expected, out);
+
+        // A wrong evaluation fails the proof.
+        let mut bad = input.clone();
+        bad[95] ^= 1;
+        assert_eq!(None, point_evaluation(setup, &bad).unwrap());
+        // Malformed inputs are errors.
+        let mut bad = input.clone();
+        bad[1] ^= 1;
+        assert!(point_evaluation(setup, &bad).is_err());
+        let mut bad = input.clone();
+        bad[32..64].copy_from_slice(&BLS_MODULUS);
+        assert!(point_evaluation(setup, &bad).is_err());
+        assert!(point_evaluation(setup, &input[1..]).is_err());
+    }
+
+    #[test]
+    fn test_constant_polynomial() {
+        // A constant commits to itself times the generator, as the Lagrange
+        // basis sums to one, and opens anywhere with the identity as proof.
+        let setup = mainnet_setup();
+        let c = Scalar::from(7);
+        let sum: G1Projective = setup
+            .g1_lagrange()
+            .unwrap()
+            .iter()
+            .map(G1Projective::from)
+            .sum();
+        assert_eq!(G1Affine::generator(), sum.to_affine());
+        let commitment = (G1Affine::generator() * c).to_affine();
+        let proof = G1Affine::identity();
+        assert!(setup.verify_proof(&commitment, &Scalar::from(3), &c, &proof));
+        assert!(!setup.verify_proof(&commitment, &Scalar::from(3), &Scalar::from(8), &proof));
+    }
+
+    #[test]
+    fn test_read_setup() {
+        assert!(TrustedSetup::read("").is_err());
+        assert!(TrustedSetup::read("4096\n1\n").is_err());
+        let text = include_str!("../data/trusted_setup.txt");
+        let lines = |n: usize| text.lines().take(n).collect::<Vec<_>>().join("\n");
+        for n in [100, 2 + 4096 + 64, 2 + 4096 + 65 + 1, 2 + 4096 + 65 + 4095] {
+            assert_eq!(
+                ErrorCode::MalformedParams,
+                TrustedSetup::read(&lines(n)).err().unwrap().code()
+            );
+        }
+        let extra = format!("{}\n{}", text, text.lines().last().unwrap());
+        assert!(TrustedSetup::read(&extra).is_err());
+
+        // The legacy format is the current one without the G1 monomial points.
+        let setup = mainnet_setup();
+        let legacy = TrustedSetup::read(&lines(2 + 4096 + 65)).unwrap();
+        assert!(legacy.g1_monomial().unwrap().is_empty());
+        assert_eq!(setup.g1_lagrange, legacy.g1_lagrange);
+        assert_eq!(setup.g2_monomial, legacy.g2_monomial);
+
+        // [s]1 and [s]2 come from the same secret.
+        let g1 = setup.g1_monomial().unwrap();
+        assert_eq!(G1Affine::generator(), g1[0]);
+        assert_eq!(
+            pairing(&g1[1], &G2Affine::generator()),
+            pairing(&G1Affine::generator(), &setup.g2_monomial[1])
+        );
+
+        // Points other than the generators are only decoded on demand.
+        let mut bad = text.lines().map(str::to_string).collect::<Vec<_>>();
+        // A compressed x coordinate above the modulus.
+        bad[2] = "9f".to_string() + &"ff".repeat(COMMITMENT_SIZE - 1);
+        let bad = TrustedSetup::read(&bad.join("\n")).unwrap();
+        assert!(bad.g1_lagrange().is_err());
+        assert!(bad.g1_monomial().is_ok());
+    }
+
+    #[test]
+    fn test_ffi() {
+        let mut input = hex(VECTOR);
+        let mut out = [0u8; POINT_EVALUATION_OUTPUT_SIZE];
+        let mut text = include_bytes!("../data/trusted_setup.txt").to_vec();
+        assert_eq!(1, kzg_load_trusted_setup(text.as_mut_ptr(), text.len()));
+        let code = kzg_point_evaluation(input.as_mut_ptr(), input.len(), out.as_mut_ptr());
+        assert_eq!(1, code);
+        assert_eq!(BLS_MODULUS, out[32..]);
+        let code = kzg_point_evaluation(input.as_mut_ptr(), input.len() - 1, out.as_mut_ptr());
+        assert_eq!(ErrorCode::MalformedInputs as libc::c_int, code);
+
+        let mut hash = [0u8; VERSIONED_HASH_SIZE];
+        let code = kzg_versioned_hash(input[96..144].as_mut_ptr(), hash.as_mut_ptr());
+        assert_eq!(1, code);
+        assert_eq!(input[..32], hash);
+        assert_eq!(50000, kzg_point_evaluation_gas());
+    }
+
+    fn test_srs() -> Srs {
+        Srs::generate(16, 5, Some(b"kzg tests")).unwrap()
+    }
+
+    fn poly(coeffs: &[u64]) -> Polynomial {
+        Polynomial(coeffs.iter().map(|&c| Scalar::from(c)).collect())
+    }
+
+    #[test]
+    fn test_polynomial() {
+        let p = poly(&[3, 0, 5, 1]);
+        assert_eq!(3, p.degree());
+        assert_eq!(Scalar::from(3 + 5 * 4 + 8), p.evaluate(&Scalar::from(2)));
+
+        let evals = p.evaluations(8).unwrap();
+        let omega = domain_generator(8).unwrap();
+        assert_eq!(p.evaluate(&omega.pow_vartime(&[5, 0, 0, 0])), evals[5]);
+        let mut q = Polynomial::from_evaluations(&evals).unwrap();
+        q.0.truncate(4);
+        assert_eq!(p, q);
+        assert!(p.evaluations(2).is_err());
+        assert!(Polynomial::from_evaluations(&evals[..6]).is_err());
+
+        let xs: Vec<_> = [1u64, 4, 9, 11].iter().map(|&x| Scalar::from(x)).collect();
+        let ys: Vec<_> = xs.iter().map(|x| p.evaluate(x)).collect();
+        assert_eq!(p, Polynomial::interpolate(&xs, &ys).unwrap());
+        let dup = [xs[0], xs[0]];
+        assert!(Polynomial::interpolate(&dup, &ys[..2]).is_err());
+        assert_eq!(Scalar::ZERO, Polynomial::vanishing(&xs).evaluate(&xs[2]));
+    }
+
+    #[test]
+    fn test_srs_roundtrip() {
+        let srs = test_srs();
+        assert_eq!(srs, Srs::generate(16, 5, Some(b"kzg tests")).unwrap());
+        assert_ne!(srs, Srs::generate(16, 5, Some(b"other")).unwrap());
+        assert_eq!(G1Affine::generator(), srs.g1[0]);
+        assert_eq!(srs, Srs::read(&srs.to_bytes()).unwrap());
+
+        let path = std::env::temp_dir().join(format!("zk-srs-{}", std::process::id()));
+        srs.save(&path).unwrap();
+        assert_eq!(srs, Srs::load(&path).unwrap());
+        std::fs::remove_file(&path).unwrap();
+
+        let b = srs.to_bytes();
+        assert!(Srs::read(&b[..b.len() - 1]).is_err());
+        let mut bad = b.clone();
+        bad[8] ^= 1;
+        assert!(Srs::read(&bad).is_err());
+        assert!(Srs::generate(4, 1, None).is_err());
+    }
+
+    #[test]
+    fn test_open() {
+        let srs = test_srs();
+        let p = poly(&[7, 1, 2, 9, 4, 4, 1]);
+        let c = srs.commit(&p).unwrap();
+        let evals = p.evaluations(8).unwrap();
+        assert_eq!(c, srs.commit_evaluations(&evals).unwrap());
+
+        let z = Scalar::from(123);
+        let (y, proof) = srs.open(&p, &z).unwrap();
+        assert_eq!(p.evaluate(&z), y);
+        assert!(srs.verify(&c, &z, &y, &proof));
+        assert!(!srs.verify(&c, &z, &(y + Scalar::ONE), &proof));
+        assert!(!srs.verify(&c, &Scalar::from(124), &y, &proof));
+
+        let big = Polynomial(vec![Scalar::ONE; 17]);
+        assert!(srs.commit(&big).is_err());
+
+        // A constant opens to itself with the identity as proof, and the
+        // zero polynomial commits to the identity.
+        let constant = poly(&[5]);
+        let (y, proof) = srs.open(&constant, &z).unwrap();
+        assert_eq!((Scalar::from(5), G1Affine::identity()), (y, proof));
+        assert!(srs.verify(&srs.commit(&constant).unwrap(), &z, &y, &proof));
+        assert_eq!(
+            G1Affine::identity(),
+            srs.commit(&Polynomial(vec![])).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_open_multi() {
+        let srs = test_srs();
+        let p = poly(&[7, 1, 2, 9, 4, 4, 1, 8, 8]);
+        let c = srs.commit(&p).unwrap();
+        let points: Vec<_> = [2u64, 3, 5, 7].iter().map(|&x| Scalar::from(x)).collect();
+        let (values, proof) = srs.open_multi(&p, &points).unwrap();
+        assert!(srs.verify_multi(&c, &points, &values, &proof).unwrap());
+        let mut bad = values.clone();
+        bad[3] += Scalar::ONE;
+        assert!(!srs.verify_multi(&c, &points, &bad, &proof).unwrap());
+        assert!(!srs
+            .verify_multi(&c, &points[..3], &values[..3], &proof)
+            .unwrap());
+
+        // A single point is a plain opening.
+        let (v, pi) = srs.open_multi(&p, &points[..1]).unwrap();
+        assert_eq!((v[0], pi), srs.open(&p, &points[0]).unwrap());
+
+        // Opening at more points than the degree leaves no quotient.
+        let low = poly(&[7, 1, 2]);
+        let (values, proof) = srs.open_multi(&low, &points).unwrap();
+        assert_eq!(G1Affine::identity(), proof);
+        let c = srs.commit(&low).unwrap();
+        assert!(srs.verify_multi(&c, &points, &values, &proof).unwrap());
+
+        // Five points need six G2 powers.
+        let five: Vec<_> = (1..=5u64).map(Scalar::from).collect();
+        let e = srs.open_multi(&p, &five).err().unwrap();
+        assert_eq!(ErrorCode::InputCountMismatch, e.code());
+    }
+
+    #[test]
+    fn test_verify_batch() {
+        let srs = test_srs();
+        let mut openings: Vec<_> = (1..5u64)
+            .map(|i| {
+                let p = poly(&[i, 2 * i, 3, 1, i * i]);
+                let z = Scalar::from(10 * i);
+                let (y, proof) = srs.open(&p, &z).unwrap();
+                Opening {
+                    commitment: srs.commit(&p).unwrap(),
+                    z,
+                    y,
+                    proof,
+                }
+            })
+            .collect();
+        assert!(srs.verify_batch(&openings));
+        assert!(srs.verify_batch(&[]));
+        openings[2].y += Scalar::ONE;
+        assert!(!srs.verify_batch(&openings));
+    }
+
+    #[test]
+    fn test_srs_ffi() {
+        let srs = test_srs();
+        let mut data = srs.to_bytes();
+        let mut handle = std::ptr::null_mut();
+        assert_eq!(1, kzg_srs_new(data.as_mut_ptr(), data.len(), &mut handle));
+        let mut seed = *b"kzg tests";
+        let mut generated = std::ptr::null_mut();
+        let code = kzg_srs_generate(16, 5, seed.as_mut_ptr(), seed.len(), &mut generated);
+        assert_eq!(1, code);
+        let mut out = vec![0u8; data.len()];
+        assert_eq!(1, kzg_srs_write(generated, out.as_mut_ptr(), out.len()));
+        assert_eq!(data, out);
+        kzg_srs_free(generated);
+
+        let p = poly(&[5, 0, 1, 3]);
+        let mut coefficients = write_field_elements(&p.0);
+        let mut c = [0u8; COMMITMENT_SIZE];
+        let code = kzg_commit(
+            handle,
+            coefficients.as_mut_ptr(),
+            coefficients.len(),
+            c.as_mut_ptr(),
+        );
+        assert_eq!(1, code);
+        let mut empty = [0u8; COMMITMENT_SIZE];
+        let code = kzg_commit(handle, std::ptr::null_mut(), 0, empty.as_mut_ptr());
+        assert_eq!(1, code);
+        assert_eq!(G1Affine::identity().to_compressed(), empty);
+        let mut evals = write_field_elements(&p.evaluations(4).unwrap());
+        let mut c2 = [0u8; COMMITMENT_SIZE];
+        let code = kzg_commit_evaluations(handle, evals.as_mut_ptr(), evals.len(), c2.as_mut_ptr());
+        assert_eq!(1, code);
+        assert_eq!(c, c2);
+
+        let mut z = write_field_elements(&[Scalar::from(42)]);
+        let mut y = [0u8; FIELD_ELEMENT_SIZE];
+        let mut proof = [0u8; COMMITMENT_SIZE];
+        let code = kzg_open(
+            handle,
+            coefficients.as_mut_ptr(),
+            coefficients.len(),
+            z.as_mut_ptr(),
+            y.as_mut_ptr(),
+            proof.as_mut_ptr(),
+        );
+        assert_eq!(1, code);
+        let code = kzg_verify(
+            handle,
+            c.as_mut_ptr(),
+            z.as_mut_ptr(),
+            y.as_mut_ptr(),
+            proof.as_mut_ptr(),
+        );
+        assert_eq!(1, code);
+        y[31] ^= 1;
+        let code = kzg_verify(
+            handle,
+            c.as_mut_ptr(),
+            z.as_mut_ptr(),
+            y.as_mut_ptr(),
+            proof.as_mut_ptr(),
+        );
+        assert_eq!(0, code);
+
+        let mut points = write_field_elements(&[Scalar::from(1), Scalar::from(2)]);
+        let mut values = [0u8; 2 * FIELD_ELEMENT_SIZE];
+        let code = kzg_open_multi(
+            handle,
+            coefficients.as_mut_ptr(),
+            coefficients.len(),
+            points.as_mut_ptr(),
+            points.len(),
+            values.as_mut_ptr(),
+            values.len(),
+            proof.as_mut_ptr(),
+        );
+        assert_eq!(1, code);
+        let code = kzg_verify_multi(
+            handle,
+            c.as_mut_ptr(),
+            points.as_mut_ptr(),
+            points.len(),
+            values.as_mut_ptr(),
+            values.len(),
+            proof.as_mut_ptr(),
+        );
+        assert_eq!(1, code);
+        let code = kzg_verify_multi(
+            handle,
+            c.as_mut_ptr(),
+            points.as_mut_ptr(),
+            points.len(),
+            values.as_mut_ptr(),
+            FIELD_ELEMENT_SIZE,
+            proof.as_mut_ptr(),
+        );
+        assert_eq!(ErrorCode::InputCountMismatch as libc::c_int, code);
+        kzg_srs_free(handle);
+    }
+}
@@ -1,27 +1,28 @@
-use bellman::groth16::{prepare_verifying_key, verify_proof, Proof, VerifyingKey};
-use bls12_381::Bls12;
-use libc;
+// Exported functions take buffers from cgo as raw pointers and slice them
+// in place, the Go wrapper guarantees they are valid for the given length.
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
 
-#[no_mangle]
-pub extern "C" fn verify(
-    proof: *mut libc::c_uchar,
-    proof_len: libc::size_t,
-    key: *mut libc::c_uchar,
-    key_len: libc::size_t,
-) -> libc::c_int {
-    let bproof = unsafe { std::slice::from_raw_parts(proof, proof_len) };
-    let bkey = unsafe { std::slice::from_raw_parts(key, key_len) };
-    let rproof = Proof::<Bls12>::read(bproof);
-    if let Ok(tproof) = rproof {
-        let rvk = VerifyingKey::read(bkey);
-        if let Ok(tvk) = rvk {
-            let tpvk = prepare_verifying_key(&tvk);
-            let result = verify_proof(&tpvk, &tproof, &[]);
-            return match result {
-                Err(_) => 0,
-                Ok(_) => 1,
-            };
-        }
-    }
-    return 0;
-}
+pub mod bls;
+pub mod bls12381;
+pub mod bn254;
+pub mod circuits;
+pub mod dkg;
+mod error;
+mod ffi;
+pub mod gas;
+pub mod groth16;
+pub mod hash_to_curve;
+pub mod kzg;
+pub mod merkle;
+pub mod mimc;
+pub mod plonk;
+pub mod pool;
+pub mod poseidon;
+pub mod precompile;
+pub mod prover;
+pub mod setup;
+pub mod snarkjs;
+pub mod threshold;
+pub mod vrf;
+
+pub use error::{Error, ErrorCode, Result};
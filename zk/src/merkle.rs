@@ -0,0 +1,344 @@
+//! Incremental Poseidon Merkle trees. Leaves are field elements and a node
+//! is `poseidon([left, right])`; empty leaves are `empty_leaf()`, so an empty
+//! subtree of height `h + 1` hashes to `poseidon([z_h, z_h])`. The membership
+//! circuit in `circuits` checks the paths produced here.
+//!
+//! The empty leaf is the hash of a fixed tag rather than zero, and it can't
+//! be appended. The membership circuit rejects it as a leaf, so that the
+//! unfilled positions of a tree, whose paths are public, aren't members.
+
+use bls12_381::Scalar;
+use halo2curves::bn256::Fr;
+
+use crate::error::{self, Error, ErrorCode, Result};
+use crate::ffi::{bytes, write_out};
+use crate::groth16::{Curve, Groth16Engine, SCALAR_SIZE};
+use crate::poseidon::{self, PoseidonField};
+
+/// Deepest supported tree, enough for 2^32 leaves.
+pub const MAX_DEPTH: usize = 32;
+
+/// Tag hashed into the empty leaf, "zkmerkle" in ASCII.
+pub const EMPTY_LEAF_TAG: u64 = u64::from_be_bytes(*b"zkmerkle");
+
+/// The value of unfilled leaves, `poseidon([EMPTY_LEAF_TAG])`.
+pub fn empty_leaf<F: PoseidonField>() -> F {
+    poseidon::hash(&[F::from(EMPTY_LEAF_TAG)]).expect("width 2 is supported")
+}
+
+/// Hashes two children into their parent.
+pub fn hash_pair<F: PoseidonField>(left: F, right: F) -> F {
+    poseidon::hash(&[left, right]).expect("width 3 is supported")
+}
+
+/// Authentication path of a leaf, from the leaf level up.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MerklePath<F> {
+    pub index: u64,
+    pub siblings: Vec<F>,
+}
+
+impl<F: PoseidonField> MerklePath<F> {
+    /// Root of the tree holding `leaf` at the path's position.
+    pub fn root(&self, leaf: F) -> F {
+        self.siblings
+            .iter()
+            .enumerate()
+            .fold(leaf, |cur, (level, sib)| {
+                if (self.index >> level) & 1 == 1 {
+                    hash_pair(*sib, cur)
+                } else {
+                    hash_pair(cur, *sib)
+                }
+            })
+    }
+}
+
+/// A Merkle tree of fixed depth filled from the left.
+pub struct MerkleTree<F> {
+    depth: usize,
+    /// Roots of empty subtrees, by height.
+    zeros: Vec<F>,
+    /// Non-empty nodes, by height, the last level holding the root.
+    levels: Vec<Vec<F>>,
+}
+
+impl<F: PoseidonField> MerkleTree<F> {
+    pub fn new(depth: usize) -> Result<Self> {
+        if !(1..=MAX_DEPTH).contains(&depth) {
+            return Err(Error::new(
+                ErrorCode::MalformedInputs,
+                format!("tree depth must be 1 to {}, got {}", MAX_DEPTH, depth),
+            ));
+        }
+        let mut zeros = vec![empty_leaf()];
+        for h in 0..depth {
+            zeros.push(hash_pair(zeros[h], zeros[h]));
+        }
+        Ok(MerkleTree {
+            depth,
+            zeros,
+            levels: vec![vec![]; depth + 1],
+        })
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Number of leaves appended so far.
+    pub fn len(&self) -> u64 {
+        self.levels[0].len() as u64
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.levels[0].is_empty()
+    }
+
+    pub fn root(&self) -> F {
+        self.node(self.depth, 0)
+    }
+
+    fn node(&self, height: usize, i: usize) -> F {
+        self.levels[height]
+            .get(i)
+            .copied()
+            .unwrap_or(self.zeros[height])
+    }
+
+    /// Appends a leaf and returns its index, updating the nodes above it.
+    pub fn append(&mut self, leaf: F) -> Result<u64> {
+        let index = self.len();
+        if index >> self.depth != 0 {
+            return Err(Error::new(
+                ErrorCode::TreeFull,
+                format!("tree of depth {} is full", self.depth),
+            ));
+        }
+        if leaf == self.zeros[0] {
+            return Err(Error::new(
+                ErrorCode::MalformedInputs,
+                "the empty leaf can't be appended",
+            ));
+        }
+        self.levels[0].push(leaf);
+        let mut i = index as usize;
+        for h in 0..self.depth {
+            i /= 2;
+            let parent = hash_pair(self.node(h, 2 * i), self.node(h, 2 * i + 1));
+            if i < self.levels[h + 1].len() {
+                self.levels[h + 1][i] = parent;
+            } else {
+                self.levels[h + 1].push(parent);
+            }
+        }
+        Ok(index)
+    }
+
+    /// Path of the leaf at `index` in the current tree.
+    pub fn path(&self, index: u64) -> Result<MerklePath<F>> {
+        if index >= self.len() {
+            return Err(Error::new(
+                ErrorCode::MalformedInputs,
+                format!("no leaf at index {}, tree has {}", index, self.len()),
+            ));
+        }
+        let siblings = (0..self.depth)
+            .map(|h| self.node(h, ((index >> h) ^ 1) as usize))
+            .collect();
+        Ok(MerklePath { index, siblings })
+    }
+}
+
+/// A tree on any of the supported curves, the object behind the handles
+/// returned by `merkle_tree_new`.
+pub enum TreeHandle {
+    Bls12381(MerkleTree<Scalar>),
+    Bn254(MerkleTree<Fr>),
+}
+
+fn read_leaf<E: Groth16Engine>(b: &[u8]) -> Result<E::Fr> {
+    E::read_scalar(b)
+        .ok_or_else(|| Error::new(ErrorCode::MalformedInputs, "leaf is not a canonical scalar"))
+}
+
+/// Encodes a path as `siblings | index`, all scalars in the curve's
+/// encoding. On BLS12-381 the leaf followed by this is the witness of the
+/// membership circuit.
+fn write_path<E: Groth16Engine>(path: &MerklePath<E::Fr>) -> Vec<u8> {
+    let mut out = Vec::with_capacity((path.siblings.len() + 1) * SCALAR_SIZE);
+    for s in path.siblings.iter() {
+        out.extend_from_slice(&E::write_scalar(s));
+    }
+    out.extend_from_slice(&E::write_scalar(&E::Fr::from(path.index)));
+    out
+}
+
+fn tree_mut<'a>(handle: *mut TreeHandle) -> Result<&'a mut TreeHandle> {
+    unsafe { handle.as_mut() }.ok_or_else(|| Error::new(ErrorCode::NullPointer, "null tree handle"))
+}
+
+/// Creates an empty tree of the given depth over the scalar field of
+/// `curve` and stores its handle in `handle`. A handle must not be used by
+/// several threads at once, and must be released with `merkle_tree_free`.
+#[no_mangle]
+pub extern "C" fn merkle_tree_new(
+    curve: libc::c_int,
+    depth: libc::size_t,
+    handle: *mut *mut TreeHandle,
+) -> libc::c_int {
+    error::status((|| {
+        if handle.is_null() {
+            return Err(Error::new(ErrorCode::NullPointer, "null handle pointer"));
+        }
+        let tree = match Curve::from_raw(curve)? {
+            Curve::Bls12381 => TreeHandle::Bls12381(MerkleTree::new(depth)?),
+            Curve::Bn254 => TreeHandle::Bn254(MerkleTree::new(depth)?),
+        };
+        unsafe { *handle = Box::into_raw(Box::new(tree)) };
+        Ok(true)
+    })())
+}
+
+/// Appends a 32-byte leaf in the curve's encoding and writes its index to
+/// `index`.
+#[no_mangle]
+pub extern "C" fn merkle_tree_append(
+    handle: *mut TreeHandle,
+    leaf: *mut libc::c_uchar,
+    index: *mut u64,
+) -> libc::c_int {
+    error::status((|| {
+        let tree = tree_mut(handle)?;
+        let leaf = bytes(leaf, SCALAR_SIZE)?;
+        let i = match tree {
+            TreeHandle::Bls12381(t) => t.append(read_leaf::<bls12_381::Bls12>(leaf)?)?,
+            TreeHandle::Bn254(t) => t.append(read_leaf::<halo2curves::bn256::Bn256>(leaf)?)?,
+        };
+        if !index.is_null() {
+            unsafe { *index = i };
+        }
+        Ok(true)
+    })())
+}
+
+/// Writes the 32-byte root of the tree to `root`.
+#[no_mangle]
+pub extern "C" fn merkle_tree_root(
+    handle: *mut TreeHandle,
+    root: *mut libc::c_uchar,
+) -> libc::c_int {
+    error::status((|| {
+        let r = match tree_mut(handle)? {
+            TreeHandle::Bls12381(t) => bls12_381::Bls12::write_scalar(&t.root()),
+            TreeHandle::Bn254(t) => halo2curves::bn256::Bn256::write_scalar(&t.root()),
+        };
+        write_out(root, SCALAR_SIZE, &r)?;
+        Ok(true)
+    })())
+}
+
+/// Writes the path of the leaf at `index` to `path` as `siblings | index`,
+/// `(depth + 1) * 32` bytes.
+#[no_mangle]
+pub extern "C" fn merkle_tree_path(
+    handle: *mut TreeHandle,
+    index: u64,
+    path: *mut libc::c_uchar,
+    path_len: libc::size_t,
+) -> libc::c_int {
+    error::status((|| {
+        let b = match tree_mut(handle)? {
+            TreeHandle::Bls12381(t) => write_path::<bls12_381::Bls12>(&t.path(index)?),
+            TreeHandle::Bn254(t) => write_path::<halo2curves::bn256::Bn256>(&t.path(index)?),
+        };
+        write_out(path, path_len, &b)?;
+        Ok(true)
+    })())
+}
+
+/// Releases a handle returned by `merkle_tree_new`, null is ignored.
+#[no_mangle]
+pub extern "C" fn merkle_tree_free(handle: *mut TreeHandle) {
+    if !handle.is_null() {
+        drop(unsafe { Box::from_raw(handle) });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ff::Field;
+
+    #[test]
+    fn test_incremental_root() {
+        let mut tree = MerkleTree::<Fr>::new(3).unwrap();
+        let empty = tree.root();
+        let leaves: Vec<_> = (1..=5).map(Fr::from).collect();
+        for (i, leaf) in leaves.iter().enumerate() {
+            assert_eq!(i as u64, tree.append(*leaf).unwrap());
+        }
+        assert_ne!(empty, tree.root());
+
+        // Recompute the root from scratch.
+        let mut level: Vec<_> = leaves.clone();
+        level.resize(8, empty_leaf());
+        while level.len() > 1 {
+            level = level.chunks(2).map(|p| hash_pair(p[0], p[1])).collect();
+        }
+        assert_eq!(level[0], tree.root());
+
+        for (i, leaf) in leaves.iter().enumerate() {
+            assert_eq!(tree.root(), tree.path(i as u64).unwrap().root(*leaf));
+        }
+        assert!(tree.path(5).is_err());
+
+        let e = tree.append(empty_leaf()).err().unwrap();
+        assert_eq!(ErrorCode::MalformedInputs, e.code());
+        assert_ne!(Fr::ZERO, empty_leaf());
+    }
+
+    #[test]
+    fn test_full_tree() {
+        let mut tree = MerkleTree::<Scalar>::new(1).unwrap();
+        tree.append(Scalar::one()).unwrap();
+        tree.append(Scalar::one()).unwrap();
+        let e = tree.append(Scalar::one()).err().unwrap();
+        assert_eq!(ErrorCode::TreeFull, e.code());
+        assert!(MerkleTree::<Scalar>::new(0).is_err());
+        assert!(MerkleTree::<Scalar>::new(MAX_DEPTH + 1).is_err());
+    }
+
+    #[test]
+    fn test_ffi() {
+        let mut handle = std::ptr::null_mut();
+        assert_eq!(
+            1,
+            merkle_tree_new(Curve::Bls12381 as libc::c_int, 4, &mut handle)
+        );
+        let mut leaf = Scalar::from(9).to_bytes();
+        let mut index = u64::MAX;
+        assert_eq!(1, merkle_tree_append(handle, leaf.as_mut_ptr(), &mut index));
+        assert_eq!(0, index);
+
+        let mut root = [0u8; SCALAR_SIZE];
+        assert_eq!(1, merkle_tree_root(handle, root.as_mut_ptr()));
+        let mut path = [0u8; 5 * SCALAR_SIZE];
+        assert_eq!(
+            1,
+            merkle_tree_path(handle, 0, path.as_mut_ptr(), path.len())
+        );
+        let code = merkle_tree_path(handle, 1, path.as_mut_ptr(), path.len());
+        assert_eq!(ErrorCode::MalformedInputs as libc::c_int, code);
+        let code = merkle_tree_path(handle, 0, path.as_mut_ptr(), path.len() - 1);
+        assert_eq!(ErrorCode::BufferTooSmall as libc::c_int, code);
+
+        let siblings = path[..4 * SCALAR_SIZE]
+            .chunks(SCALAR_SIZE)
+            .map(|b| bls12_381::Bls12::read_scalar(b).unwrap())
+            .collect();
+        let p = MerklePath { index: 0, siblings };
+        assert_eq!(root, p.root(Scalar::from(9)).to_bytes());
+        merkle_tree_free(handle);
+    }
+}
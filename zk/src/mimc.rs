@@ -0,0 +1,607 @@
+//! MiMC7 and MiMCSponge, compatible with circomlib's `MiMC7`, `MultiMiMC7`
+//! and `MiMCSponge` on BN254. The same constructions are defined over the
+//! BLS12-381 scalar field, with constants drawn the same way, and come with
+//! bellman gadgets so that circuits can use them.
+//!
+//! Round constants are a Keccak-256 chain started from the seed, `mimc` for
+//! MiMC7 and `mimcsponge` for the sponge: constant `i` is the `i+1`-th hash
+//! of the seed reduced modulo the field, the first one (and for the sponge
+//! the last one) being zero.
+
+use std::sync::OnceLock;
+
+use bellman::gadgets::num::AllocatedNum;
+use bellman::{ConstraintSystem, LinearCombination, SynthesisError};
+use ff::PrimeField;
+use halo2curves::bn256::{Bn256, Fr};
+use sha3::{Digest, Keccak256};
+
+use crate::error::{self, Error, ErrorCode, Result};
+use crate::ffi::{bytes, write_out};
+use crate::groth16::{read_scalars, Curve, Groth16Engine, SCALAR_SIZE};
+
+/// Rounds of MiMC7, `ceil(log_7(p))` for both fields.
+pub const MIMC7_ROUNDS: usize = 91;
+/// Rounds of the MiMC Feistel permutation, `2 * ceil(log_5(p))`.
+pub const SPONGE_ROUNDS: usize = 220;
+
+fn constants<F: PrimeField>(seed: &[u8], rounds: usize) -> Vec<F> {
+    let mut c = Keccak256::digest(seed);
+    let mut cts = vec![F::ZERO];
+    for _ in 1..rounds {
+        c = Keccak256::digest(c);
+        let f = c
+            .iter()
+            .fold(F::ZERO, |acc, &b| acc * F::from(256) + F::from(b as u64));
+        cts.push(f);
+    }
+    cts
+}
+
+/// Scalar fields with MiMC constants, generated on first use.
+pub trait MimcField: PrimeField {
+    fn mimc7_constants() -> &'static [Self];
+
+    fn sponge_constants() -> &'static [Self];
+}
+
+macro_rules! mimc_field {
+    ($f:ty) => {
+        impl MimcField for $f {
+            fn mimc7_constants() -> &'static [Self] {
+                static CTS: OnceLock<Vec<$f>> = OnceLock::new();
+                CTS.get_or_init(|| constants(b"mimc", MIMC7_ROUNDS))
+            }
+
+            fn sponge_constants() -> &'static [Self] {
+                static CTS: OnceLock<Vec<$f>> = OnceLock::new();
+                CTS.get_or_init(|| {
+                    let mut cts = constants(b"mimcsponge", SPONGE_ROUNDS);
+                    cts[SPONGE_ROUNDS - 1] = <$f as ff::Field>::ZERO;
+                    cts
+                })
+            }
+        }
+    };
+}
+
+mimc_field!(bls12_381::Scalar);
+mimc_field!(Fr);
+
+fn pow7<F: PrimeField>(t: F) -> F {
+    let t2 = t.square();
+    t2.square() * t2 * t
+}
+
+/// MiMC7 encryption of `x` under key `k`, circomlib's `MiMC7(91)`.
+pub fn mimc7<F: MimcField>(x: F, k: F) -> F {
+    let mut r = x;
+    for (i, c) in F::mimc7_constants().iter().enumerate() {
+        r = pow7(if i == 0 { r + k } else { r + k + c });
+    }
+    r + k
+}
+
+/// Miyaguchi-Preneel hash of `inputs` with MiMC7, circomlib's
+/// `MultiMiMC7(n, 91)`.
+pub fn mimc7_multi<F: MimcField>(inputs: &[F], key: F) -> F {
+    inputs.iter().fold(key, |r, &x| r + x + mimc7(x, r))
+}
+
+/// The MiMC Feistel permutation with x^5, circomlib's `MiMCFeistel(220)`.
+pub fn feistel<F: MimcField>(mut xl: F, mut xr: F, k: F) -> (F, F) {
+    for (i, c) in F::sponge_constants().iter().enumerate() {
+        let t = xl + k + c;
+        let t5 = t.square().square() * t;
+        if i < SPONGE_ROUNDS - 1 {
+            (xl, xr) = (xr + t5, xl);
+        } else {
+            xr += t5;
+        }
+    }
+    (xl, xr)
+}
+
+/// Absorbs `inputs` and squeezes `outputs` elements, circomlib's
+/// `MiMCSponge(n, 220, outputs)`.
+pub fn sponge<F: MimcField>(inputs: &[F], key: F, outputs: usize) -> Vec<F> {
+    let (mut r, mut c) = (F::ZERO, F::ZERO);
+    for x in inputs {
+        (r, c) = feistel(r + x, c, key);
+    }
+    let mut out = vec![r];
+    for _ in 1..outputs {
+        (r, c) = feistel(r, c, key);
+        out.push(r);
+    }
+    out
+}
+
+/// A linear combination of variables along with its value.
+#[derive(Clone)]
+struct Lc<F: PrimeField> {
+    lc: LinearCombination<F>,
+    value: Option<F>,
+}
+
+impl<F: PrimeField> Lc<F> {
+    fn constant<CS: ConstraintSystem<F>>(c: F) -> Self {
+        Lc {
+            lc: LinearCombination::zero() + (c, CS::one()),
+            value: Some(c),
+        }
+    }
+
+    fn add(&self, n: &AllocatedNum<F>) -> Self {
+        Lc {
+            lc: self.lc.clone() + n.get_variable(),
+            value: self.value.zip(n.get_value()).map(|(a, b)| a + b),
+        }
+    }
+
+    fn add_constant<CS: ConstraintSystem<F>>(&self, c: F) -> Self {
+        Lc {
+            lc: self.lc.clone() + (c, CS::one()),
+            value: self.value.map(|v| v + c),
+        }
+    }
+}
+
+impl<F: PrimeField> From<&AllocatedNum<F>> for Lc<F> {
+    fn from(n: &AllocatedNum<F>) -> Self {
+        Lc {
+            lc: LinearCombination::zero() + n.get_variable(),
+            value: n.get_value(),
+        }
+    }
+}
+
+/// Allocates `t^2` and `t^4`.
+fn square_twice<F, CS>(
+    mut cs: CS,
+    t: &Lc<F>,
+) -> std::result::Result<(AllocatedNum<F>, AllocatedNum<F>), SynthesisError>
+where
+    F: PrimeField,
+    CS: ConstraintSystem<F>,
+{
+    let t2 = AllocatedNum::alloc(cs.namespace(|| "t^2"), || {
+        t.value
+            .map(|v| v.square())
+            .ok_or(SynthesisError::AssignmentMissing)
+    })?;
+    cs.enforce(
+        || "t * t",
+        |_| t.lc.clone(),
+        |_| t.lc.clone(),
+        |lc| lc + t2.get_variable(),
+    );
+    let t4 = t2.square(cs.namespace(|| "t^4"))?;
+    Ok((t2, t4))
+}
+
+/// Allocates `a * t + b`.
+fn mul_add<F, CS>(
+    mut cs: CS,
+    a: &AllocatedNum<F>,
+    t: &Lc<F>,
+    b: &Lc<F>,
+) -> std::result::Result<AllocatedNum<F>, SynthesisError>
+where
+    F: PrimeField,
+    CS: ConstraintSystem<F>,
+{
+    let out = AllocatedNum::alloc(cs.namespace(|| "a * t + b"), || {
+        let a = a.get_value().ok_or(SynthesisError::AssignmentMissing)?;
+        let t = t.value.ok_or(SynthesisError::AssignmentMissing)?;
+        let b = b.value.ok_or(SynthesisError::AssignmentMissing)?;
+        Ok(a * t + b)
+    })?;
+    cs.enforce(
+        || "a * t = out - b",
+        |lc| lc + a.get_variable(),
+        |_| t.lc.clone(),
+        |lc| lc + out.get_variable() - &b.lc,
+    );
+    Ok(out)
+}
+
+/// Constrains `mimc7(x, k)`, 4 constraints per round.
+pub fn mimc7_gadget<F, CS>(
+    mut cs: CS,
+    x: &AllocatedNum<F>,
+    k: &AllocatedNum<F>,
+) -> std::result::Result<AllocatedNum<F>, SynthesisError>
+where
+    F: MimcField,
+    CS: ConstraintSystem<F>,
+{
+    let zero = Lc::constant::<CS>(F::ZERO);
+    let mut r = x.clone();
+    for (i, c) in F::mimc7_constants().iter().enumerate() {
+        let mut cs = cs.namespace(|| format!("round {}", i));
+        let t = Lc::from(&r).add(k);
+        let t = if i == 0 { t } else { t.add_constant::<CS>(*c) };
+        let (t2, t4) = square_twice(cs.namespace(|| "t^4"), &t)?;
+        let t6 = t4.mul(cs.namespace(|| "t^6"), &t2)?;
+        r = mul_add(cs.namespace(|| "t^7"), &t6, &t, &zero)?;
+    }
+    let out = AllocatedNum::alloc(cs.namespace(|| "r + k"), || {
+        let r = r.get_value().ok_or(SynthesisError::AssignmentMissing)?;
+        let k = k.get_value().ok_or(SynthesisError::AssignmentMissing)?;
+        Ok(r + k)
+    })?;
+    cs.enforce(
+        || "out = r + k",
+        |lc| lc + r.get_variable() + k.get_variable(),
+        |lc| lc + CS::one(),
+        |lc| lc + out.get_variable(),
+    );
+    Ok(out)
+}
+
+/// Constrains `mimc7_multi(inputs, key)`.
+pub fn mimc7_multi_gadget<F, CS>(
+    mut cs: CS,
+    inputs: &[AllocatedNum<F>],
+    key: &AllocatedNum<F>,
+) -> std::result::Result<AllocatedNum<F>, SynthesisError>
+where
+    F: MimcField,
+    CS: ConstraintSystem<F>,
+{
+    let mut r = key.clone();
+    for (i, x) in inputs.iter().enumerate() {
+        let mut cs = cs.namespace(|| format!("input {}", i));
+        let h = mimc7_gadget(cs.namespace(|| "mimc7"), x, &r)?;
+        let sum = AllocatedNum::alloc(cs.namespace(|| "r + x + h"), || {
+            let r = r.get_value().ok_or(SynthesisError::AssignmentMissing)?;
+            let x = x.get_value().ok_or(SynthesisError::AssignmentMissing)?;
+            let h = h.get_value().ok_or(SynthesisError::AssignmentMissing)?;
+            Ok(r + x + h)
+        })?;
+        cs.enforce(
+            || "sum = r + x + h",
+            |lc| lc + r.get_variable() + x.get_variable() + h.get_variable(),
+            |lc| lc + CS::one(),
+            |lc| lc + sum.get_variable(),
+        );
+        r = sum;
+    }
+    Ok(r)
+}
+
+fn feistel_lc<F, CS>(
+    mut cs: CS,
+    mut xl: Lc<F>,
+    mut xr: Lc<F>,
+    k: &AllocatedNum<F>,
+) -> std::result::Result<(Lc<F>, Lc<F>), SynthesisError>
+where
+    F: MimcField,
+    CS: ConstraintSystem<F>,
+{
+    for (i, c) in F::sponge_constants().iter().enumerate() {
+        let mut cs = cs.namespace(|| format!("round {}", i));
+        let t = xl.add(k).add_constant::<CS>(*c);
+        let (_, t4) = square_twice(cs.namespace(|| "t^4"), &t)?;
+        let n = Lc::from(&mul_add(cs.namespace(|| "xr + t^5"), &t4, &t, &xr)?);
+        if i < SPONGE_ROUNDS - 1 {
+            (xl, xr) = (n, xl);
+        } else {
+            xr = n;
+        }
+    }
+    Ok((xl, xr))
+}
+
+/// Constrains `feistel(xl, xr, k)`, 3 constraints per round.
+pub fn feistel_gadget<F, CS>(
+    mut cs: CS,
+    xl: &AllocatedNum<F>,
+    xr: &AllocatedNum<F>,
+    k: &AllocatedNum<F>,
+) -> std::result::Result<(AllocatedNum<F>, AllocatedNum<F>), SynthesisError>
+where
+    F: MimcField,
+    CS: ConstraintSystem<F>,
+{
+    let (l, r) = feistel_lc(cs.namespace(|| "feistel"), xl.into(), xr.into(), k)?;
+    Ok((
+        alloc_lc(cs.namespace(|| "xl"), &l)?,
+        alloc_lc(cs.namespace(|| "xr"), &r)?,
+    ))
+}
+
+/// Constrains `sponge(inputs, key, outputs)`.
+pub fn sponge_gadget<F, CS>(
+    mut cs: CS,
+    inputs: &[AllocatedNum<F>],
+    key: &AllocatedNum<F>,
+    outputs: usize,
+) -> std::result::Result<Vec<AllocatedNum<F>>, SynthesisError>
+where
+    F: MimcField,
+    CS: ConstraintSystem<F>,
+{
+    let mut r = Lc::constant::<CS>(F::ZERO);
+    let mut c = Lc::constant::<CS>(F::ZERO);
+    for (i, x) in inputs.iter().enumerate() {
+        let cs = cs.namespace(|| format!("absorb {}", i));
+        (r, c) = feistel_lc(cs, r.add(x), c, key)?;
+    }
+    let mut out = vec![alloc_lc(cs.namespace(|| "output 0"), &r)?];
+    for i in 1..outputs {
+        (r, c) = feistel_lc(cs.namespace(|| format!("squeeze {}", i)), r, c, key)?;
+        out.push(alloc_lc(cs.namespace(|| format!("output {}", i)), &r)?);
+    }
+    Ok(out)
+}
+
+fn alloc_lc<F, CS>(mut cs: CS, x: &Lc<F>) -> std::result::Result<AllocatedNum<F>, SynthesisError>
+where
+    F: PrimeField,
+    CS: ConstraintSystem<F>,
+{
+    let n = AllocatedNum::alloc(cs.namespace(|| "value"), || {
+        x.value.ok_or(SynthesisError::AssignmentMissing)
+    })?;
+    cs.enforce(
+        || "n = x",
+        |_| x.lc.clone(),
+        |lc| lc + CS::one(),
+        |lc| lc + n.get_variable(),
+    );
+    Ok(n)
+}
+
+fn read_key<E: Groth16Engine>(b: &[u8]) -> Result<E::Fr> {
+    match read_scalars::<E>(b)?.as_slice() {
+        [k] => Ok(*k),
+        _ => Err(Error::new(
+            ErrorCode::MalformedInputs,
+            "key must be a single scalar",
+        )),
+    }
+}
+
+fn mimc7_encoded<E>(binputs: &[u8], bkey: &[u8]) -> Result<[u8; SCALAR_SIZE]>
+where
+    E: Groth16Engine,
+    E::Fr: MimcField,
+{
+    let inputs = read_scalars::<E>(binputs)?;
+    Ok(E::write_scalar(&mimc7_multi(&inputs, read_key::<E>(bkey)?)))
+}
+
+fn sponge_encoded<E>(binputs: &[u8], bkey: &[u8], outputs: usize) -> Result<Vec<[u8; SCALAR_SIZE]>>
+where
+    E: Groth16Engine,
+    E::Fr: MimcField,
+{
+    let inputs = read_scalars::<E>(binputs)?;
+    let out = sponge(&inputs, read_key::<E>(bkey)?, outputs);
+    Ok(out.iter().map(E::write_scalar).collect())
+}
+
+/// Hashes scalars of the given `Curve` with `mimc7_multi` under a 32-byte
+/// `key`, all in the curve's encoding, and writes the 32-byte hash to
+/// `output`.
+#[no_mangle]
+pub extern "C" fn mimc7_hash(
+    curve: libc::c_int,
+    inputs: *mut libc::c_uchar,
+    inputs_len: libc::size_t,
+    key: *mut libc::c_uchar,
+    output: *mut libc::c_uchar,
+) -> libc::c_int {
+    error::status((|| {
+        let curve = Curve::from_raw(curve)?;
+        let binputs = bytes(inputs, inputs_len)?;
+        let bkey = bytes(key, SCALAR_SIZE)?;
+        let h = match curve {
+            Curve::Bls12381 => mimc7_encoded::<bls12_381::Bls12>(binputs, bkey)?,
+            Curve::Bn254 => mimc7_encoded::<Bn256>(binputs, bkey)?,
+        };
+        write_out(output, SCALAR_SIZE, &h)?;
+        Ok(true)
+    })())
+}
+
+/// Hashes scalars of the given `Curve` with the MiMC sponge under a 32-byte
+/// `key`, all in the curve's encoding. Squeezes `outputs_len / 32` elements
+/// into `outputs`.
+#[no_mangle]
+pub extern "C" fn mimc_sponge(
+    curve: libc::c_int,
+    inputs: *mut libc::c_uchar,
+    inputs_len: libc::size_t,
+    key: *mut libc::c_uchar,
+    outputs: *mut libc::c_uchar,
+    outputs_len: libc::size_t,
+) -> libc::c_int {
+    error::status((|| {
+        let curve = Curve::from_raw(curve)?;
+        let binputs = bytes(inputs, inputs_len)?;
+        let bkey = bytes(key, SCALAR_SIZE)?;
+        let n = (outputs_len / SCALAR_SIZE).max(1);
+        let out = match curve {
+            Curve::Bls12381 => sponge_encoded::<bls12_381::Bls12>(binputs, bkey, n)?,
+            Curve::Bn254 => sponge_encoded::<Bn256>(binputs, bkey, n)?,
+        };
+        write_out(outputs, outputs_len, &out.concat())?;
+        Ok(true)
+    })())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bn254;
+    use bellman::gadgets::test::TestConstraintSystem;
+    use bls12_381::Scalar;
+    use ff::Field;
+
+    fn dec<F: PrimeField>(s: &str) -> F {
+        F::from_str_vartime(s).unwrap()
+    }
+
+    fn hex(s: &str) -> Fr {
+        let mut b = [0u8; SCALAR_SIZE];
+        for (i, byte) in b.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&s[2 * i..2 * i + 2], 16).unwrap();
+        }
+        bn254::read_fr(&b).unwrap()
+    }
+
+    fn fr(values: &[u64]) -> Vec<Fr> {
+        values.iter().map(|&v| Fr::from(v)).collect()
+    }
+
+    #[test]
+    fn test_mimc7_vectors() {
+        assert_eq!(
+            dec::<Fr>(
+                "10594780656576967754230020536574539122676596303354946869887184401991294982664"
+            ),
+            mimc7(Fr::from(1), Fr::from(2))
+        );
+        assert_eq!(
+            hex("2ba7ebad3c6b6f5a20bdecba2333c63173ca1a5f2f49d958081d9fa7179c44e4"),
+            mimc7(Fr::from(12), Fr::from(45))
+        );
+        assert_eq!(
+            hex("237c92644dbddb86d8a259e0e923aaab65a93f1ec5758b8799988894ac0958fd"),
+            mimc7_multi(&fr(&[12]), Fr::ZERO)
+        );
+        assert_eq!(
+            hex("067f3202335ea256ae6e6aadcd2d5f7f4b06a00b2d1e0de903980d5ab552dc70"),
+            mimc7_multi(&fr(&[78, 41]), Fr::ZERO)
+        );
+        assert_eq!(
+            hex("284bc1f34f335933a23a433b6ff3ee179d682cd5e5e2fcdd2d964afa85104beb"),
+            mimc7_multi(&fr(&[12, 45, 78, 41]), Fr::ZERO)
+        );
+    }
+
+    #[test]
+    fn test_sponge_vectors() {
+        assert_eq!(
+            dec::<Fr>(
+                "2119542016932434047340813757208803962484943912710204325088879681995922344971"
+            ),
+            Fr::sponge_constants()[SPONGE_ROUNDS - 2]
+        );
+        assert_eq!(
+            vec![dec::<Fr>(
+                "13403990812567987967336759851318987973794445269548215402779394294754792373527"
+            )],
+            sponge(&fr(&[1, 0]), Fr::ZERO, 1)
+        );
+        let out = sponge(&fr(&[1, 2, 3]), Fr::ZERO, 3);
+        assert_eq!(3, out.len());
+        assert_eq!(out[0], sponge(&fr(&[1, 2, 3]), Fr::ZERO, 1)[0]);
+    }
+
+    #[test]
+    fn test_bls12_381_constants() {
+        assert_eq!(
+            dec::<Scalar>(
+                "12229571979494343421523498192994790888706681595080595532007690219807782377702"
+            ),
+            Scalar::mimc7_constants()[1]
+        );
+        assert_eq!(
+            dec::<Scalar>(
+                "7120861356467848435263064379192047478074060781135320967663101236819528304084"
+            ),
+            Scalar::sponge_constants()[1]
+        );
+        assert_eq!(Scalar::ZERO, Scalar::sponge_constants()[SPONGE_ROUNDS - 1]);
+    }
+
+    fn alloc(cs: &mut TestConstraintSystem<Scalar>, name: &str, v: u64) -> AllocatedNum<Scalar> {
+        AllocatedNum::alloc(cs.namespace(|| name.to_string()), || Ok(Scalar::from(v))).unwrap()
+    }
+
+    #[test]
+    fn test_mimc7_gadget() {
+        let mut cs = TestConstraintSystem::<Scalar>::new();
+        let inputs = [alloc(&mut cs, "a", 12), alloc(&mut cs, "b", 45)];
+        let key = alloc(&mut cs, "key", 7);
+        let h = mimc7_multi_gadget(cs.namespace(|| "mimc7"), &inputs, &key).unwrap();
+        assert!(cs.is_satisfied());
+        let expected = mimc7_multi(&[Scalar::from(12), Scalar::from(45)], Scalar::from(7));
+        assert_eq!(expected, h.get_value().unwrap());
+    }
+
+    #[test]
+    fn test_sponge_gadget() {
+        let mut cs = TestConstraintSystem::<Scalar>::new();
+        let inputs = [alloc(&mut cs, "a", 1), alloc(&mut cs, "b", 2)];
+        let key = alloc(&mut cs, "key", 0);
+        let out = sponge_gadget(cs.namespace(|| "sponge"), &inputs, &key, 2).unwrap();
+        assert!(cs.is_satisfied());
+        let expected = sponge(&[Scalar::from(1), Scalar::from(2)], Scalar::ZERO, 2);
+        let got: Vec<_> = out.iter().map(|n| n.get_value().unwrap()).collect();
+        assert_eq!(expected, got);
+
+        let (l, r) =
+            feistel_gadget(cs.namespace(|| "feistel"), &inputs[0], &inputs[1], &key).unwrap();
+        assert!(cs.is_satisfied());
+        assert_eq!(
+            feistel(Scalar::from(1), Scalar::from(2), Scalar::ZERO),
+            (l.get_value().unwrap(), r.get_value().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_ffi() {
+        let mut inputs = [
+            bn254::write_fr(&Fr::from(78)),
+            bn254::write_fr(&Fr::from(41)),
+        ]
+        .concat();
+        let mut key = [0u8; SCALAR_SIZE];
+        let mut out = [0u8; SCALAR_SIZE];
+        let code = mimc7_hash(
+            Curve::Bn254 as libc::c_int,
+            inputs.as_mut_ptr(),
+            inputs.len(),
+            key.as_mut_ptr(),
+            out.as_mut_ptr(),
+        );
+        assert_eq!(1, code);
+        assert_eq!(
+            mimc7_multi(&fr(&[78, 41]), Fr::ZERO),
+            bn254::read_fr(&out).unwrap()
+        );
+
+        let mut outs = [0u8; 2 * SCALAR_SIZE];
+        let code = mimc_sponge(
+            Curve::Bls12381 as libc::c_int,
+            inputs.as_mut_ptr(),
+            inputs.len(),
+            key.as_mut_ptr(),
+            outs.as_mut_ptr(),
+            outs.len(),
+        );
+        assert_eq!(1, code);
+        let scalars: Vec<_> = inputs
+            .chunks(SCALAR_SIZE)
+            .map(|b| bls12_381::Bls12::read_scalar(b).unwrap())
+            .collect();
+        let expected = sponge(&scalars, Scalar::ZERO, 2);
+        assert_eq!(expected[1].to_bytes(), outs[SCALAR_SIZE..]);
+
+        let code = mimc_sponge(
+            Curve::Bn254 as libc::c_int,
+            inputs.as_mut_ptr(),
+            inputs.len(),
+            key.as_mut_ptr(),
+            outs.as_mut_ptr(),
+            outs.len() - 1,
+        );
+        assert_eq!(ErrorCode::BufferTooSmall as libc::c_int, code);
+    }
+}
@@ -0,0 +1,948 @@
+//! PLONK verifier with KZG commitments, as implemented by snarkjs. Unlike
+//! Groth16, the only trusted setup is the universal powers of tau: keys of
+//! any circuit are derived from it without a per-circuit ceremony, and their
+//! `X_2` point is the ceremony's `tau * G2`.
+//!
+//! Gates are `qM*a*b + qL*a + qR*b + qO*c + qC + PI = 0` over a domain of
+//! `2^power` rows, public input `i` being the `a` wire of row `i`. Challenges
+//! are drawn from snarkjs' Keccak-256 transcript and the openings are checked
+//! with a single pairing, so proofs produced by `snarkjs plonk prove`
+//! verify unchanged. On BLS12-381 the same protocol is run with that curve's
+//! encoding.
+
+use std::io;
+use std::marker::PhantomData;
+
+use bls12_381::Bls12;
+use ff::{Field, PrimeField};
+use group::prime::PrimeCurveAffine;
+use group::{Curve as _, Group};
+use halo2curves::bn256::Bn256;
+use pairing::MillerLoopResult;
+use sha3::{Digest, Keccak256};
+
+use crate::bn254;
+use crate::error::{Error, ErrorCode, Result};
+use crate::groth16::{read_scalars, Groth16Engine, SCALAR_SIZE};
+
+/// Number of commitments in a proof.
+const PROOF_POINTS: usize = 9;
+/// Number of evaluations in a proof.
+const PROOF_EVALUATIONS: usize = 6;
+/// Number of G1 commitments in a verifying key.
+const KEY_POINTS: usize = 8;
+
+/// Wire format of PLONK proofs and verifying keys on a curve. Points are
+/// encoded like those of Groth16 proofs and scalars like public inputs.
+pub trait PlonkEngine: Groth16Engine {
+    /// Size of an encoded G1 point.
+    const G1_SIZE: usize;
+    /// Size of an encoded G2 point.
+    const G2_SIZE: usize;
+
+    fn read_g1(b: &[u8]) -> io::Result<Self::G1Affine>;
+
+    fn read_g2(b: &[u8]) -> io::Result<Self::G2Affine>;
+
+    fn write_g1(p: &Self::G1Affine) -> Vec<u8>;
+
+    fn write_g2(p: &Self::G2Affine) -> Vec<u8>;
+
+    /// Encodes a point the way snarkjs hashes it into the transcript,
+    /// uncompressed big-endian `x | y`, or zeroes with the infinity flag
+    /// `0x40` set in the first byte for the point at infinity.
+    fn transcript_point(p: &Self::G1Affine) -> Vec<u8>;
+
+    /// Encodes a scalar as a big-endian 32-byte word, the inverse of
+    /// `read_word`.
+    fn write_word(s: &Self::Fr) -> [u8; SCALAR_SIZE];
+}
+
+fn invalid(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+impl PlonkEngine for Bls12 {
+    const G1_SIZE: usize = 48;
+    const G2_SIZE: usize = 96;
+
+    fn read_g1(b: &[u8]) -> io::Result<bls12_381::G1Affine> {
+        let mut repr = [0u8; 48];
+        repr.copy_from_slice(b);
+        Option::from(bls12_381::G1Affine::from_compressed(&repr))
+            .ok_or_else(|| invalid("G1 point is not on the curve or not in the subgroup"))
+    }
+
+    fn read_g2(b: &[u8]) -> io::Result<bls12_381::G2Affine> {
+        let mut repr = [0u8; 96];
+        repr.copy_from_slice(b);
+        Option::from(bls12_381::G2Affine::from_compressed(&repr))
+            .ok_or_else(|| invalid("G2 point is not on the curve or not in the subgroup"))
+    }
+
+    fn write_g1(p: &bls12_381::G1Affine) -> Vec<u8> {
+        p.to_compressed().to_vec()
+    }
+
+    fn write_g2(p: &bls12_381::G2Affine) -> Vec<u8> {
+        p.to_compressed().to_vec()
+    }
+
+    fn transcript_point(p: &bls12_381::G1Affine) -> Vec<u8> {
+        p.to_uncompressed().to_vec()
+    }
+
+    fn write_word(s: &bls12_381::Scalar) -> [u8; SCALAR_SIZE] {
+        let mut b = s.to_bytes();
+        b.reverse();
+        b
+    }
+}
+
+impl PlonkEngine for Bn256 {
+    const G1_SIZE: usize = bn254::G1_SIZE;
+    const G2_SIZE: usize = bn254::G2_SIZE;
+
+    fn read_g1(b: &[u8]) -> io::Result<Self::G1Affine> {
+        bn254::read_g1(b)
+    }
+
+    fn read_g2(b: &[u8]) -> io::Result<Self::G2Affine> {
+        bn254::read_g2(b)
+    }
+
+    fn write_g1(p: &Self::G1Affine) -> Vec<u8> {
+        let mut b = vec![0u8; bn254::G1_SIZE];
+        bn254::write_g1(p, &mut b);
+        b
+    }
+
+    fn write_g2(p: &Self::G2Affine) -> Vec<u8> {
+        let mut b = vec![0u8; bn254::G2_SIZE];
+        bn254::write_g2(p, &mut b);
+        b
+    }
+
+    fn transcript_point(p: &Self::G1Affine) -> Vec<u8> {
+        // Unlike the EIP-196 encoding, snarkjs flags infinity as on BLS12-381.
+        let mut b = Self::write_g1(p);
+        if bool::from(p.is_identity()) {
+            b[0] = 0x40;
+        }
+        b
+    }
+
+    fn write_word(s: &Self::Fr) -> [u8; SCALAR_SIZE] {
+        bn254::write_fr(s)
+    }
+}
+
+/// The verifying key of a circuit, encoded as
+/// `power | nPublic | k1 | k2 | Qm | Ql | Qr | Qo | Qc | S1 | S2 | S3 | X_2`
+/// with `power` and `nPublic` big-endian u32s.
+#[derive(Clone)]
+pub struct VerifyingKey<E: PlonkEngine> {
+    /// Log2 of the number of rows.
+    pub power: u32,
+    pub n_public: usize,
+    /// Shifts of the `b` and `c` wire cosets in the permutation argument.
+    pub k1: E::Fr,
+    pub k2: E::Fr,
+    pub qm: E::G1Affine,
+    pub ql: E::G1Affine,
+    pub qr: E::G1Affine,
+    pub qo: E::G1Affine,
+    pub qc: E::G1Affine,
+    pub s1: E::G1Affine,
+    pub s2: E::G1Affine,
+    pub s3: E::G1Affine,
+    pub x_2: E::G2Affine,
+}
+
+impl<E: PlonkEngine> VerifyingKey<E> {
+    fn size() -> usize {
+        8 + 2 * SCALAR_SIZE + KEY_POINTS * E::G1_SIZE + E::G2_SIZE
+    }
+
+    pub fn read(b: &[u8]) -> io::Result<Self> {
+        if b.len() != Self::size() {
+            return Err(invalid(format!(
+                "verifying key must be {} bytes",
+                Self::size()
+            )));
+        }
+        let word = |i: usize| u32::from_be_bytes([b[i], b[i + 1], b[i + 2], b[i + 3]]);
+        let power = word(0);
+        if power == 0 || power > E::Fr::S {
+            return Err(invalid(format!("unsupported domain size 2^{}", power)));
+        }
+        let n_public = word(4) as usize;
+        if n_public > 1 << power {
+            return Err(invalid("more public inputs than rows"));
+        }
+        let scalar = |i: usize| {
+            E::read_scalar(&b[8 + i * SCALAR_SIZE..8 + (i + 1) * SCALAR_SIZE])
+                .ok_or_else(|| invalid("k1 and k2 must be canonical scalars"))
+        };
+        let points = 8 + 2 * SCALAR_SIZE;
+        let g1 = b[points..points + KEY_POINTS * E::G1_SIZE]
+            .chunks_exact(E::G1_SIZE)
+            .map(E::read_g1)
+            .collect::<io::Result<Vec<_>>>()?;
+        Ok(VerifyingKey {
+            power,
+            n_public,
+            k1: scalar(0)?,
+            k2: scalar(1)?,
+            qm: g1[0],
+            ql: g1[1],
+            qr: g1[2],
+            qo: g1[3],
+            qc: g1[4],
+            s1: g1[5],
+            s2: g1[6],
+            s3: g1[7],
+            x_2: E::read_g2(&b[points + KEY_POINTS * E::G1_SIZE..])?,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut b = Vec::with_capacity(Self::size());
+        b.extend(self.power.to_be_bytes());
+        b.extend((self.n_public as u32).to_be_bytes());
+        b.extend(E::write_scalar(&self.k1));
+        b.extend(E::write_scalar(&self.k2));
+        for p in [
+            self.qm, self.ql, self.qr, self.qo, self.qc, self.s1, self.s2, self.s3,
+        ] {
+            b.extend(E::write_g1(&p));
+        }
+        b.extend(E::write_g2(&self.x_2));
+        b
+    }
+
+    /// Checks a proof against public inputs, following snarkjs'
+    /// `plonk_verify`.
+    pub fn verify(&self, proof: &Proof<E>, inputs: &[E::Fr]) -> Result<bool> {
+        if inputs.len() != self.n_public {
+            return Err(Error::new(
+                ErrorCode::InputCountMismatch,
+                format!(
+                    "expected {} public inputs, got {}",
+                    self.n_public,
+                    inputs.len()
+                ),
+            ));
+        }
+        let ch = Challenges::new(self, proof, inputs);
+        let w = root_of_unity::<E::Fr>(self.power);
+
+        // The vanishing polynomial and the Lagrange polynomials of the
+        // public input rows at xi, L1 being needed even without inputs.
+        let mut xin = ch.xi;
+        for _ in 0..self.power {
+            xin = xin.square();
+        }
+        let zh = xin - E::Fr::ONE;
+        let n = E::Fr::from(1u64 << self.power);
+        let mut l = vec![];
+        let mut wi = E::Fr::ONE;
+        for _ in 0..self.n_public.max(1) {
+            let Some(inv) = Option::<E::Fr>::from((n * (ch.xi - wi)).invert()) else {
+                // xi is a row of the domain, which the prover can't steer
+                // it to.
+                return Ok(false);
+            };
+            l.push(wi * zh * inv);
+            wi *= w;
+        }
+        let pi = inputs
+            .iter()
+            .zip(l.iter())
+            .fold(E::Fr::ZERO, |acc, (x, l)| acc - *x * l);
+
+        let p = proof;
+        let alpha2 = ch.alpha.square();
+        let e3a = p.eval_a + ch.beta * p.eval_s1 + ch.gamma;
+        let e3b = p.eval_b + ch.beta * p.eval_s2 + ch.gamma;
+        let e3c = p.eval_c + ch.gamma;
+        let r0 = pi - l[0] * alpha2 - e3a * e3b * e3c * p.eval_zw * ch.alpha;
+
+        // D, the commitment to the linearization polynomial.
+        let d1 = self.qm * (p.eval_a * p.eval_b)
+            + self.ql * p.eval_a
+            + self.qr * p.eval_b
+            + self.qo * p.eval_c
+            + self.qc;
+        let betaxi = ch.beta * ch.xi;
+        let d2a = (p.eval_a + betaxi + ch.gamma)
+            * (p.eval_b + betaxi * self.k1 + ch.gamma)
+            * (p.eval_c + betaxi * self.k2 + ch.gamma)
+            * ch.alpha;
+        let d2 = p.z * (d2a + l[0] * alpha2 + ch.u);
+        let d3 = self.s3 * (e3a * e3b * ch.alpha * ch.beta * p.eval_zw);
+        let d4 = (p.t1.to_curve() + p.t2 * xin + p.t3 * xin.square()) * zh;
+        let d = d1 + d2 - d3 - d4;
+
+        let v = ch.v;
+        let f = d + p.a * v[0] + p.b * v[1] + p.c * v[2] + self.s1 * v[3] + self.s2 * v[4];
+        let e = v[0] * p.eval_a
+            + v[1] * p.eval_b
+            + v[2] * p.eval_c
+            + v[3] * p.eval_s1
+            + v[4] * p.eval_s2
+            + ch.u * p.eval_zw
+            - r0;
+
+        // e(-(Wxi + u*Wxiw), X_2) * e(xi*Wxi + u*xi*w*Wxiw + F - E, G2) == 1
+        let a1 = p.wxi.to_curve() + p.wxiw * ch.u;
+        let b1 = p.wxi * ch.xi + p.wxiw * (ch.u * ch.xi * w) + f - E::G1Affine::generator() * e;
+        let x_2 = E::G2Prepared::from(self.x_2);
+        let g2 = E::G2Prepared::from(E::G2Affine::generator());
+        Ok(
+            E::multi_miller_loop(&[(&(-a1).to_affine(), &x_2), (&b1.to_affine(), &g2)])
+                .final_exponentiation()
+                .is_identity()
+                .into(),
+        )
+    }
+}
+
+/// A proof, encoded as
+/// `A | B | C | Z | T1 | T2 | T3 | Wxi | Wxiw | eval_a | eval_b | eval_c |
+/// eval_s1 | eval_s2 | eval_zw`, the order of snarkjs' Solidity calldata.
+#[derive(Clone)]
+pub struct Proof<E: PlonkEngine> {
+    pub a: E::G1Affine,
+    pub b: E::G1Affine,
+    pub c: E::G1Affine,
+    pub z: E::G1Affine,
+    pub t1: E::G1Affine,
+    pub t2: E::G1Affine,
+    pub t3: E::G1Affine,
+    pub wxi: E::G1Affine,
+    pub wxiw: E::G1Affine,
+    pub eval_a: E::Fr,
+    pub eval_b: E::Fr,
+    pub eval_c: E::Fr,
+    pub eval_s1: E::Fr,
+    pub eval_s2: E::Fr,
+    pub eval_zw: E::Fr,
+}
+
+impl<E: PlonkEngine> Proof<E> {
+    /// Size of an encoded proof.
+    pub fn size() -> usize {
+        PROOF_POINTS * E::G1_SIZE + PROOF_EVALUATIONS * SCALAR_SIZE
+    }
+
+    pub fn read(b: &[u8]) -> io::Result<Self> {
+        if b.len() != Self::size() {
+            return Err(invalid(format!("proof must be {} bytes", Self::size())));
+        }
+        let (points, scalars) = b.split_at(PROOF_POINTS * E::G1_SIZE);
+        let g1 = points
+            .chunks_exact(E::G1_SIZE)
+            .map(E::read_g1)
+            .collect::<io::Result<Vec<_>>>()?;
+        let evals = scalars
+            .chunks_exact(SCALAR_SIZE)
+            .map(|s| {
+                E::read_scalar(s).ok_or_else(|| invalid("evaluation is not a canonical scalar"))
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        Ok(Proof {
+            a: g1[0],
+            b: g1[1],
+            c: g1[2],
+            z: g1[3],
+            t1: g1[4],
+            t2: g1[5],
+            t3: g1[6],
+            wxi: g1[7],
+            wxiw: g1[8],
+            eval_a: evals[0],
+            eval_b: evals[1],
+            eval_c: evals[2],
+            eval_s1: evals[3],
+            eval_s2: evals[4],
+            eval_zw: evals[5],
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut b = Vec::with_capacity(Self::size());
+        for p in [
+            self.a, self.b, self.c, self.z, self.t1, self.t2, self.t3, self.wxi, self.wxiw,
+        ] {
+            b.extend(E::write_g1(&p));
+        }
+        for s in [
+            self.eval_a,
+            self.eval_b,
+            self.eval_c,
+            self.eval_s1,
+            self.eval_s2,
+            self.eval_zw,
+        ] {
+            b.extend(E::write_scalar(&s));
+        }
+        b
+    }
+}
+
+/// snarkjs' Keccak-256 transcript: a challenge is the hash of what was added
+/// since the previous one, taken as a big-endian number modulo the group
+/// order.
+pub(crate) struct Transcript<E: PlonkEngine> {
+    data: Vec<u8>,
+    engine: PhantomData<E>,
+}
+
+impl<E: PlonkEngine> Transcript<E> {
+    pub(crate) fn new() -> Self {
+        Transcript {
+            data: vec![],
+            engine: PhantomData,
+        }
+    }
+
+    pub(crate) fn point(&mut self, p: &E::G1Affine) {
+        self.data.extend(E::transcript_point(p));
+    }
+
+    pub(crate) fn scalar(&mut self, s: &E::Fr) {
+        self.data.extend(E::write_word(s));
+    }
+
+    pub(crate) fn challenge(&mut self) -> E::Fr {
+        let h = Keccak256::digest(&self.data);
+        self.data.clear();
+        let base = E::Fr::from(256);
+        h.iter()
+            .fold(E::Fr::ZERO, |acc, b| acc * base + E::Fr::from(*b as u64))
+    }
+}
+
+pub(crate) struct Challenges<F> {
+    pub(crate) beta: F,
+    pub(crate) gamma: F,
+    pub(crate) alpha: F,
+    pub(crate) xi: F,
+    /// v, v^2, .. v^5.
+    pub(crate) v: [F; 5],
+    pub(crate) u: F,
+}
+
+impl<F: PrimeField> Challenges<F> {
+    fn new<E: PlonkEngine<Fr = F>>(vk: &VerifyingKey<E>, p: &Proof<E>, inputs: &[F]) -> Self {
+        let mut t = Transcript::<E>::new();
+        for q in [vk.qm, vk.ql, vk.qr, vk.qo, vk.qc, vk.s1, vk.s2, vk.s3] {
+            t.point(&q);
+        }
+        for x in inputs {
+            t.scalar(x);
+        }
+        for q in [p.a, p.b, p.c] {
+            t.point(&q);
+        }
+        let beta = t.challenge();
+        t.scalar(&beta);
+        let gamma = t.challenge();
+        t.scalar(&beta);
+        t.scalar(&gamma);
+        t.point(&p.z);
+        let alpha = t.challenge();
+        t.scalar(&alpha);
+        for q in [p.t1, p.t2, p.t3] {
+            t.point(&q);
+        }
+        let xi = t.challenge();
+        t.scalar(&xi);
+        for s in [
+            p.eval_a, p.eval_b, p.eval_c, p.eval_s1, p.eval_s2, p.eval_zw,
+        ] {
+            t.scalar(&s);
+        }
+        let mut v = [t.challenge(); 5];
+        for i in 1..5 {
+            v[i] = v[i - 1] * v[0];
+        }
+        t.point(&p.wxi);
+        t.point(&p.wxiw);
+        let u = t.challenge();
+        Challenges {
+            beta,
+            gamma,
+            alpha,
+            xi,
+            v,
+            u,
+        }
+    }
+}
+
+/// The generator of the `2^power` roots of unity snarkjs uses: the smallest
+/// quadratic non-residue raised to the odd part of `r - 1` and squared down
+/// to the right order. It differs from `PrimeField::ROOT_OF_UNITY`, which is
+/// derived from the multiplicative generator instead.
+pub fn root_of_unity<F: PrimeField>(power: u32) -> F {
+    let mut nqr = F::from(2);
+    while bool::from(nqr.sqrt().is_some()) {
+        nqr += F::ONE;
+    }
+    // t = (r - 1) >> S, from the little-endian limbs of r - 1.
+    let repr = (-F::ONE).to_repr();
+    let mut limbs = [0u64; 4];
+    for (limb, chunk) in limbs.iter_mut().zip(repr.as_ref().chunks(8)) {
+        let mut b = [0u8; 8];
+        b[..chunk.len()].copy_from_slice(chunk);
+        *limb = u64::from_le_bytes(b);
+    }
+    let s = F::S;
+    let mut t = [0u64; 4];
+    for i in 0..4 {
+        t[i] = limbs[i] >> s;
+        if i < 3 {
+            t[i] |= limbs[i + 1] << (64 - s);
+        }
+    }
+    let mut w = nqr.pow_vartime(t);
+    for _ in power..s {
+        w = w.square();
+    }
+    w
+}
+
+/// Verifies a proof with a verifying key and public inputs, all in the
+/// curve's encoding.
+pub fn verify_plonk<E: PlonkEngine>(bproof: &[u8], bkey: &[u8], binputs: &[u8]) -> Result<bool> {
+    let key = VerifyingKey::<E>::read(bkey).map_err(|e| {
+        Error::new(
+            ErrorCode::MalformedKey,
+            format!("malformed verifying key: {}", e),
+        )
+    })?;
+    let proof = Proof::<E>::read(bproof)
+        .map_err(|e| Error::new(ErrorCode::MalformedProof, format!("malformed proof: {}", e)))?;
+    key.verify(&proof, &read_scalars::<E>(binputs)?)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::groth16::{self, Curve, ProofSystem};
+    use crate::ErrorCode;
+    use halo2curves::bn256::Fr;
+
+    /// Polynomials in coefficient form, enough to prove small circuits.
+    fn evaluate<F: Field>(p: &[F], x: F) -> F {
+        p.iter().rev().fold(F::ZERO, |acc, c| acc * x + c)
+    }
+
+    fn add<F: Field>(p: &[F], q: &[F]) -> Vec<F> {
+        let mut r = vec![F::ZERO; p.len().max(q.len())];
+        for (i, c) in p.iter().enumerate() {
+            r[i] += c;
+        }
+        for (i, c) in q.iter().enumerate() {
+            r[i] += c;
+        }
+        r
+    }
+
+    fn scale<F: Field>(p: &[F], s: F) -> Vec<F> {
+        p.iter().map(|c| *c * s).collect()
+    }
+
+    fn mul<F: Field>(p: &[F], q: &[F]) -> Vec<F> {
+        let mut r = vec![F::ZERO; p.len() + q.len() - 1];
+        for (i, a) in p.iter().enumerate() {
+            for (j, b) in q.iter().enumerate() {
+                r[i + j] += *a * b;
+            }
+        }
+        r
+    }
+
+    /// The polynomial taking `evals` over the domain of `w`.
+    fn interpolate<F: PrimeField>(evals: &[F], w: F) -> Vec<F> {
+        let n = evals.len();
+        let (w_inv, n_inv) = (w.invert().unwrap(), F::from(n as u64).invert().unwrap());
+        (0..n)
+            .map(|j| {
+                let wj = w_inv.pow_vartime([j as u64]);
+                evals
+                    .iter()
+                    .enumerate()
+                    .fold(F::ZERO, |acc, (i, e)| acc + *e * wj.pow_vartime([i as u64]))
+                    * n_inv
+            })
+            .collect()
+    }
+
+    /// p(X) / (X^n - 1), which must divide it.
+    fn divide_vanishing<F: Field>(p: &[F], n: usize) -> Vec<F> {
+        let mut rem = p.to_vec();
+        let mut q = vec![F::ZERO; p.len().saturating_sub(n)];
+        for i in (n..rem.len()).rev() {
+            let c = rem[i];
+            q[i - n] = c;
+            rem[i] = F::ZERO;
+            rem[i - n] += c;
+        }
+        assert!(rem.iter().all(|c| bool::from(c.is_zero())));
+        q
+    }
+
+    /// (p(X) - p(z)) / (X - z).
+    fn divide_linear<F: Field>(p: &[F], z: F) -> Vec<F> {
+        let mut q = vec![F::ZERO; p.len() - 1];
+        let mut acc = F::ZERO;
+        for i in (1..p.len()).rev() {
+            acc = acc * z + p[i];
+            q[i - 1] = acc;
+        }
+        q
+    }
+
+    /// Proves `out = x * y + x` for public `out` with a setup whose `tau` is
+    /// known, on a domain of 8 rows:
+    ///
+    /// ```text
+    /// row 0: a = out          public input
+    /// row 1: a * b = c        x * y = m
+    /// row 2: a + b = c        m + x = out
+    /// ```
+    ///
+    /// with copies `c1 = a2`, `a1 = b2` and `c2 = a0`. Returns the key, the
+    /// proof and the public input.
+    pub(crate) fn prove<E: PlonkEngine>(x: u64, y: u64) -> (VerifyingKey<E>, Proof<E>, E::Fr) {
+        let power = 3;
+        let n = 1usize << power;
+        let fr = |v: u64| E::Fr::from(v);
+        let tau = fr(0x7a0);
+        let w = root_of_unity::<E::Fr>(power);
+        let (k1, k2) = (fr(2), fr(3));
+        let commit = |p: &[E::Fr]| (E::G1Affine::generator() * evaluate(p, tau)).to_affine();
+
+        let (x, y) = (fr(x), fr(y));
+        let (m, out) = (x * y, x * y + x);
+        let mut wires = vec![vec![E::Fr::ZERO; n]; 3];
+        for (row, abc) in [[out, E::Fr::ZERO, E::Fr::ZERO], [x, y, m], [m, x, out]]
+            .iter()
+            .enumerate()
+        {
+            for (wire, v) in abc.iter().enumerate() {
+                wires[wire][row] = *v;
+            }
+        }
+        let mut selectors = vec![vec![E::Fr::ZERO; n]; 5];
+        // qM, qL, qR, qO, qC per row.
+        selectors[1][0] = E::Fr::ONE;
+        selectors[0][1] = E::Fr::ONE;
+        selectors[3][1] = -E::Fr::ONE;
+        selectors[1][2] = E::Fr::ONE;
+        selectors[2][2] = E::Fr::ONE;
+        selectors[3][2] = -E::Fr::ONE;
+
+        // Wire (j, i) is labelled k_j * w^i, the permutation swaps the
+        // labels of each pair of copied wires.
+        let shifts = [E::Fr::ONE, k1, k2];
+        let label = |(j, i): (usize, usize)| shifts[j] * w.pow_vartime([i as u64]);
+        let mut sigma = (0..3)
+            .map(|j| (0..n).map(|i| label((j, i))).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        for (p, q) in [((2, 1), (0, 2)), ((0, 1), (1, 2)), ((2, 2), (0, 0))] {
+            sigma[p.0][p.1] = label(q);
+            sigma[q.0][q.1] = label(p);
+        }
+
+        let poly = |evals: &[E::Fr]| interpolate(evals, w);
+        let [a, b, c] = [0, 1, 2].map(|j| poly(&wires[j]));
+        let [qm, ql, qr, qo, qc] = [0, 1, 2, 3, 4].map(|j| poly(&selectors[j]));
+        let [s1, s2, s3] = [0, 1, 2].map(|j| poly(&sigma[j]));
+        let mut pi = vec![E::Fr::ZERO; n];
+        pi[0] = -out;
+        let pi = poly(&pi);
+        let mut l1 = vec![E::Fr::ZERO; n];
+        l1[0] = E::Fr::ONE;
+        let l1 = poly(&l1);
+
+        let vk = VerifyingKey::<E> {
+            power,
+            n_public: 1,
+            k1,
+            k2,
+            qm: commit(&qm),
+            ql: commit(&ql),
+            qr: commit(&qr),
+            qo: commit(&qo),
+            qc: commit(&qc),
+            s1: commit(&s1),
+            s2: commit(&s2),
+            s3: commit(&s3),
+            x_2: (E::G2Affine::generator() * tau).to_affine(),
+        };
+        let mut t = Transcript::<E>::new();
+        for q in [vk.qm, vk.ql, vk.qr, vk.qo, vk.qc, vk.s1, vk.s2, vk.s3] {
+            t.point(&q);
+        }
+        t.scalar(&out);
+        let (ca, cb, cc) = (commit(&a), commit(&b), commit(&c));
+        for q in [ca, cb, cc] {
+            t.point(&q);
+        }
+        let beta = t.challenge();
+        t.scalar(&beta);
+        let gamma = t.challenge();
+
+        let mut zs = vec![E::Fr::ONE];
+        for i in 0..n - 1 {
+            let (mut num, mut den) = (E::Fr::ONE, E::Fr::ONE);
+            for j in 0..3 {
+                num *= wires[j][i] + beta * label((j, i)) + gamma;
+                den *= wires[j][i] + beta * sigma[j][i] + gamma;
+            }
+            zs.push(zs[i] * num * den.invert().unwrap());
+        }
+        let z = poly(&zs);
+        let cz = commit(&z);
+        t.scalar(&beta);
+        t.scalar(&gamma);
+        t.point(&cz);
+        let alpha = t.challenge();
+
+        // The quotient of the gate, permutation and Z(1) = 1 constraints.
+        let lin = |p: &[E::Fr], k: E::Fr| add(p, &[gamma, beta * k]);
+        let sig = |p: &[E::Fr], s: &[E::Fr]| add(p, &add(&scale(s, beta), &[gamma]));
+        let zw = z
+            .iter()
+            .enumerate()
+            .map(|(i, c)| *c * w.pow_vartime([i as u64]))
+            .collect::<Vec<_>>();
+        let gate = [
+            mul(&mul(&a, &b), &qm),
+            mul(&a, &ql),
+            mul(&b, &qr),
+            mul(&c, &qo),
+            pi,
+            qc.clone(),
+        ]
+        .iter()
+        .fold(vec![], |acc, p| add(&acc, p));
+        let perm = add(
+            &mul(
+                &mul(&mul(&lin(&a, E::Fr::ONE), &lin(&b, k1)), &lin(&c, k2)),
+                &z,
+            ),
+            &scale(
+                &mul(&mul(&mul(&sig(&a, &s1), &sig(&b, &s2)), &sig(&c, &s3)), &zw),
+                -E::Fr::ONE,
+            ),
+        );
+        let first = mul(&add(&z, &[-E::Fr::ONE]), &l1);
+        let numerator = add(
+            &gate,
+            &add(&scale(&perm, alpha), &scale(&first, alpha.square())),
+        );
+        let mut tq = divide_vanishing(&numerator, n);
+        tq.resize(3 * n, E::Fr::ZERO);
+        let (t1, t2, t3) = (&tq[..n], &tq[n..2 * n], &tq[2 * n..]);
+        let (ct1, ct2, ct3) = (commit(t1), commit(t2), commit(t3));
+        t.scalar(&alpha);
+        for q in [ct1, ct2, ct3] {
+            t.point(&q);
+        }
+        let xi = t.challenge();
+
+        let ev = |p: &[E::Fr]| evaluate(p, xi);
+        let (ea, eb, ec, es1, es2) = (ev(&a), ev(&b), ev(&c), ev(&s1), ev(&s2));
+        let ezw = evaluate(&z, xi * w);
+        t.scalar(&xi);
+        for s in [ea, eb, ec, es1, es2, ezw] {
+            t.scalar(&s);
+        }
+        let v = t.challenge();
+
+        // The linearization polynomial committed to by D, less u * Z.
+        let xin = xi.pow_vartime([n as u64]);
+        let zh = xin - E::Fr::ONE;
+        let l1_xi = ev(&l1);
+        let betaxi = beta * xi;
+        let r = [
+            scale(&qm, ea * eb),
+            scale(&ql, ea),
+            scale(&qr, eb),
+            scale(&qo, ec),
+            qc,
+            scale(
+                &z,
+                (ea + betaxi + gamma)
+                    * (eb + betaxi * k1 + gamma)
+                    * (ec + betaxi * k2 + gamma)
+                    * alpha
+                    + l1_xi * alpha.square(),
+            ),
+            scale(
+                &s3,
+                -(ea + beta * es1 + gamma) * (eb + beta * es2 + gamma) * alpha * beta * ezw,
+            ),
+            scale(t1, -zh),
+            scale(t2, -zh * xin),
+            scale(t3, -zh * xin.square()),
+        ]
+        .iter()
+        .fold(vec![], |acc, p| add(&acc, p));
+        let opened = [a, b, c, s1, s2].iter().enumerate().fold(r, |acc, (i, p)| {
+            add(&acc, &scale(p, v.pow_vartime([i as u64 + 1])))
+        });
+        let wxi = commit(&divide_linear(&opened, xi));
+        let wxiw = commit(&divide_linear(&z, xi * w));
+
+        let proof = Proof {
+            a: ca,
+            b: cb,
+            c: cc,
+            z: cz,
+            t1: ct1,
+            t2: ct2,
+            t3: ct3,
+            wxi,
+            wxiw,
+            eval_a: ea,
+            eval_b: eb,
+            eval_c: ec,
+            eval_s1: es1,
+            eval_s2: es2,
+            eval_zw: ezw,
+        };
+        (vk, proof, out)
+    }
+
+    fn check<E: PlonkEngine>() {
+        let (vk, proof, out) = prove::<E>(6, 4);
+        assert_eq!(E::Fr::from(30), out);
+        assert!(vk.verify(&proof, &[out]).unwrap());
+        assert!(!vk.verify(&proof, &[out + E::Fr::ONE]).unwrap());
+        let err = vk.verify(&proof, &[]).unwrap_err();
+        assert_eq!(ErrorCode::InputCountMismatch, err.code());
+
+        let mut bad = proof.clone();
+        bad.eval_zw += E::Fr::ONE;
+        assert!(!vk.verify(&bad, &[out]).unwrap());
+        let mut bad = proof.clone();
+        bad.wxi = bad.wxiw;
+        assert!(!vk.verify(&bad, &[out]).unwrap());
+        let mut other = vk.clone();
+        other.k1 = E::Fr::from(5);
+        assert!(!other.verify(&proof, &[out]).unwrap());
+
+        // A key from another setup doesn't accept the proof.
+        let mut other = vk.clone();
+        other.x_2 = (E::G2Affine::generator() * E::Fr::from(0x7a1)).to_affine();
+        assert!(!other.verify(&proof, &[out]).unwrap());
+
+        let (bkey, bproof) = (vk.to_bytes(), proof.to_bytes());
+        assert_eq!(bkey, VerifyingKey::<E>::read(&bkey).unwrap().to_bytes());
+        assert_eq!(bproof, Proof::<E>::read(&bproof).unwrap().to_bytes());
+        let inputs = E::write_scalar(&out);
+        assert!(verify_plonk::<E>(&bproof, &bkey, &inputs).unwrap());
+        let err = verify_plonk::<E>(&bproof[1..], &bkey, &inputs).unwrap_err();
+        assert_eq!(ErrorCode::MalformedProof, err.code());
+        let err = verify_plonk::<E>(&bproof, &bkey[..bkey.len() - 1], &inputs).unwrap_err();
+        assert_eq!(ErrorCode::MalformedKey, err.code());
+        let mut huge = bkey.clone();
+        huge[..4].copy_from_slice(&(E::Fr::S + 1).to_be_bytes());
+        let err = verify_plonk::<E>(&bproof, &huge, &inputs).unwrap_err();
+        assert_eq!(ErrorCode::MalformedKey, err.code());
+        let err = verify_plonk::<E>(&bproof, &bkey, &inputs[1..]).unwrap_err();
+        assert_eq!(ErrorCode::MalformedInputs, err.code());
+    }
+
+    #[test]
+    fn test_bn254() {
+        check::<Bn256>();
+    }
+
+    fn check_infinity<E: PlonkEngine>(size: usize) {
+        let mut expected = vec![0u8; size];
+        expected[0] = 0x40;
+        assert_eq!(expected, E::transcript_point(&E::G1Affine::identity()));
+        // The test circuit has no constant, so its Qc is at infinity.
+        let (vk, proof, out) = prove::<E>(6, 4);
+        assert!(bool::from(vk.qc.is_identity()));
+        assert!(vk.verify(&proof, &[out]).unwrap());
+    }
+
+    #[test]
+    fn test_transcript_infinity() {
+        check_infinity::<Bn256>(64);
+        check_infinity::<Bls12>(96);
+    }
+
+    #[test]
+    fn test_bls12381() {
+        check::<Bls12>();
+    }
+
+    #[test]
+    fn test_ffi() {
+        let call = |system: libc::c_int, curve: Curve, p: &[u8], k: &[u8], i: &[u8]| {
+            groth16::verify_with_system(
+                system,
+                curve as libc::c_int,
+                p.as_ptr() as *mut _,
+                p.len(),
+                k.as_ptr() as *mut _,
+                k.len(),
+                i.as_ptr() as *mut _,
+                i.len(),
+            )
+        };
+        let plonk = ProofSystem::Plonk as libc::c_int;
+        let (vk, proof, out) = prove::<Bn256>(2, 5);
+        let (bkey, bproof) = (vk.to_bytes(), proof.to_bytes());
+        assert_eq!(768, bproof.len());
+        let inputs = bn254::write_fr(&out);
+        assert_eq!(1, call(plonk, Curve::Bn254, &bproof, &bkey, &inputs));
+        let wrong = bn254::write_fr(&(out + Fr::ONE));
+        assert_eq!(0, call(plonk, Curve::Bn254, &bproof, &bkey, &wrong));
+        // A PLONK proof isn't a Groth16 one.
+        let code = call(
+            ProofSystem::Groth16 as libc::c_int,
+            Curve::Bn254,
+            &bproof,
+            &bkey,
+            &inputs,
+        );
+        assert_eq!(ErrorCode::MalformedProof as libc::c_int, code);
+        let code = call(2, Curve::Bn254, &bproof, &bkey, &inputs);
+        assert_eq!(ErrorCode::UnsupportedProofSystem as libc::c_int, code);
+
+        let (vk, proof, out) = prove::<Bls12>(2, 5);
+        let (bkey, bproof) = (vk.to_bytes(), proof.to_bytes());
+        assert_eq!(624, bproof.len());
+        let inputs = out.to_bytes();
+        assert_eq!(1, call(plonk, Curve::Bls12381, &bproof, &bkey, &inputs));
+        let code = call(plonk, Curve::Bn254, &bproof, &bkey, &inputs);
+        assert_eq!(ErrorCode::MalformedKey as libc::c_int, code);
+    }
+
+    #[test]
+    fn test_root_of_unity() {
+        // Fr.w[28] of snarkjs' bn128 field.
+        let w = root_of_unity::<Fr>(28);
+        assert_eq!(
+            "2a3c09f0a58a7e8500e0a7eb8ef62abc402d111e41112ed49bd61b6e725b19f0",
+            bn254::write_fr(&w)
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect::<String>()
+        );
+        assert_eq!(-Fr::ONE, root_of_unity::<Fr>(1));
+        let w = root_of_unity::<bls12_381::Scalar>(4);
+        assert_eq!(bls12_381::Scalar::ONE, w.pow_vartime(&[16, 0, 0, 0]));
+        assert_ne!(bls12_381::Scalar::ONE, w.pow_vartime(&[8, 0, 0, 0]));
+    }
+}
@@ -0,0 +1,359 @@
+//! Shielded pool primitives, in the style of Tornado Cash. A deposit appends
+//! the commitment of a secret note to the pool's Poseidon Merkle tree, see
+//! the `merkle` module. A withdrawal proves with the `Spend` circuit that it
+//! knows a note in the tree under a public root while only revealing the
+//! note's nullifier hash, which the pool records to refuse double spends.
+//!
+//! A note is a random `nullifier | secret` pair, its commitment is
+//! `poseidon([nullifier, secret])` and its nullifier hash is
+//! `poseidon([nullifier])`. Withdrawal data (recipient, relayer, fee...) is
+//! bound to the proof by its `external_hash`, so that a proof seen in the
+//! mempool can't be replayed to another recipient. Spend proofs are on
+//! BLS12-381 and checked by `verify` like any other Groth16 proof, with
+//! `spend_inputs` as public inputs.
+
+use bls12_381::{Bls12, Scalar};
+use halo2curves::bn256::Bn256;
+use rand_core::{OsRng, RngCore};
+use sha3::{Digest, Keccak256};
+
+use crate::error::{self, Error, ErrorCode, Result};
+use crate::ffi::{bytes, write_out};
+use crate::groth16::{Curve, Groth16Engine, SCALAR_SIZE};
+use crate::merkle::MerklePath;
+use crate::poseidon::{self, PoseidonField};
+
+/// Size of a serialized note, `nullifier | secret`.
+pub const NOTE_SIZE: usize = 2 * SCALAR_SIZE;
+
+/// A shielded note, whoever knows it can spend it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Note<F> {
+    pub nullifier: F,
+    pub secret: F,
+}
+
+impl<F: PoseidonField> Note<F> {
+    pub fn random(mut rng: impl RngCore) -> Self {
+        Note {
+            nullifier: F::random(&mut rng),
+            secret: F::random(&mut rng),
+        }
+    }
+
+    /// The leaf a deposit of the note appends to the pool's tree.
+    pub fn commitment(&self) -> F {
+        poseidon::hash(&[self.nullifier, self.secret]).expect("width 3 is supported")
+    }
+
+    /// The value a withdrawal of the note reveals.
+    pub fn nullifier_hash(&self) -> F {
+        poseidon::hash(&[self.nullifier]).expect("width 2 is supported")
+    }
+}
+
+/// Decodes a note made of two scalars in the curve's encoding.
+pub fn read_note<E: Groth16Engine>(b: &[u8]) -> Result<Note<E::Fr>> {
+    if b.len() != NOTE_SIZE {
+        return Err(Error::new(
+            ErrorCode::MalformedInputs,
+            format!("note must be {} bytes, got {}", NOTE_SIZE, b.len()),
+        ));
+    }
+    let scalar = |b| {
+        E::read_scalar(b)
+            .ok_or_else(|| Error::new(ErrorCode::MalformedInputs, "note is not canonical scalars"))
+    };
+    Ok(Note {
+        nullifier: scalar(&b[..SCALAR_SIZE])?,
+        secret: scalar(&b[SCALAR_SIZE..])?,
+    })
+}
+
+pub fn write_note<E: Groth16Engine>(note: &Note<E::Fr>) -> [u8; NOTE_SIZE] {
+    let mut b = [0u8; NOTE_SIZE];
+    b[..SCALAR_SIZE].copy_from_slice(&E::write_scalar(&note.nullifier));
+    b[SCALAR_SIZE..].copy_from_slice(&E::write_scalar(&note.secret));
+    b
+}
+
+/// Hashes withdrawal data to a scalar: its Keccak-256 with the top three
+/// bits cleared, read as a big-endian word. That's cheap to compute on the
+/// EVM and always below both scalar field moduli.
+pub fn external_hash<E: Groth16Engine>(data: &[u8]) -> E::Fr {
+    let mut word: [u8; SCALAR_SIZE] = Keccak256::digest(data).into();
+    word[0] &= 0x1f;
+    E::read_word(&word).expect("253-bit words are canonical")
+}
+
+/// Serializes the witness of the `Spend` circuit spending `note` from the
+/// leaf at `path`.
+pub fn spend_witness(note: &Note<Scalar>, path: &MerklePath<Scalar>, external: &Scalar) -> Vec<u8> {
+    let mut w = Vec::with_capacity((path.siblings.len() + 4) * SCALAR_SIZE);
+    w.extend_from_slice(&write_note::<Bls12>(note));
+    for s in path.siblings.iter() {
+        w.extend_from_slice(&s.to_bytes());
+    }
+    w.extend_from_slice(&Scalar::from(path.index).to_bytes());
+    w.extend_from_slice(&external.to_bytes());
+    w
+}
+
+/// Serializes the public inputs of a `Spend` proof.
+pub fn spend_inputs(root: &Scalar, nullifier_hash: &Scalar, external: &Scalar) -> Vec<u8> {
+    [
+        root.to_bytes(),
+        nullifier_hash.to_bytes(),
+        external.to_bytes(),
+    ]
+    .concat()
+}
+
+fn note_hash<E>(note: &[u8], f: impl Fn(&Note<E::Fr>) -> E::Fr) -> Result<[u8; SCALAR_SIZE]>
+where
+    E: Groth16Engine,
+    E::Fr: PoseidonField,
+{
+    Ok(E::write_scalar(&f(&read_note::<E>(note)?)))
+}
+
+/// Draws a random note over the scalar field of `curve` and writes it to
+/// `note`, `NOTE_SIZE` (64) bytes.
+#[no_mangle]
+pub extern "C" fn note_new(curve: libc::c_int, note: *mut libc::c_uchar) -> libc::c_int {
+    error::status((|| {
+        let b = match Curve::from_raw(curve)? {
+            Curve::Bls12381 => write_note::<Bls12>(&Note::random(OsRng)),
+            Curve::Bn254 => write_note::<Bn256>(&Note::random(OsRng)),
+        };
+        write_out(note, NOTE_SIZE, &b)?;
+        Ok(true)
+    })())
+}
+
+/// Writes the 32-byte commitment of a 64-byte note to `commitment`.
+#[no_mangle]
+pub extern "C" fn note_commitment(
+    curve: libc::c_int,
+    note: *mut libc::c_uchar,
+    commitment: *mut libc::c_uchar,
+) -> libc::c_int {
+    error::status((|| {
+        let note = bytes(note, NOTE_SIZE)?;
+        let c = match Curve::from_raw(curve)? {
+            Curve::Bls12381 => note_hash::<Bls12>(note, Note::commitment)?,
+            Curve::Bn254 => note_hash::<Bn256>(note, Note::commitment)?,
+        };
+        write_out(commitment, SCALAR_SIZE, &c)?;
+        Ok(true)
+    })())
+}
+
+/// Writes the 32-byte nullifier hash of a 64-byte note to `nullifier_hash`.
+#[no_mangle]
+pub extern "C" fn note_nullifier_hash(
+    curve: libc::c_int,
+    note: *mut libc::c_uchar,
+    nullifier_hash: *mut libc::c_uchar,
+) -> libc::c_int {
+    error::status((|| {
+        let note = bytes(note, NOTE_SIZE)?;
+        let h = match Curve::from_raw(curve)? {
+            Curve::Bls12381 => note_hash::<Bls12>(note, Note::nullifier_hash)?,
+            Curve::Bn254 => note_hash::<Bn256>(note, Note::nullifier_hash)?,
+        };
+        write_out(nullifier_hash, SCALAR_SIZE, &h)?;
+        Ok(true)
+    })())
+}
+
+/// Writes the 32-byte `external_hash` of withdrawal data to `hash`.
+#[no_mangle]
+pub extern "C" fn external_data_hash(
+    curve: libc::c_int,
+    data: *mut libc::c_uchar,
+    data_len: libc::size_t,
+    hash: *mut libc::c_uchar,
+) -> libc::c_int {
+    error::status((|| {
+        let data = bytes(data, data_len)?;
+        let h = match Curve::from_raw(curve)? {
+            Curve::Bls12381 => Bls12::write_scalar(&external_hash::<Bls12>(data)),
+            Curve::Bn254 => Bn256::write_scalar(&external_hash::<Bn256>(data)),
+        };
+        write_out(hash, SCALAR_SIZE, &h)?;
+        Ok(true)
+    })())
+}
+
+/// Builds the witness of the `Spend` circuit from a BLS12-381 note, the
+/// path of its commitment as written by `merkle_tree_path` and the
+/// 32-byte external hash. `witness` must hold `path_len + 96` bytes.
+#[no_mangle]
+pub extern "C" fn spend_witness_new(
+    note: *mut libc::c_uchar,
+    path: *mut libc::c_uchar,
+    path_len: libc::size_t,
+    external: *mut libc::c_uchar,
+    witness: *mut libc::c_uchar,
+    witness_len: libc::size_t,
+) -> libc::c_int {
+    error::status((|| {
+        let note = read_note::<Bls12>(bytes(note, NOTE_SIZE)?)?;
+        let path = bytes(path, path_len)?;
+        if path.is_empty() || !path.len().is_multiple_of(SCALAR_SIZE) {
+            return Err(Error::new(
+                ErrorCode::MalformedInputs,
+                format!("malformed Merkle path of {} bytes", path.len()),
+            ));
+        }
+        let external = Bls12::read_scalar(bytes(external, SCALAR_SIZE)?).ok_or_else(|| {
+            Error::new(
+                ErrorCode::MalformedInputs,
+                "external hash is not a canonical scalar",
+            )
+        })?;
+        // The path is checked when the witness is assigned to the circuit.
+        let mut w = write_note::<Bls12>(&note).to_vec();
+        w.extend_from_slice(path);
+        w.extend_from_slice(&external.to_bytes());
+        write_out(witness, witness_len, &w)?;
+        Ok(true)
+    })())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuits::CircuitId;
+    use crate::groth16::verify_groth16;
+    use crate::merkle::MerkleTree;
+    use crate::prover::create_proof;
+    use crate::setup::generate_parameters;
+    use bellman::gadgets::test::TestConstraintSystem;
+    use bellman::Circuit;
+    use ff::Field;
+    use halo2curves::bn256::Fr;
+
+    #[test]
+    fn test_note() {
+        let note = Note::<Fr>::random(OsRng);
+        assert_ne!(note, Note::random(OsRng));
+        assert_ne!(note.commitment(), note.nullifier_hash());
+        let b = write_note::<Bn256>(&note);
+        assert_eq!(note, read_note::<Bn256>(&b).unwrap());
+        assert!(read_note::<Bn256>(&b[1..]).is_err());
+        assert!(read_note::<Bn256>(&[0xff; NOTE_SIZE]).is_err());
+
+        let mut bnote = [0u8; NOTE_SIZE];
+        assert_eq!(1, note_new(Curve::Bn254 as libc::c_int, bnote.as_mut_ptr()));
+        let note = read_note::<Bn256>(&bnote).unwrap();
+        let mut c = [0u8; SCALAR_SIZE];
+        assert_eq!(
+            1,
+            note_commitment(
+                Curve::Bn254 as libc::c_int,
+                bnote.as_mut_ptr(),
+                c.as_mut_ptr()
+            )
+        );
+        assert_eq!(Bn256::write_scalar(&note.commitment()), c);
+        assert_eq!(
+            1,
+            note_nullifier_hash(
+                Curve::Bn254 as libc::c_int,
+                bnote.as_mut_ptr(),
+                c.as_mut_ptr()
+            )
+        );
+        assert_eq!(Bn256::write_scalar(&note.nullifier_hash()), c);
+    }
+
+    #[test]
+    fn test_external_hash() {
+        let mut data = *b"recipient";
+        let h = external_hash::<Bn256>(&data);
+        assert_ne!(h, external_hash::<Bn256>(b"recipient2"));
+        let mut b = [0u8; SCALAR_SIZE];
+        let code = external_data_hash(
+            Curve::Bn254 as libc::c_int,
+            data.as_mut_ptr(),
+            data.len(),
+            b.as_mut_ptr(),
+        );
+        assert_eq!(1, code);
+        assert_eq!(Bn256::write_scalar(&h), b);
+        let mut digest: [u8; SCALAR_SIZE] = Keccak256::digest(data).into();
+        digest[0] &= 0x1f;
+        assert_eq!(digest, b);
+    }
+
+    fn pool(depth: usize) -> (MerkleTree<Scalar>, Note<Scalar>, u64) {
+        let mut tree = MerkleTree::new(depth).unwrap();
+        for _ in 0..3 {
+            tree.append(Note::<Scalar>::random(OsRng).commitment())
+                .unwrap();
+        }
+        let note = Note::random(OsRng);
+        let index = tree.append(note.commitment()).unwrap();
+        (tree, note, index)
+    }
+
+    #[test]
+    fn test_spend_circuit() {
+        let (tree, note, index) = pool(4);
+        let external = external_hash::<Bls12>(b"recipient");
+        let witness = spend_witness(&note, &tree.path(index).unwrap(), &external);
+
+        let mut cs = TestConstraintSystem::new();
+        CircuitId::Spend(4)
+            .assign(&witness)
+            .unwrap()
+            .synthesize(&mut cs)
+            .unwrap();
+        assert!(cs.is_satisfied());
+        assert_eq!(3, cs.num_inputs() - 1);
+        assert_eq!(tree.root(), cs.get_input(1, "root/input variable"));
+        assert_eq!(
+            note.nullifier_hash(),
+            cs.get_input(2, "nullifier hash input/input variable")
+        );
+        assert_eq!(external, cs.get_input(3, "external input/input variable"));
+
+        let mut path = witness[NOTE_SIZE..witness.len() - SCALAR_SIZE].to_vec();
+        let mut bnote = write_note::<Bls12>(&note);
+        let mut bexternal = external.to_bytes();
+        let mut w = vec![0u8; witness.len()];
+        let code = spend_witness_new(
+            bnote.as_mut_ptr(),
+            path.as_mut_ptr(),
+            path.len(),
+            bexternal.as_mut_ptr(),
+            w.as_mut_ptr(),
+            w.len(),
+        );
+        assert_eq!(1, code);
+        assert_eq!(witness, w);
+    }
+
+    #[test]
+    fn test_spend_proof() {
+        let circuit = CircuitId::Spend(4);
+        let params = generate_parameters(circuit, Some(b"test")).unwrap();
+        let mut bkey = vec![];
+        params.vk.write(&mut bkey).unwrap();
+
+        let (tree, note, index) = pool(4);
+        let external = external_hash::<Bls12>(b"recipient");
+        let witness = spend_witness(&note, &tree.path(index).unwrap(), &external);
+        let bproof = create_proof(circuit, &params, &witness, &mut OsRng).unwrap();
+
+        let inputs = spend_inputs(&tree.root(), &note.nullifier_hash(), &external);
+        assert!(verify_groth16::<Bls12>(&bproof, &bkey, &inputs).unwrap());
+        let other = external_hash::<Bls12>(b"thief");
+        let inputs = spend_inputs(&tree.root(), &note.nullifier_hash(), &other);
+        assert!(!verify_groth16::<Bls12>(&bproof, &bkey, &inputs).unwrap());
+        let inputs = spend_inputs(&tree.root(), &Scalar::ZERO, &external);
+        assert!(!verify_groth16::<Bls12>(&bproof, &bkey, &inputs).unwrap());
+    }
+}
@@ -0,0 +1,497 @@
+//! Poseidon hash (<https://eprint.iacr.org/2019/458>) over the BLS12-381 and
+//! BN254 scalar fields, with the x^5 S-box, 8 full rounds and the partial
+//! rounds of the reference parameters for a 128-bit security level. Round
+//! constants and MDS matrices come from the Grain LFSR exactly as the
+//! reference `generate_parameters_grain.sage` script draws them, so on BN254
+//! hashes match circomlib's `Poseidon(n)`.
+//!
+//! A state of width `t` hashes `t - 1` inputs: the state starts as
+//! `0 | inputs`, is permuted and the hash is its first element. Widths 2 to
+//! 16 are supported, so 1 to 15 inputs.
+
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+
+use bellman::gadgets::num::AllocatedNum;
+use bellman::{ConstraintSystem, Index, LinearCombination, SynthesisError, Variable};
+use ff::{PrimeField, PrimeFieldBits};
+use halo2curves::bn256::{Bn256, Fr};
+
+use crate::error::{self, Error, ErrorCode, Result};
+use crate::ffi::bytes;
+use crate::groth16::{read_scalars, Curve, Groth16Engine, SCALAR_SIZE};
+
+/// Smallest supported width.
+pub const MIN_WIDTH: usize = 2;
+/// Largest supported width.
+pub const MAX_WIDTH: usize = 16;
+
+/// Number of full rounds, half of them before the partial rounds.
+const FULL_ROUNDS: usize = 8;
+
+/// Number of partial rounds for widths 2 to 16.
+const PARTIAL_ROUNDS: [usize; MAX_WIDTH - MIN_WIDTH + 1] =
+    [56, 57, 56, 60, 60, 63, 64, 63, 60, 66, 60, 65, 70, 60, 64];
+
+/// Constants of the permutation for one width.
+pub struct Params<F> {
+    t: usize,
+    partial_rounds: usize,
+    /// Round constants, `t` per round.
+    ark: Vec<F>,
+    mds: Vec<Vec<F>>,
+}
+
+impl<F: PrimeFieldBits> Params<F> {
+    /// Draws the constants for width `t` from the Grain LFSR.
+    fn generate(t: usize) -> Self {
+        let partial_rounds = PARTIAL_ROUNDS[t - MIN_WIDTH];
+        let mut grain = Grain::new(F::NUM_BITS, t, partial_rounds);
+        let ark = (0..(FULL_ROUNDS + partial_rounds) * t)
+            .map(|_| grain.next_canonical())
+            .collect();
+        let mds = loop {
+            let xy: Vec<F> = (0..2 * t).map(|_| grain.next_reduced()).collect();
+            if (1..xy.len()).any(|i| xy[..i].contains(&xy[i])) {
+                continue;
+            }
+            let (xs, ys) = xy.split_at(t);
+            let m: Option<Vec<Vec<F>>> = xs
+                .iter()
+                .map(|x| ys.iter().map(|y| Option::from((*x + y).invert())).collect())
+                .collect();
+            if let Some(m) = m {
+                break m;
+            }
+        };
+        Params {
+            t,
+            partial_rounds,
+            ark,
+            mds,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.t
+    }
+
+    fn rounds(&self) -> usize {
+        FULL_ROUNDS + self.partial_rounds
+    }
+
+    fn is_full_round(&self, r: usize) -> bool {
+        r < FULL_ROUNDS / 2 || r >= FULL_ROUNDS / 2 + self.partial_rounds
+    }
+}
+
+/// The Grain LFSR of the reference parameter generation, seeded with the
+/// field, S-box, field size, width and round numbers.
+struct Grain {
+    /// 80-bit state, bit `i` being the `i`-th oldest bit.
+    state: u128,
+    bits: u32,
+}
+
+impl Grain {
+    fn new(bits: u32, t: usize, partial_rounds: usize) -> Self {
+        let fields: [(u64, u32); 6] = [
+            (1, 2), // prime field
+            (0, 4), // x^alpha S-box
+            (bits as u64, 12),
+            (t as u64, 12),
+            (FULL_ROUNDS as u64, 10),
+            (partial_rounds as u64, 10),
+        ];
+        let mut init = vec![];
+        for (value, width) in fields {
+            init.extend((0..width).rev().map(|i| (value >> i) & 1 == 1));
+        }
+        init.resize(80, true);
+        let state = init
+            .iter()
+            .enumerate()
+            .fold(0u128, |s, (i, &b)| s | ((b as u128) << i));
+        let mut grain = Grain { state, bits };
+        for _ in 0..160 {
+            grain.step();
+        }
+        grain
+    }
+
+    fn step(&mut self) -> bool {
+        let s = self.state;
+        let bit = ((s >> 62) ^ (s >> 51) ^ (s >> 38) ^ (s >> 23) ^ (s >> 13) ^ s) & 1;
+        self.state = (s >> 1) | (bit << 79);
+        bit == 1
+    }
+
+    /// Output bits are the second of each pair of steps whose first is set.
+    fn next_bit(&mut self) -> bool {
+        loop {
+            let keep = self.step();
+            let bit = self.step();
+            if keep {
+                return bit;
+            }
+        }
+    }
+
+    /// Draws a field-size integer, most significant bit first.
+    fn next_bits(&mut self) -> Vec<bool> {
+        let mut bits: Vec<bool> = (0..self.bits).map(|_| self.next_bit()).collect();
+        bits.reverse();
+        bits
+    }
+
+    /// Draws an integer below the modulus by rejection sampling.
+    fn next_canonical<F: PrimeFieldBits>(&mut self) -> F {
+        loop {
+            let bits = self.next_bits();
+            let f: F = from_le_bits(&bits);
+            if f.to_le_bits().iter().by_vals().eq(bits
+                .iter()
+                .copied()
+                .chain(std::iter::repeat(false))
+                .take(f.to_le_bits().len()))
+            {
+                return f;
+            }
+        }
+    }
+
+    /// Draws an integer reduced modulo the field modulus.
+    fn next_reduced<F: PrimeFieldBits>(&mut self) -> F {
+        from_le_bits(&self.next_bits())
+    }
+}
+
+fn from_le_bits<F: PrimeField>(bits: &[bool]) -> F {
+    bits.iter().rev().fold(F::ZERO, |acc, &b| {
+        let acc = acc.double();
+        if b {
+            acc + F::ONE
+        } else {
+            acc
+        }
+    })
+}
+
+/// Scalar fields with Poseidon constants, generated on first use and shared
+/// afterwards.
+pub trait PoseidonField: PrimeFieldBits {
+    fn params(t: usize) -> &'static Params<Self>;
+}
+
+type Cache<F> = [OnceLock<Params<F>>; MAX_WIDTH - MIN_WIDTH + 1];
+
+macro_rules! poseidon_field {
+    ($f:ty) => {
+        impl PoseidonField for $f {
+            fn params(t: usize) -> &'static Params<Self> {
+                static CACHE: Cache<$f> = [const { OnceLock::new() }; MAX_WIDTH - MIN_WIDTH + 1];
+                CACHE[t - MIN_WIDTH].get_or_init(|| Params::generate(t))
+            }
+        }
+    };
+}
+
+poseidon_field!(bls12_381::Scalar);
+poseidon_field!(Fr);
+
+fn width_for(inputs: usize) -> Result<usize> {
+    let t = inputs + 1;
+    if !(MIN_WIDTH..=MAX_WIDTH).contains(&t) {
+        return Err(Error::new(
+            ErrorCode::MalformedInputs,
+            format!(
+                "Poseidon takes {} to {} inputs, got {}",
+                MIN_WIDTH - 1,
+                MAX_WIDTH - 1,
+                inputs
+            ),
+        ));
+    }
+    Ok(t)
+}
+
+/// Applies the Poseidon permutation to a state of a supported width.
+pub fn permute<F: PoseidonField>(state: &mut [F]) {
+    let p = F::params(state.len());
+    for r in 0..p.rounds() {
+        for (s, c) in state.iter_mut().zip(&p.ark[r * p.t..]) {
+            *s += c;
+        }
+        let sboxes = if p.is_full_round(r) { p.t } else { 1 };
+        for s in state[..sboxes].iter_mut() {
+            *s = s.square().square() * *s;
+        }
+        let mixed: Vec<F> = p
+            .mds
+            .iter()
+            .map(|row| row.iter().zip(state.iter()).map(|(m, s)| *m * s).sum())
+            .collect();
+        state.copy_from_slice(&mixed);
+    }
+}
+
+/// Hashes 1 to 15 field elements.
+pub fn hash<F: PoseidonField>(inputs: &[F]) -> Result<F> {
+    let mut state = vec![F::ZERO; width_for(inputs.len())?];
+    state[1..].copy_from_slice(inputs);
+    permute(&mut state);
+    Ok(state[0])
+}
+
+/// A state element in a circuit, a linear combination of variables along
+/// with its value. Terms are merged by variable, bellman's
+/// `LinearCombination` would keep duplicates and grow exponentially through
+/// the MDS mixes.
+#[derive(Clone)]
+struct Elt<F: PrimeField> {
+    terms: BTreeMap<(bool, usize), (Variable, F)>,
+    value: Option<F>,
+}
+
+impl<F: PrimeField> Elt<F> {
+    fn zero() -> Self {
+        Elt {
+            terms: BTreeMap::new(),
+            value: Some(F::ZERO),
+        }
+    }
+
+    fn var(var: Variable, value: Option<F>) -> Self {
+        let mut e = Elt {
+            terms: BTreeMap::new(),
+            value,
+        };
+        e.add_term(var, F::ONE);
+        e
+    }
+
+    fn add_term(&mut self, var: Variable, coeff: F) {
+        let key = match var.get_unchecked() {
+            Index::Input(i) => (false, i),
+            Index::Aux(i) => (true, i),
+        };
+        self.terms.entry(key).or_insert((var, F::ZERO)).1 += coeff;
+    }
+
+    fn add_constant<CS: ConstraintSystem<F>>(&mut self, c: F) {
+        self.add_term(CS::one(), c);
+        self.value = self.value.map(|v| v + c);
+    }
+
+    /// Adds `m * other` to the element.
+    fn add_scaled(&mut self, m: F, other: &Self) {
+        for (var, c) in other.terms.values() {
+            self.add_term(*var, m * c);
+        }
+        self.value = self.value.zip(other.value).map(|(a, v)| a + m * v);
+    }
+
+    fn lc(&self) -> LinearCombination<F> {
+        self.terms
+            .values()
+            .fold(LinearCombination::zero(), |lc, (var, c)| lc + (*c, *var))
+    }
+
+    fn sbox<CS: ConstraintSystem<F>>(
+        &self,
+        mut cs: CS,
+    ) -> std::result::Result<Self, SynthesisError> {
+        let x2 = self.value.map(|v| v.square());
+        let x4 = x2.map(|v| v.square());
+        let x5 = x4.zip(self.value).map(|(a, b)| a * b);
+        let v2 = cs.alloc(|| "x^2", || x2.ok_or(SynthesisError::AssignmentMissing))?;
+        let v4 = cs.alloc(|| "x^4", || x4.ok_or(SynthesisError::AssignmentMissing))?;
+        let v5 = cs.alloc(|| "x^5", || x5.ok_or(SynthesisError::AssignmentMissing))?;
+        let x = self.lc();
+        cs.enforce(|| "x * x", |_| x.clone(), |_| x.clone(), |lc| lc + v2);
+        cs.enforce(|| "x^2 * x^2", |lc| lc + v2, |lc| lc + v2, |lc| lc + v4);
+        cs.enforce(|| "x^4 * x", |lc| lc + v4, |_| x, |lc| lc + v5);
+        Ok(Elt::var(v5, x5))
+    }
+}
+
+/// Constrains the Poseidon hash of 1 to 15 allocated numbers, with the same
+/// constants and output as `hash`. A full round costs `3 * t` constraints and
+/// a partial round 3.
+pub fn gadget<F, CS>(
+    mut cs: CS,
+    inputs: &[AllocatedNum<F>],
+) -> std::result::Result<AllocatedNum<F>, SynthesisError>
+where
+    F: PoseidonField,
+    CS: ConstraintSystem<F>,
+{
+    let t = width_for(inputs.len()).map_err(|_| SynthesisError::Unsatisfiable)?;
+    let p = F::params(t);
+    let mut state = vec![Elt::zero()];
+    state.extend(
+        inputs
+            .iter()
+            .map(|n| Elt::var(n.get_variable(), n.get_value())),
+    );
+    for r in 0..p.rounds() {
+        for (s, c) in state.iter_mut().zip(&p.ark[r * t..]) {
+            s.add_constant::<CS>(*c);
+        }
+        let sboxes = if p.is_full_round(r) { t } else { 1 };
+        for (i, s) in state[..sboxes].iter_mut().enumerate() {
+            *s = s.sbox(cs.namespace(|| format!("round {} sbox {}", r, i)))?;
+        }
+        state = p
+            .mds
+            .iter()
+            .map(|row| {
+                let mut e = Elt::zero();
+                for (m, s) in row.iter().zip(state.iter()) {
+                    e.add_scaled(*m, s);
+                }
+                e
+            })
+            .collect();
+    }
+    let out = AllocatedNum::alloc(cs.namespace(|| "hash"), || {
+        state[0].value.ok_or(SynthesisError::AssignmentMissing)
+    })?;
+    cs.enforce(
+        || "hash = state[0]",
+        |_| state[0].lc(),
+        |lc| lc + CS::one(),
+        |lc| lc + out.get_variable(),
+    );
+    Ok(out)
+}
+
+fn hash_encoded<E>(binputs: &[u8]) -> Result<[u8; SCALAR_SIZE]>
+where
+    E: Groth16Engine,
+    E::Fr: PoseidonField,
+{
+    let inputs = read_scalars::<E>(binputs)?;
+    Ok(E::write_scalar(&hash(&inputs)?))
+}
+
+/// Hashes 1 to 15 scalars of the given `Curve`, concatenated in `inputs` in
+/// the curve's encoding, and writes the 32-byte hash in the same encoding to
+/// `output`.
+#[no_mangle]
+pub extern "C" fn poseidon(
+    curve: libc::c_int,
+    inputs: *mut libc::c_uchar,
+    inputs_len: libc::size_t,
+    output: *mut libc::c_uchar,
+) -> libc::c_int {
+    error::status((|| {
+        let curve = Curve::from_raw(curve)?;
+        if output.is_null() {
+            return Err(Error::new(ErrorCode::NullPointer, "null output buffer"));
+        }
+        let binputs = bytes(inputs, inputs_len)?;
+        let h = match curve {
+            Curve::Bls12381 => hash_encoded::<bls12_381::Bls12>(binputs)?,
+            Curve::Bn254 => hash_encoded::<Bn256>(binputs)?,
+        };
+        unsafe { std::ptr::copy_nonoverlapping(h.as_ptr(), output, SCALAR_SIZE) };
+        Ok(true)
+    })())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bn254;
+    use bellman::gadgets::test::TestConstraintSystem;
+
+    fn bn(hex: &str) -> Fr {
+        let mut b = [0u8; SCALAR_SIZE];
+        for (i, byte) in b.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).unwrap();
+        }
+        bn254::read_fr(&b).unwrap()
+    }
+
+    fn fr(values: &[u64]) -> Vec<Fr> {
+        values.iter().map(|&v| Fr::from(v)).collect()
+    }
+
+    #[test]
+    fn test_circomlib_vectors() {
+        assert_eq!(
+            bn("115cc0f5e7d690413df64c6b9662e9cf2a3617f2743245519e19607a4417189a"),
+            hash(&fr(&[1, 2])).unwrap()
+        );
+        assert_eq!(
+            bn("29176100eaa962bdc1fe6c654d6a3c130e96a4d1168b33848b897dc502820133"),
+            hash(&fr(&[1])).unwrap()
+        );
+        assert_eq!(
+            bn("299c867db6c1fdd79dcefa40e4510b9837e60ebb1ce0663dbaa525df65250465"),
+            hash(&fr(&[1, 2, 3, 4])).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_widths() {
+        assert!(hash::<Fr>(&[]).is_err());
+        assert!(hash(&fr(&[0; MAX_WIDTH])).is_err());
+        for n in 1..MAX_WIDTH {
+            let inputs: Vec<_> = (0..n as u64).map(bls12_381::Scalar::from).collect();
+            let mut shifted = inputs.clone();
+            shifted[0] += bls12_381::Scalar::one();
+            assert_ne!(hash(&inputs).unwrap(), hash(&shifted).unwrap());
+        }
+    }
+
+    fn check_gadget<F: PoseidonField>(inputs: &[F]) {
+        let mut cs = TestConstraintSystem::<F>::new();
+        let nums: Vec<_> = inputs
+            .iter()
+            .enumerate()
+            .map(|(i, v)| {
+                AllocatedNum::alloc(cs.namespace(|| format!("in {}", i)), || Ok(*v)).unwrap()
+            })
+            .collect();
+        let out = gadget(cs.namespace(|| "poseidon"), &nums).unwrap();
+        assert!(cs.is_satisfied());
+        assert_eq!(hash(inputs).unwrap(), out.get_value().unwrap());
+        let p = F::params(inputs.len() + 1);
+        assert_eq!(
+            3 * (FULL_ROUNDS * p.t + p.partial_rounds) + 1,
+            cs.num_constraints()
+        );
+    }
+
+    #[test]
+    fn test_gadget() {
+        check_gadget(&fr(&[1, 2]));
+        check_gadget(&fr(&[5, 6, 7, 8, 9, 10, 11, 12]));
+        check_gadget(&[bls12_381::Scalar::from(3)]);
+        check_gadget(&[bls12_381::Scalar::from(3), -bls12_381::Scalar::one()]);
+    }
+
+    #[test]
+    fn test_ffi() {
+        let mut inputs = [bn254::write_fr(&Fr::from(1)), bn254::write_fr(&Fr::from(2))].concat();
+        let mut out = [0u8; SCALAR_SIZE];
+        let code = poseidon(
+            Curve::Bn254 as libc::c_int,
+            inputs.as_mut_ptr(),
+            inputs.len(),
+            out.as_mut_ptr(),
+        );
+        assert_eq!(1, code);
+        assert_eq!(hash(&fr(&[1, 2])).unwrap(), bn254::read_fr(&out).unwrap());
+
+        let code = poseidon(
+            Curve::Bls12381 as libc::c_int,
+            inputs.as_mut_ptr(),
+            inputs.len() - 1,
+            out.as_mut_ptr(),
+        );
+        assert_eq!(ErrorCode::MalformedInputs as libc::c_int, code);
+    }
+}
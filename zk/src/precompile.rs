@@ -0,0 +1,376 @@
+//! Groth16 verification shaped as an EVM precompiled contract. The input is
+//! the ABI encoding, without a function selector, of
+//!
+//! ```text
+//! (uint256 curve, bytes key, bytes proof, uint256[] inputs)
+//! ```
+//!
+//! where `curve` is a `Curve` value, `key` is either the 32-byte id of a key
+//! registered with `register_key` or a whole serialized verifying key, `proof`
+//! is a serialized proof and `inputs` are the public inputs as big-endian
+//! words, whatever the curve. Keys and proofs use the curve's encoding, see
+//! `Groth16Engine`. The output is a 32-byte word set to 1 if the proof is
+//! valid and 0 otherwise.
+//!
+//! An inline key must declare one IC point more than there are inputs, which
+//! is checked on its header before any point is decoded, and its points are
+//! charged for on top of the verification.
+
+use bls12_381::Bls12;
+use halo2curves::bn256::Bn256;
+
+use crate::error::{self, Error, ErrorCode, Result};
+use crate::ffi::bytes;
+use crate::gas::{groth16_gas, key_gas};
+use crate::groth16::{
+    key_ic_count, read_proof, registered_key, Curve, Groth16Engine, KeyHandle, PreparedKey,
+    KEY_ID_SIZE,
+};
+
+/// Size of an ABI word and of the output.
+pub const WORD_SIZE: usize = 32;
+
+/// Gas charged for an input that can't be parsed, enough to pay for looking
+/// at it.
+const MALFORMED_GAS: u64 = 3000;
+
+/// Decoded precompile call, borrowing from the calldata.
+pub struct Call<'a> {
+    pub curve: Curve,
+    pub key: &'a [u8],
+    pub proof: &'a [u8],
+    pub inputs: Vec<&'a [u8]>,
+}
+
+impl<'a> Call<'a> {
+    pub fn decode(input: &'a [u8]) -> Result<Self> {
+        let curve = word(input, 0)?;
+        if curve[..WORD_SIZE - 1].iter().any(|&b| b != 0) {
+            return Err(malformed("curve is out of range"));
+        }
+        let curve = Curve::from_raw(curve[WORD_SIZE - 1] as libc::c_int)?;
+        let key = dynamic_bytes(input, offset(input, 1)?)?;
+        let proof = dynamic_bytes(input, offset(input, 2)?)?;
+        let inputs = words(input, offset(input, 3)?)?;
+        Ok(Call {
+            curve,
+            key,
+            proof,
+            inputs,
+        })
+    }
+
+    /// Whether the call refers to a registered key rather than carrying one.
+    pub fn by_id(&self) -> bool {
+        self.key.len() == KEY_ID_SIZE
+    }
+
+    pub fn run(&self) -> Result<bool> {
+        if self.by_id() {
+            let kh = registered_key(self.key).ok_or_else(|| {
+                Error::new(ErrorCode::UnknownKey, "verifying key is not registered")
+            })?;
+            if kh.curve() != self.curve {
+                return Err(Error::new(
+                    ErrorCode::UnsupportedCurve,
+                    format!("registered key is for {:?}", kh.curve()),
+                ));
+            }
+            return match kh.as_ref() {
+                KeyHandle::Bls12381(pk) => self.verify(pk),
+                KeyHandle::Bn254(pk) => self.verify(pk),
+            };
+        }
+        // Counting the IC points first keeps a key far bigger than the call
+        // pays for from being decoded.
+        let ic = match self.curve {
+            Curve::Bls12381 => key_ic_count::<Bls12>(self.key)?,
+            Curve::Bn254 => key_ic_count::<Bn256>(self.key)?,
+        };
+        if ic != self.inputs.len() + 1 {
+            return Err(Error::new(
+                ErrorCode::InputCountMismatch,
+                format!(
+                    "verifying key has {} IC points for {} public inputs",
+                    ic,
+                    self.inputs.len()
+                ),
+            ));
+        }
+        match KeyHandle::read(self.curve, self.key)? {
+            KeyHandle::Bls12381(pk) => self.verify(&pk),
+            KeyHandle::Bn254(pk) => self.verify(&pk),
+        }
+    }
+
+    fn verify<E: Groth16Engine>(&self, pk: &PreparedKey<E>) -> Result<bool> {
+        let proof = read_proof::<E>(self.proof)?;
+        let inputs = self
+            .inputs
+            .iter()
+            .map(|w| {
+                E::read_word(w).ok_or_else(|| {
+                    Error::new(
+                        ErrorCode::MalformedInputs,
+                        "public input is not a canonical scalar",
+                    )
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        pk.verify_inputs(&proof, &inputs)
+    }
+
+    /// Gas of a single verification, see the `gas` module. An inline key is
+    /// charged for decoding it with the IC points it must have to be accepted.
+    pub fn gas(&self) -> u64 {
+        let inputs = self.inputs.len() as u64;
+        let gas = groth16_gas(self.curve, inputs, 1);
+        match self.by_id() {
+            true => gas,
+            false => gas.saturating_add(key_gas(self.curve, inputs.saturating_add(1))),
+        }
+    }
+}
+
+fn malformed(msg: &str) -> Error {
+    Error::new(
+        ErrorCode::MalformedInputs,
+        format!("malformed calldata: {}", msg),
+    )
+}
+
+fn word(input: &[u8], at: usize) -> Result<&[u8]> {
+    let start = at
+        .checked_mul(WORD_SIZE)
+        .ok_or_else(|| malformed("offset overflow"))?;
+    input
+        .get(start..start + WORD_SIZE)
+        .ok_or_else(|| malformed("input is too short"))
+}
+
+/// Reads a word used as a length or an offset, rejecting values that can't
+/// possibly fit in the input.
+fn usize_word(input: &[u8], at: usize) -> Result<usize> {
+    let w = word(input, at)?;
+    if w[..WORD_SIZE - 8].iter().any(|&b| b != 0) {
+        return Err(malformed("length or offset is out of range"));
+    }
+    let mut n = [0u8; 8];
+    n.copy_from_slice(&w[WORD_SIZE - 8..]);
+    usize::try_from(u64::from_be_bytes(n))
+        .ok()
+        .filter(|&n| n <= input.len())
+        .ok_or_else(|| malformed("length or offset is out of range"))
+}
+
+/// Reads the head word pointing to a dynamic argument, checking it's word
+/// aligned.
+fn offset(input: &[u8], at: usize) -> Result<usize> {
+    let off = usize_word(input, at)?;
+    if !off.is_multiple_of(WORD_SIZE) {
+        return Err(malformed("unaligned offset"));
+    }
+    Ok(off / WORD_SIZE)
+}
+
+fn dynamic_bytes(input: &[u8], at: usize) -> Result<&[u8]> {
+    let len = usize_word(input, at)?;
+    let start = (at + 1) * WORD_SIZE;
+    input
+        .get(start..start + len)
+        .ok_or_else(|| malformed("bytes run past the end of the input"))
+}
+
+fn words(input: &[u8], at: usize) -> Result<Vec<&[u8]>> {
+    let len = usize_word(input, at)?;
+    (0..len).map(|i| word(input, at + 1 + i)).collect()
+}
+
+/// Runs the precompile on ABI-encoded `input`, writing the 32-byte result to
+/// `output`. Returns `Ok` or `Invalid` along with the result word; a negative
+/// code means the call reverts and `output` is left untouched.
+#[no_mangle]
+pub extern "C" fn groth16_precompile(
+    input: *mut libc::c_uchar,
+    input_len: libc::size_t,
+    output: *mut libc::c_uchar,
+) -> libc::c_int {
+    error::status((|| {
+        if output.is_null() {
+            return Err(Error::new(ErrorCode::NullPointer, "null output buffer"));
+        }
+        let ok = Call::decode(bytes(input, input_len)?)?.run()?;
+        let mut word = [0u8; WORD_SIZE];
+        word[WORD_SIZE - 1] = ok as u8;
+        unsafe { std::ptr::copy_nonoverlapping(word.as_ptr(), output, WORD_SIZE) };
+        Ok(ok)
+    })())
+}
+
+/// Returns the gas `groth16_precompile` requires for `input`. It only looks
+/// at the curve, the number of public inputs and whether the key is inline,
+/// so it's cheap; input that can't be parsed is charged a flat fee.
+#[no_mangle]
+pub extern "C" fn groth16_precompile_gas(
+    input: *mut libc::c_uchar,
+    input_len: libc::size_t,
+) -> u64 {
+    bytes(input, input_len)
+        .and_then(Call::decode)
+        .map(|call| call.gas())
+        .unwrap_or(MALFORMED_GAS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::groth16::register_key;
+    use crate::groth16::tests::{bn254_inputs, simulate_bn254};
+
+    fn pad(b: &[u8]) -> Vec<u8> {
+        let mut v = b.to_vec();
+        v.resize(b.len().div_ceil(WORD_SIZE) * WORD_SIZE, 0);
+        v
+    }
+
+    fn uint(n: usize) -> [u8; WORD_SIZE] {
+        let mut w = [0u8; WORD_SIZE];
+        w[WORD_SIZE - 8..].copy_from_slice(&(n as u64).to_be_bytes());
+        w
+    }
+
+    fn encode(curve: Curve, key: &[u8], proof: &[u8], inputs: &[u8]) -> Vec<u8> {
+        let key_off = 4 * WORD_SIZE;
+        let proof_off = key_off + WORD_SIZE + pad(key).len();
+        let inputs_off = proof_off + WORD_SIZE + pad(proof).len();
+        let mut v = vec![];
+        v.extend_from_slice(&uint(curve as usize));
+        v.extend_from_slice(&uint(key_off));
+        v.extend_from_slice(&uint(proof_off));
+        v.extend_from_slice(&uint(inputs_off));
+        v.extend_from_slice(&uint(key.len()));
+        v.extend(pad(key));
+        v.extend_from_slice(&uint(proof.len()));
+        v.extend(pad(proof));
+        v.extend_from_slice(&uint(inputs.len() / WORD_SIZE));
+        v.extend_from_slice(inputs);
+        v
+    }
+
+    fn run(mut input: Vec<u8>) -> (libc::c_int, [u8; WORD_SIZE]) {
+        let mut out = [0xffu8; WORD_SIZE];
+        let code = groth16_precompile(input.as_mut_ptr(), input.len(), out.as_mut_ptr());
+        (code, out)
+    }
+
+    #[test]
+    fn test_precompile_inline_key() {
+        let (key, proof) = simulate_bn254(&[4, 9]);
+        let input = encode(Curve::Bn254, &key, &proof, &bn254_inputs(&[4, 9]));
+        assert_eq!((1, uint(1)), run(input));
+
+        let input = encode(Curve::Bn254, &key, &proof, &bn254_inputs(&[4, 8]));
+        assert_eq!((0, uint(0)), run(input));
+
+        let input = encode(Curve::Bn254, &key, &proof, &bn254_inputs(&[4]));
+        assert_eq!(ErrorCode::InputCountMismatch as libc::c_int, run(input).0);
+    }
+
+    #[test]
+    fn test_precompile_oversized_key() {
+        // A header declaring thousands of IC points followed by bytes that
+        // aren't points at all: the count is rejected before any decoding,
+        // which would report a malformed key.
+        let (key, proof) = simulate_bn254(&[]);
+        let header = Bn256::KEY_HEADER_SIZE;
+        assert_eq!(1, key_ic_count::<Bn256>(&key).unwrap());
+        let mut big = key[..header].to_vec();
+        big.extend_from_slice(&5000u32.to_be_bytes());
+        big.extend(vec![0xff; 5000 * crate::bn254::G1_SIZE]);
+        let input = encode(Curve::Bn254, &big, &proof, &[]);
+        assert_eq!(ErrorCode::InputCountMismatch as libc::c_int, run(input).0);
+        let mut fixed = key[..header].to_vec();
+        fixed.extend_from_slice(&1u32.to_be_bytes());
+        fixed.extend(vec![0xff; crate::bn254::G1_SIZE]);
+        let input = encode(Curve::Bn254, &fixed, &proof, &[]);
+        assert_eq!(ErrorCode::MalformedKey as libc::c_int, run(input).0);
+
+        let params = crate::groth16::tests::parameters();
+        let mut bkey = vec![];
+        params.vk.write(&mut bkey).unwrap();
+        assert_eq!(params.vk.ic.len(), key_ic_count::<Bls12>(&bkey).unwrap());
+        let input = encode(
+            Curve::Bls12381,
+            &bkey[..Bls12::KEY_HEADER_SIZE],
+            &proof,
+            &[],
+        );
+        assert_eq!(ErrorCode::MalformedKey as libc::c_int, run(input).0);
+    }
+
+    #[test]
+    fn test_precompile_registered_key() {
+        let (mut key, proof) = simulate_bn254(&[6]);
+        let mut id = [0u8; KEY_ID_SIZE];
+        let code = register_key(
+            Curve::Bn254 as libc::c_int,
+            key.as_mut_ptr(),
+            key.len(),
+            id.as_mut_ptr(),
+        );
+        assert_eq!(1, code);
+        assert_eq!(blake2s_simd::blake2s(&key).as_bytes(), &id);
+
+        let input = encode(Curve::Bn254, &id, &proof, &bn254_inputs(&[6]));
+        assert_eq!((1, uint(1)), run(input));
+
+        let input = encode(Curve::Bls12381, &id, &proof, &bn254_inputs(&[6]));
+        assert_eq!(ErrorCode::UnsupportedCurve as libc::c_int, run(input).0);
+
+        assert_eq!(1, crate::groth16::unregister_key(id.as_mut_ptr()));
+        let input = encode(Curve::Bn254, &id, &proof, &bn254_inputs(&[6]));
+        assert_eq!(ErrorCode::UnknownKey as libc::c_int, run(input).0);
+        assert_eq!(
+            ErrorCode::UnknownKey as libc::c_int,
+            crate::groth16::unregister_key(id.as_mut_ptr())
+        );
+    }
+
+    #[test]
+    fn test_precompile_malformed() {
+        let (key, proof) = simulate_bn254(&[4, 9]);
+        let input = encode(Curve::Bn254, &key, &proof, &bn254_inputs(&[4, 9]));
+
+        let (code, out) = run(input[..input.len() - 1].to_vec());
+        assert_eq!(ErrorCode::MalformedInputs as libc::c_int, code);
+        assert_eq!([0xff; WORD_SIZE], out);
+
+        let mut bad_offset = input.clone();
+        bad_offset[2 * WORD_SIZE - 1] += 1;
+        assert_eq!(ErrorCode::MalformedInputs as libc::c_int, run(bad_offset).0);
+
+        let mut huge_len = input.clone();
+        huge_len[4 * WORD_SIZE] = 1;
+        assert_eq!(ErrorCode::MalformedInputs as libc::c_int, run(huge_len).0);
+
+        let mut bad_curve = input;
+        bad_curve[WORD_SIZE - 1] = 7;
+        assert_eq!(ErrorCode::UnsupportedCurve as libc::c_int, run(bad_curve).0);
+    }
+
+    #[test]
+    fn test_precompile_gas() {
+        let (key, proof) = simulate_bn254(&[4, 9]);
+        let mut input = encode(Curve::Bn254, &key, &proof, &bn254_inputs(&[4, 9]));
+        let gas = groth16_precompile_gas(input.as_mut_ptr(), input.len());
+        assert_eq!(98100 + 2 * 9400 + 167400 + 3 * 100, gas);
+        let id = blake2s_simd::blake2s(&key);
+        let mut input = encode(Curve::Bn254, id.as_bytes(), &proof, &bn254_inputs(&[4, 9]));
+        let gas = groth16_precompile_gas(input.as_mut_ptr(), input.len());
+        assert_eq!(98100 + 2 * 9400, gas);
+        assert_eq!(
+            MALFORMED_GAS,
+            groth16_precompile_gas(std::ptr::null_mut(), 0)
+        );
+    }
+}
@@ -0,0 +1,132 @@
+//! Groth16 proof generation for the built-in circuits. Proofs are on
+//! BLS12-381 and come out in the encoding `verify` consumes.
+
+use bellman::groth16::{create_random_proof, Parameters};
+use bls12_381::Bls12;
+use rand_core::{CryptoRng, OsRng, RngCore};
+
+use crate::circuits::CircuitId;
+use crate::error::{self, Error, ErrorCode, Result};
+use crate::ffi::{bytes, write_out};
+use crate::groth16::Groth16Engine;
+
+/// Decodes proving parameters, checking that every point is on the curve
+/// and in the right subgroup.
+pub fn read_parameters(bparams: &[u8]) -> Result<Parameters<Bls12>> {
+    Parameters::read(bparams, true).map_err(|e| {
+        Error::new(
+            ErrorCode::MalformedParams,
+            format!("malformed proving parameters: {}", e),
+        )
+    })
+}
+
+/// Proves `circuit` for a serialized witness, see the `circuits` module for
+/// its encoding.
+pub fn create_proof<R: RngCore + CryptoRng>(
+    circuit: CircuitId,
+    params: &Parameters<Bls12>,
+    witness: &[u8],
+    rng: &mut R,
+) -> Result<Vec<u8>> {
+    let proof = create_random_proof(circuit.assign(witness)?, params, rng).map_err(|e| {
+        Error::new(
+            ErrorCode::MalformedParams,
+            format!("can't prove with these parameters: {}", e),
+        )
+    })?;
+    Ok(Bls12::write_proof(&proof))
+}
+
+/// Creates a proof for one of the built-in circuits from serialized proving
+/// parameters and a witness, writing it to `proof`, which must be
+/// `Bls12::PROOF_SIZE` (192) bytes. The prover doesn't check the witness
+/// satisfies the circuit, a wrong one yields a proof that doesn't verify.
+#[no_mangle]
+pub extern "C" fn prove(
+    circuit: libc::c_int,
+    params: *mut libc::c_uchar,
+    params_len: libc::size_t,
+    witness: *mut libc::c_uchar,
+    witness_len: libc::size_t,
+    proof: *mut libc::c_uchar,
+    proof_len: libc::size_t,
+) -> libc::c_int {
+    error::status((|| {
+        let circuit = CircuitId::from_raw(circuit)?;
+        let params = read_parameters(bytes(params, params_len)?)?;
+        let bproof = create_proof(circuit, &params, bytes(witness, witness_len)?, &mut OsRng)?;
+        write_out(proof, proof_len, &bproof)?;
+        Ok(true)
+    })())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::groth16::tests::parameters;
+    use crate::groth16::verify_groth16;
+    use bls12_381::Scalar;
+
+    fn witness(a: u64, b: u64) -> Vec<u8> {
+        [Scalar::from(a).to_bytes(), Scalar::from(b).to_bytes()].concat()
+    }
+
+    #[test]
+    fn test_prove() {
+        let params = parameters();
+        let mut bparams = vec![];
+        params.write(&mut bparams).unwrap();
+        let mut bkey = vec![];
+        params.vk.write(&mut bkey).unwrap();
+
+        let mut w = witness(3, 7);
+        let mut bproof = [0u8; 192];
+        let code = prove(
+            CircuitId::Multiply.to_raw(),
+            bparams.as_mut_ptr(),
+            bparams.len(),
+            w.as_mut_ptr(),
+            w.len(),
+            bproof.as_mut_ptr(),
+            bproof.len(),
+        );
+        assert_eq!(1, code);
+        let c = Scalar::from(21).to_bytes();
+        assert!(verify_groth16::<Bls12>(&bproof, &bkey, &c).unwrap());
+        let c = Scalar::from(20).to_bytes();
+        assert!(!verify_groth16::<Bls12>(&bproof, &bkey, &c).unwrap());
+
+        let code = prove(
+            CircuitId::Multiply.to_raw(),
+            bparams.as_mut_ptr(),
+            bparams.len(),
+            w.as_mut_ptr(),
+            w.len(),
+            bproof.as_mut_ptr(),
+            bproof.len() - 1,
+        );
+        assert_eq!(ErrorCode::BufferTooSmall as libc::c_int, code);
+        let mut large = [0u8; 193];
+        let code = prove(
+            CircuitId::Multiply.to_raw(),
+            bparams.as_mut_ptr(),
+            bparams.len(),
+            w.as_mut_ptr(),
+            w.len(),
+            large.as_mut_ptr(),
+            large.len(),
+        );
+        assert_eq!(ErrorCode::BufferTooSmall as libc::c_int, code);
+    }
+
+    #[test]
+    fn test_prove_malformed_params() {
+        let e = create_proof(CircuitId::Multiply, &parameters(), &[1], &mut OsRng)
+            .err()
+            .unwrap();
+        assert_eq!(ErrorCode::MalformedWitness, e.code());
+        let e = read_parameters(&[1, 2, 3]).err().unwrap();
+        assert_eq!(ErrorCode::MalformedParams, e.code());
+    }
+}
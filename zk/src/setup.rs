@@ -0,0 +1,103 @@
+//! Circuit-specific trusted setup for the built-in circuits. The proving
+//! parameters are what `prove` takes and the verifying key is what `verify`
+//! and `prepare_key` take for `Curve::Bls12381`.
+//!
+//! A seeded setup is reproducible, which is handy for devnets and privnets,
+//! but anyone knowing the seed can forge proofs: never use one where proofs
+//! protect anything of value.
+
+use std::fs;
+use std::path::Path;
+
+use bellman::groth16::{generate_random_parameters, Parameters};
+use bls12_381::Bls12;
+use rand_chacha::rand_core::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use rand_core::OsRng;
+
+use crate::circuits::CircuitId;
+use crate::error::{Error, ErrorCode, Result};
+
+/// Personalization of the hash turning a seed into the RNG key.
+const SEED_PERSONALIZATION: &[u8; 8] = b"zkSetup_";
+
+/// Runs the setup for `circuit`, drawing the toxic waste from `seed` if it's
+/// given and from the OS otherwise.
+pub fn generate_parameters(circuit: CircuitId, seed: Option<&[u8]>) -> Result<Parameters<Bls12>> {
+    let res = match seed {
+        Some(seed) => {
+            let key = blake2s_simd::Params::new()
+                .personal(SEED_PERSONALIZATION)
+                .to_state()
+                .update(circuit.to_string().as_bytes())
+                .update(&[0])
+                .update(seed)
+                .finalize();
+            let mut rng = ChaCha20Rng::from_seed(*key.as_array());
+            generate_random_parameters::<Bls12, _, _>(circuit.blank(), &mut rng)
+        }
+        None => generate_random_parameters::<Bls12, _, _>(circuit.blank(), &mut OsRng),
+    };
+    res.map_err(|e| {
+        Error::new(
+            ErrorCode::MalformedParams,
+            format!("setup of {} failed: {}", circuit, e),
+        )
+    })
+}
+
+/// Runs the setup for `circuit` and writes the proving parameters and the
+/// verifying key to the given files.
+pub fn write_setup(
+    circuit: CircuitId,
+    seed: Option<&[u8]>,
+    params_path: &Path,
+    key_path: &Path,
+) -> std::io::Result<()> {
+    let params = generate_parameters(circuit, seed).map_err(std::io::Error::other)?;
+    let mut bparams = vec![];
+    params.write(&mut bparams)?;
+    let mut bkey = vec![];
+    params.vk.write(&mut bkey)?;
+    fs::write(params_path, bparams)?;
+    fs::write(key_path, bkey)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::groth16::verify_groth16;
+    use crate::prover::{create_proof, read_parameters};
+    use bls12_381::Scalar;
+
+    fn key_bytes(params: &Parameters<Bls12>) -> Vec<u8> {
+        let mut bkey = vec![];
+        params.vk.write(&mut bkey).unwrap();
+        bkey
+    }
+
+    #[test]
+    fn test_seeded_setup() {
+        let a = generate_parameters(CircuitId::Multiply, Some(b"devnet")).unwrap();
+        let b = generate_parameters(CircuitId::Multiply, Some(b"devnet")).unwrap();
+        let c = generate_parameters(CircuitId::Multiply, Some(b"testnet")).unwrap();
+        assert_eq!(key_bytes(&a), key_bytes(&b));
+        assert_ne!(key_bytes(&a), key_bytes(&c));
+    }
+
+    #[test]
+    fn test_write_setup() {
+        let dir = std::env::temp_dir().join(format!("zk-setup-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (params_path, key_path) = (dir.join("multiply.params"), dir.join("multiply.vk"));
+        write_setup(CircuitId::Multiply, None, &params_path, &key_path).unwrap();
+
+        let params = read_parameters(&fs::read(&params_path).unwrap()).unwrap();
+        let bkey = fs::read(&key_path).unwrap();
+        let witness = [Scalar::from(2).to_bytes(), Scalar::from(5).to_bytes()].concat();
+        let bproof = create_proof(CircuitId::Multiply, &params, &witness, &mut OsRng).unwrap();
+        let c = Scalar::from(10).to_bytes();
+        assert!(verify_groth16::<Bls12>(&bproof, &bkey, &c).unwrap());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
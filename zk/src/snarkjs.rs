@@ -0,0 +1,637 @@
+//! Import of the Groth16 and PLONK files produced by snarkjs: `proof.json`,
+//! `public.json` and `verification_key.json`. Numbers are decimal strings,
+//! G1 points are `[x, y, z]` and G2 points are
+//! `[[x.c0, x.c1], [y.c0, y.c1], [z.c0, z.c1]]`, snarkjs always exports them
+//! in affine form with `z = 1`, or `z = 0` for the point at infinity. The
+//! files are converted to the curve's binary encoding and verified by the
+//! regular path of their proof system.
+
+use std::io;
+
+use bellman::groth16::{Proof, VerifyingKey};
+use bls12_381::{Bls12, G1Affine, G2Affine};
+use ff::PrimeField;
+use group::prime::PrimeCurveAffine;
+use halo2curves::bn256::Bn256;
+use serde_json::Value;
+
+use crate::bn254;
+use crate::error::{self, Error, ErrorCode, Result};
+use crate::ffi::bytes;
+use crate::groth16::{self, Curve, ProofSystem, SCALAR_SIZE};
+use crate::plonk::{self, PlonkEngine};
+
+/// Verifies a snarkjs proof given the contents of its `proof.json`,
+/// `verification_key.json` and `public.json` files. The proof system and the
+/// curve are taken from the files, `groth16` and `plonk` proofs on both
+/// `bn128` and `bls12381` are supported.
+#[no_mangle]
+pub extern "C" fn verify_snarkjs(
+    proof: *mut libc::c_uchar,
+    proof_len: libc::size_t,
+    key: *mut libc::c_uchar,
+    key_len: libc::size_t,
+    inputs: *mut libc::c_uchar,
+    inputs_len: libc::size_t,
+) -> libc::c_int {
+    error::status((|| {
+        let c = convert(
+            bytes(proof, proof_len)?,
+            bytes(key, key_len)?,
+            bytes(inputs, inputs_len)?,
+        )?;
+        groth16::verify_system(c.system, c.curve, &c.proof, &c.key, &c.inputs)
+    })())
+}
+
+/// snarkjs files converted to the binary encoding of their curve.
+pub struct Converted {
+    pub system: ProofSystem,
+    pub curve: Curve,
+    pub proof: Vec<u8>,
+    pub key: Vec<u8>,
+    pub inputs: Vec<u8>,
+}
+
+/// Converts the contents of snarkjs `proof.json`, `verification_key.json`
+/// and `public.json` files, checking that the proof and the key agree on the
+/// protocol and the curve.
+pub fn convert(proof: &[u8], key: &[u8], inputs: &[u8]) -> Result<Converted> {
+    let proof = parse(proof, ErrorCode::MalformedProof)?;
+    let key = parse(key, ErrorCode::MalformedKey)?;
+    let inputs = parse(inputs, ErrorCode::MalformedInputs)?;
+    let (system, curve) = header(&key, ErrorCode::MalformedKey)?;
+    let (proof_system, proof_curve) = header(&proof, ErrorCode::MalformedProof)?;
+    if proof_system != system {
+        return Err(Error::new(
+            ErrorCode::MalformedProof,
+            "proof and verifying key are for different protocols",
+        ));
+    }
+    if proof_curve != curve {
+        return Err(Error::new(
+            ErrorCode::MalformedProof,
+            "proof and verifying key are on different curves",
+        ));
+    }
+    match curve {
+        Curve::Bls12381 => convert_on::<Bls12>(system, curve, &proof, &key, &inputs),
+        Curve::Bn254 => convert_on::<Bn256>(system, curve, &proof, &key, &inputs),
+    }
+}
+
+fn convert_on<E: SnarkjsEngine>(
+    system: ProofSystem,
+    curve: Curve,
+    proof: &Value,
+    key: &Value,
+    inputs: &Value,
+) -> Result<Converted> {
+    let (proof, key) = match system {
+        ProofSystem::Groth16 => (
+            read_proof::<E>(proof).map(|p| E::write_proof(&p)),
+            read_key::<E>(key).map(|k| E::write_key(&k)),
+        ),
+        ProofSystem::Plonk => (
+            read_plonk_proof::<E>(proof).map(|p| p.to_bytes()),
+            read_plonk_key::<E>(key).map(|k| k.to_bytes()),
+        ),
+    };
+    let proof = proof
+        .map_err(|e| Error::new(ErrorCode::MalformedProof, format!("malformed proof: {}", e)))?;
+    let key = key.map_err(|e| {
+        Error::new(
+            ErrorCode::MalformedKey,
+            format!("malformed verifying key: {}", e),
+        )
+    })?;
+    let inputs = read_inputs::<E>(inputs).map_err(|e| {
+        Error::new(
+            ErrorCode::MalformedInputs,
+            format!("malformed public inputs: {}", e),
+        )
+    })?;
+    Ok(Converted {
+        system,
+        curve,
+        proof,
+        key,
+        inputs: inputs.iter().flat_map(E::write_scalar).collect(),
+    })
+}
+
+fn parse(b: &[u8], code: ErrorCode) -> Result<Value> {
+    serde_json::from_slice(b).map_err(|e| Error::new(code, format!("invalid JSON: {}", e)))
+}
+
+/// Returns the proof system named by the `protocol` field and the curve
+/// named by the `curve` field of a proof or a key.
+fn header(v: &Value, code: ErrorCode) -> Result<(ProofSystem, Curve)> {
+    let system = match v.get("protocol").and_then(Value::as_str) {
+        Some("groth16") => ProofSystem::Groth16,
+        Some("plonk") => ProofSystem::Plonk,
+        Some(p) => return Err(Error::new(code, format!("unsupported protocol {}", p))),
+        None => return Err(Error::new(code, "missing protocol")),
+    };
+    match v.get("curve").and_then(Value::as_str) {
+        Some("bn128") | Some("bn254") => Ok((system, Curve::Bn254)),
+        Some("bls12381") => Ok((system, Curve::Bls12381)),
+        Some(c) => Err(Error::new(
+            ErrorCode::UnsupportedCurve,
+            format!("unsupported curve {}", c),
+        )),
+        None => Err(Error::new(code, "missing curve")),
+    }
+}
+
+fn invalid(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+/// Parses a decimal string into a big-endian number of `size` bytes.
+fn decimal(s: &str, size: usize) -> io::Result<Vec<u8>> {
+    if s.is_empty() || !s.bytes().all(|c| c.is_ascii_digit()) {
+        return Err(invalid(format!("{:?} is not a decimal number", s)));
+    }
+    let mut out = vec![0u8; size];
+    for c in s.bytes() {
+        let mut carry = (c - b'0') as u32;
+        for byte in out.iter_mut().rev() {
+            let v = *byte as u32 * 10 + carry;
+            *byte = v as u8;
+            carry = v >> 8;
+        }
+        if carry != 0 {
+            return Err(invalid(format!("{} doesn't fit in {} bytes", s, size)));
+        }
+    }
+    Ok(out)
+}
+
+fn field(name: &str, v: &Value) -> io::Result<Value> {
+    v.get(name)
+        .cloned()
+        .ok_or_else(|| invalid(format!("missing {}", name)))
+}
+
+fn strings(v: &Value, n: usize) -> io::Result<Vec<&str>> {
+    let arr = v
+        .as_array()
+        .filter(|arr| arr.len() == n)
+        .ok_or_else(|| invalid(format!("expected an array of {} elements", n)))?;
+    arr.iter()
+        .map(|s| s.as_str().ok_or_else(|| invalid("expected a string")))
+        .collect()
+}
+
+/// Tells whether the `z` coordinate marks an affine point or the infinity.
+fn is_infinity(z: &[&str]) -> io::Result<bool> {
+    match z {
+        ["1"] | ["1", "0"] => Ok(false),
+        ["0"] | ["0", "0"] => Ok(true),
+        _ => Err(invalid("points must be in affine form")),
+    }
+}
+
+/// Curve-specific decoding of big-endian point coordinates.
+trait SnarkjsEngine: PlonkEngine {
+    /// Size of a base field element.
+    const BASE_SIZE: usize;
+
+    /// Decodes `x | y`.
+    fn g1(xy: &[u8]) -> io::Result<Self::G1Affine>;
+
+    /// Decodes `x.c1 | x.c0 | y.c1 | y.c0`.
+    fn g2(xy: &[u8]) -> io::Result<Self::G2Affine>;
+}
+
+impl SnarkjsEngine for Bls12 {
+    const BASE_SIZE: usize = 48;
+
+    fn g1(xy: &[u8]) -> io::Result<G1Affine> {
+        let mut b = [0u8; 96];
+        b.copy_from_slice(xy);
+        // The top bits hold the encoding flags, a valid coordinate never
+        // reaches them.
+        if b[0] & 0xe0 != 0 {
+            return Err(invalid("coordinate is not a canonical field element"));
+        }
+        Option::from(G1Affine::from_uncompressed(&b))
+            .ok_or_else(|| invalid("G1 point is not on the curve or not in the subgroup"))
+    }
+
+    fn g2(xy: &[u8]) -> io::Result<G2Affine> {
+        let mut b = [0u8; 192];
+        b.copy_from_slice(xy);
+        if b[0] & 0xe0 != 0 {
+            return Err(invalid("coordinate is not a canonical field element"));
+        }
+        Option::from(G2Affine::from_uncompressed(&b))
+            .ok_or_else(|| invalid("G2 point is not on the curve or not in the subgroup"))
+    }
+}
+
+impl SnarkjsEngine for Bn256 {
+    const BASE_SIZE: usize = bn254::FIELD_SIZE;
+
+    fn g1(xy: &[u8]) -> io::Result<Self::G1Affine> {
+        bn254::read_g1(xy)
+    }
+
+    fn g2(xy: &[u8]) -> io::Result<Self::G2Affine> {
+        bn254::read_g2(xy)
+    }
+}
+
+fn read_g1<E: SnarkjsEngine>(v: &Value) -> io::Result<E::G1Affine> {
+    let p = strings(v, 3)?;
+    if is_infinity(&p[2..])? {
+        return Ok(E::G1Affine::identity());
+    }
+    let mut xy = decimal(p[0], E::BASE_SIZE)?;
+    xy.extend(decimal(p[1], E::BASE_SIZE)?);
+    E::g1(&xy)
+}
+
+fn read_g2<E: SnarkjsEngine>(v: &Value) -> io::Result<E::G2Affine> {
+    let p = v
+        .as_array()
+        .filter(|arr| arr.len() == 3)
+        .ok_or_else(|| invalid("expected an array of 3 elements"))?;
+    if is_infinity(&strings(&p[2], 2)?)? {
+        return Ok(E::G2Affine::identity());
+    }
+    let mut xy = vec![];
+    for c in p[..2].iter() {
+        let c = strings(c, 2)?;
+        xy.extend(decimal(c[1], E::BASE_SIZE)?);
+        xy.extend(decimal(c[0], E::BASE_SIZE)?);
+    }
+    E::g2(&xy)
+}
+
+fn read_proof<E: SnarkjsEngine>(v: &Value) -> io::Result<Proof<E>> {
+    Ok(Proof {
+        a: read_g1::<E>(&field("pi_a", v)?)?,
+        b: read_g2::<E>(&field("pi_b", v)?)?,
+        c: read_g1::<E>(&field("pi_c", v)?)?,
+    })
+}
+
+fn read_key<E: SnarkjsEngine>(v: &Value) -> io::Result<VerifyingKey<E>> {
+    let ic = field("IC", v)?
+        .as_array()
+        .ok_or_else(|| invalid("IC must be an array"))?
+        .iter()
+        .map(read_g1::<E>)
+        .collect::<io::Result<Vec<_>>>()?;
+    if let Some(n) = v.get("nPublic") {
+        if ic.is_empty() || n.as_u64() != Some(ic.len() as u64 - 1) {
+            return Err(invalid("nPublic doesn't match the IC length"));
+        }
+    }
+    Ok(VerifyingKey {
+        alpha_g1: read_g1::<E>(&field("vk_alpha_1", v)?)?,
+        // beta_g1 and delta_g1 are only needed by the prover, snarkjs
+        // doesn't export them.
+        beta_g1: E::G1Affine::identity(),
+        beta_g2: read_g2::<E>(&field("vk_beta_2", v)?)?,
+        gamma_g2: read_g2::<E>(&field("vk_gamma_2", v)?)?,
+        delta_g1: E::G1Affine::identity(),
+        delta_g2: read_g2::<E>(&field("vk_delta_2", v)?)?,
+        ic,
+    })
+}
+
+fn read_plonk_proof<E: SnarkjsEngine>(v: &Value) -> io::Result<plonk::Proof<E>> {
+    let g1 = |name: &str| read_g1::<E>(&field(name, v)?);
+    let fr = |name: &str| read_scalar::<E>(&field(name, v)?);
+    Ok(plonk::Proof {
+        a: g1("A")?,
+        b: g1("B")?,
+        c: g1("C")?,
+        z: g1("Z")?,
+        t1: g1("T1")?,
+        t2: g1("T2")?,
+        t3: g1("T3")?,
+        wxi: g1("Wxi")?,
+        wxiw: g1("Wxiw")?,
+        eval_a: fr("eval_a")?,
+        eval_b: fr("eval_b")?,
+        eval_c: fr("eval_c")?,
+        eval_s1: fr("eval_s1")?,
+        eval_s2: fr("eval_s2")?,
+        eval_zw: fr("eval_zw")?,
+    })
+}
+
+fn read_plonk_key<E: SnarkjsEngine>(v: &Value) -> io::Result<plonk::VerifyingKey<E>> {
+    let g1 = |name: &str| read_g1::<E>(&field(name, v)?);
+    let number = |name: &str| {
+        field(name, v)?
+            .as_u64()
+            .and_then(|n| u32::try_from(n).ok())
+            .ok_or_else(|| invalid(format!("{} must be a small integer", name)))
+    };
+    let key = plonk::VerifyingKey {
+        power: number("power")?,
+        n_public: number("nPublic")? as usize,
+        k1: read_scalar::<E>(&field("k1", v)?)?,
+        k2: read_scalar::<E>(&field("k2", v)?)?,
+        qm: g1("Qm")?,
+        ql: g1("Ql")?,
+        qr: g1("Qr")?,
+        qo: g1("Qo")?,
+        qc: g1("Qc")?,
+        s1: g1("S1")?,
+        s2: g1("S2")?,
+        s3: g1("S3")?,
+        x_2: read_g2::<E>(&field("X_2", v)?)?,
+    };
+    // The root of unity is informative, the verifier derives it from the
+    // power like snarkjs does.
+    if let Some(w) = v.get("w") {
+        if key.power > E::Fr::S || read_scalar::<E>(w)? != plonk::root_of_unity(key.power) {
+            return Err(invalid("w isn't the root of unity of the domain"));
+        }
+    }
+    Ok(key)
+}
+
+fn read_scalar<E: SnarkjsEngine>(v: &Value) -> io::Result<E::Fr> {
+    let s = v.as_str().ok_or_else(|| invalid("expected a string"))?;
+    E::read_word(&decimal(s, SCALAR_SIZE)?)
+        .ok_or_else(|| invalid(format!("{} is not a canonical scalar", s)))
+}
+
+fn read_inputs<E: SnarkjsEngine>(v: &Value) -> io::Result<Vec<E::Fr>> {
+    let arr = v
+        .as_array()
+        .ok_or_else(|| invalid("public inputs must be an array"))?;
+    arr.iter().map(read_scalar::<E>).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::groth16::tests::{parameters, prove, simulate_bn254};
+    use crate::groth16::Groth16Engine;
+    use crate::plonk::tests as plonk_tests;
+    use group::Curve as _;
+
+    /// Formats a big-endian number in decimal.
+    fn to_decimal(be: &[u8]) -> String {
+        let mut n = be.to_vec();
+        let mut digits = vec![];
+        while n.iter().any(|&b| b != 0) {
+            let mut rem = 0u32;
+            for b in n.iter_mut() {
+                let v = (rem << 8) | *b as u32;
+                *b = (v / 10) as u8;
+                rem = v % 10;
+            }
+            digits.push(b'0' + rem as u8);
+        }
+        if digits.is_empty() {
+            digits.push(b'0');
+        }
+        digits.reverse();
+        String::from_utf8(digits).unwrap()
+    }
+
+    /// Formats points given as big-endian `x | y` or `x.c1 | x.c0 | y.c1 | y.c0`
+    /// coordinates the way snarkjs does.
+    fn g1_json(xy: &[u8]) -> Value {
+        let n = xy.len() / 2;
+        serde_json::json!([to_decimal(&xy[..n]), to_decimal(&xy[n..]), "1"])
+    }
+
+    fn g2_json(xy: &[u8]) -> Value {
+        let n = xy.len() / 4;
+        let c = |i: usize| to_decimal(&xy[i * n..(i + 1) * n]);
+        serde_json::json!([[c(1), c(0)], [c(3), c(2)], ["1", "0"]])
+    }
+
+    fn files(curve: &str, g1: &[Vec<u8>], g2: &[Vec<u8>], inputs: &[&str]) -> [Vec<u8>; 3] {
+        // g1 holds a, c, alpha and the IC points, g2 holds b, beta, gamma, delta.
+        let proof = serde_json::json!({
+            "pi_a": g1_json(&g1[0]),
+            "pi_b": g2_json(&g2[0]),
+            "pi_c": g1_json(&g1[1]),
+            "protocol": "groth16",
+            "curve": curve,
+        });
+        let key = serde_json::json!({
+            "protocol": "groth16",
+            "curve": curve,
+            "nPublic": inputs.len(),
+            "vk_alpha_1": g1_json(&g1[2]),
+            "vk_beta_2": g2_json(&g2[1]),
+            "vk_gamma_2": g2_json(&g2[2]),
+            "vk_delta_2": g2_json(&g2[3]),
+            "IC": g1[3..].iter().map(|p| g1_json(p)).collect::<Vec<_>>(),
+        });
+        [
+            proof.to_string().into_bytes(),
+            key.to_string().into_bytes(),
+            serde_json::json!(inputs).to_string().into_bytes(),
+        ]
+    }
+
+    fn verify_files(files: &[Vec<u8>; 3]) -> Result<bool> {
+        let c = convert(&files[0], &files[1], &files[2])?;
+        groth16::verify_system(c.system, c.curve, &c.proof, &c.key, &c.inputs)
+    }
+
+    /// snarkjs files of a PLONK proof, `point` encoding G1 and G2 points as
+    /// big-endian coordinates.
+    fn plonk_files<E: PlonkEngine>(
+        curve: &str,
+        point: impl Fn(&E::G1Affine) -> Vec<u8>,
+        x_2: Vec<u8>,
+        w: Option<E::Fr>,
+    ) -> [Vec<u8>; 3] {
+        let (vk, p, out) = plonk_tests::prove::<E>(6, 4);
+        // Qc is the point at infinity, there are no constant gates.
+        let g1 = |q: &E::G1Affine| match bool::from(q.is_identity()) {
+            true => serde_json::json!(["0", "1", "0"]),
+            false => g1_json(&point(q)),
+        };
+        let fr = |s: &E::Fr| Value::from(to_decimal(&E::write_word(s)));
+        let proof = serde_json::json!({
+            "A": g1(&p.a), "B": g1(&p.b), "C": g1(&p.c), "Z": g1(&p.z),
+            "T1": g1(&p.t1), "T2": g1(&p.t2), "T3": g1(&p.t3),
+            "Wxi": g1(&p.wxi), "Wxiw": g1(&p.wxiw),
+            "eval_a": fr(&p.eval_a), "eval_b": fr(&p.eval_b), "eval_c": fr(&p.eval_c),
+            "eval_s1": fr(&p.eval_s1), "eval_s2": fr(&p.eval_s2), "eval_zw": fr(&p.eval_zw),
+            "protocol": "plonk",
+            "curve": curve,
+        });
+        let mut key = serde_json::json!({
+            "protocol": "plonk",
+            "curve": curve,
+            "nPublic": vk.n_public,
+            "power": vk.power,
+            "k1": fr(&vk.k1), "k2": fr(&vk.k2),
+            "Qm": g1(&vk.qm), "Ql": g1(&vk.ql), "Qr": g1(&vk.qr), "Qo": g1(&vk.qo),
+            "Qc": g1(&vk.qc), "S1": g1(&vk.s1), "S2": g1(&vk.s2), "S3": g1(&vk.s3),
+            "X_2": g2_json(&x_2),
+        });
+        if let Some(w) = w {
+            key["w"] = fr(&w);
+        }
+        [
+            proof.to_string().into_bytes(),
+            key.to_string().into_bytes(),
+            serde_json::json!([to_decimal(&E::write_word(&out))])
+                .to_string()
+                .into_bytes(),
+        ]
+    }
+
+    #[test]
+    fn test_bls12381() {
+        let params = parameters();
+        let proof = Proof::<Bls12>::read(&prove(&params, 3, 7)[..]).unwrap();
+        let vk = &params.vk;
+        let mut g1 = vec![proof.a, proof.c, vk.alpha_g1];
+        g1.extend(vk.ic.iter());
+        let g1 = g1
+            .iter()
+            .map(|p| p.to_uncompressed().to_vec())
+            .collect::<Vec<_>>();
+        let g2 = [proof.b, vk.beta_g2, vk.gamma_g2, vk.delta_g2]
+            .iter()
+            .map(|p| p.to_uncompressed().to_vec())
+            .collect::<Vec<_>>();
+        assert!(verify_files(&files("bls12381", &g1, &g2, &["21"])).unwrap());
+        assert!(!verify_files(&files("bls12381", &g1, &g2, &["22"])).unwrap());
+    }
+
+    #[test]
+    fn test_bn128() {
+        let (key, proof) = simulate_bn254(&[4, 9]);
+        let vk = Bn256::read_key(&key).unwrap();
+        let proof = Bn256::read_proof(&proof).unwrap();
+        let mut g1 = vec![proof.a, proof.c, vk.alpha_g1];
+        g1.extend(vk.ic.iter());
+        let g1 = g1
+            .iter()
+            .map(|p| bn254::g1_bytes(&p.to_curve()).to_vec())
+            .collect::<Vec<_>>();
+        let g2 = [proof.b, vk.beta_g2, vk.gamma_g2, vk.delta_g2]
+            .iter()
+            .map(|p| bn254::g2_bytes(&p.to_curve()).to_vec())
+            .collect::<Vec<_>>();
+        let good = files("bn128", &g1, &g2, &["4", "9"]);
+        assert!(verify_files(&good).unwrap());
+        let code = verify_snarkjs(
+            good[0].as_ptr() as *mut _,
+            good[0].len(),
+            good[1].as_ptr() as *mut _,
+            good[1].len(),
+            good[2].as_ptr() as *mut _,
+            good[2].len(),
+        );
+        assert_eq!(ErrorCode::Ok as libc::c_int, code);
+        assert!(!verify_files(&files("bn128", &g1, &g2, &["4", "8"])).unwrap());
+
+        let err = verify_files(&files("bn128", &g1, &g2, &["4"])).unwrap_err();
+        assert_eq!(ErrorCode::MalformedKey, err.code());
+        let err = verify_files(&files("secp256k1", &g1, &g2, &["4", "9"])).unwrap_err();
+        assert_eq!(ErrorCode::UnsupportedCurve, err.code());
+        let err = convert(&good[0], &good[1], b"[\"4\", 9]").err().unwrap();
+        assert_eq!(ErrorCode::MalformedInputs, err.code());
+        let err = convert(&good[1], &good[1], &good[2]).err().unwrap();
+        assert_eq!(ErrorCode::MalformedProof, err.code());
+    }
+
+    #[test]
+    fn test_plonk() {
+        use halo2curves::bn256::{Fr, G2Affine as Bn256G2};
+        // The test setup's tau, the verifying key's X_2 isn't returned in
+        // the curve's encoding.
+        let tau = 0x7a0;
+        let x_2 = bn254::g2_bytes(&(Bn256G2::generator() * Fr::from(tau))).to_vec();
+        let w = plonk::root_of_unity::<Fr>(3);
+        let files = plonk_files::<Bn256>(
+            "bn128",
+            |p| bn254::g1_bytes(&p.to_curve()).to_vec(),
+            x_2.clone(),
+            Some(w),
+        );
+        let c = convert(&files[0], &files[1], &files[2]).unwrap();
+        assert_eq!(ProofSystem::Plonk, c.system);
+        assert_eq!(Curve::Bn254, c.curve);
+        assert!(verify_files(&files).unwrap());
+        let bad = plonk_files::<Bn256>(
+            "bn128",
+            |p| bn254::g1_bytes(&p.to_curve()).to_vec(),
+            x_2,
+            Some(w.square()),
+        );
+        let err = verify_files(&bad).unwrap_err();
+        assert_eq!(ErrorCode::MalformedKey, err.code());
+
+        let x_2 = (G2Affine::generator() * bls12_381::Scalar::from(tau))
+            .to_affine()
+            .to_uncompressed()
+            .to_vec();
+        let files = plonk_files::<Bls12>("bls12381", |p| p.to_uncompressed().to_vec(), x_2, None);
+        assert!(verify_files(&files).unwrap());
+        let wrong = serde_json::json!(["31"]).to_string().into_bytes();
+        assert!(!verify_files(&[files[0].clone(), files[1].clone(), wrong]).unwrap());
+
+        // A PLONK proof doesn't go with a Groth16 key.
+        let groth16 = br#"{"protocol": "groth16", "curve": "bls12381"}"#;
+        let err = convert(&files[0], groth16, &files[2]).err().unwrap();
+        assert_eq!(ErrorCode::MalformedProof, err.code());
+    }
+
+    /// Files of a Groth16 proof made by snarkjs for the RLN circuit, from
+    /// the tests of the `rln` crate. The verifying key is its zkey's, with
+    /// `vk_alphabeta_12` left out.
+    fn rln_files() -> [Vec<u8>; 3] {
+        [
+            include_bytes!("../data/snarkjs/rln/proof.json").to_vec(),
+            include_bytes!("../data/snarkjs/rln/verification_key.json").to_vec(),
+            include_bytes!("../data/snarkjs/rln/public.json").to_vec(),
+        ]
+    }
+
+    #[test]
+    fn test_snarkjs_files() {
+        let files = rln_files();
+        assert!(verify_files(&files).unwrap());
+        let mut inputs: Vec<String> = serde_json::from_slice(&files[2]).unwrap();
+        inputs[3].replace_range(0..1, "1");
+        let tampered = serde_json::to_vec(&inputs).unwrap();
+        assert!(!verify_files(&[files[0].clone(), files[1].clone(), tampered]).unwrap());
+        let err =
+            verify_files(&[files[0].clone(), files[1].clone(), b"[\"1\"]".to_vec()]).unwrap_err();
+        assert_eq!(ErrorCode::InputCountMismatch, err.code());
+
+        // A key exported by snarkjs, from the test vectors of ark-circom. Its
+        // G2 points are only on the twist in snarkjs' coordinate order.
+        let text = include_bytes!("../data/snarkjs/mycircuit/verification_key.json");
+        let mut key = parse(text, ErrorCode::MalformedKey).unwrap();
+        assert_eq!(2, read_key::<Bn256>(&key).unwrap().ic.len());
+        for point in ["vk_beta_2", "vk_gamma_2", "vk_delta_2"] {
+            for c in key[point].as_array_mut().unwrap()[..2].iter_mut() {
+                c.as_array_mut().unwrap().reverse();
+            }
+        }
+        assert!(read_key::<Bn256>(&key).is_err());
+    }
+
+    #[test]
+    fn test_decimal() {
+        assert_eq!(vec![0, 0, 1, 0], decimal("256", 4).unwrap());
+        assert_eq!(vec![0xff, 0xff], decimal("65535", 2).unwrap());
+        assert!(decimal("65536", 2).is_err());
+        assert!(decimal("", 2).is_err());
+        assert!(decimal("-1", 2).is_err());
+        assert!(decimal("0x10", 2).is_err());
+        assert_eq!("65535", to_decimal(&[0xff, 0xff]));
+    }
+}
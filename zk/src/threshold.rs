@@ -0,0 +1,561 @@
+//! Threshold BLS signatures: a secret key is split with Shamir's scheme
+//! into `n` shares, any `t` of which sign for the group. The dealer samples
+//! a polynomial of degree `t - 1` whose constant term is the key, share `i`
+//! is its value at `i` (1 to `n`), and Feldman commitments to its
+//! coefficients let anyone derive the public key of a share and check the
+//! share against it.
+//!
+//! A partial signature is a BLS signature by a share, of the message hashed
+//! as the group key would have it in the selected scheme. Lagrange
+//! interpolation at zero of `t` partial signatures gives the signature of
+//! the group key, a plain 96-byte BLS signature checked by `bls::verify`
+//! whatever the committee size.
+
+use bls12_381::{G1Affine, G1Projective, G2Projective, Scalar};
+use ff::Field;
+use group::Curve as _;
+use rand_core::{OsRng, RngCore};
+
+use crate::bls::{
+    pairing_check, read_all, PublicKey, Scheme, SecretKey, Signature, PUBLIC_KEY_SIZE,
+    SECRET_KEY_SIZE, SIGNATURE_SIZE,
+};
+use crate::error::{self, Error, ErrorCode, Result};
+use crate::ffi::{bytes, write_out};
+
+/// Largest number of shares a key is split in.
+pub const MAX_SHARES: usize = 1024;
+/// Size of a serialized share, its big-endian u32 index and secret key.
+pub const SHARE_SIZE: usize = 4 + SECRET_KEY_SIZE;
+/// Size of a serialized partial signature, its signer's big-endian u32
+/// index and signature.
+pub const PARTIAL_SIGNATURE_SIZE: usize = 4 + SIGNATURE_SIZE;
+
+/// A Shamir share of a secret key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Share {
+    pub index: u32,
+    pub key: SecretKey,
+}
+
+/// A signature by a share.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PartialSignature {
+    pub index: u32,
+    pub sig: Signature,
+}
+
+/// Feldman commitments to the coefficients of a sharing polynomial, the
+/// first being the group public key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Commitments(pub(crate) Vec<G1Affine>);
+
+pub(crate) fn read_index(b: &[u8]) -> Result<u32> {
+    let index = u32::from_be_bytes(b[..4].try_into().expect("4 bytes"));
+    if index == 0 || index as usize > MAX_SHARES {
+        return Err(Error::new(
+            ErrorCode::MalformedInputs,
+            format!("share index must be 1 to {}, got {}", MAX_SHARES, index),
+        ));
+    }
+    Ok(index)
+}
+
+impl Share {
+    pub fn from_bytes(b: &[u8]) -> Result<Self> {
+        if b.len() != SHARE_SIZE {
+            return Err(Error::new(
+                ErrorCode::MalformedKey,
+                format!("share must be {} bytes, got {}", SHARE_SIZE, b.len()),
+            ));
+        }
+        Ok(Share {
+            index: read_index(b)?,
+            key: SecretKey::from_bytes(&b[4..])?,
+        })
+    }
+
+    pub fn to_bytes(&self) -> [u8; SHARE_SIZE] {
+        let mut b = [0u8; SHARE_SIZE];
+        b[..4].copy_from_slice(&self.index.to_be_bytes());
+        b[4..].copy_from_slice(&self.key.to_bytes());
+        b
+    }
+
+    /// Signs `msg` for the group with public key `group`.
+    pub fn sign(&self, scheme: Scheme, group: &PublicKey, msg: &[u8]) -> PartialSignature {
+        PartialSignature {
+            index: self.index,
+            sig: Signature((scheme.hash(group, msg) * self.key.0).to_affine()),
+        }
+    }
+}
+
+impl PartialSignature {
+    pub fn from_bytes(b: &[u8]) -> Result<Self> {
+        if b.len() != PARTIAL_SIGNATURE_SIZE {
+            return Err(Error::new(
+                ErrorCode::MalformedSignature,
+                format!(
+                    "partial signature must be {} bytes, got {}",
+                    PARTIAL_SIGNATURE_SIZE,
+                    b.len()
+                ),
+            ));
+        }
+        Ok(PartialSignature {
+            index: read_index(b)?,
+            sig: Signature::from_bytes(&b[4..])?,
+        })
+    }
+
+    pub fn to_bytes(&self) -> [u8; PARTIAL_SIGNATURE_SIZE] {
+        let mut b = [0u8; PARTIAL_SIGNATURE_SIZE];
+        b[..4].copy_from_slice(&self.index.to_be_bytes());
+        b[4..].copy_from_slice(&self.sig.to_bytes());
+        b
+    }
+}
+
+impl Commitments {
+    /// Decodes concatenated compressed G1 points, at least one.
+    pub fn from_bytes(b: &[u8]) -> Result<Self> {
+        let points = read_all(b, PUBLIC_KEY_SIZE, |p| {
+            let repr: [u8; PUBLIC_KEY_SIZE] = p.try_into().expect("chunk size");
+            Option::<G1Affine>::from(G1Affine::from_compressed(&repr))
+                .ok_or_else(|| Error::new(ErrorCode::MalformedKey, "malformed commitment"))
+        })?;
+        if points.is_empty() || points.len() > MAX_SHARES {
+            return Err(Error::new(
+                ErrorCode::MalformedKey,
+                format!(
+                    "expected 1 to {} commitments, got {}",
+                    MAX_SHARES,
+                    points.len()
+                ),
+            ));
+        }
+        Ok(Commitments(points))
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.0.iter().flat_map(|p| p.to_compressed()).collect()
+    }
+
+    /// Number of shares needed to sign.
+    pub fn threshold(&self) -> usize {
+        self.0.len()
+    }
+
+    /// The group public key, the commitment to the secret key.
+    pub fn public_key(&self) -> Result<PublicKey> {
+        PublicKey::from_bytes(&self.0[0].to_compressed())
+    }
+
+    /// The public key of share `index`, the committed polynomial evaluated
+    /// at `index` in the exponent.
+    pub fn share_public_key(&self, index: u32) -> PublicKey {
+        let x = Scalar::from(index as u64);
+        let p = self
+            .0
+            .iter()
+            .rev()
+            .fold(G1Projective::identity(), |acc, c| acc * x + c);
+        PublicKey(p.to_affine())
+    }
+
+    /// Checks a share against the commitments.
+    pub fn verify_share(&self, share: &Share) -> bool {
+        self.share_public_key(share.index) == share.key.public_key()
+    }
+
+    /// Checks a partial signature of `msg` against the public key of its
+    /// share.
+    pub fn verify_partial(&self, scheme: Scheme, msg: &[u8], partial: &PartialSignature) -> bool {
+        let group = match self.public_key() {
+            Ok(pk) => pk,
+            Err(_) => return false,
+        };
+        let pk = self.share_public_key(partial.index);
+        pairing_check(&partial.sig, &[(pk.0, scheme.hash(&group, msg))])
+    }
+}
+
+/// Evaluates the polynomial with the given coefficients at `x`.
+pub(crate) fn evaluate(coefficients: &[Scalar], x: u32) -> Scalar {
+    let x = Scalar::from(x as u64);
+    coefficients
+        .iter()
+        .rev()
+        .fold(Scalar::ZERO, |acc, c| acc * x + c)
+}
+
+pub(crate) fn check_threshold(threshold: usize, n: usize) -> Result<()> {
+    if threshold == 0 || threshold > n || n > MAX_SHARES {
+        return Err(Error::new(
+            ErrorCode::MalformedInputs,
+            format!(
+                "threshold must be 1 to the number of shares, itself at most {}, got {} of {}",
+                MAX_SHARES, threshold, n
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Splits a secret key into `n` shares, `threshold` of which can sign.
+pub fn split(
+    sk: &SecretKey,
+    threshold: usize,
+    n: usize,
+    mut rng: impl RngCore,
+) -> Result<(Vec<Share>, Commitments)> {
+    check_threshold(threshold, n)?;
+    let coefficients: Vec<Scalar> = std::iter::once(sk.0)
+        .chain((1..threshold).map(|_| Scalar::random(&mut rng)))
+        .collect();
+    let shares = (1..=n as u32)
+        .map(|index| {
+            let key = evaluate(&coefficients, index);
+            // A zero share has negligible probability, but isn't a key.
+            if key == Scalar::ZERO {
+                return Err(Error::new(ErrorCode::MalformedKey, "zero share"));
+            }
+            Ok(Share {
+                index,
+                key: SecretKey(key),
+            })
+        })
+        .collect::<Result<_>>()?;
+    Ok((shares, commit(&coefficients)))
+}
+
+pub(crate) fn commit(coefficients: &[Scalar]) -> Commitments {
+    let points: Vec<G1Projective> = coefficients
+        .iter()
+        .map(|c| G1Affine::generator() * c)
+        .collect();
+    let mut affine = vec![G1Affine::identity(); points.len()];
+    G1Projective::batch_normalize(&points, &mut affine);
+    Commitments(affine)
+}
+
+/// Lagrange coefficients at zero of the given distinct indices.
+pub(crate) fn lagrange_at_zero(indices: &[u32]) -> Vec<Scalar> {
+    indices
+        .iter()
+        .map(|&i| {
+            let xi = Scalar::from(i as u64);
+            let (num, den) = indices.iter().filter(|&&j| j != i).fold(
+                (Scalar::ONE, Scalar::ONE),
+                |(num, den), &j| {
+                    let xj = Scalar::from(j as u64);
+                    (num * xj, den * (xj - xi))
+                },
+            );
+            num * den.invert().expect("indices are distinct")
+        })
+        .collect()
+}
+
+/// Recovers the group signature from the first `threshold` partial
+/// signatures, which must come from distinct shares.
+pub fn combine(partials: &[PartialSignature], threshold: usize) -> Result<Signature> {
+    if threshold == 0 || partials.len() < threshold {
+        return Err(Error::new(
+            ErrorCode::InputCountMismatch,
+            format!(
+                "{} partial signatures for a threshold of {}",
+                partials.len(),
+                threshold
+            ),
+        ));
+    }
+    let partials = &partials[..threshold];
+    let indices: Vec<u32> = partials.iter().map(|p| p.index).collect();
+    if (1..indices.len()).any(|i| indices[..i].contains(&indices[i])) {
+        return Err(Error::new(
+            ErrorCode::MalformedSignature,
+            "partial signatures from the same share",
+        ));
+    }
+    let sig: G2Projective = partials
+        .iter()
+        .zip(lagrange_at_zero(&indices))
+        .map(|(p, l)| p.sig.0 * l)
+        .sum();
+    Ok(Signature(sig.to_affine()))
+}
+
+/// Splits a secret key into `n` shares, `threshold` of which can sign.
+/// `shares` gets the `n` shares of `SHARE_SIZE` (36) bytes and
+/// `commitments` the `threshold` 48-byte commitments, whose first is the
+/// group public key.
+#[no_mangle]
+pub extern "C" fn bls_threshold_split(
+    secret_key: *mut libc::c_uchar,
+    threshold: libc::size_t,
+    n: libc::size_t,
+    shares: *mut libc::c_uchar,
+    shares_len: libc::size_t,
+    commitments: *mut libc::c_uchar,
+    commitments_len: libc::size_t,
+) -> libc::c_int {
+    error::status((|| {
+        let sk = SecretKey::from_bytes(bytes(secret_key, SECRET_KEY_SIZE)?)?;
+        let (s, c) = split(&sk, threshold, n, OsRng)?;
+        let bshares: Vec<u8> = s.iter().flat_map(Share::to_bytes).collect();
+        write_out(shares, shares_len, &bshares)?;
+        write_out(commitments, commitments_len, &c.to_bytes())?;
+        Ok(true)
+    })())
+}
+
+/// Writes the 48-byte public key of share `index` to `public_key`.
+#[no_mangle]
+pub extern "C" fn bls_threshold_share_public_key(
+    commitments: *mut libc::c_uchar,
+    commitments_len: libc::size_t,
+    index: u32,
+    public_key: *mut libc::c_uchar,
+) -> libc::c_int {
+    error::status((|| {
+        let c = Commitments::from_bytes(bytes(commitments, commitments_len)?)?;
+        let index = read_index(&index.to_be_bytes())?;
+        write_out(
+            public_key,
+            PUBLIC_KEY_SIZE,
+            &c.share_public_key(index).to_bytes(),
+        )?;
+        Ok(true)
+    })())
+}
+
+/// Checks a 36-byte share against the commitments of its dealer, returns
+/// `Ok` if it's consistent and `Invalid` if not.
+#[no_mangle]
+pub extern "C" fn bls_threshold_verify_share(
+    commitments: *mut libc::c_uchar,
+    commitments_len: libc::size_t,
+    share: *mut libc::c_uchar,
+) -> libc::c_int {
+    error::status((|| {
+        let c = Commitments::from_bytes(bytes(commitments, commitments_len)?)?;
+        Ok(c.verify_share(&Share::from_bytes(bytes(share, SHARE_SIZE)?)?))
+    })())
+}
+
+/// Signs a message with a 36-byte share for the group with the 48-byte
+/// public key `group_public_key` and writes the `PARTIAL_SIGNATURE_SIZE`
+/// (100) bytes partial signature to `partial`.
+#[no_mangle]
+pub extern "C" fn bls_threshold_sign(
+    scheme: libc::c_int,
+    share: *mut libc::c_uchar,
+    group_public_key: *mut libc::c_uchar,
+    msg: *mut libc::c_uchar,
+    msg_len: libc::size_t,
+    partial: *mut libc::c_uchar,
+) -> libc::c_int {
+    error::status((|| {
+        let scheme = Scheme::from_raw(scheme)?;
+        let share = Share::from_bytes(bytes(share, SHARE_SIZE)?)?;
+        let group = PublicKey::from_bytes(bytes(group_public_key, PUBLIC_KEY_SIZE)?)?;
+        let p = share.sign(scheme, &group, bytes(msg, msg_len)?);
+        write_out(partial, PARTIAL_SIGNATURE_SIZE, &p.to_bytes())?;
+        Ok(true)
+    })())
+}
+
+/// Checks a partial signature of a message against the commitments of the
+/// group, returns `Ok` if it's valid and `Invalid` if not.
+#[no_mangle]
+pub extern "C" fn bls_threshold_verify_partial(
+    scheme: libc::c_int,
+    commitments: *mut libc::c_uchar,
+    commitments_len: libc::size_t,
+    msg: *mut libc::c_uchar,
+    msg_len: libc::size_t,
+    partial: *mut libc::c_uchar,
+) -> libc::c_int {
+    error::status((|| {
+        let scheme = Scheme::from_raw(scheme)?;
+        let c = Commitments::from_bytes(bytes(commitments, commitments_len)?)?;
+        let p = PartialSignature::from_bytes(bytes(partial, PARTIAL_SIGNATURE_SIZE)?)?;
+        Ok(c.verify_partial(scheme, bytes(msg, msg_len)?, &p))
+    })())
+}
+
+/// Recovers the 96-byte group signature from the first `threshold` of the
+/// concatenated partial signatures in `partials` and writes it to
+/// `signature`. Partial signatures aren't checked, a wrong one yields a
+/// group signature that doesn't verify.
+#[no_mangle]
+pub extern "C" fn bls_threshold_combine(
+    partials: *mut libc::c_uchar,
+    partials_len: libc::size_t,
+    threshold: libc::size_t,
+    signature: *mut libc::c_uchar,
+) -> libc::c_int {
+    error::status((|| {
+        let p = read_all(
+            bytes(partials, partials_len)?,
+            PARTIAL_SIGNATURE_SIZE,
+            PartialSignature::from_bytes,
+        )?;
+        write_out(
+            signature,
+            SIGNATURE_SIZE,
+            &combine(&p, threshold)?.to_bytes(),
+        )?;
+        Ok(true)
+    })())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bls::{self, key_gen};
+
+    fn setup(t: usize, n: usize) -> (SecretKey, Vec<Share>, Commitments) {
+        let sk = key_gen(&[9; 32], &[]).unwrap();
+        let (shares, c) = split(&sk, t, n, OsRng).unwrap();
+        (sk, shares, c)
+    }
+
+    #[test]
+    fn test_split() {
+        let (sk, shares, c) = setup(3, 5);
+        assert_eq!(5, shares.len());
+        assert_eq!(3, c.threshold());
+        assert_eq!(sk.public_key(), c.public_key().unwrap());
+        for s in shares.iter() {
+            assert!(c.verify_share(s));
+            assert_eq!(*s, Share::from_bytes(&s.to_bytes()).unwrap());
+        }
+        let mut bad = shares[0];
+        bad.index = 2;
+        assert!(!c.verify_share(&bad));
+        assert_eq!(c, Commitments::from_bytes(&c.to_bytes()).unwrap());
+
+        assert!(split(&sk, 0, 5, OsRng).is_err());
+        assert!(split(&sk, 6, 5, OsRng).is_err());
+        assert!(split(&sk, 1, MAX_SHARES + 1, OsRng).is_err());
+    }
+
+    #[test]
+    fn test_threshold_signature() {
+        let (sk, shares, c) = setup(3, 5);
+        let group = c.public_key().unwrap();
+        for scheme in [Scheme::Basic, Scheme::Augmented, Scheme::ProofOfPossession] {
+            let partials: Vec<_> = shares
+                .iter()
+                .map(|s| s.sign(scheme, &group, b"root"))
+                .collect();
+            for p in partials.iter() {
+                assert!(c.verify_partial(scheme, b"root", p));
+                assert!(!c.verify_partial(scheme, b"rook", p));
+            }
+            // Any 3 shares recover the signature of the group key.
+            let expected = sk.sign(scheme, b"root");
+            for subset in [[0, 1, 2], [4, 2, 0], [1, 3, 4]] {
+                let ps: Vec<_> = subset.iter().map(|&i| partials[i]).collect();
+                let sig = combine(&ps, 3).unwrap();
+                assert_eq!(expected, sig);
+                assert!(bls::verify(scheme, &group, b"root", &sig));
+            }
+            // Two don't.
+            let sig = combine(&partials[..2], 2).unwrap();
+            assert!(!bls::verify(scheme, &group, b"root", &sig));
+        }
+
+        let partials: Vec<_> = shares
+            .iter()
+            .map(|s| s.sign(Scheme::Basic, &group, b"root"))
+            .collect();
+        assert_eq!(
+            ErrorCode::InputCountMismatch,
+            combine(&partials[..2], 3).err().unwrap().code()
+        );
+        let dup = [partials[0], partials[1], partials[0]];
+        assert_eq!(
+            ErrorCode::MalformedSignature,
+            combine(&dup, 3).err().unwrap().code()
+        );
+    }
+
+    #[test]
+    fn test_ffi() {
+        let mut sk = key_gen(&[5; 32], &[]).unwrap().to_bytes();
+        let mut shares = [0u8; 4 * SHARE_SIZE];
+        let mut commitments = [0u8; 2 * PUBLIC_KEY_SIZE];
+        let code = bls_threshold_split(
+            sk.as_mut_ptr(),
+            3,
+            4,
+            shares.as_mut_ptr(),
+            shares.len(),
+            commitments.as_mut_ptr(),
+            commitments.len(),
+        );
+        assert_eq!(ErrorCode::BufferTooSmall as libc::c_int, code);
+        let code = bls_threshold_split(
+            sk.as_mut_ptr(),
+            2,
+            4,
+            shares.as_mut_ptr(),
+            shares.len(),
+            commitments.as_mut_ptr(),
+            commitments.len(),
+        );
+        assert_eq!(1, code);
+
+        let mut pk = [0u8; PUBLIC_KEY_SIZE];
+        let code = bls_threshold_share_public_key(
+            commitments.as_mut_ptr(),
+            commitments.len(),
+            3,
+            pk.as_mut_ptr(),
+        );
+        assert_eq!(1, code);
+        let share = Share::from_bytes(&shares[2 * SHARE_SIZE..3 * SHARE_SIZE]).unwrap();
+        assert_eq!(share.key.public_key().to_bytes(), pk);
+        let code = bls_threshold_verify_share(
+            commitments.as_mut_ptr(),
+            commitments.len(),
+            shares[SHARE_SIZE..].as_mut_ptr(),
+        );
+        assert_eq!(1, code);
+
+        let mut group = commitments[..PUBLIC_KEY_SIZE].to_vec();
+        let mut msg = *b"root";
+        let mut partials = [0u8; 2 * PARTIAL_SIGNATURE_SIZE];
+        for (i, p) in partials.chunks_mut(PARTIAL_SIGNATURE_SIZE).enumerate() {
+            let code = bls_threshold_sign(
+                0,
+                shares[(2 * i + 1) * SHARE_SIZE..].as_mut_ptr(),
+                group.as_mut_ptr(),
+                msg.as_mut_ptr(),
+                msg.len(),
+                p.as_mut_ptr(),
+            );
+            assert_eq!(1, code);
+            let code = bls_threshold_verify_partial(
+                0,
+                commitments.as_mut_ptr(),
+                commitments.len(),
+                msg.as_mut_ptr(),
+                msg.len(),
+                p.as_mut_ptr(),
+            );
+            assert_eq!(1, code);
+        }
+        let mut sig = [0u8; SIGNATURE_SIZE];
+        let code =
+            bls_threshold_combine(partials.as_mut_ptr(), partials.len(), 2, sig.as_mut_ptr());
+        assert_eq!(1, code);
+        let group = PublicKey::from_bytes(&group).unwrap();
+        let sig = Signature::from_bytes(&sig).unwrap();
+        assert!(bls::verify(Scheme::Basic, &group, b"root", &sig));
+    }
+}
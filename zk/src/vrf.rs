@@ -0,0 +1,186 @@
+//! A verifiable random function from BLS signatures, for randomness the
+//! holder of a key can't choose nor others predict, such as the selection of
+//! the next primary. A BLS signature is unique, a key has a single valid
+//! signature of a message, so the proof of input `alpha` is the signature
+//! of it and the output its hash.
+//!
+//! Keys are those of `bls`. The input is hashed to G2 with the public key
+//! prepended, as in the augmented scheme, under a tag of its own so proofs
+//! can't be mistaken for signatures in any scheme.
+
+use bls12_381::G2Projective;
+use group::Curve as _;
+use sha2::{Digest, Sha256};
+
+use crate::bls::{
+    pairing_check, PublicKey, SecretKey, Signature, PUBLIC_KEY_SIZE, SECRET_KEY_SIZE,
+    SIGNATURE_SIZE,
+};
+use crate::error;
+use crate::ffi::{bytes, write_out};
+use crate::hash_to_curve::hash_to_g2;
+
+/// Size of a proof, a compressed G2 point.
+pub const PROOF_SIZE: usize = SIGNATURE_SIZE;
+/// Size of an output.
+pub const OUTPUT_SIZE: usize = 32;
+
+/// Domain separation tag of the input hash.
+const VRF_DST: &[u8] = b"ZK_VRF_BLS12381G2_XMD:SHA-256_SSWU_RO_";
+
+/// Prefix of the proof hashed into the output.
+const OUTPUT_PREFIX: &[u8] = b"ZK_VRF_OUTPUT";
+
+fn hash_input(pk: &PublicKey, alpha: &[u8]) -> G2Projective {
+    let msg = [&pk.to_bytes()[..], alpha].concat();
+    hash_to_g2(&msg, VRF_DST).expect("the tag isn't empty")
+}
+
+/// The proof of `alpha` by a secret key.
+pub fn prove(sk: &SecretKey, alpha: &[u8]) -> Signature {
+    Signature((hash_input(&sk.public_key(), alpha) * sk.0).to_affine())
+}
+
+/// The output of a proof, meaningful only once the proof is verified.
+pub fn proof_to_hash(proof: &Signature) -> [u8; OUTPUT_SIZE] {
+    let mut h = Sha256::new();
+    h.update(OUTPUT_PREFIX);
+    h.update(proof.to_bytes());
+    h.finalize().into()
+}
+
+/// Checks the proof of `alpha` by `pk` and returns its output if it's valid.
+pub fn verify(pk: &PublicKey, alpha: &[u8], proof: &Signature) -> Option<[u8; OUTPUT_SIZE]> {
+    pairing_check(proof, &[(pk.0, hash_input(pk, alpha))]).then(|| proof_to_hash(proof))
+}
+
+/// Writes the 96-byte proof of `alpha` by a secret key to `proof`.
+#[no_mangle]
+pub extern "C" fn vrf_prove(
+    secret_key: *mut libc::c_uchar,
+    alpha: *mut libc::c_uchar,
+    alpha_len: libc::size_t,
+    proof: *mut libc::c_uchar,
+) -> libc::c_int {
+    error::status((|| {
+        let sk = SecretKey::from_bytes(bytes(secret_key, SECRET_KEY_SIZE)?)?;
+        write_out(
+            proof,
+            PROOF_SIZE,
+            &prove(&sk, bytes(alpha, alpha_len)?).to_bytes(),
+        )?;
+        Ok(true)
+    })())
+}
+
+/// Checks the proof of `alpha` by a public key. Returns `Ok` after writing
+/// the 32-byte output to `output` if it's valid, and `Invalid` if not.
+#[no_mangle]
+pub extern "C" fn vrf_verify(
+    public_key: *mut libc::c_uchar,
+    alpha: *mut libc::c_uchar,
+    alpha_len: libc::size_t,
+    proof: *mut libc::c_uchar,
+    output: *mut libc::c_uchar,
+) -> libc::c_int {
+    error::status((|| {
+        let pk = PublicKey::from_bytes(bytes(public_key, PUBLIC_KEY_SIZE)?)?;
+        let proof = Signature::from_bytes(bytes(proof, PROOF_SIZE)?)?;
+        match verify(&pk, bytes(alpha, alpha_len)?, &proof) {
+            Some(out) => {
+                write_out(output, OUTPUT_SIZE, &out)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    })())
+}
+
+/// Writes the 32-byte output of a proof to `output`, without checking the
+/// proof.
+#[no_mangle]
+pub extern "C" fn vrf_proof_to_hash(
+    proof: *mut libc::c_uchar,
+    output: *mut libc::c_uchar,
+) -> libc::c_int {
+    error::status((|| {
+        let proof = Signature::from_bytes(bytes(proof, PROOF_SIZE)?)?;
+        write_out(output, OUTPUT_SIZE, &proof_to_hash(&proof))?;
+        Ok(true)
+    })())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bls::{self, key_gen, Scheme};
+    use crate::ErrorCode;
+
+    #[test]
+    fn test_prove_verify() {
+        let sk = key_gen(&[3; 32], &[]).unwrap();
+        let pk = sk.public_key();
+        let proof = prove(&sk, b"height 7 view 0");
+        // Proofs are deterministic.
+        assert_eq!(proof, prove(&sk, b"height 7 view 0"));
+        assert_eq!(
+            Some(proof_to_hash(&proof)),
+            verify(&pk, b"height 7 view 0", &proof)
+        );
+        assert_eq!(None, verify(&pk, b"height 7 view 1", &proof));
+        let other = key_gen(&[4; 32], &[]).unwrap();
+        assert_eq!(
+            None,
+            verify(&other.public_key(), b"height 7 view 0", &proof)
+        );
+        assert_ne!(
+            proof_to_hash(&proof),
+            proof_to_hash(&prove(&sk, b"height 7 view 1"))
+        );
+        // Not a signature in any scheme.
+        for scheme in [Scheme::Basic, Scheme::Augmented, Scheme::ProofOfPossession] {
+            assert!(!bls::verify(scheme, &pk, b"height 7 view 0", &proof));
+        }
+    }
+
+    #[test]
+    fn test_ffi() {
+        let sk = key_gen(&[3; 32], &[]).unwrap();
+        let mut bsk = sk.to_bytes();
+        let mut pk = sk.public_key().to_bytes();
+        let mut alpha = *b"seed";
+        let mut proof = [0u8; PROOF_SIZE];
+        let code = vrf_prove(
+            bsk.as_mut_ptr(),
+            alpha.as_mut_ptr(),
+            alpha.len(),
+            proof.as_mut_ptr(),
+        );
+        assert_eq!(1, code);
+
+        let mut output = [0u8; OUTPUT_SIZE];
+        let code = vrf_verify(
+            pk.as_mut_ptr(),
+            alpha.as_mut_ptr(),
+            alpha.len(),
+            proof.as_mut_ptr(),
+            output.as_mut_ptr(),
+        );
+        assert_eq!(1, code);
+        let mut hash = [0u8; OUTPUT_SIZE];
+        assert_eq!(1, vrf_proof_to_hash(proof.as_mut_ptr(), hash.as_mut_ptr()));
+        assert_eq!(output, hash);
+
+        let code = vrf_verify(
+            pk.as_mut_ptr(),
+            alpha.as_mut_ptr(),
+            alpha.len() - 1,
+            proof.as_mut_ptr(),
+            output.as_mut_ptr(),
+        );
+        assert_eq!(0, code);
+        proof[0] ^= 1;
+        let code = vrf_proof_to_hash(proof.as_mut_ptr(), hash.as_mut_ptr());
+        assert_eq!(ErrorCode::MalformedSignature as libc::c_int, code);
+    }
+}